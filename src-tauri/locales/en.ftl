@@ -0,0 +1,56 @@
+# Text the backend writes into exports, titles and error messages.
+# Keep every message in step with nl.ftl.
+
+unknown-speaker = Unknown
+speaker-number = Speaker { $number }
+track-microphone = Me
+track-system = Others
+someone-else = someone else
+default-title = Transcript
+part-two = { $title } (part 2)
+
+heading-summary = Summary
+heading-transcript = Transcript
+heading-comments = Comments
+
+interview-heading = Interview Q&A
+interview-asked = Asked by { $speaker } at { $time }
+interview-no-answer = No answer
+interview-no-answer-text = (no answer)
+
+verbatim-page = Page { $page } of { $pages }
+verbatim-inaudible = [inaudible { $time }]
+verbatim-certification = CERTIFICATION
+verbatim-statement = I hereby certify that the foregoing is a true and accurate transcript of the recording, to the best of my ability, prepared verbatim including all utterances, and that sections marked inaudible could not be understood.
+heading-time = Time
+
+error-io-error = { $detail }
+error-database-error = database error: { $detail }
+error-schema-too-new = this database uses schema version { $found }, but this version of the app only supports up to { $supported }; please update the app
+error-audio-device = audio device error: { $detail }
+error-decode-error = audio decode error: { $detail }
+error-transcription-failed = transcription failed: { $detail }
+error-preflight-failed = pre-flight check failed: { $detail }
+error-not-found = not found: { $detail }
+error-invalid-input = invalid input: { $detail }
+error-provider-error = summarization provider error: { $detail }
+error-offline-mode = offline mode is on: { $detail }
+error-keychain-error = keychain error: { $detail }
+error-cancelled = cancelled: { $detail }
+error-edit-conflict = this transcript was changed by { $editor } (now version { $current }); reload it before saving
+error-quota-exceeded = project { $project } is over its quota: this import would use { $used } of { $limit }
+
+io-not-found = file or folder not found
+io-permission-denied = permission denied
+io-already-exists = already exists
+io-storage-full = the disk is full
+missing-transcription = transcription
+missing-watch-folder = watch folder
+missing-recording-session = recording session
+missing-audio-file = audio file
+missing-comment = comment
+missing-segment = segment
+missing-folder = folder
+missing-file = file
+missing-job = job
+missing-app-data-directory = app data directory
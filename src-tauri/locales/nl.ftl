@@ -0,0 +1,56 @@
+# Tekst die de backend in exports, titels en foutmeldingen schrijft.
+# Houd elke melding gelijk met en.ftl.
+
+unknown-speaker = Onbekend
+speaker-number = Spreker { $number }
+track-microphone = Ik
+track-system = Anderen
+someone-else = iemand anders
+default-title = Transcriptie
+part-two = { $title } (deel 2)
+
+heading-summary = Samenvatting
+heading-transcript = Transcriptie
+heading-comments = Opmerkingen
+
+interview-heading = Interview: vragen en antwoorden
+interview-asked = Gevraagd door { $speaker } op { $time }
+interview-no-answer = Geen antwoord
+interview-no-answer-text = (geen antwoord)
+
+verbatim-page = Pagina { $page } van { $pages }
+verbatim-inaudible = [onverstaanbaar { $time }]
+verbatim-certification = VERKLARING
+verbatim-statement = Ik verklaar dat het voorgaande naar beste vermogen een waarheidsgetrouwe en nauwkeurige woordelijke uitwerking van de opname is, inclusief alle uitingen, en dat als onverstaanbaar gemarkeerde delen niet te verstaan waren.
+heading-time = Tijd
+
+error-io-error = { $detail }
+error-database-error = databasefout: { $detail }
+error-schema-too-new = deze database gebruikt schemaversie { $found }, maar deze versie van de app ondersteunt maximaal { $supported }; werk de app bij
+error-audio-device = fout in audioapparaat: { $detail }
+error-decode-error = audio kon niet worden gedecodeerd: { $detail }
+error-transcription-failed = transcriptie mislukt: { $detail }
+error-preflight-failed = controle vooraf mislukt: { $detail }
+error-not-found = niet gevonden: { $detail }
+error-invalid-input = ongeldige invoer: { $detail }
+error-provider-error = fout bij samenvattingsdienst: { $detail }
+error-offline-mode = offlinemodus staat aan: { $detail }
+error-keychain-error = fout in sleutelhanger: { $detail }
+error-cancelled = geannuleerd: { $detail }
+error-edit-conflict = dit transcript is gewijzigd door { $editor } (nu versie { $current }); laad het opnieuw voordat je opslaat
+error-quota-exceeded = project { $project } zit boven zijn quotum: deze import zou { $used } van { $limit } gebruiken
+
+io-not-found = bestand of map niet gevonden
+io-permission-denied = geen toegang
+io-already-exists = bestaat al
+io-storage-full = de schijf is vol
+missing-transcription = transcriptie
+missing-watch-folder = bewaakte map
+missing-recording-session = opnamesessie
+missing-audio-file = audiobestand
+missing-comment = opmerking
+missing-segment = segment
+missing-folder = map
+missing-file = bestand
+missing-job = taak
+missing-app-data-directory = app-gegevensmap
@@ -0,0 +1,290 @@
+//! Conversation dynamics computed from speaker-labeled segments.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::Result;
+use crate::evaluation::normalize_words;
+use crate::segments::{self, StoredSegment};
+use crate::{db, i18n};
+
+/// A small valence lexicon; enough to tell a tense exchange from a friendly one.
+const POSITIVE_WORDS: &[&str] = &[
+    "agree",
+    "amazing",
+    "appreciate",
+    "awesome",
+    "best",
+    "better",
+    "excellent",
+    "excited",
+    "fantastic",
+    "glad",
+    "good",
+    "great",
+    "happy",
+    "helpful",
+    "like",
+    "love",
+    "nice",
+    "perfect",
+    "pleased",
+    "thank",
+    "thanks",
+    "wonderful",
+    "yes",
+];
+const NEGATIVE_WORDS: &[&str] = &[
+    "angry",
+    "annoyed",
+    "awful",
+    "bad",
+    "blocked",
+    "broken",
+    "concerned",
+    "disagree",
+    "disappointed",
+    "fail",
+    "failed",
+    "frustrated",
+    "hate",
+    "issue",
+    "no",
+    "problem",
+    "sad",
+    "terrible",
+    "unfortunately",
+    "upset",
+    "worried",
+    "worse",
+    "worst",
+    "wrong",
+];
+const NEGATIONS: &[&str] = &[
+    "not", "no", "never", "don't", "didn't", "isn't", "wasn't", "can't",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeakerAnalytics {
+    pub speaker: String,
+    /// Seconds spent speaking.
+    pub talk_time: f64,
+    /// Share of total talk time, between 0 and 1.
+    pub talk_share: f64,
+    pub turns: usize,
+    pub words: usize,
+    pub words_per_minute: f64,
+    /// Times this speaker started while someone else was still talking.
+    pub interruptions: usize,
+    /// Times someone else started while this speaker was talking.
+    pub interrupted: usize,
+    pub mean_sentiment: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentSentiment {
+    pub position: i64,
+    pub speaker: String,
+    /// From -1 (negative) to 1 (positive).
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationAnalytics {
+    pub speakers: Vec<SpeakerAnalytics>,
+    pub total_talk_time: f64,
+    /// Present when sentiment was requested.
+    pub sentiment: Option<Vec<SegmentSentiment>>,
+}
+
+/// Lexicon sentiment of one utterance; negation flips the next scored word.
+pub fn sentiment(text: &str) -> f64 {
+    let mut score = 0i32;
+    let mut hits = 0i32;
+    let mut negate = false;
+    for word in normalize_words(text) {
+        let polarity = if POSITIVE_WORDS.contains(&word.as_str()) {
+            1
+        } else if NEGATIVE_WORDS.contains(&word.as_str()) {
+            -1
+        } else {
+            0
+        };
+        if polarity != 0 {
+            score += if negate { -polarity } else { polarity };
+            hits += 1;
+            negate = false;
+        } else if NEGATIONS.contains(&word.as_str()) {
+            negate = true;
+        }
+    }
+    if hits == 0 {
+        0.0
+    } else {
+        score as f64 / hits as f64
+    }
+}
+
+fn speaker_of<'a>(segment: &'a StoredSegment, unknown: &'a str) -> &'a str {
+    segment.speaker.as_deref().unwrap_or(unknown)
+}
+
+pub fn analyze(segments: &[StoredSegment], include_sentiment: bool) -> ConversationAnalytics {
+    let mut order: Vec<String> = Vec::new();
+    let mut stats: HashMap<String, SpeakerAnalytics> = HashMap::new();
+    let mut sentiments: Vec<SegmentSentiment> = Vec::new();
+
+    let mut previous: Option<&StoredSegment> = None;
+    // Latest end time of each speaker, to detect overlapping starts.
+    let mut talking_until: HashMap<&str, f64> = HashMap::new();
+    let unknown = i18n::t("unknown-speaker");
+
+    for segment in segments {
+        let speaker = speaker_of(segment, &unknown);
+        if !stats.contains_key(speaker) {
+            order.push(speaker.to_string());
+        }
+        let entry = stats
+            .entry(speaker.to_string())
+            .or_insert_with(|| SpeakerAnalytics {
+                speaker: speaker.to_string(),
+                talk_time: 0.0,
+                talk_share: 0.0,
+                turns: 0,
+                words: 0,
+                words_per_minute: 0.0,
+                interruptions: 0,
+                interrupted: 0,
+                mean_sentiment: None,
+            });
+        entry.talk_time += (segment.end - segment.start).max(0.0);
+        entry.words += normalize_words(&segment.text).len();
+        if previous.is_none_or(|p| speaker_of(p, &unknown) != speaker) {
+            entry.turns += 1;
+        }
+
+        let overlapped: Vec<String> = talking_until
+            .iter()
+            .filter(|(other, until)| **other != speaker && **until > segment.start)
+            .map(|(other, _)| other.to_string())
+            .collect();
+        if !overlapped.is_empty() {
+            entry.interruptions += 1;
+        }
+        for other in overlapped {
+            if let Some(other) = stats.get_mut(&other) {
+                other.interrupted += 1;
+            }
+        }
+
+        if include_sentiment {
+            sentiments.push(SegmentSentiment {
+                position: segment.position,
+                speaker: speaker.to_string(),
+                score: sentiment(&segment.text),
+            });
+        }
+
+        let until = talking_until.entry(speaker).or_insert(segment.end);
+        *until = until.max(segment.end);
+        previous = Some(segment);
+    }
+
+    let total_talk_time: f64 = stats.values().map(|s| s.talk_time).sum();
+    let speakers = order
+        .into_iter()
+        .filter_map(|name| stats.remove(&name))
+        .map(|mut s| {
+            s.talk_share = if total_talk_time > 0.0 {
+                s.talk_time / total_talk_time
+            } else {
+                0.0
+            };
+            s.words_per_minute = if s.talk_time > 0.0 {
+                s.words as f64 / (s.talk_time / 60.0)
+            } else {
+                0.0
+            };
+            if include_sentiment {
+                let scores: Vec<f64> = sentiments
+                    .iter()
+                    .filter(|entry| entry.speaker == s.speaker)
+                    .map(|entry| entry.score)
+                    .collect();
+                s.mean_sentiment =
+                    (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64);
+            }
+            s
+        })
+        .collect();
+
+    ConversationAnalytics {
+        speakers,
+        total_talk_time,
+        sentiment: include_sentiment.then_some(sentiments),
+    }
+}
+
+#[tauri::command]
+pub fn get_conversation_analytics(
+    app: AppHandle,
+    id: String,
+    include_sentiment: Option<bool>,
+) -> Result<ConversationAnalytics> {
+    let conn = db::connect(&app)?;
+    db::transcription_text(&conn, &id)?;
+    let segments = segments::for_transcription(&conn, &id)?;
+    Ok(analyze(&segments, include_sentiment.unwrap_or(false)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(position: i64, speaker: &str, text: &str, start: f64, end: f64) -> StoredSegment {
+        StoredSegment {
+            id: position.to_string(),
+            transcription_id: "t".into(),
+            position,
+            speaker: Some(speaker.into()),
+            text: text.into(),
+            start,
+            end,
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn counts_talk_time_and_interruptions() {
+        let analytics = analyze(
+            &[
+                segment(0, "Ana", "we should ship on friday", 0.0, 6.0),
+                segment(1, "Ben", "wait that is not possible", 5.0, 8.0),
+                segment(2, "Ana", "okay", 9.0, 10.0),
+            ],
+            false,
+        );
+
+        let ana = &analytics.speakers[0];
+        let ben = &analytics.speakers[1];
+        assert_eq!(ana.talk_time, 7.0);
+        assert_eq!(ana.turns, 2);
+        assert_eq!(ben.interruptions, 1);
+        assert_eq!(ana.interrupted, 1);
+        assert_eq!(ben.words_per_minute, 100.0);
+        assert!(analytics.sentiment.is_none());
+    }
+
+    #[test]
+    fn negation_flips_sentiment() {
+        assert_eq!(sentiment("this is great, thanks"), 1.0);
+        assert_eq!(sentiment("that is not good"), -1.0);
+        assert_eq!(sentiment("the meeting is at noon"), 0.0);
+    }
+}
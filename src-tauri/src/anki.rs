@@ -0,0 +1,140 @@
+//! Flashcards for language learners, in a file Anki imports.
+//!
+//! Each selected segment becomes a note: the original text with its audio
+//! clip on the front, the stored translation on the back. The notes are a
+//! tab-separated file with Anki's import headers; the clips go in a `media`
+//! folder next to it, to be copied into the profile's `collection.media`
+//! folder, where Anki's `[sound:…]` references look for them.
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::segments::{self, StoredSegment};
+use crate::share::escape_html;
+use crate::{clips, db, translations};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlashcardExport {
+    pub notes_path: PathBuf,
+    pub media_dir: PathBuf,
+    pub cards: usize,
+}
+
+/// A field as Anki reads it with `#html:true`: escaped, on one line.
+fn field(text: &str) -> String {
+    escape_html(text.trim())
+        .replace('\t', " ")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+fn clip_name(transcription_id: &str, position: i64) -> String {
+    format!(
+        "transcriber-{}-{}.wav",
+        &transcription_id[..transcription_id.len().min(8)],
+        position
+    )
+}
+
+/// The notes file for `cards`, each a segment with its translation.
+fn render(deck: &str, tag: &str, cards: &[(&StoredSegment, Option<&str>, String)]) -> String {
+    let mut out = format!(
+        "#separator:tab\n#html:true\n#notetype:Basic\n#deck:{}\n#tags column:3\n",
+        deck.replace(['\t', '\n'], " ")
+    );
+    for (segment, translation, clip) in cards {
+        out.push_str(&format!(
+            "{} [sound:{}]\t{}\t{}\n",
+            field(&segment.text),
+            clip,
+            field(translation.unwrap_or_default()),
+            tag
+        ));
+    }
+    out
+}
+
+/// Export the segments at `positions` as flashcards in `dir`, with the
+/// translation into `translation_language` on the back when there is one.
+#[tauri::command]
+pub async fn export_flashcards(
+    app: AppHandle,
+    transcription_id: String,
+    positions: Vec<i64>,
+    translation_language: Option<String>,
+    deck: Option<String>,
+    dir: PathBuf,
+) -> Result<FlashcardExport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::connect(&app)?;
+        let selected: Vec<StoredSegment> = segments::for_transcription(&conn, &transcription_id)?
+            .into_iter()
+            .filter(|segment| positions.contains(&segment.position))
+            .collect();
+        if selected.is_empty() {
+            return Err(Error::InvalidInput("no segments selected".into()));
+        }
+        let translations = match &translation_language {
+            Some(language) => translations::for_language(&conn, &transcription_id, language)?,
+            None => Vec::new(),
+        };
+
+        let media_dir = dir.join("media");
+        fs::create_dir_all(&media_dir)?;
+        let pcm = clips::trimmed_pcm(&conn, &transcription_id)?;
+        let mut cards = Vec::new();
+        for segment in &selected {
+            let clip = clip_name(&transcription_id, segment.position);
+            clips::extract(&pcm, segment.start, segment.end, &media_dir.join(&clip))?;
+            let translation = translations
+                .iter()
+                .find(|t| t.position == segment.position)
+                .map(|t| t.text.as_str());
+            cards.push((segment, translation, clip));
+        }
+
+        let deck = deck
+            .filter(|deck| !deck.trim().is_empty())
+            .unwrap_or_else(|| "Transcriber".into());
+        let notes_path = dir.join("flashcards.txt");
+        fs::write(&notes_path, render(&deck, "transcriber", &cards))?;
+        Ok(FlashcardExport {
+            notes_path,
+            media_dir,
+            cards: cards.len(),
+        })
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_one_escaped_note_per_segment() {
+        let segment = StoredSegment {
+            id: String::new(),
+            transcription_id: "a1b2c3d4e5".into(),
+            position: 3,
+            speaker: None,
+            text: " Ik heb <geen>\ttijd.".into(),
+            start: 1.0,
+            end: 2.0,
+            confidence: None,
+            words: Vec::new(),
+        };
+        let clip = clip_name("a1b2c3d4e5", 3);
+        assert_eq!(
+            render("Dutch", "transcriber", &[(&segment, Some("I have no time."), clip)]),
+            "#separator:tab\n#html:true\n#notetype:Basic\n#deck:Dutch\n#tags column:3\n\
+             Ik heb &lt;geen&gt; tijd. [sound:transcriber-a1b2c3d4-3.wav]\tI have no time.\ttranscriber\n"
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! Cold storage for source audio that is rarely played back.
+//!
+//! Archiving re-encodes a file to 16 kHz mono Opus in an Ogg container,
+//! which is what transcription uses anyway and about a fiftieth the size of
+//! a CD-quality WAV, or deletes the audio outright while keeping its
+//! transcripts. Either way the original location is kept in
+//! `audio_archive` so a compressed file can be restored as WAV.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use ogg::reading::PacketReader;
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use opus::{Application, Bitrate, Channels};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::audio::{self, WHISPER_SAMPLE_RATE};
+use crate::error::{Error, Result};
+use crate::{audio_files, audit, bulk, db};
+
+/// Directory archived audio is written to; defaults to the app data dir.
+pub const DIRECTORY_PREFERENCE: &str = "archive_directory";
+
+/// Plenty for intelligible speech at 16 kHz.
+const BITRATE: i32 = 24_000;
+/// 20 ms frames.
+const FRAME_SAMPLES: usize = WHISPER_SAMPLE_RATE as usize / 50;
+/// Ogg Opus granule positions always count 48 kHz samples.
+const GRANULE_RATE: u64 = 48_000;
+const STREAM_SERIAL: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveMode {
+    /// Re-encode to Opus and remove the original.
+    Compress,
+    /// Remove the audio, keeping transcripts and metadata.
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveRecord {
+    pub audio_file_id: String,
+    pub state: ArchiveMode,
+    pub original_path: PathBuf,
+    pub original_size: u64,
+    pub archive_path: Option<PathBuf>,
+    pub archive_size: Option<u64>,
+    pub archived_at: String,
+}
+
+/// Outcome for one file of an `archive_audio` call.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveOutcome {
+    pub id: String,
+    pub record: Option<ArchiveRecord>,
+    pub error: Option<String>,
+}
+
+fn codec_error(err: opus::Error) -> Error {
+    Error::Decode(err.to_string())
+}
+
+fn container_error(err: ogg::reading::OggReadError) -> Error {
+    Error::Decode(err.to_string())
+}
+
+pub fn archive_dir(app: &AppHandle, conn: &Connection) -> Result<PathBuf> {
+    let dir = match db::get_preference(conn, DIRECTORY_PREFERENCE)? {
+        Some(dir) => PathBuf::from(dir),
+        None => app
+            .path_resolver()
+            .app_data_dir()
+            .ok_or_else(|| Error::NotFound("app data directory".into()))?
+            .join("archive"),
+    };
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// The identification header of an Ogg Opus stream (RFC 7845, section 5.1).
+fn opus_head(pre_skip: u16) -> Vec<u8> {
+    let mut head = b"OpusHead".to_vec();
+    head.push(1); // version
+    head.push(1); // mono
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&WHISPER_SAMPLE_RATE.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // mono/stereo channel mapping
+    head
+}
+
+fn opus_tags() -> Vec<u8> {
+    let vendor = concat!("transcriber ", env!("CARGO_PKG_VERSION"));
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor.as_bytes());
+    tags.extend_from_slice(&0u32.to_le_bytes()); // no comments
+    tags
+}
+
+/// Granule position at the end of frame `index` of `frames` encoding
+/// `samples` 16 kHz samples: the 48 kHz samples decoded by then, which
+/// include the `pre_skip` the player drops (RFC 7845 §4). The last one
+/// trims the padding of the final frame.
+fn granule_after(index: usize, frames: usize, samples: usize, pre_skip: u64) -> u64 {
+    let scale = GRANULE_RATE / WHISPER_SAMPLE_RATE as u64;
+    let decoded = if index + 1 == frames {
+        samples
+    } else {
+        (index + 1) * FRAME_SAMPLES
+    };
+    decoded as u64 * scale + pre_skip
+}
+
+/// Encode 16 kHz mono samples to an Ogg Opus file.
+pub fn encode(pcm: &[f32], path: &Path) -> Result<()> {
+    let mut encoder = opus::Encoder::new(WHISPER_SAMPLE_RATE, Channels::Mono, Application::Voip)
+        .map_err(codec_error)?;
+    encoder
+        .set_bitrate(Bitrate::Bits(BITRATE))
+        .map_err(codec_error)?;
+    let scale = GRANULE_RATE / WHISPER_SAMPLE_RATE as u64;
+    let pre_skip = encoder.get_lookahead().map_err(codec_error)? as u64 * scale;
+
+    let mut writer = PacketWriter::new(BufWriter::new(File::create(path)?));
+    writer.write_packet(
+        opus_head(pre_skip as u16),
+        STREAM_SERIAL,
+        PacketWriteEndInfo::EndPage,
+        0,
+    )?;
+    writer.write_packet(opus_tags(), STREAM_SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+    let frames = pcm.len().div_ceil(FRAME_SAMPLES).max(1);
+    let mut packet = vec![0u8; 4000];
+    for index in 0..frames {
+        let start = (index * FRAME_SAMPLES).min(pcm.len());
+        let mut frame = pcm[start..(start + FRAME_SAMPLES).min(pcm.len())].to_vec();
+        frame.resize(FRAME_SAMPLES, 0.0);
+        let len = encoder
+            .encode_float(&frame, &mut packet)
+            .map_err(codec_error)?;
+        let granule = granule_after(index, frames, pcm.len(), pre_skip);
+        let end = if index + 1 == frames {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        writer.write_packet(packet[..len].to_vec(), STREAM_SERIAL, end, granule)?;
+    }
+    Ok(())
+}
+
+/// Decode an Ogg Opus file written by [`encode`] back to 16 kHz mono.
+pub fn decode(path: &Path) -> Result<Vec<f32>> {
+    let mut reader = PacketReader::new(BufReader::new(File::open(path)?));
+    let head = reader
+        .read_packet()
+        .map_err(container_error)?
+        .ok_or_else(|| Error::Decode("archive is empty".into()))?;
+    if !head.data.starts_with(b"OpusHead") || head.data.len() < 19 {
+        return Err(Error::Decode("archive is not an Opus stream".into()));
+    }
+    let scale = (GRANULE_RATE / WHISPER_SAMPLE_RATE as u64) as usize;
+    let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as usize / scale;
+
+    let mut decoder =
+        opus::Decoder::new(WHISPER_SAMPLE_RATE, Channels::Mono).map_err(codec_error)?;
+    let mut pcm = Vec::new();
+    let mut frame = vec![0f32; FRAME_SAMPLES * 6];
+    let mut end_granule = None;
+    // The second packet holds the tags, which are not needed.
+    reader.read_packet().map_err(container_error)?;
+    while let Some(packet) = reader.read_packet().map_err(container_error)? {
+        let samples = decoder
+            .decode_float(&packet.data, &mut frame, false)
+            .map_err(codec_error)?;
+        pcm.extend_from_slice(&frame[..samples]);
+        if packet.last_in_stream() {
+            end_granule = Some(packet.absgp_page() as usize / scale);
+        }
+    }
+
+    let end = end_granule.unwrap_or(pcm.len()).min(pcm.len());
+    Ok(pcm.get(pre_skip.min(end)..end).unwrap_or_default().to_vec())
+}
+
+pub fn write_wav(pcm: &[f32], path: &Path) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: WHISPER_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let wav_error = |err: hound::Error| Error::Decode(err.to_string());
+    let mut writer = hound::WavWriter::create(path, spec).map_err(wav_error)?;
+    for &sample in pcm {
+        writer
+            .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .map_err(wav_error)?;
+    }
+    writer.finalize().map_err(wav_error)
+}
+
+pub fn record(conn: &Connection, audio_file_id: &str) -> Result<Option<ArchiveRecord>> {
+    Ok(conn
+        .query_row(
+            "SELECT state, original_path, original_size, archive_path, archive_size, archived_at
+             FROM audio_archive WHERE audio_file_id = ?1",
+            [audio_file_id],
+            |row| {
+                Ok(ArchiveRecord {
+                    audio_file_id: audio_file_id.to_string(),
+                    state: match row.get::<_, String>(0)?.as_str() {
+                        "delete" => ArchiveMode::Delete,
+                        _ => ArchiveMode::Compress,
+                    },
+                    original_path: PathBuf::from(row.get::<_, String>(1)?),
+                    original_size: row.get::<_, i64>(2)? as u64,
+                    archive_path: row.get::<_, Option<String>>(3)?.map(PathBuf::from),
+                    archive_size: row.get::<_, Option<i64>>(4)?.map(|size| size as u64),
+                    archived_at: row.get(5)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+/// Archive one imported file, recording `audit_action` on `audit_targets`
+/// in the audit log. The original is only removed once the compressed copy
+/// has been written, and the archive row and audit entry are only committed
+/// once the original is gone.
+pub fn archive(
+    conn: &Connection,
+    dir: &Path,
+    audio_file_id: &str,
+    mode: ArchiveMode,
+    audit_action: &str,
+    audit_targets: &[&str],
+) -> Result<ArchiveRecord> {
+    if record(conn, audio_file_id)?.is_some() {
+        return Err(Error::InvalidInput(format!(
+            "audio file {} is already archived",
+            audio_file_id
+        )));
+    }
+    let file = audio_files::get(conn, audio_file_id)?;
+    let original_size = fs::metadata(&file.path)?.len();
+
+    let archive_path = match mode {
+        ArchiveMode::Compress => {
+            let path = dir.join(format!("{}.opus", audio_file_id));
+            encode(&audio::load_pcm(&file.path)?, &path)?;
+            Some(path)
+        }
+        ArchiveMode::Delete => None,
+    };
+    let archive_size = archive_path
+        .as_deref()
+        .map(fs::metadata)
+        .transpose()?
+        .map(|meta| meta.len());
+
+    let original = file.path.to_string_lossy();
+    let tx = conn.unchecked_transaction()?;
+    let stored: Result<()> = (|| {
+        tx.execute(
+            "INSERT INTO audio_archive
+                 (audio_file_id, state, original_path, original_size, archive_path, archive_size)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                audio_file_id,
+                match mode {
+                    ArchiveMode::Compress => "compress",
+                    ArchiveMode::Delete => "delete",
+                },
+                original,
+                original_size as i64,
+                archive_path
+                    .as_deref()
+                    .map(|p| p.to_string_lossy().to_string()),
+                archive_size.map(|size| size as i64)
+            ],
+        )?;
+        audit::record(&tx, audit_action, audit_targets, Some(&original))?;
+        fs::remove_file(&file.path)?;
+        Ok(())
+    })();
+    if let Err(err) = stored {
+        // Rolled back with the transaction; the original is still in place.
+        if let Some(path) = &archive_path {
+            let _ = fs::remove_file(path);
+        }
+        return Err(err);
+    }
+    tx.commit()?;
+    record(conn, audio_file_id)?
+        .ok_or_else(|| Error::NotFound(format!("archive of {}", audio_file_id)))
+}
+
+/// Restore a compressed file as WAV next to where the original was, and
+/// point the audio file at it. A file that has since taken the original's
+/// name is kept, and the WAV gets a numbered name instead. Deleted audio
+/// cannot be restored.
+pub fn restore(conn: &Connection, audio_file_id: &str) -> Result<PathBuf> {
+    let record = record(conn, audio_file_id)?
+        .ok_or_else(|| Error::NotFound(format!("archive of {}", audio_file_id)))?;
+    let archive_path = match (record.state, record.archive_path) {
+        (ArchiveMode::Compress, Some(path)) => path,
+        _ => {
+            return Err(Error::InvalidInput(format!(
+                "the audio of {} was deleted and cannot be restored",
+                audio_file_id
+            )))
+        }
+    };
+
+    if let Some(parent) = record.original_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let restored = bulk::free_path(record.original_path.with_extension("wav"));
+    write_wav(&decode(&archive_path)?, &restored)?;
+    let tx = conn.unchecked_transaction()?;
+    let stored: Result<()> = (|| {
+        tx.execute(
+            "UPDATE audio_files SET path = ?2, size = ?3 WHERE id = ?1",
+            params![
+                audio_file_id,
+                restored.to_string_lossy(),
+                fs::metadata(&restored)?.len() as i64
+            ],
+        )?;
+        tx.execute(
+            "DELETE FROM audio_archive WHERE audio_file_id = ?1",
+            [audio_file_id],
+        )?;
+        Ok(())
+    })();
+    if let Err(err) = stored {
+        let _ = fs::remove_file(&restored);
+        return Err(err);
+    }
+    tx.commit()?;
+    fs::remove_file(&archive_path)?;
+    Ok(restored)
+}
+
+/// Archive each imported file, continuing past files that fail.
+#[tauri::command]
+pub async fn archive_audio(
+    app: AppHandle,
+    ids: Vec<String>,
+    mode: Option<ArchiveMode>,
+) -> Result<Vec<ArchiveOutcome>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::connect(&app)?;
+        let dir = archive_dir(&app, &conn)?;
+        let mode = mode.unwrap_or(ArchiveMode::Compress);
+        Ok(ids
+            .into_iter()
+            .map(|id| {
+                let action = match mode {
+                    ArchiveMode::Compress => "audio_archived",
+                    ArchiveMode::Delete => "audio_deleted",
+                };
+                match archive(&conn, &dir, &id, mode, action, &[&id]) {
+                    Ok(record) => ArchiveOutcome {
+                        id,
+                        record: Some(record),
+                        error: None,
+                    },
+                    Err(err) => ArchiveOutcome {
+                        id,
+                        record: None,
+                        error: Some(err.to_string()),
+                    },
+                }
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+/// Restore an archived file; returns the path of the restored WAV.
+#[tauri::command]
+pub async fn restore_audio(app: AppHandle, id: String) -> Result<PathBuf> {
+    tauri::async_runtime::spawn_blocking(move || restore(&db::connect(&app)?, &id))
+        .await
+        .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[tauri::command]
+pub fn get_audio_archive(app: AppHandle, id: String) -> Result<Option<ArchiveRecord>> {
+    record(&db::connect(&app)?, &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opus_round_trip_keeps_length_and_signal() {
+        let pcm: Vec<f32> = (0..WHISPER_SAMPLE_RATE as usize)
+            .map(|n| {
+                (n as f32 * 440.0 * std::f32::consts::TAU / WHISPER_SAMPLE_RATE as f32).sin() * 0.5
+            })
+            .collect();
+        let path = std::env::temp_dir().join(format!("archive-{}.opus", uuid::Uuid::new_v4()));
+        encode(&pcm, &path).unwrap();
+        let decoded = decode(&path).unwrap();
+        let size = fs::metadata(&path).unwrap().len();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(decoded.len(), pcm.len());
+        assert!(size < (pcm.len() * 2) as u64 / 5);
+        let energy = decoded.iter().map(|s| s * s).sum::<f32>() / decoded.len() as f32;
+        assert!(energy > 0.05);
+    }
+
+    #[test]
+    fn granule_positions_count_the_pre_skip() {
+        let samples = FRAME_SAMPLES * 2 + 100;
+        let granules: Vec<u64> = (0..3)
+            .map(|index| granule_after(index, 3, samples, 312))
+            .collect();
+        assert_eq!(
+            granules,
+            [
+                FRAME_SAMPLES as u64 * 3 + 312,
+                FRAME_SAMPLES as u64 * 6 + 312,
+                samples as u64 * 3 + 312
+            ]
+        );
+    }
+}
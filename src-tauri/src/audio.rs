@@ -0,0 +1,149 @@
+//! Audio decoding for the transcription engine.
+//!
+//! Whisper expects 16 kHz mono `f32` samples, so every supported container
+//! (WAV, MP3, M4A, FLAC, OGG) is decoded with symphonia, downmixed and
+//! resampled here before it reaches the model.
+
+use std::fs::File;
+use std::path::Path;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::error::{Error, Result};
+
+/// Sample rate whisper models are trained on.
+pub const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+fn decode_error(err: SymphoniaError) -> Error {
+    Error::Decode(err.to_string())
+}
+
+/// Decode an audio file into 16 kHz mono samples.
+pub fn load_pcm(path: &Path) -> Result<Vec<f32>> {
+    let (mono, source_rate) = load_mono(path)?;
+    Ok(resample(&mono, source_rate, WHISPER_SAMPLE_RATE))
+}
+
+/// Decode an audio file into mono samples at its own sample rate.
+pub fn load_mono(path: &Path) -> Result<(Vec<f32>, u32)> {
+    decode(path, None)
+}
+
+/// Decode one channel, counted from 0, of an audio file into 16 kHz
+/// samples, e.g. the far side of a call recorded with one party per channel.
+pub fn load_channel_pcm(path: &Path, channel: usize) -> Result<Vec<f32>> {
+    let (samples, source_rate) = decode(path, Some(channel))?;
+    Ok(resample(&samples, source_rate, WHISPER_SAMPLE_RATE))
+}
+
+/// Decode `channel` of an audio file, or all channels downmixed.
+fn decode(path: &Path, channel: Option<usize>) -> Result<(Vec<f32>, u32)> {
+    let file = File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(decode_error)?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| Error::Decode("no audio track found".into()))?;
+    let track_id = track.id;
+    let source_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| Error::Decode("unknown sample rate".into()))?;
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(decode_error)?;
+
+    let mut mono = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(err))
+                if err.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break
+            }
+            Err(err) => return Err(decode_error(err)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt frame should not abort the whole file.
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(err) => return Err(decode_error(err)),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        if let Some(channel) = channel.filter(|&channel| channel >= channels) {
+            return Err(Error::InvalidInput(format!(
+                "{} has {} channels, so none with index {}",
+                path.display(),
+                channels,
+                channel
+            )));
+        }
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+
+        let frames = buffer.samples().chunks(channels);
+        match channel {
+            Some(channel) => mono.extend(frames.map(|frame| frame[channel])),
+            None => mono.extend(frames.map(|frame| frame.iter().sum::<f32>() / channels as f32)),
+        }
+    }
+
+    Ok((mono, source_rate))
+}
+
+/// Linear-interpolation resampler; adequate for speech recognition input.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio).floor() as usize;
+
+    (0..out_len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position.floor() as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = samples[index];
+            let next = samples.get(index + 1).copied().unwrap_or(current);
+            current + (next - current) * fraction
+        })
+        .collect()
+}
+
+/// Duration in seconds of a buffer produced by [`load_pcm`].
+pub fn duration_secs(pcm: &[f32]) -> f64 {
+    pcm.len() as f64 / WHISPER_SAMPLE_RATE as f64
+}
@@ -0,0 +1,325 @@
+//! Imported audio files and the metadata embedded in them.
+//!
+//! ID3, Vorbis comments and MP4 atoms are read through symphonia's tag
+//! mapping. The embedded title becomes the default title of transcripts
+//! made from the file.
+//!
+//! Files added in the window arrive as bytes, since the webview does not
+//! expose their paths; they are kept in the app's `audio` directory.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::{db, quota};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub recorded_at: Option<String>,
+    /// Recording device, when the recorder writes one (phones usually do).
+    pub device: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioFileRecord {
+    pub id: String,
+    pub path: PathBuf,
+    pub file_name: String,
+    pub size: u64,
+    /// Seconds, when the container declares it.
+    pub duration: Option<f64>,
+    #[serde(flatten)]
+    pub tags: AudioTags,
+    pub imported_at: String,
+    pub project: Option<String>,
+    /// Where the file came from when not picked from disk, such as
+    /// `companion:<device name>`.
+    pub source: Option<String>,
+}
+
+/// One tag as read from the file: its standard meaning, raw key and value.
+pub type RawTag = (Option<StandardTagKey>, String, String);
+
+/// Pick the fields we keep from a file's tags; earlier tags win.
+pub fn select_tags(tags: &[RawTag]) -> AudioTags {
+    let find_std = |keys: &[StandardTagKey]| {
+        keys.iter().find_map(|wanted| {
+            tags.iter()
+                .find(|(key, _, value)| *key == Some(*wanted) && !value.trim().is_empty())
+                .map(|(_, _, value)| value.trim().to_string())
+        })
+    };
+    let find_raw = |needles: &[&str]| {
+        tags.iter()
+            .find(|(_, key, value)| {
+                let key = key.to_lowercase();
+                !value.trim().is_empty() && needles.iter().any(|needle| key.contains(needle))
+            })
+            .map(|(_, _, value)| value.trim().to_string())
+    };
+
+    AudioTags {
+        title: find_std(&[StandardTagKey::TrackTitle]),
+        artist: find_std(&[StandardTagKey::Artist, StandardTagKey::AlbumArtist]),
+        album: find_std(&[StandardTagKey::Album]),
+        recorded_at: find_std(&[
+            StandardTagKey::Date,
+            StandardTagKey::OriginalDate,
+            StandardTagKey::EncodingDate,
+        ])
+        .or_else(|| find_raw(&["creationdate", "creation_time"])),
+        device: find_raw(&["model", "make", "device"])
+            .or_else(|| find_std(&[StandardTagKey::Encoder])),
+    }
+}
+
+fn collect(revision: Option<&MetadataRevision>, out: &mut Vec<RawTag>) {
+    for tag in revision.map(|r| r.tags()).unwrap_or_default() {
+        out.push((tag.std_key, tag.key.clone(), tag.value.to_string()));
+    }
+}
+
+/// Read embedded tags and declared duration without decoding any audio.
+pub fn probe(path: &Path) -> Result<(AudioTags, Option<f64>)> {
+    let stream = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| Error::Decode(e.to_string()))?;
+
+    let mut tags = Vec::new();
+    // Tags found in the container take precedence over leading ID3 tags.
+    collect(probed.format.metadata().current(), &mut tags);
+    if let Some(metadata) = probed.metadata.get() {
+        collect(metadata.current(), &mut tags);
+    }
+
+    let duration = probed.format.default_track().and_then(|track| {
+        let params = &track.codec_params;
+        Some(params.n_frames? as f64 / params.sample_rate? as f64)
+    });
+    Ok((select_tags(&tags), duration))
+}
+
+const COLUMNS: &str =
+    "id, path, file_name, size, duration, title, artist, album, recorded_at, device, imported_at, project, source";
+
+fn from_row(row: &Row) -> rusqlite::Result<AudioFileRecord> {
+    Ok(AudioFileRecord {
+        id: row.get(0)?,
+        path: PathBuf::from(row.get::<_, String>(1)?),
+        file_name: row.get(2)?,
+        size: row.get::<_, i64>(3)? as u64,
+        duration: row.get(4)?,
+        tags: AudioTags {
+            title: row.get(5)?,
+            artist: row.get(6)?,
+            album: row.get(7)?,
+            recorded_at: row.get(8)?,
+            device: row.get(9)?,
+        },
+        imported_at: row.get(10)?,
+        project: row.get(11)?,
+        source: row.get(12)?,
+    })
+}
+
+pub fn get(conn: &Connection, id: &str) -> Result<AudioFileRecord> {
+    conn.query_row(
+        &format!("SELECT {} FROM audio_files WHERE id = ?1", COLUMNS),
+        [id],
+        from_row,
+    )
+    .optional()?
+    .ok_or_else(|| Error::NotFound(format!("audio file {}", id)))
+}
+
+/// Fail with `QUOTA_EXCEEDED` if importing `path` as audio file `id` would
+/// put `project` over its quota.
+pub fn check_quota(conn: &Connection, id: &str, path: &Path, project: &str) -> Result<()> {
+    let size = fs::metadata(path)?.len();
+    let duration = probe(path).ok().and_then(|(_, duration)| duration);
+    quota::check(conn, project, id, size, duration)
+}
+
+/// Record a file and its metadata under `id`, replacing an earlier import.
+///
+/// Transcripts of the file that have no title yet take the embedded one.
+/// Without a `project`, a file imported again stays in the project it was
+/// in. An import into a project fails if it would break the project's quota.
+pub fn import(
+    conn: &Connection,
+    id: &str,
+    path: &Path,
+    project: Option<&str>,
+) -> Result<AudioFileRecord> {
+    let size = fs::metadata(path)?.len();
+    // Unreadable tags should not block the import itself.
+    let (tags, duration) = probe(path).unwrap_or_default();
+    let project: Option<String> = match project {
+        Some(project) => Some(project.to_string()),
+        None => conn
+            .query_row(
+                "SELECT project FROM audio_files WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten(),
+    };
+    if let Some(project) = &project {
+        quota::check(conn, project, id, size, duration)?;
+    }
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    conn.execute(
+        "INSERT OR REPLACE INTO audio_files
+             (id, path, file_name, size, duration, title, artist, album, recorded_at, device, project)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            id,
+            path.to_string_lossy(),
+            file_name,
+            size as i64,
+            duration,
+            tags.title,
+            tags.artist,
+            tags.album,
+            tags.recorded_at,
+            tags.device,
+            project
+        ],
+    )?;
+    conn.execute(
+        "UPDATE transcriptions SET title = ?2 WHERE audio_file_id = ?1 AND title IS NULL AND ?2 IS NOT NULL",
+        params![id, tags.title],
+    )?;
+    get(conn, id)
+}
+
+/// Import a file from disk, returning its stored record. `id` defaults to a
+/// new identifier; pass the frontend's audio file id to link transcripts.
+#[tauri::command]
+pub async fn import_audio_file(
+    app: AppHandle,
+    path: PathBuf,
+    id: Option<String>,
+    project: Option<String>,
+) -> Result<AudioFileRecord> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let id = id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let project = project.as_deref().map(str::trim).filter(|p| !p.is_empty());
+        import(&db::connect(&app)?, &id, &path, project)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+/// Import a file added in the window from its contents, stored under the
+/// app data directory first, optionally into a `project`.
+#[tauri::command]
+pub async fn import_audio_data(
+    app: AppHandle,
+    id: String,
+    file_name: String,
+    data: Vec<u8>,
+    project: Option<String>,
+) -> Result<AudioFileRecord> {
+    tauri::async_runtime::spawn_blocking(move || {
+        // The id names a directory, so it must not be a path.
+        uuid::Uuid::parse_str(&id)
+            .map_err(|_| Error::InvalidInput(format!("{} is not an audio file id", id)))?;
+        let name = Path::new(&file_name)
+            .file_name()
+            .ok_or_else(|| Error::InvalidInput(format!("{} is not a file name", file_name)))?;
+        let dir = app
+            .path_resolver()
+            .app_data_dir()
+            .ok_or_else(|| Error::NotFound("app data directory".into()))?
+            .join("audio")
+            .join(&id);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(name);
+        fs::write(&path, data)?;
+        let project = project.as_deref().map(str::trim).filter(|p| !p.is_empty());
+        import(&db::connect(&app)?, &id, &path, project).map_err(|err| {
+            let _ = fs::remove_dir_all(&dir);
+            err
+        })
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[tauri::command]
+pub fn get_audio_file(app: AppHandle, id: String) -> Result<AudioFileRecord> {
+    get(&db::connect(&app)?, &id)
+}
+
+/// Move an imported file into `project`, or out of any with `None`. Fails
+/// if the file would put the project over its quota.
+#[tauri::command]
+pub fn set_audio_file_project(
+    app: AppHandle,
+    id: String,
+    project: Option<String>,
+) -> Result<AudioFileRecord> {
+    let conn = db::connect(&app)?;
+    let file = get(&conn, &id)?;
+    let project = project.as_deref().map(str::trim).filter(|p| !p.is_empty());
+    if let Some(project) = project {
+        quota::check(&conn, project, &id, file.size, file.duration)?;
+    }
+    conn.execute(
+        "UPDATE audio_files SET project = ?2 WHERE id = ?1",
+        params![id, project],
+    )?;
+    get(&conn, &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(key: Option<StandardTagKey>, raw: &str, value: &str) -> RawTag {
+        (key, raw.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn prefers_standard_keys_and_finds_device_in_raw_keys() {
+        let tags = select_tags(&[
+            tag(Some(StandardTagKey::Encoder), "TSSE", "Lavf58"),
+            tag(Some(StandardTagKey::TrackTitle), "TIT2", " Weekly sync "),
+            tag(None, "com.apple.quicktime.model", "iPhone 13"),
+            tag(Some(StandardTagKey::Date), "TDRC", "2024-03-05"),
+        ]);
+        assert_eq!(tags.title.as_deref(), Some("Weekly sync"));
+        assert_eq!(tags.recorded_at.as_deref(), Some("2024-03-05"));
+        assert_eq!(tags.device.as_deref(), Some("iPhone 13"));
+        assert_eq!(tags.artist, None);
+    }
+}
@@ -0,0 +1,150 @@
+//! Append-only record of destructive operations.
+//!
+//! Each entry names the action, the ids it affected and who ran it, so an
+//! install shared by several people can tell what was removed and by whom.
+//! Triggers on the table reject updates and deletes, and clearing the
+//! history leaves it in place. Actions recorded:
+//!
+//! - `transcription_deleted`, `transcriptions_merged`, `comment_deleted`
+//! - `audio_archived`, `audio_deleted`, `source_deleted`, `source_moved`
+//! - `api_key_changed`, `api_key_deleted`
+//! - `history_cleared`
+//!
+//! Each is written in the same transaction as the change it records.
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::Result;
+use crate::{db, editing};
+
+/// Entries returned when the filter sets no limit.
+const DEFAULT_LIMIT: u32 = 500;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: String,
+    pub action: String,
+    pub target_ids: Vec<String>,
+    pub actor: String,
+    pub detail: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditFilter {
+    pub action: Option<String>,
+    /// Entries that affected this id.
+    pub target_id: Option<String>,
+    pub actor: Option<String>,
+    /// First day to include, as `YYYY-MM-DD` in UTC like `created_at`.
+    pub date_from: Option<String>,
+    /// Last day to include, as `YYYY-MM-DD` in UTC.
+    pub date_to: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// Append an entry for `action` on `target_ids`, attributed to the
+/// configured editor.
+pub fn record(
+    conn: &Connection,
+    action: &str,
+    target_ids: &[&str],
+    detail: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO audit_log (id, action, target_ids, actor, detail)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            action,
+            serde_json::to_string(target_ids).unwrap(),
+            editing::editor_name(conn)?,
+            detail
+        ],
+    )?;
+    Ok(())
+}
+
+/// The WHERE clause and its parameters for `filter`.
+fn conditions(filter: &AuditFilter) -> (String, Vec<Value>) {
+    let mut sql = String::from("WHERE 1=1");
+    let mut values = Vec::new();
+    let mut add = |clause: &str, value: &Option<String>| {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            sql.push_str(clause);
+            values.push(Value::Text(value.to_string()));
+        }
+    };
+    add(" AND action = ?", &filter.action);
+    add(
+        " AND EXISTS (SELECT 1 FROM json_each(target_ids) WHERE value = ?)",
+        &filter.target_id,
+    );
+    add(" AND actor = ?", &filter.actor);
+    add(" AND date(created_at) >= date(?)", &filter.date_from);
+    add(" AND date(created_at) <= date(?)", &filter.date_to);
+    (sql, values)
+}
+
+/// Matching entries, newest first.
+#[tauri::command]
+pub fn get_audit_log(app: AppHandle, filter: Option<AuditFilter>) -> Result<Vec<AuditEntry>> {
+    let filter = filter.unwrap_or_default();
+    let (conditions, values) = conditions(&filter);
+    let conn = db::connect(&app)?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT id, action, target_ids, actor, detail, created_at FROM audit_log
+         {} ORDER BY created_at DESC, rowid DESC LIMIT {}",
+        conditions,
+        filter.limit.unwrap_or(DEFAULT_LIMIT)
+    ))?;
+    let entries = statement
+        .query_map(params_from_iter(values), |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                action: row.get(1)?,
+                target_ids: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+                actor: row.get(3)?,
+                detail: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_only_on_the_fields_set() {
+        let (sql, values) = conditions(&AuditFilter {
+            action: Some("transcription_deleted".into()),
+            target_id: Some("t1".into()),
+            actor: Some(String::new()),
+            ..Default::default()
+        });
+        assert_eq!(
+            sql,
+            "WHERE 1=1 AND action = ? \
+             AND EXISTS (SELECT 1 FROM json_each(target_ids) WHERE value = ?)"
+        );
+        assert_eq!(values.len(), 2);
+
+        let (sql, _) = conditions(&AuditFilter {
+            date_from: Some("2024-05-01".into()),
+            date_to: Some("2024-05-01".into()),
+            ..Default::default()
+        });
+        assert_eq!(
+            sql,
+            "WHERE 1=1 AND date(created_at) >= date(?) AND date(created_at) <= date(?)"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Exports written automatically when a transcription completes.
+//!
+//! Each rule in the `auto_export` preference writes the fresh transcript
+//! in one format, either next to the source file (the usual subtitle
+//! workflow: `talk.mp4` gets `talk.srt`) or into a chosen folder. An
+//! existing file is never replaced; the export gets a numbered name such
+//! as `talk-2.srt` instead. A rule that fails is logged and does not fail
+//! the transcription. The export template of the transcription's meeting
+//! type is written next to the source too, unless a rule already writes
+//! that format.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::bulk::{self, ExportFormat};
+use crate::error::{Error, Result};
+use crate::postprocess::PostProcessing;
+use crate::transcription::TranscriptionOutput;
+use crate::{crash, db};
+
+pub const RULES_PREFERENCE: &str = "auto_export";
+
+/// Template used when a rule sets none: the source file's own name.
+const DEFAULT_FILE_NAME: &str = "{source}";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoExportRule {
+    pub format: ExportFormat,
+    /// Folder to write into; `None` writes next to the source file.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+    /// File name template, as for exports; defaults to `{source}`.
+    #[serde(default)]
+    pub file_name: Option<String>,
+    #[serde(default)]
+    pub rules: PostProcessing,
+}
+
+pub fn rules(conn: &Connection) -> Result<Vec<AutoExportRule>> {
+    Ok(db::get_preference(conn, RULES_PREFERENCE)?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+/// Run the auto-export rules for a transcription of `source`. Returns the
+/// files written.
+pub fn run(app: &AppHandle, source: &Path, output: &TranscriptionOutput) -> Vec<PathBuf> {
+    let mut rules = match db::connect(app).and_then(|conn| rules(&conn)) {
+        Ok(rules) => rules,
+        Err(err) => {
+            crash::log(format!("auto-export rules unavailable: {}", err));
+            return Vec::new();
+        }
+    };
+    let template = output
+        .meeting_type
+        .as_ref()
+        .and_then(|meeting_type| meeting_type.export_template.as_deref())
+        .and_then(ExportFormat::from_template)
+        .filter(|format| rules.iter().all(|rule| rule.format != *format));
+    if let Some(format) = template {
+        rules.push(AutoExportRule {
+            format,
+            dir: None,
+            file_name: None,
+            rules: PostProcessing::default(),
+        });
+    }
+    let mut written = Vec::new();
+    for rule in rules {
+        let Some(dir) = rule.dir.as_deref().or(source.parent()) else {
+            continue;
+        };
+        let template = rule.file_name.as_deref().unwrap_or(DEFAULT_FILE_NAME);
+        match bulk::write_output(output, source, rule.format, dir, template, &rule.rules) {
+            Ok(path) => written.push(path),
+            Err(err) => crash::log(format!(
+                "auto-export of {} failed: {}",
+                source.display(),
+                err
+            )),
+        }
+    }
+    written
+}
+
+#[tauri::command]
+pub fn get_auto_export(app: AppHandle) -> Result<Vec<AutoExportRule>> {
+    rules(&db::connect(&app)?)
+}
+
+#[tauri::command]
+pub fn set_auto_export(app: AppHandle, rules: Vec<AutoExportRule>) -> Result<()> {
+    for rule in &rules {
+        if let Some(dir) = rule.dir.as_deref().filter(|dir| !dir.is_dir()) {
+            return Err(Error::NotFound(format!("folder {}", dir.display())));
+        }
+        if rule
+            .file_name
+            .as_deref()
+            .is_some_and(|template| template.trim().is_empty())
+        {
+            return Err(Error::InvalidInput(
+                "auto-export file name template is empty".into(),
+            ));
+        }
+    }
+    db::set_preference(
+        &db::connect(&app)?,
+        RULES_PREFERENCE,
+        &serde_json::to_string(&rules).unwrap(),
+    )
+}
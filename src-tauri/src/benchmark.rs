@@ -0,0 +1,179 @@
+//! Model benchmarking, to help pick a speed/accuracy tradeoff per machine.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sysinfo::System;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::{audio, evaluation, models, whisper};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelBenchmark {
+    pub model: String,
+    pub load_time_ms: u64,
+    pub transcribe_time_ms: u64,
+    /// Processing time divided by audio duration; below 1.0 is faster than realtime.
+    pub realtime_factor: f64,
+    /// Peak increase in resident memory over the baseline before loading.
+    pub peak_memory_bytes: u64,
+    pub word_error_rate: Option<f64>,
+    pub error: Option<String>,
+}
+
+fn resident_memory(system: &mut System) -> u64 {
+    let Ok(pid) = sysinfo::get_current_pid() else {
+        return 0;
+    };
+    system.refresh_process(pid);
+    system
+        .process(pid)
+        .map(|process| process.memory())
+        .unwrap_or(0)
+}
+
+/// Samples process memory on a background thread until dropped.
+struct MemorySampler {
+    peak: Arc<AtomicU64>,
+    running: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MemorySampler {
+    fn start() -> Self {
+        let peak = Arc::new(AtomicU64::new(0));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let handle = {
+            let peak = peak.clone();
+            let running = running.clone();
+            thread::spawn(move || {
+                let mut system = System::new();
+                while running.load(Ordering::Relaxed) {
+                    peak.fetch_max(resident_memory(&mut system), Ordering::Relaxed);
+                    thread::sleep(Duration::from_millis(100));
+                }
+            })
+        };
+
+        Self {
+            peak,
+            running,
+            handle: Some(handle),
+        }
+    }
+
+    fn stop(mut self) -> u64 {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        self.peak.load(Ordering::Relaxed)
+    }
+}
+
+fn run_one(
+    model: &str,
+    model_path: &std::path::Path,
+    pcm: &[f32],
+    options: &whisper::DecodeOptions,
+    reference: Option<&str>,
+) -> Result<ModelBenchmark> {
+    let baseline = resident_memory(&mut System::new());
+    let sampler = MemorySampler::start();
+
+    let started = Instant::now();
+    let ctx = whisper::load_context(model_path)?;
+    let load_time = started.elapsed();
+
+    let started = Instant::now();
+    let segments = whisper::transcribe(&ctx, pcm, options)?;
+    let transcribe_time = started.elapsed();
+
+    drop(ctx);
+    let peak = sampler.stop();
+
+    let duration = audio::duration_secs(pcm);
+    Ok(ModelBenchmark {
+        model: model.to_string(),
+        load_time_ms: load_time.as_millis() as u64,
+        transcribe_time_ms: transcribe_time.as_millis() as u64,
+        realtime_factor: if duration > 0.0 {
+            transcribe_time.as_secs_f64() / duration
+        } else {
+            0.0
+        },
+        peak_memory_bytes: peak.saturating_sub(baseline),
+        word_error_rate: reference.map(|reference| {
+            evaluation::word_error_rate(reference, &whisper::join_text(&segments))
+        }),
+        error: None,
+    })
+}
+
+/// Run a short sample through each model and report speed, memory and accuracy.
+///
+/// `models` defaults to every downloaded model. When `reference_path` points
+/// to a plain-text transcript of the sample, word error rate is included.
+/// A model that fails is reported with `error` set rather than aborting the run.
+#[tauri::command]
+pub async fn benchmark_models(
+    app: AppHandle,
+    sample_path: PathBuf,
+    models: Option<Vec<String>>,
+    reference_path: Option<PathBuf>,
+    language: Option<String>,
+) -> Result<Vec<ModelBenchmark>> {
+    let targets = match models {
+        Some(names) => names
+            .into_iter()
+            .map(|name| {
+                let path = models::model_path(&app, &name)?;
+                Ok((name, path))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => models::downloaded_models(&app)?
+            .into_iter()
+            .map(|model| (model.name, model.path))
+            .collect(),
+    };
+    if targets.is_empty() {
+        return Err(Error::NotFound("no downloaded models to benchmark".into()));
+    }
+
+    let reference = reference_path.map(fs::read_to_string).transpose()?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let pcm = audio::load_pcm(&sample_path)?;
+        let options = whisper::DecodeOptions {
+            language,
+            ..Default::default()
+        };
+
+        Ok(targets
+            .iter()
+            .map(|(name, path)| {
+                run_one(name, path, &pcm, &options, reference.as_deref()).unwrap_or_else(|err| {
+                    ModelBenchmark {
+                        model: name.clone(),
+                        load_time_ms: 0,
+                        transcribe_time_ms: 0,
+                        realtime_factor: 0.0,
+                        peak_memory_bytes: 0,
+                        word_error_rate: None,
+                        error: Some(err.to_string()),
+                    }
+                })
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
@@ -0,0 +1,851 @@
+//! Batch operations over many stored transcriptions.
+//!
+//! A batch runs as a single job on the queue and reports aggregate progress
+//! as each item finishes. Items fail independently: a missing transcription
+//! or a failed summary is recorded against its id and the batch carries on.
+//!
+//! Exported file names can follow a template such as `{date}_{title}_{lang}`;
+//! see [`expand_file_name`] for the fields. A diarized transcript can also be
+//! exported as one file per speaker, holding only what that speaker said.
+//! Reviewer comments follow the transcript in text and Markdown exports.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::comments::Comment;
+use crate::error::{Error, Result};
+use crate::jobs::{self, JobKind, JobPriority};
+use crate::postprocess::{self, PostProcessing};
+use crate::segments::{self, StoredSegment};
+use crate::transcription::TranscriptionOutput;
+use crate::{audit, comments, db, i18n, summarize};
+
+/// File name template used by exports that do not set their own.
+pub const FILE_NAME_PREFERENCE: &str = "export_file_name_template";
+
+/// Longest file name stem a template may produce.
+const MAX_STEM_CHARS: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Txt,
+    Markdown,
+    Srt,
+    Json,
+}
+
+impl ExportFormat {
+    /// The format a meeting type's export template names, e.g. `md`.
+    /// Templates this build cannot write, such as `docx`, name none.
+    pub fn from_template(name: &str) -> Option<ExportFormat> {
+        match name.trim().to_lowercase().as_str() {
+            "txt" | "text" => Some(ExportFormat::Txt),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "srt" => Some(ExportFormat::Srt),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Txt => "txt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Srt => "srt",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkAction {
+    Delete,
+    Tag {
+        tag: String,
+    },
+    Export {
+        /// Without a format, each transcription is written in its meeting
+        /// type's export template, else as plain text.
+        #[serde(default)]
+        format: Option<ExportFormat>,
+        dir: PathBuf,
+        /// Text rules for this export, on top of those applied when the
+        /// transcriptions were made.
+        #[serde(default)]
+        rules: PostProcessing,
+        /// File name template; defaults to the preference, else the
+        /// title and the start of the id.
+        #[serde(default)]
+        file_name: Option<String>,
+        /// Write one file per speaker instead of one per transcription.
+        #[serde(default)]
+        by_speaker: bool,
+    },
+    Summarize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemFailure {
+    pub id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchProgress {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: Vec<ItemFailure>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportItem {
+    id: String,
+    title: Option<String>,
+    text: String,
+    language: String,
+    duration: f64,
+    created_at: String,
+    segments: Vec<StoredSegment>,
+    #[serde(skip)]
+    model_used: String,
+    /// Source audio file name, without its extension.
+    #[serde(skip)]
+    source: Option<String>,
+    /// Set when the item holds one speaker's part of a transcript.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speaker: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    comments: Vec<Comment>,
+    /// Format named by the export template of its meeting type.
+    #[serde(skip)]
+    export_template: Option<ExportFormat>,
+}
+
+fn load(conn: &Connection, id: &str) -> Result<ExportItem> {
+    let item = conn
+        .query_row(
+            "SELECT t.title, t.text, t.language, t.duration, t.created_at, t.model_used,
+                    a.file_name, m.export_template
+             FROM transcriptions t LEFT JOIN audio_files a ON a.id = t.audio_file_id
+                  LEFT JOIN meeting_types m ON m.id = t.meeting_type_id
+             WHERE t.id = ?1",
+            [id],
+            |row| {
+                Ok(ExportItem {
+                    id: id.to_string(),
+                    title: row.get(0)?,
+                    text: row.get(1)?,
+                    language: row.get(2)?,
+                    duration: row.get(3)?,
+                    created_at: row.get(4)?,
+                    segments: Vec::new(),
+                    model_used: row.get(5)?,
+                    source: row
+                        .get::<_, Option<String>>(6)?
+                        .map(|name| file_stem(Path::new(&name))),
+                    speaker: None,
+                    comments: Vec::new(),
+                    export_template: row
+                        .get::<_, Option<String>>(7)?
+                        .as_deref()
+                        .and_then(ExportFormat::from_template),
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("transcription {}", id)))?;
+    Ok(ExportItem {
+        segments: segments::for_transcription(conn, id)?,
+        comments: comments::for_transcription(conn, id)?,
+        ..item
+    })
+}
+
+pub fn srt_time(secs: f64) -> String {
+    let millis = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Comments as `[00:01:05] Author: text` lines, in timeline order.
+fn comment_lines<'a>(item: &'a ExportItem) -> impl Iterator<Item = String> + 'a {
+    item.comments.iter().map(|comment| {
+        format!(
+            "[{}] {}: {}",
+            &srt_time(comment.anchor_ms as f64 / 1000.0)[..8],
+            comment.author,
+            comment.text
+        )
+    })
+}
+
+fn render(item: &ExportItem, format: ExportFormat) -> String {
+    let title = item.title.as_deref().unwrap_or(&item.id);
+    let title = match &item.speaker {
+        Some(speaker) => format!("{} — {}", title, speaker),
+        None => title.to_string(),
+    };
+    match format {
+        ExportFormat::Txt => {
+            let mut text = format!("{}\n\n{}\n", title, item.text.trim());
+            if !item.comments.is_empty() {
+                text.push_str(&format!("\n{}\n\n", i18n::t("heading-comments")));
+                for line in comment_lines(item) {
+                    text.push_str(&line);
+                    text.push('\n');
+                }
+            }
+            text
+        }
+        ExportFormat::Markdown => {
+            let mut text = format!(
+                "---\ntitle: \"{}\"\nlanguage: \"{}\"\ndate: \"{}\"\n---\n\n# {}\n\n{}\n",
+                title.replace('"', "'"),
+                item.language,
+                item.created_at,
+                title,
+                item.text.trim()
+            );
+            if !item.comments.is_empty() {
+                text.push_str(&format!("\n## {}\n\n", i18n::t("heading-comments")));
+                for line in comment_lines(item) {
+                    text.push_str(&format!("- {}\n", line));
+                }
+            }
+            text
+        }
+        ExportFormat::Srt => item
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(n, segment)| {
+                format!(
+                    "{}\n{} --> {}\n{}\n\n",
+                    n + 1,
+                    srt_time(segment.start),
+                    srt_time(segment.end),
+                    segment.text.trim()
+                )
+            })
+            .collect(),
+        ExportFormat::Json => serde_json::to_string_pretty(item).unwrap(),
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// `text` with everything but letters, digits and dashes replaced by `_`.
+pub fn safe_name(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Fill in a file name template. `{date}` and `{time}` are when the
+/// transcription was made, `{title}`, `{lang}`, `{model}` and `{id}` come
+/// from it, `{source}` is the name of its audio file and `{speaker}` the
+/// speaker of a per-speaker export. Field values are
+/// made safe for file names; unknown fields are left as written.
+fn expand_file_name(template: &str, item: &ExportItem) -> String {
+    let field = |name: &str| -> Option<String> {
+        let value = match name {
+            "date" => item.created_at.get(..10).unwrap_or(&item.created_at).into(),
+            "time" => item.created_at.get(11..16).unwrap_or("").replace(':', "-"),
+            "title" => item.title.clone().unwrap_or_else(|| item.id.clone()),
+            "lang" => item.language.clone(),
+            "model" => item.model_used.clone(),
+            "id" => item.id.clone(),
+            "source" => item.source.clone().unwrap_or_default(),
+            "speaker" => item.speaker.clone().unwrap_or_default(),
+            _ => return None,
+        };
+        Some(safe_name(value.trim()))
+    };
+
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let tail = &rest[open..];
+        match tail
+            .find('}')
+            .and_then(|close| Some((close, field(&tail[1..close])?)))
+        {
+            Some((close, value)) => {
+                out.push_str(&value);
+                rest = &tail[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    // Separators in the template itself must not escape the folder.
+    out.replace(['/', '\\'], "_")
+        .chars()
+        .take(MAX_STEM_CHARS)
+        .collect()
+}
+
+/// File name for an exported item: the expanded `template`, else its
+/// title when it has one, kept unique by the start of its id. Per-speaker
+/// items end in the speaker unless the template places it. A name that
+/// expands to nothing falls back to the id, or for output not saved yet,
+/// to the source file's name.
+fn file_name(item: &ExportItem, format: ExportFormat, template: Option<&str>) -> String {
+    let blank = |stem: &str| stem.trim_matches(['_', '.', ' ']).is_empty();
+    let fallback = || {
+        [Some(item.id.as_str()), item.source.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(safe_name)
+            .find(|stem| !blank(stem))
+            .unwrap_or_else(|| "transcript".into())
+    };
+    let stem = match (template, item.title.as_deref()) {
+        (Some(template), _) => expand_file_name(template, item),
+        (None, Some(title)) => {
+            let safe: String = safe_name(title).chars().take(80).collect();
+            format!("{}-{}", safe, &item.id[..item.id.len().min(8)])
+        }
+        (None, None) => fallback(),
+    };
+    let stem = if blank(&stem) { fallback() } else { stem };
+    match item.speaker.as_deref() {
+        Some(speaker) if !template.is_some_and(|template| template.contains("{speaker}")) => {
+            format!("{}_{}.{}", stem, safe_name(speaker), format.extension())
+        }
+        _ => format!("{}.{}", stem, format.extension()),
+    }
+}
+
+pub fn file_name_template(conn: &Connection) -> Result<Option<String>> {
+    Ok(db::get_preference(conn, FILE_NAME_PREFERENCE)?.filter(|t| !t.trim().is_empty()))
+}
+
+/// Apply `rules` and check `item` can be written as `format`.
+fn prepare(item: &mut ExportItem, format: ExportFormat, rules: &PostProcessing) -> Result<()> {
+    let language = postprocess::text_language(Some(&item.language), &item.text);
+    item.text = postprocess::apply_text(&item.text, &language, rules);
+    for segment in &mut item.segments {
+        segment.text = postprocess::apply_text(&segment.text, &language, rules);
+    }
+    if format == ExportFormat::Srt && item.segments.is_empty() {
+        return Err(Error::InvalidInput(
+            "SRT export needs a transcription with timed segments".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// One item per speaker, in order of first appearance, each holding that
+/// speaker's segments and a timestamped line per segment as its text, with
+/// the comments anchored inside those segments.
+/// Segments without a label go to the unknown speaker.
+fn split_by_speaker(item: ExportItem) -> Result<Vec<ExportItem>> {
+    if item
+        .segments
+        .iter()
+        .all(|segment| segment.speaker.is_none())
+    {
+        return Err(Error::InvalidInput(
+            "per-speaker export needs a transcription with speaker labels".into(),
+        ));
+    }
+    let unknown = i18n::t("unknown-speaker");
+    let mut speakers: Vec<(String, Vec<StoredSegment>)> = Vec::new();
+    for segment in &item.segments {
+        let speaker = segment.speaker.as_deref().unwrap_or(&unknown);
+        match speakers.iter_mut().find(|(name, _)| name == speaker) {
+            Some((_, segments)) => segments.push(segment.clone()),
+            None => speakers.push((speaker.to_string(), vec![segment.clone()])),
+        }
+    }
+    Ok(speakers
+        .into_iter()
+        .map(|(speaker, segments)| ExportItem {
+            comments: item
+                .comments
+                .iter()
+                .filter(|comment| {
+                    let at = comment.anchor_ms as f64 / 1000.0;
+                    segments
+                        .iter()
+                        .any(|segment| segment.start <= at && at < segment.end)
+                })
+                .cloned()
+                .collect(),
+            text: segments
+                .iter()
+                .map(|segment| {
+                    format!(
+                        "[{}] {}",
+                        &srt_time(segment.start)[..8],
+                        segment.text.trim()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            segments,
+            speaker: Some(speaker),
+            ..item.clone()
+        })
+        .collect())
+}
+
+fn export(
+    conn: &Connection,
+    id: &str,
+    format: Option<ExportFormat>,
+    dir: &Path,
+    rules: &PostProcessing,
+    template: Option<&str>,
+    by_speaker: bool,
+) -> Result<()> {
+    let mut item = load(conn, id)?;
+    let format = format.or(item.export_template).unwrap_or(ExportFormat::Txt);
+    prepare(&mut item, format, rules)?;
+    let items = if by_speaker {
+        split_by_speaker(item)?
+    } else {
+        vec![item]
+    };
+    for item in items {
+        let mut path = dir.join(file_name(&item, format, template));
+        // A template can give several items the same name; later ones keep
+        // theirs apart with the start of their id.
+        if template.is_some() && path.exists() {
+            path = dir.join(format!(
+                "{}-{}.{}",
+                file_stem(&path),
+                &item.id[..item.id.len().min(8)],
+                format.extension()
+            ));
+        }
+        fs::write(path, render(&item, format))?;
+    }
+    Ok(())
+}
+
+/// Write a transcription that has not been saved yet, named from
+/// `template` with `source` as its audio file. Returns the path written.
+pub fn write_output(
+    output: &TranscriptionOutput,
+    source: &Path,
+    format: ExportFormat,
+    dir: &Path,
+    template: &str,
+    rules: &PostProcessing,
+) -> Result<PathBuf> {
+    let mut item = ExportItem {
+        id: String::new(),
+        title: Some(file_stem(source)),
+        text: output.text.clone(),
+        language: output.language.clone(),
+        duration: output.duration,
+        created_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        segments: output
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(position, segment)| StoredSegment {
+                id: String::new(),
+                transcription_id: String::new(),
+                position: position as i64,
+                speaker: output
+                    .speakers
+                    .as_ref()
+                    .and_then(|speakers| speakers.get(position).cloned()),
+                text: segment.text.clone(),
+                start: segment.start,
+                end: segment.end,
+                confidence: segment.confidence.map(f64::from),
+                words: segment.words.clone(),
+            })
+            .collect(),
+        model_used: output.model_used.clone(),
+        source: Some(file_stem(source)),
+        speaker: None,
+        comments: Vec::new(),
+        export_template: None,
+    };
+    prepare(&mut item, format, rules)?;
+    let path = free_path(dir.join(file_name(&item, format, Some(template))));
+    fs::write(&path, render(&item, format))?;
+    Ok(path)
+}
+
+/// `path`, or the first of `name-2.ext`, `name-3.ext`, … that does not
+/// exist yet, so an export never replaces a file such as subtitles the
+/// user corrected by hand.
+pub fn free_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let stem = file_stem(&path);
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{}-{}{}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
+
+fn delete(conn: &Connection, id: &str) -> Result<()> {
+    let title: Option<String> = conn
+        .query_row(
+            "SELECT COALESCE(t.title, a.title) FROM transcriptions t
+             LEFT JOIN audio_files a ON a.id = t.audio_file_id WHERE t.id = ?1",
+            [id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("transcription {}", id)))?;
+    let tx = conn.unchecked_transaction()?;
+    // Summaries predate cascading deletes; the other tables cascade.
+    tx.execute("DELETE FROM summaries WHERE transcription_id = ?1", [id])?;
+    tx.execute("DELETE FROM transcriptions WHERE id = ?1", [id])?;
+    audit::record(&tx, "transcription_deleted", &[id], title.as_deref())?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn tag(conn: &Connection, id: &str, tag: &str) -> Result<()> {
+    db::transcription_text(conn, id)?;
+    conn.execute(
+        "INSERT OR IGNORE INTO transcription_tags (transcription_id, tag) VALUES (?1, ?2)",
+        params![id, tag],
+    )?;
+    Ok(())
+}
+
+/// Apply `action` to each transcription in turn, passing the progress so
+/// far to `on_progress` after every item.
+pub fn run(
+    app: &AppHandle,
+    ids: &[String],
+    action: &BulkAction,
+    mut on_progress: impl FnMut(&BatchProgress),
+) -> Result<BatchProgress> {
+    let conn = db::connect(app)?;
+    if let BulkAction::Export { dir, .. } = action {
+        fs::create_dir_all(dir)?;
+    }
+    let template = match action {
+        BulkAction::Export {
+            file_name: Some(template),
+            ..
+        } => Some(template.clone()),
+        BulkAction::Export { .. } => file_name_template(&conn)?,
+        _ => None,
+    };
+
+    let mut progress = BatchProgress {
+        total: ids.len(),
+        ..Default::default()
+    };
+    on_progress(&progress);
+    for id in ids {
+        let outcome = match action {
+            BulkAction::Delete => delete(&conn, id),
+            BulkAction::Tag { tag: name } => tag(&conn, id, name),
+            BulkAction::Export {
+                format,
+                dir,
+                rules,
+                by_speaker,
+                ..
+            } => export(
+                &conn,
+                id,
+                *format,
+                dir,
+                rules,
+                template.as_deref(),
+                *by_speaker,
+            ),
+            BulkAction::Summarize => summarize::summarize(app, id, None, None, None).map(|_| ()),
+        };
+        match outcome {
+            Ok(()) => progress.succeeded += 1,
+            Err(err) => progress.failed.push(ItemFailure {
+                id: id.clone(),
+                error: err.to_string(),
+            }),
+        }
+        on_progress(&progress);
+    }
+    Ok(progress)
+}
+
+fn enqueue(app: &AppHandle, ids: Vec<String>, action: BulkAction) -> Result<String> {
+    if ids.is_empty() {
+        return Err(Error::InvalidInput("no transcriptions selected".into()));
+    }
+    Ok(jobs::enqueue(
+        app,
+        JobKind::Bulk { ids, action },
+        JobPriority::Interactive,
+    ))
+}
+
+/// Delete transcriptions with their summaries, segments and comments.
+/// Returns the id of the batch job.
+#[tauri::command]
+pub fn delete_transcriptions(app: AppHandle, ids: Vec<String>) -> Result<String> {
+    enqueue(&app, ids, BulkAction::Delete)
+}
+
+/// Delete one transcription right away, outside the job queue.
+#[tauri::command]
+pub fn delete_transcription(app: AppHandle, id: String) -> Result<()> {
+    delete(&db::connect(&app)?, &id)
+}
+
+/// Remove every transcription, audio file and preference. The audit log
+/// is kept and records the reset.
+#[tauri::command]
+pub fn clear_all_data(app: AppHandle) -> Result<()> {
+    let mut conn = db::connect(&app)?;
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    // Recorded first, while the editor name is still in the preferences.
+    audit::record(&tx, "history_cleared", &[], None)?;
+    for table in [
+        "comments",
+        "segments",
+        "summaries",
+        "transcriptions",
+        "audio_files",
+        "user_preferences",
+    ] {
+        tx.execute(&format!("DELETE FROM {}", table), [])?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn tag_transcriptions(app: AppHandle, ids: Vec<String>, tag: String) -> Result<String> {
+    let tag = tag.trim().to_string();
+    if tag.is_empty() {
+        return Err(Error::InvalidInput("tag must not be empty".into()));
+    }
+    enqueue(&app, ids, BulkAction::Tag { tag })
+}
+
+/// Write each transcription to its own file in `dir`, or each of its
+/// speakers when `by_speaker` is set, named by the `file_name` template if
+/// given. Without a `format`, each uses its meeting type's export template.
+#[tauri::command]
+pub fn export_transcriptions(
+    app: AppHandle,
+    ids: Vec<String>,
+    format: Option<ExportFormat>,
+    dir: PathBuf,
+    rules: Option<PostProcessing>,
+    file_name: Option<String>,
+    by_speaker: Option<bool>,
+) -> Result<String> {
+    enqueue(
+        &app,
+        ids,
+        BulkAction::Export {
+            format,
+            dir,
+            rules: rules.unwrap_or_default(),
+            file_name: file_name.filter(|template| !template.trim().is_empty()),
+            by_speaker: by_speaker.unwrap_or(false),
+        },
+    )
+}
+
+/// Summarize each transcription with the default prompt.
+#[tauri::command]
+pub fn summarize_transcriptions(app: AppHandle, ids: Vec<String>) -> Result<String> {
+    enqueue(&app, ids, BulkAction::Summarize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str) -> ExportItem {
+        ExportItem {
+            id: "a1b2c3d4e5".into(),
+            title: Some(title.into()),
+            text: String::new(),
+            language: "en".into(),
+            duration: 0.0,
+            created_at: "2024-03-05 09:30:00".into(),
+            segments: Vec::new(),
+            model_used: "whisper-base".into(),
+            source: None,
+            speaker: None,
+            comments: Vec::new(),
+            export_template: None,
+        }
+    }
+
+    #[test]
+    fn renders_srt_from_segments() {
+        let item = ExportItem {
+            text: "Hello. Welcome back.".into(),
+            source: Some("zoom_0".into()),
+            segments: vec![StoredSegment {
+                id: String::new(),
+                transcription_id: "a1b2c3d4e5".into(),
+                position: 0,
+                speaker: None,
+                text: " Hello.".into(),
+                start: 61.5,
+                end: 63.25,
+                confidence: None,
+                words: Vec::new(),
+            }],
+            ..item("Weekly sync")
+        };
+        assert_eq!(
+            render(&item, ExportFormat::Srt),
+            "1\n00:01:01,500 --> 00:01:03,250\nHello.\n\n"
+        );
+        assert_eq!(
+            file_name(&item, ExportFormat::Srt, None),
+            "Weekly_sync-a1b2c3d4.srt"
+        );
+    }
+
+    #[test]
+    fn lists_comments_after_text_exports() {
+        let item = ExportItem {
+            text: "Hello.".into(),
+            comments: vec![Comment {
+                id: "c1".into(),
+                transcription_id: "a1b2c3d4e5".into(),
+                anchor_ms: 65_400,
+                author: "Ann".into(),
+                text: "Check this figure.".into(),
+                created_at: String::new(),
+                updated_at: String::new(),
+            }],
+            ..item("Weekly sync")
+        };
+        assert_eq!(
+            render(&item, ExportFormat::Txt),
+            "Weekly sync\n\nHello.\n\nComments\n\n[00:01:05] Ann: Check this figure.\n"
+        );
+        assert!(render(&item, ExportFormat::Markdown)
+            .ends_with("Hello.\n\n## Comments\n\n- [00:01:05] Ann: Check this figure.\n"));
+    }
+
+    #[test]
+    fn expands_file_name_templates() {
+        let item = ExportItem {
+            source: Some("zoom_0".into()),
+            ..item("Board call: Q3/Q4")
+        };
+        assert_eq!(
+            file_name(&item, ExportFormat::Txt, Some("{date}_{title}_{lang}")),
+            "2024-03-05_Board_call__Q3_Q4_en.txt"
+        );
+        assert_eq!(
+            expand_file_name("{source} {time} {unknown} {", &item),
+            "zoom_0 09-30 {unknown} {"
+        );
+        assert_eq!(
+            file_name(&item, ExportFormat::Srt, Some("{nothing}/")),
+            "{nothing}_.srt"
+        );
+
+        // Output not saved yet has no id to fall back to.
+        let unsaved = ExportItem {
+            id: String::new(),
+            ..item
+        };
+        assert_eq!(
+            file_name(&unsaved, ExportFormat::Srt, Some("{speaker}")),
+            "zoom_0.srt"
+        );
+    }
+
+    #[test]
+    fn never_overwrites_an_existing_export() {
+        let dir = std::env::temp_dir().join(format!("bulk-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("talk.srt");
+        assert_eq!(free_path(path.clone()), path);
+        fs::write(&path, "corrected").unwrap();
+        fs::write(dir.join("talk-2.srt"), "").unwrap();
+        assert_eq!(free_path(path), dir.join("talk-3.srt"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn splits_by_speaker() {
+        let segment = |position: i64, speaker: Option<&str>, start: f64| StoredSegment {
+            id: String::new(),
+            transcription_id: "a1b2c3d4e5".into(),
+            position,
+            speaker: speaker.map(String::from),
+            text: format!(" Line {}.", position),
+            start,
+            end: start + 2.0,
+            confidence: None,
+            words: Vec::new(),
+        };
+        let item = ExportItem {
+            segments: vec![
+                segment(0, Some("Host"), 0.0),
+                segment(1, Some("Guest"), 2.0),
+                segment(2, None, 4.0),
+                segment(3, Some("Guest"), 65.0),
+            ],
+            ..item("Interview")
+        };
+        let parts = split_by_speaker(item.clone()).unwrap();
+        let names: Vec<_> = parts.iter().map(|part| part.speaker.as_deref()).collect();
+        assert_eq!(names, [Some("Host"), Some("Guest"), Some("Unknown")]);
+        assert_eq!(parts[1].text, "[00:00:02] Line 1.\n\n[00:01:05] Line 3.");
+        assert_eq!(
+            file_name(&parts[1], ExportFormat::Txt, None),
+            "Interview-a1b2c3d4_Guest.txt"
+        );
+        assert_eq!(
+            file_name(&parts[1], ExportFormat::Txt, Some("{speaker}-{date}")),
+            "Guest-2024-03-05.txt"
+        );
+
+        let unlabeled = ExportItem {
+            segments: vec![segment(0, None, 0.0)],
+            ..item
+        };
+        assert!(split_by_speaker(unlabeled).is_err());
+    }
+}
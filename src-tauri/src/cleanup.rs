@@ -0,0 +1,443 @@
+//! Opt-in removal of source audio once its transcript is done.
+//!
+//! For workflows where only the text matters, a recording can be deleted or
+//! moved away after its transcription (and summary, when one is expected)
+//! has succeeded and the transcript's confidence clears a threshold.
+//! Removal waits out a grace period, during which it can be cancelled, and
+//! is written to the audit log. Settings come from the watch folder the
+//! recording arrived through, else the global preference; both keep the
+//! audio by default.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::archive::{self, ArchiveMode};
+use crate::error::{Error, Result};
+use crate::{audio_files, audit, crash, db};
+
+pub const SETTINGS_PREFERENCE: &str = "source_cleanup";
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupAction {
+    #[default]
+    Keep,
+    Delete,
+    /// Move the file into `destination`, e.g. a slower backup drive.
+    Move,
+}
+
+impl CleanupAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            CleanupAction::Keep => "keep",
+            CleanupAction::Delete => "delete",
+            CleanupAction::Move => "move",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "delete" => CleanupAction::Delete,
+            "move" => CleanupAction::Move,
+            _ => CleanupAction::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SourceCleanup {
+    pub action: CleanupAction,
+    /// Folder recordings are moved into, for `move`.
+    pub destination: Option<PathBuf>,
+    /// Lowest transcript confidence, between 0 and 1, that allows removal.
+    pub min_confidence: f64,
+    /// Hours between success and removal.
+    pub grace_hours: u32,
+    /// Also wait for a summary. Recordings from watch folders that
+    /// summarize automatically always wait for it.
+    pub require_summary: bool,
+}
+
+impl Default for SourceCleanup {
+    fn default() -> Self {
+        Self {
+            action: CleanupAction::Keep,
+            destination: None,
+            min_confidence: 0.8,
+            grace_hours: 24,
+            require_summary: false,
+        }
+    }
+}
+
+impl SourceCleanup {
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.min_confidence) {
+            return Err(Error::InvalidInput(
+                "minimum confidence must be between 0 and 1".into(),
+            ));
+        }
+        if self.action == CleanupAction::Move
+            && !self.destination.as_deref().is_some_and(Path::is_dir)
+        {
+            return Err(Error::InvalidInput(
+                "moving source audio needs an existing destination folder".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Why a transcript does not qualify for removal, if it does not.
+    fn refusal(&self, confidence: Option<f64>, summarized: bool) -> Option<String> {
+        match confidence {
+            _ if self.action == CleanupAction::Keep => Some("cleanup is off".into()),
+            _ if self.require_summary && !summarized => Some("it has no summary yet".into()),
+            None => Some("its confidence is unknown".into()),
+            Some(confidence) if confidence < self.min_confidence => Some(format!(
+                "its confidence {:.2} is below {:.2}",
+                confidence, self.min_confidence
+            )),
+            Some(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupStatus {
+    Scheduled,
+    Done,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledCleanup {
+    pub audio_file_id: String,
+    pub transcription_id: String,
+    pub action: CleanupAction,
+    pub destination: Option<PathBuf>,
+    /// Unix seconds.
+    pub due_at: u64,
+    pub status: CleanupStatus,
+    pub error: Option<String>,
+}
+
+const COLUMNS: &str = "audio_file_id, transcription_id, action, destination, due_at, status, error";
+
+fn from_row(row: &Row) -> rusqlite::Result<ScheduledCleanup> {
+    Ok(ScheduledCleanup {
+        audio_file_id: row.get(0)?,
+        transcription_id: row.get(1)?,
+        action: CleanupAction::parse(&row.get::<_, String>(2)?),
+        destination: row.get::<_, Option<String>>(3)?.map(PathBuf::from),
+        due_at: row.get::<_, i64>(4)? as u64,
+        status: match row.get::<_, String>(5)?.as_str() {
+            "done" => CleanupStatus::Done,
+            "cancelled" => CleanupStatus::Cancelled,
+            "failed" => CleanupStatus::Failed,
+            _ => CleanupStatus::Scheduled,
+        },
+        error: row.get(6)?,
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn settings(conn: &Connection) -> Result<SourceCleanup> {
+    Ok(db::get_preference(conn, SETTINGS_PREFERENCE)?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+/// A watch folder's own settings and whether it summarizes automatically.
+fn folder_settings(conn: &Connection, folder_id: &str) -> Result<(Option<SourceCleanup>, bool)> {
+    let (cleanup, auto_summary): (Option<String>, bool) = conn
+        .query_row(
+            "SELECT cleanup, auto_summary FROM watch_folders WHERE id = ?1",
+            [folder_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("watch folder {}", folder_id)))?;
+    Ok((
+        cleanup.and_then(|value| serde_json::from_str(&value).ok()),
+        auto_summary,
+    ))
+}
+
+/// The stored confidence of a transcript, else the mean of its segments'.
+fn confidence(conn: &Connection, transcription_id: &str) -> Result<Option<f64>> {
+    Ok(conn
+        .query_row(
+            "SELECT COALESCE(t.confidence,
+                 (SELECT AVG(s.confidence) FROM segments s WHERE s.transcription_id = t.id))
+             FROM transcriptions t WHERE t.id = ?1",
+            [transcription_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("transcription {}", transcription_id)))?)
+}
+
+fn get(conn: &Connection, audio_file_id: &str) -> Result<Option<ScheduledCleanup>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM source_cleanup WHERE audio_file_id = ?1",
+                COLUMNS
+            ),
+            [audio_file_id],
+            from_row,
+        )
+        .optional()?)
+}
+
+/// Move a file, copying when the destination is on another volume.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        return Err(Error::InvalidInput(format!(
+            "{} already exists",
+            to.display()
+        )));
+    }
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+fn remove(app: &AppHandle, conn: &Connection, cleanup: &ScheduledCleanup) -> Result<()> {
+    let id = cleanup.audio_file_id.as_str();
+    match cleanup.action {
+        CleanupAction::Keep => Ok(()),
+        CleanupAction::Delete => {
+            archive::archive(
+                conn,
+                &archive::archive_dir(app, conn)?,
+                id,
+                ArchiveMode::Delete,
+                "source_deleted",
+                &[id, &cleanup.transcription_id],
+            )?;
+            Ok(())
+        }
+        CleanupAction::Move => {
+            let file = audio_files::get(conn, id)?;
+            let destination = cleanup
+                .destination
+                .as_deref()
+                .ok_or_else(|| Error::InvalidInput("no destination folder".into()))?
+                .join(&file.file_name);
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "UPDATE audio_files SET path = ?2 WHERE id = ?1",
+                params![id, destination.to_string_lossy()],
+            )?;
+            audit::record(
+                &tx,
+                "source_moved",
+                &[id, &cleanup.transcription_id],
+                Some(&format!(
+                    "{} -> {}",
+                    file.path.display(),
+                    destination.display()
+                )),
+            )?;
+            move_file(&file.path, &destination)?;
+            // Dropping the transaction on a failed move rolls the row back.
+            tx.commit()?;
+            Ok(())
+        }
+    }
+}
+
+/// Carry out every removal whose grace period has passed.
+fn sweep(app: &AppHandle) -> Result<()> {
+    let conn = db::connect(app)?;
+    let due = conn
+        .prepare(&format!(
+            "SELECT {} FROM source_cleanup WHERE status = 'scheduled' AND due_at <= ?1",
+            COLUMNS
+        ))?
+        .query_map([now() as i64], from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for cleanup in due {
+        let (status, error) = match remove(app, &conn, &cleanup) {
+            Ok(()) => ("done", None),
+            Err(err) => {
+                crash::log(format!(
+                    "source cleanup of {} failed: {}",
+                    cleanup.audio_file_id, err
+                ));
+                ("failed", Some(err.to_string()))
+            }
+        };
+        conn.execute(
+            "UPDATE source_cleanup SET status = ?2, error = ?3 WHERE audio_file_id = ?1",
+            params![cleanup.audio_file_id, status, error],
+        )?;
+    }
+    Ok(())
+}
+
+/// Start carrying out scheduled removals. Call once during app setup.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(SWEEP_INTERVAL);
+        if let Err(err) = sweep(&app) {
+            crash::log(format!("source cleanup unavailable: {}", err));
+        }
+    });
+}
+
+/// Report that a transcription, and its summary if one was made, has been
+/// saved. Schedules removal of its source audio when the applicable
+/// settings allow it; returns the schedule, or `None` if the audio is kept.
+#[tauri::command]
+pub fn source_processed(
+    app: AppHandle,
+    transcription_id: String,
+    watch_folder_id: Option<String>,
+) -> Result<Option<ScheduledCleanup>> {
+    schedule(&app, &transcription_id, watch_folder_id.as_deref())
+}
+
+/// [`source_processed`] for transcripts the backend saves itself, such as
+/// finished queue jobs.
+pub fn schedule(
+    app: &AppHandle,
+    transcription_id: &str,
+    watch_folder_id: Option<&str>,
+) -> Result<Option<ScheduledCleanup>> {
+    let conn = db::connect(app)?;
+    let (folder, auto_summary) = match watch_folder_id {
+        Some(id) => folder_settings(&conn, id)?,
+        None => (None, false),
+    };
+    let mut settings = match folder {
+        Some(settings) => settings,
+        None => settings(&conn)?,
+    };
+    settings.require_summary |= auto_summary;
+
+    let summarized: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM summaries WHERE transcription_id = ?1)",
+        [transcription_id],
+        |row| row.get(0),
+    )?;
+    if let Some(reason) = settings.refusal(confidence(&conn, transcription_id)?, summarized) {
+        if settings.action != CleanupAction::Keep {
+            crash::log(format!(
+                "keeping the source audio of {}: {}",
+                transcription_id, reason
+            ));
+        }
+        return Ok(None);
+    }
+
+    let audio_file_id: String = conn.query_row(
+        "SELECT audio_file_id FROM transcriptions WHERE id = ?1",
+        [transcription_id],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT OR REPLACE INTO source_cleanup
+             (audio_file_id, transcription_id, action, destination, due_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            audio_file_id,
+            transcription_id,
+            settings.action.as_str(),
+            settings
+                .destination
+                .as_deref()
+                .map(|p| p.to_string_lossy().to_string()),
+            (now() + settings.grace_hours as u64 * 60 * 60) as i64
+        ],
+    )?;
+    get(&conn, &audio_file_id)
+}
+
+#[tauri::command]
+pub fn get_source_cleanup(app: AppHandle) -> Result<SourceCleanup> {
+    settings(&db::connect(&app)?)
+}
+
+#[tauri::command]
+pub fn set_source_cleanup(app: AppHandle, settings: SourceCleanup) -> Result<()> {
+    settings.validate()?;
+    db::set_preference(
+        &db::connect(&app)?,
+        SETTINGS_PREFERENCE,
+        &serde_json::to_string(&settings).unwrap(),
+    )
+}
+
+/// Scheduled and past removals, soonest due first.
+#[tauri::command]
+pub fn list_source_cleanups(app: AppHandle) -> Result<Vec<ScheduledCleanup>> {
+    let conn = db::connect(&app)?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM source_cleanup ORDER BY due_at",
+        COLUMNS
+    ))?;
+    let cleanups = statement
+        .query_map([], from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(cleanups)
+}
+
+/// Keep a recording whose removal is still in its grace period.
+#[tauri::command]
+pub fn cancel_source_cleanup(app: AppHandle, audio_file_id: String) -> Result<()> {
+    let conn = db::connect(&app)?;
+    let cancelled = conn.execute(
+        "UPDATE source_cleanup SET status = 'cancelled'
+         WHERE audio_file_id = ?1 AND status = 'scheduled'",
+        [&audio_file_id],
+    )?;
+    if cancelled == 0 {
+        return Err(Error::NotFound(format!(
+            "scheduled cleanup of {}",
+            audio_file_id
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_confident_finished_transcripts_qualify() {
+        let settings = SourceCleanup {
+            action: CleanupAction::Delete,
+            require_summary: true,
+            ..Default::default()
+        };
+        assert_eq!(settings.refusal(Some(0.9), true), None);
+        assert!(settings.refusal(Some(0.9), false).is_some());
+        assert!(settings.refusal(Some(0.5), true).is_some());
+        assert!(settings.refusal(None, true).is_some());
+        assert!(SourceCleanup::default().refusal(Some(1.0), true).is_some());
+    }
+}
@@ -0,0 +1,279 @@
+//! Audio clips cut from a transcription's source audio.
+//!
+//! Clips are 16 kHz mono WAV, the format transcription works in. Audio
+//! that was archived is read back from its compressed copy, and a split or
+//! merged transcription reads the stretches of audio it covers.
+//!
+//! Speaker stems are whole-length clips, one per speaker of a diarized
+//! transcript, that keep only that speaker's turns so they stay in sync
+//! with each other. When the recording has one party per channel, a
+//! speaker can be mapped to a channel and gets it unmasked instead.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OptionalExtension};
+use tauri::AppHandle;
+
+use crate::archive::{self, ArchiveMode};
+use crate::audio::{self, WHISPER_SAMPLE_RATE};
+use crate::error::{Error, Result};
+use crate::{audio_files, bulk, db, i18n, parts, segments, trim};
+
+/// Silence kept on each side so words at the edges are not clipped.
+pub const PADDING_SECS: f64 = 0.25;
+
+fn audio_file_id(conn: &Connection, transcription_id: &str) -> Result<String> {
+    conn.query_row(
+        "SELECT audio_file_id FROM transcriptions WHERE id = ?1",
+        [transcription_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| Error::NotFound(format!("transcription {}", transcription_id)))
+}
+
+/// A transcription's audio from `load`, which decodes one audio file. A
+/// split or merged transcription gets the stretches of each file it
+/// covers, placed at their times in the transcription.
+fn assemble(
+    conn: &Connection,
+    transcription_id: &str,
+    load: impl Fn(&str) -> Result<Vec<f32>>,
+) -> Result<Vec<f32>> {
+    let parts = parts::stored(conn, transcription_id)?;
+    if parts.is_empty() {
+        return load(&audio_file_id(conn, transcription_id)?);
+    }
+    let samples = |secs: f64| (secs.max(0.0) * WHISPER_SAMPLE_RATE as f64) as usize;
+    let mut pcm = Vec::new();
+    for part in parts {
+        let source = load(&part.audio_file_id)?;
+        let from = samples(part.audio_start).min(source.len());
+        let to = samples(part.audio_start + part.duration).clamp(from, source.len());
+        pcm.resize(pcm.len().max(samples(part.start)), 0.0);
+        pcm.extend_from_slice(&source[from..to]);
+    }
+    Ok(pcm)
+}
+
+/// The file an audio file is read from: itself, or its compressed copy if
+/// it was archived. True for a compressed copy.
+fn stored_file(
+    conn: &Connection,
+    transcription_id: &str,
+    audio_file_id: &str,
+) -> Result<(PathBuf, bool)> {
+    match archive::record(conn, audio_file_id)? {
+        Some(record) => match (record.state, record.archive_path) {
+            (ArchiveMode::Compress, Some(path)) => Ok((path, true)),
+            _ => Err(Error::NotFound(format!(
+                "audio of transcription {} (it was deleted when archived)",
+                transcription_id
+            ))),
+        },
+        None => Ok((audio_files::get(conn, audio_file_id)?.path, false)),
+    }
+}
+
+/// Source audio of a transcription as 16 kHz mono samples.
+pub fn source_pcm(conn: &Connection, transcription_id: &str) -> Result<Vec<f32>> {
+    assemble(conn, transcription_id, |audio_file_id| {
+        match stored_file(conn, transcription_id, audio_file_id)? {
+            (path, true) => archive::decode(&path),
+            (path, false) => audio::load_pcm(&path),
+        }
+    })
+}
+
+/// The files [`source_pcm`] reads, for checks made before decoding them.
+pub fn source_files(conn: &Connection, transcription_id: &str) -> Result<Vec<PathBuf>> {
+    let parts = parts::stored(conn, transcription_id)?;
+    let ids = if parts.is_empty() {
+        vec![audio_file_id(conn, transcription_id)?]
+    } else {
+        parts.into_iter().map(|part| part.audio_file_id).collect()
+    };
+    ids.iter()
+        .map(|id| Ok(stored_file(conn, transcription_id, id)?.0))
+        .collect()
+}
+
+/// Source audio with the transcription's trim applied: silence before the
+/// trim start and nothing after its end, so clip times need no shifting.
+pub fn trimmed_pcm(conn: &Connection, transcription_id: &str) -> Result<Vec<f32>> {
+    let mut pcm = source_pcm(conn, transcription_id)?;
+    apply_trim(conn, transcription_id, &mut pcm)?;
+    Ok(pcm)
+}
+
+fn apply_trim(conn: &Connection, transcription_id: &str, pcm: &mut Vec<f32>) -> Result<()> {
+    let kept = trim::for_transcription(conn, transcription_id)?
+        .sample_range(pcm.len(), WHISPER_SAMPLE_RATE);
+    pcm.truncate(kept.end);
+    pcm[..kept.start].fill(0.0);
+    Ok(())
+}
+
+/// One channel of a transcription's original audio, trimmed. Archived
+/// copies are downmixed, so this needs the original file.
+fn channel_pcm(conn: &Connection, transcription_id: &str, channel: usize) -> Result<Vec<f32>> {
+    let mut pcm = assemble(conn, transcription_id, |audio_file_id| {
+        if archive::record(conn, audio_file_id)?.is_some() {
+            return Err(Error::InvalidInput(
+                "channel mapping needs the original audio, which was archived".into(),
+            ));
+        }
+        audio::load_channel_pcm(&audio_files::get(conn, audio_file_id)?.path, channel)
+    })?;
+    apply_trim(conn, transcription_id, &mut pcm)?;
+    Ok(pcm)
+}
+
+/// Silence everything outside `turns` (start and end seconds), keeping
+/// [`PADDING_SECS`] around each.
+fn mask(pcm: &mut [f32], turns: &[(f64, f64)]) {
+    let index = |secs: f64| ((secs.max(0.0) * WHISPER_SAMPLE_RATE as f64) as usize).min(pcm.len());
+    let mut kept = vec![false; pcm.len()];
+    for &(start, end) in turns {
+        let from = index(start - PADDING_SECS);
+        kept[from..index(end + PADDING_SECS).max(from)].fill(true);
+    }
+    for (sample, kept) in pcm.iter_mut().zip(kept) {
+        if !kept {
+            *sample = 0.0;
+        }
+    }
+}
+
+/// Samples between `start` and `end` seconds, padded and kept in range.
+pub fn cut(pcm: &[f32], start: f64, end: f64) -> &[f32] {
+    let index = |secs: f64| ((secs.max(0.0) * WHISPER_SAMPLE_RATE as f64) as usize).min(pcm.len());
+    let from = index(start - PADDING_SECS);
+    let to = index(end + PADDING_SECS).max(from);
+    &pcm[from..to]
+}
+
+pub fn extract(pcm: &[f32], start: f64, end: f64, path: &Path) -> Result<()> {
+    if end <= start {
+        return Err(Error::InvalidInput(format!(
+            "clip ends before it starts at {:.2}s",
+            start
+        )));
+    }
+    archive::write_wav(cut(pcm, start, end), path)
+}
+
+/// Write the audio of a transcription from `start` to `end` seconds to
+/// `path` as WAV. Trimmed-off audio is left out.
+#[tauri::command]
+pub async fn extract_clip(
+    app: AppHandle,
+    transcription_id: String,
+    start: f64,
+    end: f64,
+    path: PathBuf,
+) -> Result<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let pcm = trimmed_pcm(&db::connect(&app)?, &transcription_id)?;
+        extract(&pcm, start, end, &path)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+/// Write one WAV per speaker of a transcription into `dir`, named after
+/// the transcription and the speaker. `channels` maps speakers to the
+/// channel, counted from 0, that carries them in the original file.
+/// Returns the files written.
+#[tauri::command]
+pub async fn export_speaker_stems(
+    app: AppHandle,
+    transcription_id: String,
+    dir: PathBuf,
+    channels: Option<HashMap<String, usize>>,
+) -> Result<Vec<PathBuf>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::connect(&app)?;
+        let channels = channels.unwrap_or_default();
+        let unknown = i18n::t("unknown-speaker");
+        let mut turns: Vec<(String, Vec<(f64, f64)>)> = Vec::new();
+        for segment in segments::for_transcription(&conn, &transcription_id)? {
+            let speaker = segment.speaker.unwrap_or_else(|| unknown.clone());
+            match turns.iter_mut().find(|(name, _)| *name == speaker) {
+                Some((_, spans)) => spans.push((segment.start, segment.end)),
+                None => turns.push((speaker, vec![(segment.start, segment.end)])),
+            }
+        }
+        if turns.iter().all(|(speaker, _)| *speaker == unknown) {
+            return Err(Error::InvalidInput(
+                "speaker stems need a transcription with speaker labels".into(),
+            ));
+        }
+        if let Some(speaker) = channels
+            .keys()
+            .find(|speaker| !turns.iter().any(|(name, _)| name == *speaker))
+        {
+            return Err(Error::NotFound(format!("speaker {}", speaker)));
+        }
+        let title: String = conn.query_row(
+            "SELECT COALESCE(t.title, a.title, t.id) FROM transcriptions t
+             LEFT JOIN audio_files a ON a.id = t.audio_file_id WHERE t.id = ?1",
+            [&transcription_id],
+            |row| row.get(0),
+        )?;
+
+        std::fs::create_dir_all(&dir)?;
+        let mixed = if turns
+            .iter()
+            .any(|(speaker, _)| !channels.contains_key(speaker))
+        {
+            trimmed_pcm(&conn, &transcription_id)?
+        } else {
+            Vec::new()
+        };
+        let mut written = Vec::new();
+        for (speaker, spans) in turns {
+            let pcm = match channels.get(&speaker) {
+                Some(&channel) => channel_pcm(&conn, &transcription_id, channel)?,
+                None => {
+                    let mut pcm = mixed.clone();
+                    mask(&mut pcm, &spans);
+                    pcm
+                }
+            };
+            let path = dir.join(format!(
+                "{}_{}.wav",
+                bulk::safe_name(&title),
+                bulk::safe_name(&speaker)
+            ));
+            archive::write_wav(&pcm, &path)?;
+            written.push(path);
+        }
+        Ok(written)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pads_and_clamps_to_the_audio() {
+        let pcm = vec![0.0; WHISPER_SAMPLE_RATE as usize * 2];
+        assert_eq!(cut(&pcm, 0.1, 1.0).len(), 20_000);
+        assert_eq!(cut(&pcm, 1.5, 5.0).len(), 12_000);
+        assert!(cut(&pcm, 3.0, 4.0).is_empty());
+    }
+
+    #[test]
+    fn masks_other_speakers() {
+        let mut pcm = vec![1.0; WHISPER_SAMPLE_RATE as usize * 4];
+        mask(&mut pcm, &[(1.0, 2.0), (3.5, 9.0)]);
+        let at = |secs: f64| pcm[(secs * WHISPER_SAMPLE_RATE as f64) as usize];
+        assert_eq!((at(0.5), at(0.8), at(1.5), at(2.2)), (0.0, 1.0, 1.0, 1.0));
+        assert_eq!((at(2.3), at(3.2), at(3.3)), (0.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,137 @@
+//! Reviewer comments anchored to points in a transcription.
+//!
+//! Comments live beside the transcript rather than in it, so annotating never
+//! changes the transcribed text. Exports list them with the transcript,
+//! as footnotes where the format has them.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::{audit, db};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Comment {
+    pub id: String,
+    pub transcription_id: String,
+    /// Position in the audio the comment refers to, in milliseconds.
+    pub anchor_ms: i64,
+    pub author: String,
+    pub text: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+const COLUMNS: &str = "id, transcription_id, anchor_ms, author, text, created_at, updated_at";
+
+fn from_row(row: &Row) -> rusqlite::Result<Comment> {
+    Ok(Comment {
+        id: row.get(0)?,
+        transcription_id: row.get(1)?,
+        anchor_ms: row.get(2)?,
+        author: row.get(3)?,
+        text: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn get(conn: &Connection, id: &str) -> Result<Comment> {
+    conn.query_row(
+        &format!("SELECT {} FROM comments WHERE id = ?1", COLUMNS),
+        [id],
+        from_row,
+    )
+    .optional()?
+    .ok_or_else(|| Error::NotFound(format!("comment {}", id)))
+}
+
+fn validate(anchor_ms: i64, text: &str) -> Result<()> {
+    if anchor_ms < 0 {
+        return Err(Error::InvalidInput("anchor must not be negative".into()));
+    }
+    if text.trim().is_empty() {
+        return Err(Error::InvalidInput("comment text is empty".into()));
+    }
+    Ok(())
+}
+
+/// All comments on a transcription, in timeline order.
+pub fn for_transcription(conn: &Connection, transcription_id: &str) -> Result<Vec<Comment>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM comments WHERE transcription_id = ?1 ORDER BY anchor_ms, created_at",
+        COLUMNS
+    ))?;
+    let comments = statement
+        .query_map([transcription_id], from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(comments)
+}
+
+#[tauri::command]
+pub fn add_comment(
+    app: AppHandle,
+    transcription_id: String,
+    anchor_ms: i64,
+    author: String,
+    text: String,
+) -> Result<Comment> {
+    validate(anchor_ms, &text)?;
+    let conn = db::connect(&app)?;
+    // Surface a missing transcription as NOT_FOUND, not a constraint error.
+    db::transcription_text(&conn, &transcription_id)?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO comments (id, transcription_id, anchor_ms, author, text)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![id, transcription_id, anchor_ms, author.trim(), text.trim()],
+    )?;
+    get(&conn, &id)
+}
+
+#[tauri::command]
+pub fn list_comments(app: AppHandle, transcription_id: String) -> Result<Vec<Comment>> {
+    for_transcription(&db::connect(&app)?, &transcription_id)
+}
+
+/// Change a comment's text and, optionally, move its anchor.
+#[tauri::command]
+pub fn update_comment(
+    app: AppHandle,
+    id: String,
+    text: String,
+    anchor_ms: Option<i64>,
+) -> Result<Comment> {
+    let conn = db::connect(&app)?;
+    let existing = get(&conn, &id)?;
+    let anchor_ms = anchor_ms.unwrap_or(existing.anchor_ms);
+    validate(anchor_ms, &text)?;
+
+    conn.execute(
+        "UPDATE comments SET text = ?2, anchor_ms = ?3, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1",
+        params![id, text.trim(), anchor_ms],
+    )?;
+    get(&conn, &id)
+}
+
+#[tauri::command]
+pub fn delete_comment(app: AppHandle, id: String) -> Result<()> {
+    let conn = db::connect(&app)?;
+    let transcription_id: String = conn
+        .query_row(
+            "SELECT transcription_id FROM comments WHERE id = ?1",
+            [&id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("comment {}", id)))?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM comments WHERE id = ?1", [&id])?;
+    audit::record(&tx, "comment_deleted", &[&id, &transcription_id], None)?;
+    tx.commit()?;
+    Ok(())
+}
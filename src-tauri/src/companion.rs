@@ -0,0 +1,735 @@
+//! Voice memos pushed from a phone over the local network.
+//!
+//! When turned on, the app serves HTTPS on the LAN and announces itself
+//! over mDNS as `_transcriber._tcp`, with its certificate fingerprint in
+//! the TXT record. The certificate is self-signed and made once, so a
+//! phone pins the fingerprint shown while pairing.
+//!
+//! Pairing swaps a six-digit code, shown in the app for a few minutes and
+//! burned after a few wrong guesses, for a token the phone sends with each
+//! upload. Only a hash of the token is stored. An uploaded memo is imported
+//! into the configured project, subject to its quota, tagged with the
+//! device it came from and queued for transcription like a watch folder
+//! file, with the transcript saved to it. A memo the queue's checks turn
+//! away, for example for lack of disk space, is not kept.
+//!
+//! The key behind the certificate is readable only by the user, and at most
+//! [`MAX_CONNECTIONS`] requests are served at once; further phones wait.
+//!
+//! Routes:
+//! - `POST /pair` with `{ "code", "deviceName" }` returns `{ "deviceId", "token" }`.
+//! - `POST /memos` with `Authorization: Bearer <token>`, `X-File-Name` and
+//!   optionally `X-Recorded-At` (RFC 3339), the audio as the body, returns
+//!   `{ "audioFileId", "jobId" }`.
+//!
+//! Failures answer with the usual `{ code, message }` error.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, State};
+use tiny_http::{Header, Method, Request, Response, Server, SslConfig};
+
+use crate::error::{Error, Result};
+use crate::jobs::{self, JobPriority, SaveTarget};
+use crate::watch::AUDIO_EXTENSIONS;
+use crate::{audio_files, bulk, crash, db};
+
+pub const SETTINGS_PREFERENCE: &str = "companion_settings";
+pub const MEMO_EVENT: &str = "companion://memo";
+const SERVICE_TYPE: &str = "_transcriber._tcp.local.";
+const DEFAULT_PORT: u16 = 47810;
+const PAIRING_LIFETIME: Duration = Duration::from_secs(5 * 60);
+const PAIRING_ATTEMPTS: u32 = 5;
+const MAX_MEMO_BYTES: u64 = 1024 * 1024 * 1024;
+const MAX_PAIRING_BYTES: u64 = 64 * 1024;
+/// Requests served at once; each holds a thread and, for uploads, a file.
+const MAX_CONNECTIONS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CompanionSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Project memos are imported into; `None` keeps them outside any.
+    pub project: Option<String>,
+    pub model: Option<String>,
+    pub language: Option<String>,
+}
+
+impl Default for CompanionSettings {
+    fn default() -> Self {
+        CompanionSettings {
+            enabled: false,
+            port: DEFAULT_PORT,
+            project: None,
+            model: None,
+            language: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanionStatus {
+    pub settings: CompanionSettings,
+    /// The server is accepting uploads.
+    pub listening: bool,
+    pub host_name: String,
+    /// SHA-256 of the server certificate, once one has been made.
+    pub fingerprint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingInfo {
+    pub code: String,
+    pub expires_in_secs: u64,
+    pub host_name: String,
+    pub port: u16,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanionDevice {
+    pub id: String,
+    pub name: String,
+    pub paired_at: String,
+    pub last_seen_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceivedMemo {
+    pub audio_file_id: String,
+    pub job_id: String,
+    pub device_name: String,
+    pub project: Option<String>,
+    pub file_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PairRequest {
+    code: String,
+    device_name: String,
+}
+
+struct Pairing {
+    code: String,
+    expires: Instant,
+    attempts_left: u32,
+}
+
+/// Take `code` against the open pairing, closing it once the code is used,
+/// expired or guessed wrong too often.
+fn redeem(slot: &mut Option<Pairing>, code: &str, now: Instant) -> bool {
+    let Some(pairing) = slot else {
+        return false;
+    };
+    if now >= pairing.expires {
+        *slot = None;
+        return false;
+    }
+    if pairing.code == code.trim() {
+        *slot = None;
+        return true;
+    }
+    pairing.attempts_left -= 1;
+    if pairing.attempts_left == 0 {
+        *slot = None;
+    }
+    false
+}
+
+struct Running {
+    server: Arc<Server>,
+    mdns: Option<ServiceDaemon>,
+    port: u16,
+    fingerprint: String,
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(mdns) = self.mdns.take() {
+            let _ = mdns.shutdown();
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CompanionState {
+    running: Mutex<Option<Running>>,
+    pairing: Mutex<Option<Pairing>>,
+}
+
+fn other(err: impl std::fmt::Display) -> Error {
+    Error::Io(io::Error::other(err.to_string()))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The DER bytes inside a PEM block.
+fn pem_der(pem: &str) -> Option<Vec<u8>> {
+    use base64::Engine;
+    let body: String = pem
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    base64::engine::general_purpose::STANDARD.decode(body).ok()
+}
+
+/// Certificate fingerprint as phones show it: `AB:CD:…`.
+fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn bearer(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.trim().split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+fn host_name() -> String {
+    sysinfo::System::host_name().unwrap_or_else(|| "transcriber".into())
+}
+
+fn companion_dir(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| Error::NotFound("app data directory".into()))?
+        .join("companion");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Make `path` readable only by the user. Elsewhere than Unix the app data
+/// directory is already private to them.
+fn restrict(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Write `contents` to a file only the user can read, never readable by
+/// others even briefly.
+fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())?;
+    // An existing file keeps its mode when truncated.
+    restrict(path)
+}
+
+/// The server certificate and key as PEM, made on first use.
+fn identity(app: &AppHandle) -> Result<(String, String)> {
+    let dir = companion_dir(app)?;
+    let (cert_path, key_path) = (dir.join("cert.pem"), dir.join("key.pem"));
+    if cert_path.is_file() && key_path.is_file() {
+        // Keys made by earlier versions may be readable by others.
+        restrict(&key_path)?;
+        return Ok((
+            fs::read_to_string(cert_path)?,
+            fs::read_to_string(key_path)?,
+        ));
+    }
+    let cert = rcgen::generate_simple_self_signed(vec![
+        format!("{}.local", host_name()),
+        "localhost".into(),
+    ])
+    .map_err(other)?;
+    let cert_pem = cert.serialize_pem().map_err(other)?;
+    let key_pem = cert.serialize_private_key_pem();
+    write_private(&key_path, &key_pem)?;
+    fs::write(&cert_path, &cert_pem)?;
+    Ok((cert_pem, key_pem))
+}
+
+fn stored_fingerprint(app: &AppHandle) -> Option<String> {
+    let pem = fs::read_to_string(companion_dir(app).ok()?.join("cert.pem")).ok()?;
+    pem_der(&pem).map(|der| fingerprint(&der))
+}
+
+pub fn settings(conn: &Connection) -> Result<CompanionSettings> {
+    Ok(db::get_preference(conn, SETTINGS_PREFERENCE)?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+fn advertise(port: u16, fingerprint: &str) -> std::result::Result<ServiceDaemon, mdns_sd::Error> {
+    let daemon = ServiceDaemon::new()?;
+    let host = host_name();
+    let label = host.split('.').next().unwrap_or("transcriber");
+    let properties = [("fingerprint", fingerprint), ("version", "1")];
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        &host,
+        &format!("{}.local.", label),
+        "",
+        port,
+        &properties[..],
+    )?
+    .enable_addr_auto();
+    daemon.register(service)?;
+    Ok(daemon)
+}
+
+fn start(app: &AppHandle, port: u16) -> Result<()> {
+    let state = app.state::<CompanionState>();
+    let mut running = state.running.lock().unwrap();
+    if running.as_ref().is_some_and(|running| running.port == port) {
+        return Ok(());
+    }
+    // Free the old port before binding the new one.
+    *running = None;
+
+    let (cert_pem, key_pem) = identity(app)?;
+    let fingerprint = pem_der(&cert_pem)
+        .map(|der| fingerprint(&der))
+        .ok_or_else(|| other("the companion certificate is unreadable"))?;
+    let server = Server::https(
+        ("0.0.0.0", port),
+        SslConfig {
+            certificate: cert_pem.into_bytes(),
+            private_key: key_pem.into_bytes(),
+        },
+    )
+    .map(Arc::new)
+    .map_err(|err| other(format!("cannot listen on port {}: {}", port, err)))?;
+
+    for _ in 0..MAX_CONNECTIONS {
+        let listener = server.clone();
+        let app = app.clone();
+        thread::spawn(move || {
+            for request in listener.incoming_requests() {
+                serve(&app, request);
+            }
+        });
+    }
+    // Phones can still connect by address without discovery.
+    let mdns = advertise(port, &fingerprint)
+        .map_err(|err| crash::log(format!("companion discovery unavailable: {}", err)))
+        .ok();
+    *running = Some(Running {
+        server,
+        mdns,
+        port,
+        fingerprint,
+    });
+    Ok(())
+}
+
+fn stop(app: &AppHandle) {
+    app.state::<CompanionState>().running.lock().unwrap().take();
+    app.state::<CompanionState>().pairing.lock().unwrap().take();
+}
+
+/// Start the server on launch if the user has turned it on.
+pub fn init(app: &AppHandle) {
+    let Ok(settings) = db::connect(app).and_then(|conn| settings(&conn)) else {
+        return;
+    };
+    if settings.enabled {
+        if let Err(err) = start(app, settings.port) {
+            crash::log(format!("companion uploads unavailable: {}", err));
+        }
+    }
+}
+
+fn status_code(err: &Error) -> u16 {
+    match err {
+        Error::InvalidInput(_) => 400,
+        Error::NotFound(_) => 404,
+        Error::QuotaExceeded(_) => 507,
+        Error::PreflightFailed(_) => 503,
+        _ => 500,
+    }
+}
+
+type Reply = std::result::Result<(u16, Value), (u16, Error)>;
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+fn serve(app: &AppHandle, mut request: Request) {
+    let (method, url) = (request.method().clone(), request.url().to_string());
+    let reply = match (&method, url.as_str()) {
+        (Method::Post, "/pair") => pair(app, &mut request),
+        (Method::Post, "/memos") => receive(app, &mut request),
+        _ => Err((404, Error::NotFound(url.clone()))),
+    };
+    let (status, body) = match reply {
+        Ok(reply) => reply,
+        Err((status, err)) => (status, serde_json::to_value(&err).unwrap_or_default()),
+    };
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap());
+    if let Err(err) = request.respond(response) {
+        crash::log(format!("companion reply failed: {}", err));
+    }
+}
+
+fn pair(app: &AppHandle, request: &mut Request) -> Reply {
+    let fail = |err: Error| (status_code(&err), err);
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_PAIRING_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|err| fail(err.into()))?;
+    let pairing: PairRequest = serde_json::from_str(&body).map_err(|err| {
+        fail(Error::InvalidInput(format!(
+            "unreadable pairing request: {}",
+            err
+        )))
+    })?;
+    let name = pairing.device_name.trim();
+    if name.is_empty() {
+        return Err(fail(Error::InvalidInput("the device needs a name".into())));
+    }
+    let state = app.state::<CompanionState>();
+    if !redeem(
+        &mut state.pairing.lock().unwrap(),
+        &pairing.code,
+        Instant::now(),
+    ) {
+        return Err((
+            401,
+            Error::InvalidInput("the pairing code is wrong or has expired".into()),
+        ));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let token = format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    db::connect(app)
+        .and_then(|conn| {
+            conn.execute(
+                "INSERT INTO companion_devices (id, name, token_hash) VALUES (?1, ?2, ?3)",
+                params![id, name, sha256_hex(token.as_bytes())],
+            )?;
+            Ok(())
+        })
+        .map_err(fail)?;
+    Ok((200, json!({ "deviceId": id, "token": token })))
+}
+
+/// The paired device presenting `request`'s token, as id and name.
+fn device(conn: &Connection, request: &Request) -> Result<Option<(String, String)>> {
+    let Some(token) = header(request, "Authorization").and_then(bearer) else {
+        return Ok(None);
+    };
+    let device: Option<(String, String)> = conn
+        .query_row(
+            "SELECT id, name FROM companion_devices WHERE token_hash = ?1",
+            [sha256_hex(token.as_bytes())],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    if let Some((id, _)) = &device {
+        conn.execute(
+            "UPDATE companion_devices SET last_seen_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            [id],
+        )?;
+    }
+    Ok(device)
+}
+
+/// `name` reduced to a safe file name, if it has an audio extension.
+fn memo_file_name(name: &str) -> Option<String> {
+    let path = Path::new(name.rsplit(['/', '\\']).next()?);
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    AUDIO_EXTENSIONS.contains(&extension.as_str()).then(|| {
+        let stem = path
+            .file_stem()
+            .map(|stem| bulk::safe_name(&stem.to_string_lossy()))
+            .unwrap_or_default();
+        format!("{}.{}", stem, extension)
+    })
+}
+
+fn receive(app: &AppHandle, request: &mut Request) -> Reply {
+    let fail = |err: Error| (status_code(&err), err);
+    let conn = db::connect(app).map_err(fail)?;
+    let Some((_, device_name)) = device(&conn, request).map_err(fail)? else {
+        return Err((
+            401,
+            Error::InvalidInput("this device is not paired; pair it again".into()),
+        ));
+    };
+    let file_name = header(request, "X-File-Name")
+        .and_then(memo_file_name)
+        .ok_or_else(|| {
+            (
+                415,
+                Error::InvalidInput(format!(
+                    "memos need an X-File-Name ending in one of {}",
+                    AUDIO_EXTENSIONS.join(", ")
+                )),
+            )
+        })?;
+    let recorded_at = header(request, "X-Recorded-At")
+        .filter(|value| chrono::DateTime::parse_from_rfc3339(value).is_ok())
+        .map(str::to_string);
+    let too_large = || (413, Error::InvalidInput("memos can be at most 1 GB".into()));
+    if request
+        .body_length()
+        .is_some_and(|length| length as u64 > MAX_MEMO_BYTES)
+    {
+        return Err(too_large());
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let dir = companion_dir(app).map_err(fail)?.join("inbox").join(&id);
+    let path = dir.join(&file_name);
+    let written = fs::create_dir_all(&dir).and_then(|_| {
+        io::copy(
+            &mut request.as_reader().take(MAX_MEMO_BYTES + 1),
+            &mut fs::File::create(&path)?,
+        )
+    });
+    let settings = settings(&conn).map_err(fail)?;
+    let imported = match written {
+        Ok(0) => Err((400, Error::InvalidInput("the memo is empty".into()))),
+        Ok(size) if size > MAX_MEMO_BYTES => Err(too_large()),
+        Ok(_) => audio_files::import(&conn, &id, &path, settings.project.as_deref()).map_err(fail),
+        Err(err) => Err(fail(err.into())),
+    };
+    if let Err(err) = imported {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(err);
+    }
+    conn.execute(
+        "UPDATE audio_files SET source = ?2, device = COALESCE(device, ?3),
+             recorded_at = COALESCE(recorded_at, ?4)
+         WHERE id = ?1",
+        params![
+            id,
+            format!("companion:{}", device_name),
+            device_name,
+            recorded_at
+        ],
+    )
+    .map_err(|err| fail(err.into()))?;
+
+    let queued = jobs::enqueue_transcription(
+        app.clone(),
+        path,
+        settings.model,
+        settings.language,
+        Some(JobPriority::Background),
+        None,
+        None,
+        Some(SaveTarget {
+            audio_file_id: Some(id.clone()),
+            ..Default::default()
+        }),
+    );
+    let job_id = match queued {
+        Ok(job_id) => job_id,
+        Err(err) => {
+            let _ = conn.execute("DELETE FROM audio_files WHERE id = ?1", [&id]);
+            let _ = fs::remove_dir_all(&dir);
+            return Err(fail(err));
+        }
+    };
+    let _ = app.emit_all(
+        MEMO_EVENT,
+        ReceivedMemo {
+            audio_file_id: id.clone(),
+            job_id: job_id.clone(),
+            device_name,
+            project: settings.project,
+            file_name,
+        },
+    );
+    Ok((202, json!({ "audioFileId": id, "jobId": job_id })))
+}
+
+#[tauri::command]
+pub fn get_companion_status(
+    app: AppHandle,
+    state: State<'_, CompanionState>,
+) -> Result<CompanionStatus> {
+    Ok(CompanionStatus {
+        settings: settings(&db::connect(&app)?)?,
+        listening: state.running.lock().unwrap().is_some(),
+        host_name: host_name(),
+        fingerprint: stored_fingerprint(&app),
+    })
+}
+
+/// Save the settings, starting or stopping the server to match.
+#[tauri::command]
+pub fn set_companion_settings(
+    app: AppHandle,
+    settings: CompanionSettings,
+) -> Result<CompanionStatus> {
+    if settings.port < 1024 {
+        return Err(Error::InvalidInput(
+            "the companion port must be 1024 or higher".into(),
+        ));
+    }
+    let settings = CompanionSettings {
+        project: settings
+            .project
+            .map(|project| project.trim().to_string())
+            .filter(|project| !project.is_empty()),
+        ..settings
+    };
+    if settings.enabled {
+        start(&app, settings.port)?;
+    } else {
+        stop(&app);
+    }
+    db::set_preference(
+        &db::connect(&app)?,
+        SETTINGS_PREFERENCE,
+        &serde_json::to_string(&settings).unwrap(),
+    )?;
+    get_companion_status(app.clone(), app.state())
+}
+
+/// Open pairing for a few minutes, replacing any code shown before.
+#[tauri::command]
+pub fn start_companion_pairing(state: State<'_, CompanionState>) -> Result<PairingInfo> {
+    let (port, fingerprint) = match state.running.lock().unwrap().as_ref() {
+        Some(running) => (running.port, running.fingerprint.clone()),
+        None => {
+            return Err(Error::InvalidInput(
+                "turn on companion uploads before pairing".into(),
+            ))
+        }
+    };
+    let code = format!("{:06}", uuid::Uuid::new_v4().as_u128() % 1_000_000);
+    *state.pairing.lock().unwrap() = Some(Pairing {
+        code: code.clone(),
+        expires: Instant::now() + PAIRING_LIFETIME,
+        attempts_left: PAIRING_ATTEMPTS,
+    });
+    Ok(PairingInfo {
+        code,
+        expires_in_secs: PAIRING_LIFETIME.as_secs(),
+        host_name: host_name(),
+        port,
+        fingerprint,
+    })
+}
+
+#[tauri::command]
+pub fn list_companion_devices(app: AppHandle) -> Result<Vec<CompanionDevice>> {
+    let conn = db::connect(&app)?;
+    let mut statement = conn.prepare(
+        "SELECT id, name, paired_at, last_seen_at FROM companion_devices ORDER BY paired_at",
+    )?;
+    let devices = statement
+        .query_map([], |row| {
+            Ok(CompanionDevice {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                paired_at: row.get(2)?,
+                last_seen_at: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(devices)
+}
+
+/// Forget a paired device; its token stops working at once.
+#[tauri::command]
+pub fn remove_companion_device(app: AppHandle, id: String) -> Result<()> {
+    let removed =
+        db::connect(&app)?.execute("DELETE FROM companion_devices WHERE id = ?1", [&id])?;
+    if removed == 0 {
+        return Err(Error::NotFound(format!("companion device {}", id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairing_codes_are_single_use_and_burn_after_wrong_guesses() {
+        let now = Instant::now();
+        let open = |attempts_left| {
+            Some(Pairing {
+                code: "042917".into(),
+                expires: now + PAIRING_LIFETIME,
+                attempts_left,
+            })
+        };
+
+        let mut slot = open(PAIRING_ATTEMPTS);
+        assert!(redeem(&mut slot, " 042917\n", now));
+        assert!(!redeem(&mut slot, "042917", now));
+
+        let mut slot = open(2);
+        assert!(!redeem(&mut slot, "000000", now));
+        assert!(!redeem(&mut slot, "000001", now));
+        assert!(!redeem(&mut slot, "042917", now));
+
+        let mut slot = open(PAIRING_ATTEMPTS);
+        assert!(!redeem(&mut slot, "042917", now + PAIRING_LIFETIME));
+        assert!(slot.is_none());
+    }
+
+    #[test]
+    fn reads_tokens_fingerprints_and_memo_names() {
+        assert_eq!(bearer("Bearer abc123"), Some("abc123"));
+        assert_eq!(bearer("bearer  abc123 "), Some("abc123"));
+        assert_eq!(bearer("Basic abc123"), None);
+
+        let pem = "-----BEGIN CERTIFICATE-----\nAQID\n-----END CERTIFICATE-----\n";
+        let der = pem_der(pem).unwrap();
+        assert_eq!(der, [1, 2, 3]);
+        assert!(fingerprint(&der).starts_with("03:90:58:C6:F2"));
+
+        assert_eq!(
+            memo_file_name("../Voice Memo 12.M4A").as_deref(),
+            Some("Voice_Memo_12.m4a")
+        );
+        assert_eq!(memo_file_name("notes.txt"), None);
+        assert_eq!(memo_file_name("memo"), None);
+    }
+}
@@ -0,0 +1,235 @@
+//! Local crash reports.
+//!
+//! A panic hook writes each panic, its backtrace and the most recent
+//! backend log lines to a JSON file under the app data directory. Reports
+//! never leave the machine unless the user submits one explicitly, and only
+//! to the endpoint they configured.
+
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::{db, network};
+
+/// URL crash reports are POSTed to when submitted; unset by default.
+pub const ENDPOINT_PREFERENCE: &str = "crash_report_endpoint";
+
+/// Log lines kept in memory for the next report.
+const RECENT_LINES: usize = 200;
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static REPORTS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub id: String,
+    pub created_at: String,
+    pub message: String,
+    /// `file:line:column` of the panic.
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: String,
+    pub app_version: String,
+    pub os: String,
+    pub recent_log: Vec<String>,
+    pub submitted_at: Option<String>,
+}
+
+fn push_capped(lines: &mut VecDeque<String>, line: String, cap: usize) {
+    if lines.len() == cap {
+        lines.pop_front();
+    }
+    lines.push_back(line);
+}
+
+fn timestamp() -> String {
+    Local::now().to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+/// Log a backend message to stderr and keep it for crash reports.
+pub fn log(message: impl Display) {
+    let line = format!("{} {}", timestamp(), message);
+    eprintln!("{}", message);
+    if let Ok(mut recent) = RECENT.lock() {
+        push_capped(&mut recent, line, RECENT_LINES);
+    }
+}
+
+fn reports_dir(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| Error::NotFound("app data directory".into()))?
+        .join("crash_reports");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panic with a non-string payload".into())
+}
+
+fn write_report(info: &PanicHookInfo) -> Option<PathBuf> {
+    let dir = REPORTS_DIR.get()?;
+    let report = CrashReport {
+        id: uuid::Uuid::new_v4().to_string(),
+        created_at: timestamp(),
+        message: panic_message(info),
+        location: info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+        thread: thread::current().name().map(str::to_string),
+        backtrace: Backtrace::force_capture().to_string(),
+        app_version: env!("CARGO_PKG_VERSION").into(),
+        os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        // A panic while the log is locked must not deadlock the hook.
+        recent_log: RECENT
+            .try_lock()
+            .map(|recent| recent.iter().cloned().collect())
+            .unwrap_or_default(),
+        submitted_at: None,
+    };
+    let path = dir.join(format!("{}.json", report.id));
+    fs::write(&path, serde_json::to_vec_pretty(&report).ok()?).ok()?;
+    Some(path)
+}
+
+/// Install the panic hook. Call once during app setup; earlier panics only
+/// reach the default hook.
+pub fn init(app: &AppHandle) {
+    match reports_dir(app) {
+        Ok(dir) => {
+            let _ = REPORTS_DIR.set(dir);
+        }
+        Err(err) => {
+            log(format!("crash reports unavailable: {}", err));
+            return;
+        }
+    }
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Some(path) = write_report(info) {
+            eprintln!("crash report written to {}", path.display());
+        }
+        previous(info);
+    }));
+}
+
+fn report_path(app: &AppHandle, id: &str) -> Result<PathBuf> {
+    if uuid::Uuid::parse_str(id).is_err() {
+        return Err(Error::InvalidInput(format!(
+            "invalid crash report id {}",
+            id
+        )));
+    }
+    let path = reports_dir(app)?.join(format!("{}.json", id));
+    if !path.exists() {
+        return Err(Error::NotFound(format!("crash report {}", id)));
+    }
+    Ok(path)
+}
+
+fn read(path: &std::path::Path) -> Result<CrashReport> {
+    serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| Error::InvalidInput(format!("unreadable crash report: {}", e)))
+}
+
+/// Stored crash reports, newest first.
+#[tauri::command]
+pub fn list_crash_reports(app: AppHandle) -> Result<Vec<CrashReport>> {
+    let mut reports = Vec::new();
+    for entry in fs::read_dir(reports_dir(&app)?)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            if let Ok(report) = read(&path) {
+                reports.push(report);
+            }
+        }
+    }
+    reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(reports)
+}
+
+/// Send one report to the configured endpoint. Nothing is sent unless the
+/// user calls this for that report.
+#[tauri::command]
+pub async fn submit_crash_report(app: AppHandle, id: String) -> Result<CrashReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::connect(&app)?;
+        let endpoint = db::get_preference(&conn, ENDPOINT_PREFERENCE)?
+            .filter(|url| !url.trim().is_empty())
+            .ok_or_else(|| Error::InvalidInput("no crash report endpoint is configured".into()))?;
+        let path = report_path(&app, &id)?;
+        let mut report = read(&path)?;
+
+        network::no_redirect(
+            network::client(&conn)?
+                .post(&endpoint)?
+                .send_json(&report)
+                .map_err(|e| Error::Provider(e.to_string()))?,
+        )?;
+        report.submitted_at = Some(timestamp());
+        fs::write(&path, serde_json::to_vec_pretty(&report).unwrap())?;
+        Ok(report)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[tauri::command]
+pub fn delete_crash_report(app: AppHandle, id: String) -> Result<()> {
+    fs::remove_file(report_path(&app, &id)?)?;
+    Ok(())
+}
+
+/// Set or clear the submission endpoint.
+#[tauri::command]
+pub fn set_crash_report_endpoint(app: AppHandle, url: Option<String>) -> Result<()> {
+    let conn = db::connect(&app)?;
+    match url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+    {
+        Some(url) if !url.starts_with("https://") && !network::is_local(&url) => Err(
+            Error::InvalidInput("crash reports can only be sent over HTTPS".into()),
+        ),
+        Some(url) => db::set_preference(&conn, ENDPOINT_PREFERENCE, &url),
+        None => {
+            conn.execute(
+                "DELETE FROM user_preferences WHERE key = ?1",
+                [ENDPOINT_PREFERENCE],
+            )?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_latest_lines() {
+        let mut lines = VecDeque::new();
+        for n in 0..5 {
+            push_capped(&mut lines, n.to_string(), 3);
+        }
+        assert_eq!(lines, ["2", "3", "4"]);
+    }
+}
@@ -0,0 +1,639 @@
+//! Direct SQLite access for backend commands.
+//!
+//! The schema is owned by `tauri-plugin-sql`, which applies [`migrations`]
+//! when the frontend loads the database. Commands that need to read or write
+//! records open their own connection to the same file.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_sql::{Migration, MigrationKind};
+
+use crate::error::{Error, Result};
+
+pub const DB_FILE: &str = "transcription_history.db";
+
+pub fn migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            description: "Create initial tables",
+            sql: "CREATE TABLE IF NOT EXISTS transcriptions (
+                id TEXT PRIMARY KEY,
+                audio_file_id TEXT NOT NULL,
+                text TEXT NOT NULL,
+                language TEXT NOT NULL,
+                model_used TEXT NOT NULL,
+                duration REAL NOT NULL,
+                confidence REAL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            
+            CREATE TABLE IF NOT EXISTS summaries (
+                id TEXT PRIMARY KEY,
+                transcription_id TEXT NOT NULL,
+                summary TEXT NOT NULL,
+                language TEXT NOT NULL,
+                model_used TEXT NOT NULL,
+                original_length INTEGER NOT NULL,
+                summary_length INTEGER NOT NULL,
+                compression_ratio REAL NOT NULL,
+                processing_time INTEGER NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id)
+            );
+            
+            CREATE TABLE IF NOT EXISTS user_preferences (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_transcriptions_created_at ON transcriptions(created_at);
+            CREATE INDEX IF NOT EXISTS idx_transcriptions_language ON transcriptions(language);
+            CREATE INDEX IF NOT EXISTS idx_summaries_transcription_id ON summaries(transcription_id);",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 2,
+            description: "Add timestamp-anchored comments",
+            sql: "CREATE TABLE IF NOT EXISTS comments (
+                id TEXT PRIMARY KEY,
+                transcription_id TEXT NOT NULL,
+                anchor_ms INTEGER NOT NULL,
+                author TEXT NOT NULL,
+                text TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_comments_transcription_id ON comments(transcription_id, anchor_ms);",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 3,
+            description: "Add meeting types",
+            sql: "CREATE TABLE IF NOT EXISTS meeting_types (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                summary_prompt TEXT,
+                vocabulary TEXT NOT NULL DEFAULT '[]',
+                default_tags TEXT NOT NULL DEFAULT '[]',
+                export_template TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            INSERT OR IGNORE INTO meeting_types (id, name, summary_prompt, default_tags, export_template) VALUES
+                ('standup', 'Standup', 'List what each person did, what they plan to do next, and any blockers.', '[\"standup\"]', 'md'),
+                ('interview', 'Interview', 'Summarize the main questions asked and the key points of each answer.', '[\"interview\"]', 'docx'),
+                ('lecture', 'Lecture', 'Outline the topics covered, key definitions and any assignments mentioned.', '[\"lecture\"]', 'pdf');",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 4,
+            description: "Add interview Q&A documents",
+            sql: "CREATE TABLE IF NOT EXISTS qa_documents (
+                id TEXT PRIMARY KEY,
+                transcription_id TEXT NOT NULL,
+                pairs TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_qa_documents_transcription_id ON qa_documents(transcription_id);",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "Store transcript segments",
+            sql: "CREATE TABLE IF NOT EXISTS segments (
+                id TEXT PRIMARY KEY,
+                transcription_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                speaker TEXT,
+                text TEXT NOT NULL,
+                start_time REAL NOT NULL,
+                end_time REAL NOT NULL,
+                confidence REAL,
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_segments_transcription_id ON segments(transcription_id, position);",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "Add review queue",
+            sql: "CREATE TABLE IF NOT EXISTS review_items (
+                id TEXT PRIMARY KEY,
+                transcription_id TEXT NOT NULL,
+                segment_id TEXT,
+                start_time REAL NOT NULL,
+                end_time REAL NOT NULL,
+                reason TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'open',
+                note TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                resolved_at DATETIME,
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_review_items_transcription_id ON review_items(transcription_id, start_time);",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 7,
+            description: "Add transcript versions and edit attribution",
+            sql: "ALTER TABLE transcriptions ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+            ALTER TABLE transcriptions ADD COLUMN updated_by TEXT;
+
+            CREATE TABLE IF NOT EXISTS edit_log (
+                id TEXT PRIMARY KEY,
+                transcription_id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                editor TEXT NOT NULL,
+                target TEXT NOT NULL,
+                old_value TEXT,
+                new_value TEXT,
+                edited_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_edit_log_transcription_id ON edit_log(transcription_id, version);",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 8,
+            description: "Add watch folders",
+            sql: "CREATE TABLE IF NOT EXISTS watch_folders (
+                id TEXT PRIMARY KEY,
+                path TEXT NOT NULL UNIQUE,
+                preset TEXT,
+                recursive INTEGER NOT NULL DEFAULT 1,
+                model TEXT,
+                language TEXT,
+                diarize INTEGER NOT NULL DEFAULT 0,
+                auto_summary INTEGER NOT NULL DEFAULT 0,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS watch_files (
+                folder_id TEXT NOT NULL,
+                path TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                job_id TEXT,
+                seen_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (folder_id, path),
+                FOREIGN KEY (folder_id) REFERENCES watch_folders(id) ON DELETE CASCADE
+            );",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 9,
+            description: "Add audio files with embedded metadata",
+            sql: "CREATE TABLE IF NOT EXISTS audio_files (
+                id TEXT PRIMARY KEY,
+                path TEXT NOT NULL,
+                file_name TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                duration REAL,
+                title TEXT,
+                artist TEXT,
+                album TEXT,
+                recorded_at TEXT,
+                device TEXT,
+                imported_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            ALTER TABLE transcriptions ADD COLUMN title TEXT;
+
+            CREATE INDEX IF NOT EXISTS idx_audio_files_path ON audio_files(path);",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 10,
+            description: "Add prompt library",
+            sql: "CREATE TABLE IF NOT EXISTS prompts (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                template TEXT NOT NULL,
+                builtin INTEGER NOT NULL DEFAULT 0,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            INSERT OR IGNORE INTO prompts (id, name, template, builtin) VALUES
+                ('executive_summary', 'Executive summary', 'Write a concise executive summary in {{language}} of this {{meeting_type}} transcript. Lead with the outcome, then the key points and decisions.', 1),
+                ('action_items', 'Action items', 'List every action item in this {{meeting_type}} transcript in {{language}}, one per line, with the owner and due date when they are mentioned.', 1),
+                ('study_notes', 'Study notes', 'Turn this {{meeting_type}} transcript into study notes in {{language}}: headings for each topic, key definitions, and questions to review.', 1);
+
+            ALTER TABLE summaries ADD COLUMN prompt_id TEXT REFERENCES prompts(id) ON DELETE SET NULL;",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 11,
+            description: "Add provider usage tracking",
+            sql: "CREATE TABLE IF NOT EXISTS provider_usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                operation TEXT NOT NULL,
+                transcription_id TEXT,
+                input_tokens INTEGER NOT NULL DEFAULT 0,
+                output_tokens INTEGER NOT NULL DEFAULT 0,
+                audio_seconds REAL NOT NULL DEFAULT 0,
+                estimated_cost REAL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_provider_usage_created_at ON provider_usage(created_at);",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 12,
+            description: "Add custom model registry",
+            sql: "CREATE TABLE IF NOT EXISTS models (
+                name TEXT PRIMARY KEY,
+                source TEXT NOT NULL,
+                sha256 TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                registered_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 13,
+            description: "Record wall-clock recording start with timezone",
+            sql: "ALTER TABLE transcriptions ADD COLUMN recording_started_at TEXT;
+            ALTER TABLE transcriptions ADD COLUMN recording_timezone TEXT;",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 14,
+            description: "Add transcription tags",
+            sql: "CREATE TABLE IF NOT EXISTS transcription_tags (
+                transcription_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (transcription_id, tag),
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_transcription_tags_tag ON transcription_tags(tag);",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 15,
+            description: "Track archived source audio",
+            sql: "CREATE TABLE IF NOT EXISTS audio_archive (
+                audio_file_id TEXT PRIMARY KEY,
+                state TEXT NOT NULL,
+                original_path TEXT NOT NULL,
+                original_size INTEGER NOT NULL,
+                archive_path TEXT,
+                archive_size INTEGER,
+                archived_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (audio_file_id) REFERENCES audio_files(id) ON DELETE CASCADE
+            );",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 16,
+            description: "Store segment translations",
+            sql: "CREATE TABLE IF NOT EXISTS translations (
+                transcription_id TEXT NOT NULL,
+                language TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                text TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (transcription_id, language, position),
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 17,
+            description: "Track the audio behind split and merged transcriptions",
+            sql: "CREATE TABLE IF NOT EXISTS transcription_parts (
+                transcription_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                audio_file_id TEXT NOT NULL,
+                start_time REAL NOT NULL,
+                audio_start REAL NOT NULL,
+                duration REAL NOT NULL,
+                PRIMARY KEY (transcription_id, position),
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 18,
+            description: "Store non-destructive audio trim points",
+            sql: "ALTER TABLE transcriptions ADD COLUMN trim_start_ms INTEGER;
+            ALTER TABLE transcriptions ADD COLUMN trim_end_ms INTEGER;",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 19,
+            description: "Schedule source audio cleanup",
+            sql: "ALTER TABLE watch_folders ADD COLUMN cleanup TEXT;
+
+            CREATE TABLE IF NOT EXISTS source_cleanup (
+                audio_file_id TEXT PRIMARY KEY,
+                transcription_id TEXT NOT NULL,
+                action TEXT NOT NULL,
+                destination TEXT,
+                due_at INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'scheduled',
+                error TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 20,
+            description: "Add an append-only audit log",
+            sql: "CREATE TABLE IF NOT EXISTS audit_log (
+                id TEXT PRIMARY KEY,
+                action TEXT NOT NULL,
+                target_ids TEXT NOT NULL,
+                actor TEXT NOT NULL,
+                detail TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at);
+
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'the audit log is append-only');
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'the audit log is append-only');
+            END;",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 21,
+            description: "Add background search indexes",
+            sql: "CREATE VIRTUAL TABLE IF NOT EXISTS transcript_fts USING fts5(
+                transcription_id UNINDEXED,
+                title,
+                text,
+                summary,
+                tokenize = 'unicode61 remove_diacritics 2'
+            );
+
+            CREATE TABLE IF NOT EXISTS transcript_embeddings (
+                transcription_id TEXT PRIMARY KEY,
+                vector BLOB NOT NULL,
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS search_index_state (
+                transcription_id TEXT PRIMARY KEY,
+                indexed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+            );
+
+            CREATE TRIGGER IF NOT EXISTS search_index_text_changed
+            AFTER UPDATE OF text, title ON transcriptions
+            BEGIN
+                DELETE FROM search_index_state WHERE transcription_id = NEW.id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS search_index_summary_added AFTER INSERT ON summaries
+            BEGIN
+                DELETE FROM search_index_state WHERE transcription_id = NEW.transcription_id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS search_index_summary_changed AFTER UPDATE ON summaries
+            BEGIN
+                DELETE FROM search_index_state WHERE transcription_id = NEW.transcription_id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS search_index_summary_removed AFTER DELETE ON summaries
+            BEGIN
+                DELETE FROM search_index_state WHERE transcription_id = OLD.transcription_id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS search_index_transcription_removed
+            AFTER DELETE ON transcriptions
+            BEGIN
+                DELETE FROM transcript_fts WHERE transcription_id = OLD.id;
+            END;",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 22,
+            description: "Add projects and storage quotas",
+            sql: "ALTER TABLE audio_files ADD COLUMN project TEXT;
+            CREATE INDEX IF NOT EXISTS idx_audio_files_project ON audio_files(project);
+
+            CREATE TABLE IF NOT EXISTS project_quotas (
+                project TEXT PRIMARY KEY,
+                max_hours REAL,
+                max_bytes INTEGER
+            );",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 23,
+            description: "Add companion devices and audio sources",
+            sql: "ALTER TABLE audio_files ADD COLUMN source TEXT;
+
+            CREATE TABLE IF NOT EXISTS companion_devices (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                token_hash TEXT NOT NULL UNIQUE,
+                paired_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                last_seen_at DATETIME
+            );",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 24,
+            description: "Add watch folder series",
+            sql: "ALTER TABLE watch_folders ADD COLUMN series TEXT;",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 25,
+            description: "Add watch folder projects",
+            sql: "ALTER TABLE watch_folders ADD COLUMN project TEXT;",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 26,
+            description: "Remember the meeting type of a transcription",
+            sql: "ALTER TABLE transcriptions ADD COLUMN meeting_type_id TEXT;",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 27,
+            description: "Keep word times with segments",
+            sql: "ALTER TABLE segments ADD COLUMN words TEXT;",
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 28,
+            description: "Remember when rejected watch folder files were last changed",
+            sql: "ALTER TABLE watch_files ADD COLUMN modified_ms INTEGER;",
+            kind: MigrationKind::Up,
+        },
+    ]
+}
+
+/// Highest schema version this build knows how to handle.
+pub fn supported_version() -> i64 {
+    migrations().iter().map(|m| m.version).max().unwrap_or(0)
+}
+
+/// Highest migration applied to the database, as recorded by sqlx.
+fn applied_version(conn: &Connection) -> Result<i64> {
+    let has_table: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_table {
+        return Ok(0);
+    }
+    Ok(conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success = 1",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+fn ensure_supported(conn: &Connection) -> Result<i64> {
+    let found = applied_version(conn)?;
+    let supported = supported_version();
+    if found > supported {
+        return Err(Error::SchemaTooNew { found, supported });
+    }
+    Ok(found)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaVersion {
+    pub current: i64,
+    pub supported: i64,
+    /// False when the database was written by a newer build of the app.
+    pub compatible: bool,
+}
+
+/// Copy the database into `<app config>/backups` with `VACUUM INTO`,
+/// which produces a consistent snapshot even while it is in use.
+pub fn export_before_migrate(app: &AppHandle, conn: &Connection, version: i64) -> Result<PathBuf> {
+    let dir = db_path(app)?
+        .parent()
+        .map(|parent| parent.join("backups"))
+        .ok_or_else(|| Error::NotFound("database directory".into()))?;
+    fs::create_dir_all(&dir)?;
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let target = dir.join(format!("transcription_history-v{}-{}.db", version, stamp));
+    conn.execute("VACUUM INTO ?1", [target.to_string_lossy()])?;
+    Ok(target)
+}
+
+/// Run at startup, before the frontend loads the database: refuse databases
+/// from newer builds and snapshot the file when migrations are pending.
+pub fn prepare(app: &AppHandle) -> Result<()> {
+    let path = db_path(app)?;
+    if !path.exists() {
+        return Ok(());
+    }
+    let conn = Connection::open(&path)?;
+    let current = ensure_supported(&conn)?;
+    if current > 0 && current < supported_version() {
+        export_before_migrate(app, &conn, current)?;
+    }
+    Ok(())
+}
+
+/// Location used by `tauri-plugin-sql` for `sqlite:` URLs. The directory
+/// is created if needed, as on first launch.
+pub fn db_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app
+        .path_resolver()
+        .app_config_dir()
+        .ok_or_else(|| Error::NotFound("app config directory".into()))?;
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(DB_FILE))
+}
+
+pub fn connect(app: &AppHandle) -> Result<Connection> {
+    let conn = Connection::open(db_path(app)?)?;
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    ensure_supported(&conn)?;
+    Ok(conn)
+}
+
+/// The schema of the history database. Before the first launch has created
+/// it, `current` is 0 and no file is made: migrations create it later.
+#[tauri::command]
+pub fn get_schema_version(app: AppHandle) -> Result<SchemaVersion> {
+    let path = db_path(&app)?;
+    let current = if path.exists() {
+        applied_version(&Connection::open(&path)?)?
+    } else {
+        0
+    };
+    let supported = supported_version();
+    Ok(SchemaVersion {
+        current,
+        supported,
+        compatible: current <= supported,
+    })
+}
+
+/// Full text of a stored transcription.
+pub fn transcription_text(conn: &Connection, id: &str) -> Result<String> {
+    conn.query_row(
+        "SELECT text FROM transcriptions WHERE id = ?1",
+        [id],
+        |row| row.get(0),
+    )
+    .map_err(|err| match err {
+        rusqlite::Error::QueryReturnedNoRows => Error::NotFound(format!("transcription {}", id)),
+        other => other.into(),
+    })
+}
+
+pub fn get_preference(conn: &Connection, key: &str) -> Result<Option<String>> {
+    match conn.query_row(
+        "SELECT value FROM user_preferences WHERE key = ?1",
+        [key],
+        |row| row.get(0),
+    ) {
+        Ok(value) => Ok(Some(value)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn set_preference(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO user_preferences (key, value, updated_at)
+         VALUES (?1, ?2, CURRENT_TIMESTAMP)",
+        [key, value],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! The `transcriber://` URI scheme, for launchers and macro keys.
+//!
+//! Alfred, Raycast or a Stream Deck button can open a link instead of
+//! driving the window:
+//!
+//! - `transcriber://transcribe?path=/abs/file.m4a[&model=..][&language=..]`
+//!   queues a file
+//! - `transcriber://record`, `transcriber://stop` and `transcriber://marker`
+//!   control recording the same way the spoken commands do
+//!
+//! Every link also reaches the window as a [`HANDLED_EVENT`], so the
+//! interface can show what was started from outside. Any program, or a web
+//! page, can open a link, so links only act once the user turns them on in
+//! settings, paths must be absolute and name an existing audio file, and a
+//! model must already be downloaded. Queued files are saved like imports.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::{Error, Result};
+use crate::jobs::{self, JobPriority, SaveTarget};
+use crate::voice_commands::{self, VoiceCommand};
+use crate::watch::AUDIO_EXTENSIONS;
+use crate::{crash, db, models};
+
+pub const SCHEME: &str = "transcriber";
+pub const HANDLED_EVENT: &str = "deep-link://handled";
+/// Whether links may record and transcribe; off until the user opts in.
+const ENABLED_PREFERENCE: &str = "deep_links_enabled";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeepLink {
+    Transcribe {
+        path: PathBuf,
+        model: Option<String>,
+        language: Option<String>,
+    },
+    Command(VoiceCommand),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandledLink {
+    pub url: String,
+    /// Job queued by a `transcribe` link.
+    pub job_id: Option<String>,
+    /// Recording session a command acted on.
+    pub session_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Decode `%XX` escapes and `+` in a query component.
+fn decode(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Parse a `transcriber://` link.
+pub fn parse(url: &str) -> Result<DeepLink> {
+    let invalid = || Error::InvalidInput(format!("unsupported link {}", url));
+    let rest = url
+        .strip_prefix(SCHEME)
+        .and_then(|rest| rest.strip_prefix("://"))
+        .ok_or_else(invalid)?;
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let param = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| decode(value))
+            .filter(|value| !value.is_empty())
+    };
+    match action.trim_end_matches('/') {
+        "transcribe" => {
+            let path = PathBuf::from(param("path").ok_or_else(|| {
+                Error::InvalidInput("transcribe links need a path parameter".into())
+            })?);
+            if !path.is_absolute() {
+                return Err(Error::InvalidInput(format!(
+                    "{} is not an absolute path",
+                    path.display()
+                )));
+            }
+            let audio = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+            if !audio {
+                return Err(Error::InvalidInput(format!(
+                    "{} is not an audio file",
+                    path.display()
+                )));
+            }
+            Ok(DeepLink::Transcribe {
+                model: param("model"),
+                language: param("language"),
+                path,
+            })
+        }
+        "record" => Ok(DeepLink::Command(VoiceCommand::StartRecording)),
+        "stop" => Ok(DeepLink::Command(VoiceCommand::StopRecording)),
+        "marker" => Ok(DeepLink::Command(VoiceCommand::AddMarker)),
+        _ => Err(invalid()),
+    }
+}
+
+fn enabled(app: &AppHandle) -> Result<bool> {
+    Ok(db::get_preference(&db::connect(app)?, ENABLED_PREFERENCE)?.as_deref() == Some("true"))
+}
+
+fn perform(app: &AppHandle, link: DeepLink) -> Result<(Option<String>, Option<String>)> {
+    if !enabled(app)? {
+        return Err(Error::InvalidInput(
+            "links that record or transcribe are turned off in settings".into(),
+        ));
+    }
+    match link {
+        DeepLink::Transcribe {
+            path,
+            model,
+            language,
+        } => {
+            if !path.is_file() {
+                return Err(Error::NotFound(format!("file {}", path.display())));
+            }
+            if let Some(model) = &model {
+                if !models::downloaded_models(app)?
+                    .iter()
+                    .any(|downloaded| &downloaded.name == model)
+                {
+                    return Err(Error::InvalidInput(format!(
+                        "model {} is not downloaded",
+                        model
+                    )));
+                }
+            }
+            let job_id = jobs::enqueue_transcription(
+                app.clone(),
+                path,
+                model,
+                language,
+                Some(JobPriority::Interactive),
+                None,
+                None,
+                Some(SaveTarget::default()),
+            )?;
+            Ok((Some(job_id), None))
+        }
+        DeepLink::Command(command) => Ok((None, voice_commands::perform(app, command)?)),
+    }
+}
+
+/// Carry out a link and report it to the window.
+pub fn handle(app: &AppHandle, url: &str) {
+    let url = url.trim();
+    let (job_id, session_id, error) = match parse(url).and_then(|link| perform(app, link)) {
+        Ok((job_id, session_id)) => (job_id, session_id, None),
+        Err(err) => {
+            crash::log(format!("deep link {} failed: {}", url, err));
+            (None, None, Some(err.to_string()))
+        }
+    };
+    if let Some(window) = app.get_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    let _ = app.emit_all(
+        HANDLED_EVENT,
+        HandledLink {
+            url: url.to_string(),
+            job_id,
+            session_id,
+            error,
+        },
+    );
+}
+
+#[tauri::command]
+pub fn get_deep_links_enabled(app: AppHandle) -> Result<bool> {
+    enabled(&app)
+}
+
+/// Allow or refuse links that record and transcribe.
+#[tauri::command]
+pub fn set_deep_links_enabled(app: AppHandle, enabled: bool) -> Result<()> {
+    db::set_preference(
+        &db::connect(&app)?,
+        ENABLED_PREFERENCE,
+        if enabled { "true" } else { "false" },
+    )
+}
+
+/// Register the scheme with the OS and handle links opened from now on,
+/// plus the one that launched the app, if any.
+pub fn init(app: &AppHandle) {
+    let handle_app = app.clone();
+    if let Err(err) = tauri_plugin_deep_link::register(SCHEME, move |url| handle(&handle_app, &url))
+    {
+        crash::log(format!("deep link registration failed: {}", err));
+    }
+    // macOS delivers the launching link through the handler; elsewhere it
+    // arrives as the first argument.
+    #[cfg(not(target_os = "macos"))]
+    if let Some(url) = std::env::args()
+        .nth(1)
+        .filter(|arg| arg.starts_with(&format!("{}://", SCHEME)))
+    {
+        handle(app, &url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_links() {
+        let link = parse(
+            "transcriber://transcribe?path=%2FUsers%2Fanna%2FVoice%20Memos%2Fcall.M4A&model=small",
+        )
+        .unwrap();
+        assert_eq!(
+            link,
+            DeepLink::Transcribe {
+                path: PathBuf::from("/Users/anna/Voice Memos/call.M4A"),
+                model: Some("small".into()),
+                language: None,
+            }
+        );
+        assert_eq!(
+            parse("transcriber://record/").unwrap(),
+            DeepLink::Command(VoiceCommand::StartRecording)
+        );
+        assert!(parse("transcriber://transcribe?path=notes.m4a").is_err());
+        assert!(parse("transcriber://transcribe?path=%2Fetc%2Fpasswd").is_err());
+        assert!(parse("transcriber://format-disk").is_err());
+        assert!(parse("https://example.com/record").is_err());
+    }
+}
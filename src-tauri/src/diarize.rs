@@ -0,0 +1,469 @@
+//! Speaker labels from voice similarity.
+//!
+//! Each segment gets a voice fingerprint: the average and spread of its
+//! cepstral coefficients, a rough description of the shape of the voice
+//! that ignores loudness. Segments are then grouped bottom-up, always
+//! joining the two closest groups, until the speaker count the caller
+//! expects is reached or the closest groups sound too different to be one
+//! voice. Segments too short to fingerprint take the speaker of the
+//! nearest segment that could be.
+//!
+//! Labels are numbered in order of first appearance ("Speaker 1" speaks
+//! first), and [`rediarize`] regroups a stored transcript with another
+//! count or threshold without decoding it again.
+
+use std::f32::consts::PI;
+
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use crate::error::{Error, Result};
+use crate::segments::{self, StoredSegment};
+use crate::{clips, db, i18n};
+
+/// Cosine distance below which two groups count as one voice.
+pub const DEFAULT_THRESHOLD: f32 = 0.75;
+const MAX_SPEAKERS: usize = 20;
+
+/// Samples per analysis frame (32 ms) and between frame starts (20 ms).
+const FRAME: usize = 512;
+const HOP: usize = 320;
+const BANDS: usize = 24;
+/// Cepstral coefficients kept, after the first, which is loudness.
+const COEFFS: usize = 12;
+const LOW_HZ: f32 = 60.0;
+const HIGH_HZ: f32 = 7600.0;
+/// Share of a segment's frames, quietest first, left out as pauses.
+const QUIET_SHARE: f32 = 0.3;
+/// Voiced frames a segment needs for a fingerprint, about 0.4 s.
+const MIN_FRAMES: usize = 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiarizeOptions {
+    /// The number of speakers, when it is known.
+    pub speakers: Option<usize>,
+    /// Bounds on the number of speakers when it is not known exactly.
+    pub min_speakers: Option<usize>,
+    pub max_speakers: Option<usize>,
+    /// Distance up to which groups of segments are joined; lower tells
+    /// similar voices apart more readily. Defaults to [`DEFAULT_THRESHOLD`].
+    pub threshold: Option<f32>,
+}
+
+impl DiarizeOptions {
+    pub fn validate(&self) -> Result<()> {
+        for count in [self.speakers, self.min_speakers, self.max_speakers]
+            .into_iter()
+            .flatten()
+        {
+            if !(1..=MAX_SPEAKERS).contains(&count) {
+                return Err(Error::InvalidInput(format!(
+                    "speaker count {} is outside 1–{}",
+                    count, MAX_SPEAKERS
+                )));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_speakers, self.max_speakers) {
+            if min > max {
+                return Err(Error::InvalidInput(format!(
+                    "at least {} speakers but at most {}",
+                    min, max
+                )));
+            }
+        }
+        if let Some(threshold) = self.threshold {
+            if !(threshold > 0.0 && threshold <= 2.0) {
+                return Err(Error::InvalidInput(format!(
+                    "speaker threshold {} is outside 0–2",
+                    threshold
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// In-place radix-2 FFT; the length must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let (tr, ti) = (re[b] * cos - im[b] * sin, re[b] * sin + im[b] * cos);
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+/// Triangular mel filters as (first FFT bin, weights).
+fn filterbank() -> Vec<(usize, Vec<f32>)> {
+    let (low, high) = (mel(LOW_HZ), mel(HIGH_HZ));
+    let bin = |m: f32| {
+        let hz = 700.0 * (10f32.powf(m / 2595.0) - 1.0);
+        hz * FRAME as f32 / WHISPER_SAMPLE_RATE as f32
+    };
+    let edges: Vec<f32> = (0..BANDS + 2)
+        .map(|i| bin(low + (high - low) * i as f32 / (BANDS + 1) as f32))
+        .collect();
+    edges
+        .windows(3)
+        .map(|edge| {
+            let first = edge[0].ceil() as usize;
+            let weights = (first..=edge[2].floor() as usize)
+                .map(|b| {
+                    let b = b as f32;
+                    if b <= edge[1] {
+                        (b - edge[0]) / (edge[1] - edge[0])
+                    } else {
+                        (edge[2] - b) / (edge[2] - edge[1])
+                    }
+                })
+                .collect();
+            (first, weights)
+        })
+        .collect()
+}
+
+/// Voice fingerprint of some samples: the mean and spread of each cepstral
+/// coefficient over the voiced frames, or `None` when there are too few.
+fn fingerprint(pcm: &[f32], filters: &[(usize, Vec<f32>)]) -> Option<Vec<f32>> {
+    if pcm.len() < FRAME {
+        return None;
+    }
+    let window: Vec<f32> = (0..FRAME)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / FRAME as f32).cos())
+        .collect();
+    let mut frames: Vec<(f32, Vec<f32>)> = Vec::new();
+    for start in (0..=pcm.len() - FRAME).step_by(HOP) {
+        let mut re: Vec<f32> = pcm[start..start + FRAME]
+            .iter()
+            .zip(&window)
+            .map(|(sample, weight)| sample * weight)
+            .collect();
+        let mut im = vec![0.0; FRAME];
+        fft(&mut re, &mut im);
+        let power: Vec<f32> = re.iter().zip(&im).map(|(r, i)| r * r + i * i).collect();
+        let bands: Vec<f32> = filters
+            .iter()
+            .map(|(first, weights)| {
+                let energy: f32 = weights
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, weight)| weight * power.get(first + offset).unwrap_or(&0.0))
+                    .sum();
+                (energy + 1e-10).ln()
+            })
+            .collect();
+        let cepstrum: Vec<f32> = (1..=COEFFS)
+            .map(|k| {
+                bands
+                    .iter()
+                    .enumerate()
+                    .map(|(m, band)| band * (PI * k as f32 * (m as f32 + 0.5) / BANDS as f32).cos())
+                    .sum()
+            })
+            .collect();
+        frames.push((power.iter().sum(), cepstrum));
+    }
+
+    frames.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let voiced = &frames[(frames.len() as f32 * QUIET_SHARE) as usize..];
+    if voiced.len() < MIN_FRAMES || voiced.iter().all(|(energy, _)| *energy <= 1e-6) {
+        return None;
+    }
+    let count = voiced.len() as f32;
+    let mean: Vec<f32> = (0..COEFFS)
+        .map(|k| voiced.iter().map(|(_, c)| c[k]).sum::<f32>() / count)
+        .collect();
+    let spread = (0..COEFFS).map(|k| {
+        (voiced
+            .iter()
+            .map(|(_, c)| (c[k] - mean[k]).powi(2))
+            .sum::<f32>()
+            / count)
+            .sqrt()
+    });
+    Some(mean.iter().copied().chain(spread).collect())
+}
+
+/// Scale each dimension to zero mean and unit variance across segments, so
+/// what sets voices apart counts rather than what the room adds to all.
+fn standardize(fingerprints: &mut [Vec<f32>]) {
+    let Some(dims) = fingerprints.first().map(Vec::len) else {
+        return;
+    };
+    let count = fingerprints.len() as f32;
+    for d in 0..dims {
+        let mean = fingerprints.iter().map(|f| f[d]).sum::<f32>() / count;
+        let deviation = (fingerprints
+            .iter()
+            .map(|f| (f[d] - mean).powi(2))
+            .sum::<f32>()
+            / count)
+            .sqrt();
+        for fingerprint in fingerprints.iter_mut() {
+            fingerprint[d] = (fingerprint[d] - mean) / deviation.max(1e-6);
+        }
+    }
+}
+
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    1.0 - dot / (norm(a) * norm(b)).max(1e-9)
+}
+
+/// Group fingerprints by average distance and number the groups in order of
+/// first appearance.
+pub fn cluster(fingerprints: &[Vec<f32>], options: &DiarizeOptions) -> Vec<usize> {
+    let n = fingerprints.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let (fewest, most) = match options.speakers {
+        Some(count) => (count, count),
+        None => (
+            options.min_speakers.unwrap_or(1),
+            options.max_speakers.unwrap_or(n),
+        ),
+    };
+    let fewest = fewest.clamp(1, n);
+    let most = most.clamp(fewest, n);
+    let threshold = options.threshold.unwrap_or(DEFAULT_THRESHOLD);
+
+    let mut distance: Vec<Vec<f32>> = fingerprints
+        .iter()
+        .map(|a| fingerprints.iter().map(|b| cosine_distance(a, b)).collect())
+        .collect();
+    // Each group is named after its first member; merged ones point on.
+    let mut group: Vec<usize> = (0..n).collect();
+    let mut size = vec![1usize; n];
+    let mut live: Vec<usize> = (0..n).collect();
+    while live.len() > fewest {
+        let mut closest = (f32::INFINITY, 0, 0);
+        for (x, &a) in live.iter().enumerate() {
+            for &b in &live[x + 1..] {
+                if distance[a][b] < closest.0 {
+                    closest = (distance[a][b], a, b);
+                }
+            }
+        }
+        let (gap, a, b) = closest;
+        if live.len() <= most && gap > threshold {
+            break;
+        }
+        live.retain(|&g| g != b);
+        for &c in live.iter().filter(|&&c| c != a) {
+            let joined = (distance[a][c] * size[a] as f32 + distance[b][c] * size[b] as f32)
+                / (size[a] + size[b]) as f32;
+            distance[a][c] = joined;
+            distance[c][a] = joined;
+        }
+        size[a] += size[b];
+        group[b] = a;
+    }
+
+    let root = |mut g: usize| {
+        while group[g] != g {
+            g = group[g];
+        }
+        g
+    };
+    let mut order: Vec<usize> = Vec::new();
+    (0..n)
+        .map(|i| {
+            let g = root(i);
+            order.iter().position(|&seen| seen == g).unwrap_or_else(|| {
+                order.push(g);
+                order.len() - 1
+            })
+        })
+        .collect()
+}
+
+/// Speaker number of each `(start, end)` span of `pcm`, from 0.
+fn assign(pcm: &[f32], spans: &[(f64, f64)], options: &DiarizeOptions) -> Vec<usize> {
+    let filters = filterbank();
+    let index = |secs: f64| ((secs * WHISPER_SAMPLE_RATE as f64) as usize).min(pcm.len());
+    let fingerprints: Vec<Option<Vec<f32>>> = spans
+        .iter()
+        .map(|&(start, end)| {
+            let from = index(start);
+            fingerprint(&pcm[from..index(end).max(from)], &filters)
+        })
+        .collect();
+    let known: Vec<usize> = (0..spans.len())
+        .filter(|&i| fingerprints[i].is_some())
+        .collect();
+    let mut usable: Vec<Vec<f32>> = fingerprints.into_iter().flatten().collect();
+    standardize(&mut usable);
+    let numbers = cluster(&usable, options);
+
+    // Spans without a fingerprint take the nearest one's speaker, the one
+    // before on a tie, which also keeps first-appearance order.
+    (0..spans.len())
+        .map(|i| {
+            known
+                .iter()
+                .zip(&numbers)
+                .min_by_key(|(&k, _)| (k.abs_diff(i), k > i))
+                .map_or(0, |(_, &number)| number)
+        })
+        .collect()
+}
+
+/// Speaker label of each `(start, end)` span of `pcm`, in seconds.
+pub fn speakers(pcm: &[f32], spans: &[(f64, f64)], options: &DiarizeOptions) -> Vec<String> {
+    assign(pcm, spans, options)
+        .into_iter()
+        .map(|number| i18n::t_args("speaker-number", &[("number", (number + 1).to_string())]))
+        .collect()
+}
+
+/// Group a stored transcript's segments into speakers again, e.g. with the
+/// right speaker count after a first pass merged two voices. Names given to
+/// speakers are replaced by numbered labels.
+#[tauri::command]
+pub async fn rediarize(
+    app: AppHandle,
+    id: String,
+    options: Option<DiarizeOptions>,
+) -> Result<Vec<StoredSegment>> {
+    let options = options.unwrap_or_default();
+    options.validate()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut conn = db::connect(&app)?;
+        let stored = segments::for_transcription(&conn, &id)?;
+        if stored.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "transcription {} has no timed segments",
+                id
+            )));
+        }
+        let pcm = clips::source_pcm(&conn, &id)?;
+        let spans: Vec<(f64, f64)> = stored.iter().map(|s| (s.start, s.end)).collect();
+        let labels = speakers(&pcm, &spans, &options);
+
+        let tx = conn.transaction()?;
+        for (segment, label) in stored.iter().zip(&labels) {
+            tx.execute(
+                "UPDATE segments SET speaker = ?2 WHERE id = ?1",
+                params![segment.id, label],
+            )?;
+        }
+        tx.commit()?;
+        segments::for_transcription(&conn, &id)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clustering_follows_the_speaker_hint() {
+        let fingerprints = [
+            vec![1.0, 0.1, 0.0],
+            vec![0.9, 0.2, 0.0],
+            vec![0.0, 1.0, 0.1],
+            vec![1.0, 0.0, 0.1],
+            vec![0.1, 0.9, 0.0],
+            vec![0.0, 0.1, 1.0],
+        ];
+        let cluster = |options: DiarizeOptions| cluster(&fingerprints, &options);
+        assert_eq!(cluster(Default::default()), [0, 0, 1, 0, 1, 2]);
+        let two = DiarizeOptions {
+            speakers: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(cluster(two).iter().max(), Some(&1));
+        let at_least_four = DiarizeOptions {
+            min_speakers: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(cluster(at_least_four).iter().max(), Some(&3));
+        let strict = DiarizeOptions {
+            threshold: Some(0.001),
+            max_speakers: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(cluster(strict).iter().max(), Some(&4));
+        assert!(DiarizeOptions {
+            min_speakers: Some(3),
+            max_speakers: Some(2),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn tells_two_voices_apart() {
+        let rate = WHISPER_SAMPLE_RATE as f32;
+        // A low, dark voice and a high, bright one taking turns. The short
+        // interjection is too short to fingerprint and goes with the turn
+        // before it.
+        let voice = |pitch: f32, tilt: f32, secs: f32| -> Vec<f32> {
+            (0..(secs * rate) as usize)
+                .map(|n| {
+                    let t = n as f32 / rate;
+                    (1..30)
+                        .map(|h| (2.0 * PI * pitch * h as f32 * t).sin() / (h as f32).powf(tilt))
+                        .sum::<f32>()
+                        * 0.1
+                })
+                .collect()
+        };
+        let turns = [
+            (110.0, 2.0, 2.0),
+            (220.0, 0.7, 2.0),
+            (110.0, 2.0, 0.2),
+            (110.0, 2.0, 1.5),
+            (220.0, 0.7, 1.5),
+        ];
+        let mut pcm = Vec::new();
+        let mut spans = Vec::new();
+        for (pitch, tilt, secs) in turns {
+            let start = pcm.len() as f64 / rate as f64;
+            pcm.extend(voice(pitch, tilt, secs));
+            spans.push((start, pcm.len() as f64 / rate as f64));
+        }
+        assert_eq!(assign(&pcm, &spans, &Default::default()), [0, 1, 1, 0, 1]);
+        let one = DiarizeOptions {
+            speakers: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(assign(&pcm, &spans, &one), [0; 5]);
+    }
+}
@@ -0,0 +1,318 @@
+//! Dictation into other applications.
+//!
+//! While active, microphone audio is transcribed in short utterances and the
+//! text is typed into whichever application has focus through synthetic
+//! keyboard input. Spoken commands such as "new line" or "period" are turned
+//! into the corresponding keys instead of being typed literally.
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use enigo::{Direction, Enigo, Key, Keyboard, Settings};
+use serde::Serialize;
+use tauri::{AppHandle, GlobalShortcutManager, Manager, State};
+
+use crate::crash;
+use crate::error::{Error, Result};
+use crate::jobs::JobQueue;
+use crate::recording::Utterances;
+use crate::whisper::{self, DecodeOptions};
+use crate::{db, model_cache, transcription};
+
+pub const DEFAULT_HOTKEY: &str = "CmdOrCtrl+Shift+D";
+pub const HOTKEY_PREFERENCE: &str = "dictation_hotkey";
+pub const TEXT_EVENT: &str = "dictation://text";
+pub const STATE_EVENT: &str = "dictation://state";
+
+/// Utterances are flushed at a pause, or once they get this long.
+const MAX_UTTERANCE_SECS: f64 = 10.0;
+const MIN_UTTERANCE_SECS: f64 = 0.8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Text(String),
+    /// Punctuation attached to the preceding word.
+    Punctuation(char),
+    NewLine,
+    NewParagraph,
+}
+
+/// Spoken commands, longest first so "new paragraph" wins over "new".
+const COMMANDS: &[(&[&str], Action)] = &[
+    (&["new", "paragraph"], Action::NewParagraph),
+    (&["new", "line"], Action::NewLine),
+    (&["question", "mark"], Action::Punctuation('?')),
+    (&["exclamation", "mark"], Action::Punctuation('!')),
+    (&["exclamation", "point"], Action::Punctuation('!')),
+    (&["full", "stop"], Action::Punctuation('.')),
+    (&["period"], Action::Punctuation('.')),
+    (&["comma"], Action::Punctuation(',')),
+    (&["colon"], Action::Punctuation(':')),
+    (&["semicolon"], Action::Punctuation(';')),
+];
+
+fn bare(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// Split recognized text into literal text and voice command actions.
+pub fn parse_commands(text: &str) -> Vec<Action> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut actions = Vec::new();
+    let mut literal: Vec<&str> = Vec::new();
+
+    let mut i = 0;
+    while i < words.len() {
+        let matched = COMMANDS.iter().find(|(phrase, _)| {
+            phrase.len() <= words.len() - i
+                && phrase.iter().zip(&words[i..]).all(|(p, w)| bare(w) == *p)
+        });
+
+        match matched {
+            Some((phrase, action)) => {
+                if !literal.is_empty() {
+                    // Whisper punctuates on its own; drop that before a command.
+                    let text = literal.join(" ");
+                    let text = if matches!(action, Action::Punctuation(_)) {
+                        text.trim_end_matches(|c: char| c.is_ascii_punctuation())
+                            .to_string()
+                    } else {
+                        text
+                    };
+                    actions.push(Action::Text(text));
+                    literal.clear();
+                }
+                actions.push(action.clone());
+                i += phrase.len();
+            }
+            None => {
+                literal.push(words[i]);
+                i += 1;
+            }
+        }
+    }
+    if !literal.is_empty() {
+        actions.push(Action::Text(literal.join(" ")));
+    }
+    actions
+}
+
+/// Types actions into the focused application, tracking spacing between
+/// utterances.
+struct Typist {
+    enigo: Enigo,
+    at_line_start: bool,
+}
+
+impl Typist {
+    fn new() -> Result<Self> {
+        let enigo = Enigo::new(&Settings::default())
+            .map_err(|e| Error::InvalidInput(format!("keyboard input unavailable: {}", e)))?;
+        Ok(Self {
+            enigo,
+            at_line_start: true,
+        })
+    }
+
+    fn key(&mut self, key: Key) {
+        let _ = self.enigo.key(key, Direction::Click);
+    }
+
+    fn text(&mut self, text: &str) {
+        let _ = self.enigo.text(text);
+    }
+
+    fn perform(&mut self, actions: &[Action]) {
+        for action in actions {
+            match action {
+                Action::Text(text) => {
+                    let text = text.trim();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if !self.at_line_start {
+                        self.text(" ");
+                    }
+                    self.text(text);
+                    self.at_line_start = false;
+                }
+                Action::Punctuation(c) => {
+                    self.text(&c.to_string());
+                    self.at_line_start = false;
+                }
+                Action::NewLine => {
+                    self.key(Key::Return);
+                    self.at_line_start = true;
+                }
+                Action::NewParagraph => {
+                    self.key(Key::Return);
+                    self.key(Key::Return);
+                    self.at_line_start = true;
+                }
+            }
+        }
+    }
+}
+
+struct ActiveDictation {
+    stop: mpsc::Sender<()>,
+}
+
+#[derive(Default)]
+pub struct DictationState(Mutex<Option<ActiveDictation>>);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatePayload {
+    active: bool,
+}
+
+fn run(
+    app: AppHandle,
+    model: String,
+    language: Option<String>,
+    stop: mpsc::Receiver<()>,
+    ready: mpsc::Sender<Result<()>>,
+) {
+    let setup = || -> Result<_> {
+        let ctx = model_cache::context_for(&app, &model)?;
+        let utterances = Utterances::open(MIN_UTTERANCE_SECS, MAX_UTTERANCE_SECS)?;
+        Ok((ctx, utterances, Typist::new()?))
+    };
+    let (ctx, mut utterances, mut typist) = match setup() {
+        Ok(started) => started,
+        Err(err) => {
+            let _ = ready.send(Err(err));
+            return;
+        }
+    };
+    let _ = ready.send(Ok(()));
+
+    let _live = app.state::<Arc<JobQueue>>().live_guard();
+    let options = DecodeOptions {
+        language,
+        ..Default::default()
+    };
+
+    while stop.try_recv().is_err() {
+        let Some(utterance) = utterances.poll() else {
+            continue;
+        };
+        match whisper::transcribe(&ctx, &utterance, &options) {
+            Ok(segments) => {
+                let text = whisper::join_text(&segments);
+                typist.perform(&parse_commands(&text));
+                let _ = app.emit_all(TEXT_EVENT, text);
+            }
+            Err(err) => crash::log(format!("dictation decode failed: {}", err)),
+        }
+    }
+}
+
+pub fn start(app: &AppHandle, model: Option<String>, language: Option<String>) -> Result<()> {
+    let state = app.state::<DictationState>();
+    let mut active = state.0.lock().unwrap();
+    if active.is_some() {
+        return Ok(());
+    }
+
+    let model = model.unwrap_or_else(|| transcription::DEFAULT_MODEL.to_string());
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let thread_app = app.clone();
+    thread::spawn(move || run(thread_app, model, language, stop_rx, ready_tx));
+
+    ready_rx
+        .recv()
+        .map_err(|_| Error::AudioDevice("dictation thread exited".into()))??;
+    *active = Some(ActiveDictation { stop: stop_tx });
+    let _ = app.emit_all(STATE_EVENT, StatePayload { active: true });
+    Ok(())
+}
+
+pub fn stop(app: &AppHandle) {
+    if let Some(active) = app.state::<DictationState>().0.lock().unwrap().take() {
+        let _ = active.stop.send(());
+        let _ = app.emit_all(STATE_EVENT, StatePayload { active: false });
+    }
+}
+
+fn toggle(app: &AppHandle) {
+    let active = app.state::<DictationState>().0.lock().unwrap().is_some();
+    if active {
+        stop(app);
+    } else if let Err(err) = start(app, None, None) {
+        crash::log(format!("failed to start dictation: {}", err));
+    }
+}
+
+/// Register the global toggle hotkey. Call once during app setup.
+pub fn init(app: &AppHandle) {
+    let hotkey = db::connect(app)
+        .and_then(|conn| db::get_preference(&conn, HOTKEY_PREFERENCE))
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| DEFAULT_HOTKEY.to_string());
+
+    let handle = app.clone();
+    if let Err(err) = app
+        .global_shortcut_manager()
+        .register(&hotkey, move || toggle(&handle))
+    {
+        crash::log(format!(
+            "failed to register dictation hotkey {}: {}",
+            hotkey, err
+        ));
+    }
+}
+
+#[tauri::command]
+pub fn start_dictation(
+    app: AppHandle,
+    model: Option<String>,
+    language: Option<String>,
+) -> Result<()> {
+    start(&app, model, language)
+}
+
+#[tauri::command]
+pub fn stop_dictation(app: AppHandle) {
+    stop(&app)
+}
+
+#[tauri::command]
+pub fn is_dictation_active(state: State<'_, DictationState>) -> bool {
+    state.0.lock().unwrap().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turns_spoken_commands_into_actions() {
+        let actions = parse_commands("Dear team, new line. Thanks for coming period");
+        assert_eq!(
+            actions,
+            vec![
+                Action::Text("Dear team,".into()),
+                Action::NewLine,
+                Action::Text("Thanks for coming".into()),
+                Action::Punctuation('.'),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_whisper_punctuation_before_spoken_punctuation() {
+        let actions = parse_commands("Is that right? Question mark.");
+        assert_eq!(
+            actions,
+            vec![
+                Action::Text("Is that right".into()),
+                Action::Punctuation('?')
+            ]
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Attributed, conflict-checked edits to stored transcripts.
+//!
+//! When several reviewers share a database (for example on a network
+//! drive), each transcription carries a version. An edit names the version
+//! it was based on and fails with `EDIT_CONFLICT` if someone saved in the
+//! meantime, instead of silently overwriting their work. Every accepted edit
+//! is logged with the editor's name.
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::{db, segments};
+
+pub const EDITOR_PREFERENCE: &str = "editor_name";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditRecord {
+    pub id: String,
+    pub transcription_id: String,
+    /// Transcript version produced by this edit.
+    pub version: i64,
+    pub editor: String,
+    /// `text` for the full transcript, or `segment:<id>`.
+    pub target: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub edited_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditOutcome {
+    pub version: i64,
+    pub editor: String,
+}
+
+/// The configured editor name, falling back to the OS user name.
+pub fn editor_name(conn: &Connection) -> Result<String> {
+    if let Some(name) = db::get_preference(conn, EDITOR_PREFERENCE)? {
+        if !name.trim().is_empty() {
+            return Ok(name);
+        }
+    }
+    Ok(std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "Unknown".into()))
+}
+
+/// Bump the transcript version, failing if it no longer matches `expected`.
+///
+/// With `expected` of `None` the edit is applied on top of whatever version
+/// is current; use it only for edits that cannot clobber others' work.
+pub fn record_edit(
+    tx: &Transaction,
+    transcription_id: &str,
+    expected: Option<i64>,
+    target: &str,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+) -> Result<EditOutcome> {
+    let outcome = bump_version(tx, transcription_id, expected)?;
+    log_edit(tx, transcription_id, &outcome, target, old_value, new_value)?;
+    Ok(outcome)
+}
+
+/// Bump the transcript version as [`record_edit`] does, without logging
+/// what changed. Callers log each change of the edit with [`log_edit`].
+pub fn bump_version(
+    tx: &Transaction,
+    transcription_id: &str,
+    expected: Option<i64>,
+) -> Result<EditOutcome> {
+    let editor = editor_name(tx)?;
+    let (current, last_editor): (i64, Option<String>) = tx
+        .query_row(
+            "SELECT version, updated_by FROM transcriptions WHERE id = ?1",
+            [transcription_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("transcription {}", transcription_id)))?;
+
+    if expected.is_some_and(|expected| expected != current) {
+        return Err(Error::EditConflict {
+            current,
+            editor: last_editor,
+        });
+    }
+
+    let version = current + 1;
+    // The version check is repeated in SQL so a concurrent writer between
+    // the read above and this update is still caught.
+    let updated = tx.execute(
+        "UPDATE transcriptions SET version = ?3, updated_by = ?2, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1 AND version = ?4",
+        params![transcription_id, editor, version, current],
+    )?;
+    if updated == 0 {
+        return Err(Error::EditConflict {
+            current: version,
+            editor: None,
+        });
+    }
+    Ok(EditOutcome { version, editor })
+}
+
+/// Log one change made by the edit that produced `outcome`.
+pub fn log_edit(
+    tx: &Transaction,
+    transcription_id: &str,
+    outcome: &EditOutcome,
+    target: &str,
+    old_value: Option<&str>,
+    new_value: Option<&str>,
+) -> Result<()> {
+    tx.execute(
+        "INSERT INTO edit_log (id, transcription_id, version, editor, target, old_value, new_value)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            uuid::Uuid::new_v4().to_string(),
+            transcription_id,
+            outcome.version,
+            outcome.editor,
+            target,
+            old_value,
+            new_value
+        ],
+    )?;
+    Ok(())
+}
+
+/// Replace a transcript's full text, based on `expected_version`.
+#[tauri::command]
+pub fn update_transcript_text(
+    app: AppHandle,
+    id: String,
+    text: String,
+    expected_version: i64,
+) -> Result<EditOutcome> {
+    let mut conn = db::connect(&app)?;
+    // IMMEDIATE takes the write lock up front, so two editors on a shared
+    // file serialize here rather than both reading the same version.
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    let old = db::transcription_text(&tx, &id)?;
+    let outcome = record_edit(
+        &tx,
+        &id,
+        Some(expected_version),
+        "text",
+        Some(&old),
+        Some(&text),
+    )?;
+    tx.execute(
+        "UPDATE transcriptions SET text = ?2 WHERE id = ?1",
+        params![id, text],
+    )?;
+    tx.commit()?;
+    Ok(outcome)
+}
+
+/// Change the text of one segment, based on the transcript's `expected_version`.
+#[tauri::command]
+pub fn update_segment_text(
+    app: AppHandle,
+    segment_id: String,
+    text: String,
+    expected_version: i64,
+) -> Result<EditOutcome> {
+    let mut conn = db::connect(&app)?;
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    let (transcription_id, old): (String, String) = tx
+        .query_row(
+            "SELECT transcription_id, text FROM segments WHERE id = ?1",
+            [&segment_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("segment {}", segment_id)))?;
+
+    let outcome = record_edit(
+        &tx,
+        &transcription_id,
+        Some(expected_version),
+        &format!("segment:{}", segment_id),
+        Some(&old),
+        Some(text.trim()),
+    )?;
+    tx.execute(
+        "UPDATE segments SET text = ?2 WHERE id = ?1",
+        params![segment_id, text.trim()],
+    )?;
+    segments::sync_text(&tx, &transcription_id)?;
+    tx.commit()?;
+    Ok(outcome)
+}
+
+#[tauri::command]
+pub fn get_transcript_version(app: AppHandle, id: String) -> Result<i64> {
+    db::connect(&app)?
+        .query_row(
+            "SELECT version FROM transcriptions WHERE id = ?1",
+            [&id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("transcription {}", id)))
+}
+
+#[tauri::command]
+pub fn get_edit_history(app: AppHandle, id: String) -> Result<Vec<EditRecord>> {
+    let conn = db::connect(&app)?;
+    let mut statement = conn.prepare(
+        "SELECT id, transcription_id, version, editor, target, old_value, new_value, edited_at
+         FROM edit_log WHERE transcription_id = ?1 ORDER BY version DESC",
+    )?;
+    let records = statement
+        .query_map([&id], |row| {
+            Ok(EditRecord {
+                id: row.get(0)?,
+                transcription_id: row.get(1)?,
+                version: row.get(2)?,
+                editor: row.get(3)?,
+                target: row.get(4)?,
+                old_value: row.get(5)?,
+                new_value: row.get(6)?,
+                edited_at: row.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(records)
+}
+
+#[tauri::command]
+pub fn set_editor_name(app: AppHandle, name: String) -> Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::InvalidInput("editor name is empty".into()));
+    }
+    db::set_preference(&db::connect(&app)?, EDITOR_PREFERENCE, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in db::migrations() {
+            conn.execute_batch(migration.sql).unwrap();
+        }
+        conn.execute(
+            "INSERT INTO transcriptions (id, audio_file_id, text, language, model_used, duration)
+             VALUES ('t1', 'a1', 'Hello.', 'en', 'base', 1.0)",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn logged(conn: &Connection) -> Vec<(i64, String)> {
+        let mut statement = conn
+            .prepare("SELECT version, editor FROM edit_log ORDER BY version")
+            .unwrap();
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        rows.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn rejects_edits_based_on_a_stale_version() {
+        let mut conn = database();
+        db::set_preference(&conn, EDITOR_PREFERENCE, "Ann").unwrap();
+
+        let tx = conn.transaction().unwrap();
+        let outcome = record_edit(&tx, "t1", Some(1), "text", Some("Hello."), Some("Hi.")).unwrap();
+        tx.commit().unwrap();
+        assert_eq!((outcome.version, outcome.editor.as_str()), (2, "Ann"));
+
+        db::set_preference(&conn, EDITOR_PREFERENCE, "Bo").unwrap();
+        let tx = conn.transaction().unwrap();
+        let err =
+            record_edit(&tx, "t1", Some(1), "text", Some("Hello."), Some("Hey.")).unwrap_err();
+        drop(tx);
+        assert!(matches!(
+            err,
+            Error::EditConflict { current: 2, editor: Some(ref editor) } if editor == "Ann"
+        ));
+        assert_eq!(logged(&conn), [(2, "Ann".to_string())]);
+    }
+
+    #[test]
+    fn applies_unchecked_edits_on_top_of_the_current_version() {
+        let mut conn = database();
+        db::set_preference(&conn, EDITOR_PREFERENCE, "Ann").unwrap();
+        for _ in 0..2 {
+            let tx = conn.transaction().unwrap();
+            record_edit(&tx, "t1", None, "title", None, Some("Sync")).unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(logged(&conn), [(2, "Ann".into()), (3, "Ann".into())]);
+
+        let tx = conn.transaction().unwrap();
+        assert!(matches!(
+            record_edit(&tx, "missing", None, "text", None, None),
+            Err(Error::NotFound(_))
+        ));
+    }
+}
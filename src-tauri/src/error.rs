@@ -0,0 +1,109 @@
+//! Error type shared by all Tauri commands.
+//!
+//! Errors are serialized as `{ code, message }` so the frontend can branch on
+//! a stable machine-readable code while still showing a readable message.
+//! `QUOTA_EXCEEDED` errors also carry the broken limit as `quota`.
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error(
+        "this database uses schema version {found}, but this version of the app only supports up to {supported}; please update the app"
+    )]
+    SchemaTooNew { found: i64, supported: i64 },
+
+    #[error("audio device error: {0}")]
+    AudioDevice(String),
+
+    #[error("audio decode error: {0}")]
+    Decode(String),
+
+    #[error("transcription failed: {0}")]
+    Transcription(String),
+
+    #[error("pre-flight check failed: {0}")]
+    PreflightFailed(String),
+
+    #[error("not found: {0}")]
+    NotFound(String),
+
+    #[error("invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("summarization provider error: {0}")]
+    Provider(String),
+
+    #[error("offline mode is on: {0}")]
+    OfflineMode(String),
+
+    #[error("keychain error: {0}")]
+    Keychain(String),
+
+    #[error("cancelled: {0}")]
+    Cancelled(String),
+
+    #[error(
+        "this transcript was changed by {} (now version {current}); reload it before saving",
+        editor.as_deref().unwrap_or("someone else")
+    )]
+    EditConflict {
+        current: i64,
+        editor: Option<String>,
+    },
+
+    #[error(
+        "project {} is over its quota: this import would use {} of {}",
+        .0.project,
+        .0.total_label(),
+        .0.limit_label()
+    )]
+    QuotaExceeded(crate::quota::QuotaViolation),
+}
+
+impl Error {
+    /// Stable identifier used by the frontend to distinguish error kinds.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Io(_) => "IO_ERROR",
+            Error::Database(_) => "DATABASE_ERROR",
+            Error::SchemaTooNew { .. } => "SCHEMA_TOO_NEW",
+            Error::AudioDevice(_) => "AUDIO_DEVICE",
+            Error::Decode(_) => "DECODE_ERROR",
+            Error::Transcription(_) => "TRANSCRIPTION_FAILED",
+            Error::PreflightFailed(_) => "PREFLIGHT_FAILED",
+            Error::NotFound(_) => "NOT_FOUND",
+            Error::InvalidInput(_) => "INVALID_INPUT",
+            Error::Provider(_) => "PROVIDER_ERROR",
+            Error::OfflineMode(_) => "OFFLINE_MODE",
+            Error::Keychain(_) => "KEYCHAIN_ERROR",
+            Error::Cancelled(_) => "CANCELLED",
+            Error::EditConflict { .. } => "EDIT_CONFLICT",
+            Error::QuotaExceeded(_) => "QUOTA_EXCEEDED",
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let quota = match self {
+            Error::QuotaExceeded(violation) => Some(violation),
+            _ => None,
+        };
+        let mut state = serializer.serialize_struct("Error", 2 + quota.is_some() as usize)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &crate::i18n::error_message(self))?;
+        if let Some(violation) = quota {
+            state.serialize_field("quota", violation)?;
+        }
+        state.end()
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
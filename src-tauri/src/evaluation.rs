@@ -0,0 +1,207 @@
+//! Accuracy metrics for comparing a transcript against a reference.
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlignmentOp {
+    Equal,
+    Substitute,
+    Insert,
+    Delete,
+}
+
+/// One step of the word alignment, for rendering a diff in the UI.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlignedWord {
+    pub op: AlignmentOp,
+    pub reference: Option<String>,
+    pub hypothesis: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationReport {
+    pub word_error_rate: f64,
+    pub character_error_rate: f64,
+    pub reference_words: usize,
+    pub hypothesis_words: usize,
+    pub substitutions: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub alignment: Vec<AlignedWord>,
+}
+
+/// Lowercase and strip punctuation so formatting differences are not
+/// counted as recognition errors.
+pub fn normalize_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '\'')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Levenshtein distance over arbitrary tokens.
+pub fn edit_distance<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=hypothesis.len()).collect();
+    let mut current = vec![0; hypothesis.len() + 1];
+
+    for (i, r) in reference.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, h) in hypothesis.iter().enumerate() {
+            let substitution = previous[j] + usize::from(r != h);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[hypothesis.len()]
+}
+
+/// Word error rate: word-level edits divided by the reference word count.
+pub fn word_error_rate(reference: &str, hypothesis: &str) -> f64 {
+    let reference = normalize_words(reference);
+    let hypothesis = normalize_words(hypothesis);
+    if reference.is_empty() {
+        return if hypothesis.is_empty() { 0.0 } else { 1.0 };
+    }
+    edit_distance(&reference, &hypothesis) as f64 / reference.len() as f64
+}
+
+/// Character error rate over the normalized text, spaces included.
+pub fn character_error_rate(reference: &str, hypothesis: &str) -> f64 {
+    let reference: Vec<char> = normalize_words(reference).join(" ").chars().collect();
+    let hypothesis: Vec<char> = normalize_words(hypothesis).join(" ").chars().collect();
+    if reference.is_empty() {
+        return if hypothesis.is_empty() { 0.0 } else { 1.0 };
+    }
+    edit_distance(&reference, &hypothesis) as f64 / reference.len() as f64
+}
+
+/// Minimum-edit alignment between reference and hypothesis words.
+pub fn align(reference: &[String], hypothesis: &[String]) -> Vec<AlignedWord> {
+    let (n, m) = (reference.len(), hypothesis.len());
+    let mut cost = vec![vec![0usize; m + 1]; n + 1];
+    for (i, row) in cost.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    cost[0] = (0..=m).collect();
+    for i in 1..=n {
+        for j in 1..=m {
+            let substitution =
+                cost[i - 1][j - 1] + usize::from(reference[i - 1] != hypothesis[j - 1]);
+            cost[i][j] = substitution.min(cost[i - 1][j] + 1).min(cost[i][j - 1] + 1);
+        }
+    }
+
+    let mut steps = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 {
+            let matched = reference[i - 1] == hypothesis[j - 1];
+            if cost[i][j] == cost[i - 1][j - 1] + usize::from(!matched) {
+                steps.push(AlignedWord {
+                    op: if matched {
+                        AlignmentOp::Equal
+                    } else {
+                        AlignmentOp::Substitute
+                    },
+                    reference: Some(reference[i - 1].clone()),
+                    hypothesis: Some(hypothesis[j - 1].clone()),
+                });
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+        if i > 0 && cost[i][j] == cost[i - 1][j] + 1 {
+            steps.push(AlignedWord {
+                op: AlignmentOp::Delete,
+                reference: Some(reference[i - 1].clone()),
+                hypothesis: None,
+            });
+            i -= 1;
+        } else {
+            steps.push(AlignedWord {
+                op: AlignmentOp::Insert,
+                reference: None,
+                hypothesis: Some(hypothesis[j - 1].clone()),
+            });
+            j -= 1;
+        }
+    }
+
+    steps.reverse();
+    steps
+}
+
+pub fn evaluate(reference: &str, hypothesis: &str) -> EvaluationReport {
+    let reference_words = normalize_words(reference);
+    let hypothesis_words = normalize_words(hypothesis);
+    let alignment = align(&reference_words, &hypothesis_words);
+
+    let count = |op| alignment.iter().filter(|step| step.op == op).count();
+    let substitutions = count(AlignmentOp::Substitute);
+    let insertions = count(AlignmentOp::Insert);
+    let deletions = count(AlignmentOp::Delete);
+
+    EvaluationReport {
+        word_error_rate: word_error_rate(reference, hypothesis),
+        character_error_rate: character_error_rate(reference, hypothesis),
+        reference_words: reference_words.len(),
+        hypothesis_words: hypothesis_words.len(),
+        substitutions,
+        insertions,
+        deletions,
+        alignment,
+    }
+}
+
+/// Score a stored transcription against a plain-text reference transcript.
+#[tauri::command]
+pub fn evaluate_transcription(
+    app: AppHandle,
+    id: String,
+    reference_path: PathBuf,
+) -> Result<EvaluationReport> {
+    let reference = fs::read_to_string(reference_path)?;
+    let hypothesis = db::transcription_text(&db::connect(&app)?, &id)?;
+    Ok(evaluate(&reference, &hypothesis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_error_rate_ignores_case_and_punctuation() {
+        assert_eq!(word_error_rate("Hello, world.", "hello world"), 0.0);
+        assert_eq!(
+            word_error_rate("the cat sat", "the bat sat down"),
+            2.0 / 3.0
+        );
+    }
+
+    #[test]
+    fn alignment_counts_each_edit_kind() {
+        let report = evaluate("the quick brown fox", "the quack brown fox jumps");
+        assert_eq!(report.substitutions, 1);
+        assert_eq!(report.insertions, 1);
+        assert_eq!(report.deletions, 0);
+        assert_eq!(report.alignment.len(), 5);
+        assert_eq!(report.alignment[1].op, AlignmentOp::Substitute);
+    }
+}
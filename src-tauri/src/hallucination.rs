@@ -0,0 +1,243 @@
+//! Detection of text whisper invents rather than hears.
+//!
+//! Three patterns are caught after decoding: the decoder looping on the
+//! same segment or phrase, text over audio with no detected speech, and
+//! stock phrases learned from subtitled video ("thank you for watching").
+//! Depending on the `hallucination_filter` preference they are only
+//! reported (the default), removed, or left alone.
+
+use std::ops::Range;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::Result;
+use crate::vad;
+use crate::whisper::Segment;
+
+pub const FILTER_PREFERENCE: &str = "hallucination_filter";
+
+/// Segments with less detected speech than this share count as silent.
+const MIN_SPEECH_COVERAGE: f64 = 0.1;
+/// Identical consecutive segments beyond this many are a decoder loop.
+const MAX_IDENTICAL_RUN: usize = 2;
+/// A phrase repeated this many times within one segment is a loop.
+const MIN_PHRASE_REPEATS: usize = 4;
+
+/// Whole-segment phrases, compared after normalization.
+const KNOWN_PHRASES: &[&str] = &[
+    "thank you for watching",
+    "thanks for watching",
+    "thank you for watching and see you next time",
+    "please subscribe",
+    "please like and subscribe",
+    "dont forget to like and subscribe",
+    "see you in the next video",
+    "bedankt voor het kijken",
+    "vielen dank fürs zuschauen",
+    "merci davoir regardé",
+];
+
+/// Credits burned into subtitle training data.
+const KNOWN_PREFIXES: &[&str] = &[
+    "subtitles by",
+    "subtitled by",
+    "transcribed by",
+    "captions by",
+    "ondertiteling",
+    "untertitel",
+    "amaraorg",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterMode {
+    Remove,
+    /// Report findings but keep the text, so nothing real is lost unless
+    /// the user opts into removal.
+    #[default]
+    Flag,
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HallucinationKind {
+    Repetition,
+    NoSpeech,
+    KnownPhrase,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    pub kind: HallucinationKind,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HallucinationReport {
+    /// Segments taken out of the transcript.
+    pub removed: usize,
+    pub findings: Vec<Finding>,
+}
+
+pub fn filter_mode(conn: &Connection) -> Result<FilterMode> {
+    Ok(db::get_preference(conn, FILTER_PREFERENCE)?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn get_hallucination_filter(app: AppHandle) -> Result<FilterMode> {
+    filter_mode(&db::connect(&app)?)
+}
+
+#[tauri::command]
+pub fn set_hallucination_filter(app: AppHandle, mode: FilterMode) -> Result<()> {
+    db::set_preference(
+        &db::connect(&app)?,
+        FILTER_PREFERENCE,
+        &serde_json::to_string(&mode).unwrap(),
+    )
+}
+
+/// Lowercase words without punctuation, for comparing segment text.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether the words are one short phrase said over and over.
+fn is_phrase_loop(words: &[&str]) -> bool {
+    (1..=4).any(|len| {
+        words.len() >= len * MIN_PHRASE_REPEATS
+            && words.len() % len == 0
+            && words.chunks(len).all(|chunk| chunk == &words[..len])
+    })
+}
+
+fn is_known_phrase(normalized: &str) -> bool {
+    KNOWN_PHRASES.contains(&normalized)
+        || KNOWN_PREFIXES
+            .iter()
+            .any(|prefix| normalized.starts_with(prefix))
+}
+
+/// Classify each segment, given the speech regions of its audio when known.
+pub fn detect(
+    segments: &[Segment],
+    speech: Option<&[Range<usize>]>,
+) -> Vec<Option<HallucinationKind>> {
+    let normalized: Vec<String> = segments.iter().map(|s| normalize(&s.text)).collect();
+    let mut run = 0;
+    segments
+        .iter()
+        .enumerate()
+        .map(|(index, segment)| {
+            let text = &normalized[index];
+            run = match index.checked_sub(1) {
+                Some(previous) if !text.is_empty() && normalized[previous] == *text => run + 1,
+                _ => 1,
+            };
+            let words: Vec<&str> = text.split_whitespace().collect();
+
+            if text.is_empty() {
+                None
+            } else if is_known_phrase(text) {
+                Some(HallucinationKind::KnownPhrase)
+            } else if run > MAX_IDENTICAL_RUN || is_phrase_loop(&words) {
+                Some(HallucinationKind::Repetition)
+            } else if speech.is_some_and(|regions| {
+                vad::speech_coverage(regions, segment.start, segment.end) < MIN_SPEECH_COVERAGE
+            }) {
+                Some(HallucinationKind::NoSpeech)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Apply `mode` to decoded segments, returning the kept segments and what
+/// was found.
+pub fn apply(
+    segments: Vec<Segment>,
+    speech: Option<&[Range<usize>]>,
+    mode: FilterMode,
+) -> (Vec<Segment>, HallucinationReport) {
+    if mode == FilterMode::Off {
+        return (segments, HallucinationReport::default());
+    }
+    let kinds = detect(&segments, speech);
+    let mut report = HallucinationReport::default();
+    let mut kept = Vec::with_capacity(segments.len());
+    for (segment, kind) in segments.into_iter().zip(kinds) {
+        match kind {
+            Some(kind) => {
+                report.findings.push(Finding {
+                    kind,
+                    start: segment.start,
+                    end: segment.end,
+                    text: segment.text.clone(),
+                });
+                if mode == FilterMode::Remove {
+                    report.removed += 1;
+                } else {
+                    kept.push(segment);
+                }
+            }
+            None => kept.push(segment),
+        }
+    }
+    (kept, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str, start: f64) -> Segment {
+        Segment {
+            text: text.into(),
+            start,
+            end: start + 2.0,
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn removes_loops_and_stock_phrases() {
+        let segments = vec![
+            segment("We ship on Friday.", 0.0),
+            segment("We ship on Friday.", 2.0),
+            segment("We ship on Friday.", 4.0),
+            segment("Thank you for watching!", 6.0),
+            segment("okay okay okay okay okay okay okay okay", 8.0),
+        ];
+        let (kept, report) = apply(segments, None, FilterMode::Remove);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(report.removed, 3);
+        assert_eq!(report.findings[1].kind, HallucinationKind::KnownPhrase);
+    }
+
+    #[test]
+    fn flag_mode_keeps_segments() {
+        let segments = vec![segment("Subtitles by the Amara.org community", 0.0)];
+        let (kept, report) = apply(segments, None, FilterMode::Flag);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(report.removed, 0);
+        assert_eq!(report.findings.len(), 1);
+    }
+}
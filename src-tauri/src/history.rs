@@ -0,0 +1,192 @@
+//! Spreadsheet export of the transcription history.
+//!
+//! The filter mirrors the history view's, so an export contains exactly the
+//! rows the user was looking at, without the paging.
+
+use std::fs;
+use std::path::PathBuf;
+
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use serde::Deserialize;
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::Result;
+
+/// Characters of the summary kept in the excerpt column.
+const EXCERPT_CHARS: usize = 200;
+
+const HEADER: [&str; 9] = [
+    "id",
+    "title",
+    "date",
+    "duration_seconds",
+    "language",
+    "model",
+    "word_count",
+    "tags",
+    "summary_excerpt",
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryFilter {
+    pub language: Option<String>,
+    pub model_used: Option<String>,
+    pub date_from: Option<String>,
+    pub date_to: Option<String>,
+    pub search_text: Option<String>,
+    pub artist: Option<String>,
+    pub device: Option<String>,
+    pub recorded_from: Option<String>,
+    pub recorded_to: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// The WHERE clause and its parameters for `filter`.
+fn conditions(filter: &HistoryFilter) -> (String, Vec<Value>) {
+    let mut sql = String::from("WHERE 1=1");
+    let mut values = Vec::new();
+    let mut add = |clause: &str, value: &Option<String>, pattern: bool| {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            sql.push_str(clause);
+            let value = if pattern {
+                format!("%{}%", value)
+            } else {
+                value.to_string()
+            };
+            values.extend(std::iter::repeat(Value::Text(value)).take(clause.matches('?').count()));
+        }
+    };
+    add(" AND t.language = ?", &filter.language, false);
+    add(" AND t.model_used = ?", &filter.model_used, false);
+    add(" AND t.created_at >= ?", &filter.date_from, false);
+    add(" AND t.created_at <= ?", &filter.date_to, false);
+    add(
+        " AND (t.text LIKE ? OR s.summary LIKE ? OR t.title LIKE ? OR a.title LIKE ?)",
+        &filter.search_text,
+        true,
+    );
+    add(" AND a.artist LIKE ?", &filter.artist, true);
+    add(" AND a.device LIKE ?", &filter.device, true);
+    add(" AND a.recorded_at >= ?", &filter.recorded_from, false);
+    add(" AND a.recorded_at <= ?", &filter.recorded_to, false);
+    add(
+        " AND t.id IN (SELECT transcription_id FROM transcription_tags WHERE tag = ?)",
+        &filter.tag,
+        false,
+    );
+    (sql, values)
+}
+
+/// Quote a field when it holds a separator, quote or line break. Fields a
+/// spreadsheet would run as a formula get a leading `'`.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn excerpt(summary: &str) -> String {
+    let flat = summary.split_whitespace().collect::<Vec<_>>().join(" ");
+    match flat.char_indices().nth(EXCERPT_CHARS) {
+        Some((cut, _)) => format!("{}…", flat[..cut].trim_end()),
+        None => flat,
+    }
+}
+
+/// CSV of the matching transcriptions, newest first, with a header row,
+/// and the number of transcriptions in it.
+pub fn render(conn: &Connection, filter: &HistoryFilter) -> Result<(String, usize)> {
+    let (conditions, values) = conditions(filter);
+    // The latest summary only, so each transcription is one row.
+    let sql = format!(
+        "SELECT t.id, COALESCE(t.title, a.title, ''), t.created_at, t.duration, t.language,
+                t.model_used, t.text,
+                (SELECT group_concat(tag, '; ') FROM transcription_tags WHERE transcription_id = t.id),
+                s.summary
+         FROM transcriptions t
+         LEFT JOIN summaries s ON s.id = (
+             SELECT id FROM summaries WHERE transcription_id = t.id ORDER BY created_at DESC LIMIT 1)
+         LEFT JOIN audio_files a ON t.audio_file_id = a.id
+         {}
+         ORDER BY t.created_at DESC",
+        conditions
+    );
+
+    let mut out = HEADER.join(",");
+    out.push_str("\r\n");
+    let mut statement = conn.prepare(&sql)?;
+    let mut rows = statement.query(params_from_iter(values))?;
+    let mut count = 0;
+    while let Some(row) = rows.next()? {
+        count += 1;
+        let text: String = row.get(6)?;
+        let tags: Option<String> = row.get(7)?;
+        let summary: Option<String> = row.get(8)?;
+        let fields = [
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            format!("{:.1}", row.get::<_, f64>(3)?),
+            row.get::<_, String>(4)?,
+            row.get::<_, String>(5)?,
+            text.split_whitespace().count().to_string(),
+            tags.unwrap_or_default(),
+            summary.as_deref().map(excerpt).unwrap_or_default(),
+        ];
+        let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    }
+    Ok((out, count))
+}
+
+/// Write the transcriptions matching `filter` to a CSV file at `path`.
+/// Returns the number of rows written.
+#[tauri::command]
+pub fn export_history_csv(
+    app: AppHandle,
+    filter: Option<HistoryFilter>,
+    path: PathBuf,
+) -> Result<usize> {
+    let (csv, rows) = render(&db::connect(&app)?, &filter.unwrap_or_default())?;
+    // A byte order mark makes spreadsheet apps read the file as UTF-8.
+    fs::write(path, format!("\u{feff}{}", csv))?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_fields_that_need_it() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\"\nthen"), "\"say \"\"hi\"\"\nthen\"");
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_field("-5 dB"), "'-5 dB");
+        assert_eq!(csv_field("@team"), "'@team");
+    }
+
+    #[test]
+    fn search_binds_one_value_per_placeholder() {
+        let (sql, values) = conditions(&HistoryFilter {
+            search_text: Some("budget".into()),
+            tag: Some("q3".into()),
+            ..Default::default()
+        });
+        assert_eq!(sql.matches('?').count(), values.len());
+        assert_eq!(values[0], Value::Text("%budget%".into()));
+        assert_eq!(values[4], Value::Text("q3".into()));
+    }
+}
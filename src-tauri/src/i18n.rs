@@ -0,0 +1,244 @@
+//! Localization of text the backend writes itself.
+//!
+//! Export headings, default titles and labels, and error messages follow
+//! the `ui_language` preference, so a Dutch transcript is not exported
+//! with English boilerplate. Messages live in Fluent files under
+//! `locales/`, compiled into the binary; a message missing from the
+//! chosen language falls back to English.
+//!
+//! Within error messages, file system errors and the kind of record that
+//! was not found are translated too; other details, such as file paths or
+//! the text of a decoder error, are kept as they are.
+
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{Error, Result};
+
+pub const LANGUAGE_PREFERENCE: &str = "ui_language";
+const FALLBACK: &str = "en";
+
+const SOURCES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("nl", include_str!("../locales/nl.ftl")),
+];
+
+static BUNDLES: OnceLock<HashMap<&'static str, FluentBundle<FluentResource>>> = OnceLock::new();
+/// The chosen language; empty until the preference is read.
+static CURRENT: RwLock<String> = RwLock::new(String::new());
+
+fn bundles() -> &'static HashMap<&'static str, FluentBundle<FluentResource>> {
+    BUNDLES.get_or_init(|| {
+        SOURCES
+            .iter()
+            .map(|&(language, source)| {
+                let resource = FluentResource::try_new(source.to_string())
+                    .unwrap_or_else(|(resource, _)| resource);
+                let mut bundle = FluentBundle::new_concurrent(vec![language.parse().unwrap()]);
+                // Isolation marks around arguments would end up in files.
+                bundle.set_use_isolating(false);
+                let _ = bundle.add_resource(resource);
+                (language, bundle)
+            })
+            .collect()
+    })
+}
+
+fn supported(language: &str) -> bool {
+    SOURCES.iter().any(|(code, _)| *code == language)
+}
+
+/// The language backend text is written in.
+pub fn language() -> String {
+    let current = CURRENT.read().unwrap();
+    if current.is_empty() {
+        FALLBACK.to_string()
+    } else {
+        current.clone()
+    }
+}
+
+/// Message `id` in `language`, else in English, else the id itself.
+pub fn message(language: &str, id: &str, args: &[(&str, String)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+    [language, FALLBACK]
+        .iter()
+        .filter_map(|language| bundles().get(language))
+        .find_map(|bundle| {
+            let pattern = bundle.get_message(id)?.value()?;
+            let mut errors = Vec::new();
+            Some(
+                bundle
+                    .format_pattern(pattern, Some(&fluent_args), &mut errors)
+                    .into_owned(),
+            )
+        })
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// Message `id` in the chosen language.
+pub fn t(id: &str) -> String {
+    message(&language(), id, &[])
+}
+
+/// Message `id` in the chosen language, with arguments.
+pub fn t_args(id: &str, args: &[(&str, String)]) -> String {
+    message(&language(), id, args)
+}
+
+/// Records named in not-found errors, by the detail's English prefix.
+const MISSING: &[(&str, &str)] = &[
+    ("transcription ", "missing-transcription"),
+    ("watch folder ", "missing-watch-folder"),
+    ("recording session ", "missing-recording-session"),
+    ("audio file ", "missing-audio-file"),
+    ("comment ", "missing-comment"),
+    ("segment ", "missing-segment"),
+    ("folder ", "missing-folder"),
+    ("file ", "missing-file"),
+    ("job ", "missing-job"),
+    ("app data directory", "missing-app-data-directory"),
+];
+
+/// `err` as its translated kind where there is one, else as the OS says it.
+fn io_detail(err: &std::io::Error) -> String {
+    let id = match err.kind() {
+        std::io::ErrorKind::NotFound => "io-not-found",
+        std::io::ErrorKind::PermissionDenied => "io-permission-denied",
+        std::io::ErrorKind::AlreadyExists => "io-already-exists",
+        std::io::ErrorKind::StorageFull => "io-storage-full",
+        _ => return err.to_string(),
+    };
+    t(id)
+}
+
+/// A not-found detail with the kind of record translated.
+fn missing_detail(detail: &str) -> String {
+    MISSING
+        .iter()
+        .find_map(|(prefix, id)| {
+            let rest = detail.strip_prefix(prefix)?;
+            let separator = if prefix.ends_with(' ') { " " } else { "" };
+            Some(format!("{}{}{}", t(id), separator, rest))
+        })
+        .unwrap_or_else(|| detail.to_string())
+}
+
+/// The message shown for `error`, in the chosen language.
+pub fn error_message(error: &Error) -> String {
+    let args = match error {
+        Error::Io(err) => vec![("detail", io_detail(err))],
+        Error::Database(err) => vec![("detail", err.to_string())],
+        Error::SchemaTooNew { found, supported } => vec![
+            ("found", found.to_string()),
+            ("supported", supported.to_string()),
+        ],
+        Error::EditConflict { current, editor } => vec![
+            ("current", current.to_string()),
+            (
+                "editor",
+                editor.clone().unwrap_or_else(|| t("someone-else")),
+            ),
+        ],
+        Error::QuotaExceeded(violation) => vec![
+            ("project", violation.project.clone()),
+            ("used", violation.total_label()),
+            ("limit", violation.limit_label()),
+        ],
+        Error::AudioDevice(detail)
+        | Error::Decode(detail)
+        | Error::Transcription(detail)
+        | Error::PreflightFailed(detail)
+        | Error::InvalidInput(detail)
+        | Error::Provider(detail)
+        | Error::OfflineMode(detail)
+        | Error::Keychain(detail)
+        | Error::Cancelled(detail) => vec![("detail", detail.clone())],
+        Error::NotFound(detail) => vec![("detail", missing_detail(detail))],
+    };
+    let id = format!("error-{}", error.code().to_lowercase().replace('_', "-"));
+    t_args(&id, &args)
+}
+
+fn set_current(language: Option<String>) {
+    *CURRENT.write().unwrap() = language
+        .filter(|language| supported(language))
+        .unwrap_or_default();
+}
+
+/// Load the language preference.
+pub fn init(app: &AppHandle) {
+    if let Ok(language) =
+        db::connect(app).and_then(|conn| db::get_preference(&conn, LANGUAGE_PREFERENCE))
+    {
+        set_current(language);
+    }
+}
+
+#[tauri::command]
+pub fn get_ui_language() -> String {
+    language()
+}
+
+#[tauri::command]
+pub fn set_ui_language(app: AppHandle, language: String) -> Result<()> {
+    if !supported(&language) {
+        return Err(Error::InvalidInput(format!(
+            "{} is not a supported language",
+            language
+        )));
+    }
+    db::set_preference(&db::connect(&app)?, LANGUAGE_PREFERENCE, &language)?;
+    set_current(Some(language));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(source: &str) -> Vec<&str> {
+        source
+            .lines()
+            .filter(|line| !line.starts_with(['#', ' ']))
+            .filter_map(|line| line.split_once(" = ").map(|(id, _)| id))
+            .collect()
+    }
+
+    #[test]
+    fn every_language_has_every_message() {
+        let english = ids(SOURCES[0].1);
+        for (language, source) in SOURCES {
+            assert_eq!(ids(source), english, "messages of {}", language);
+        }
+    }
+
+    #[test]
+    fn formats_and_falls_back() {
+        let args = [("title", "Weekoverleg".to_string())];
+        assert_eq!(message("nl", "part-two", &args), "Weekoverleg (deel 2)");
+        assert_eq!(message("fr", "part-two", &args), "Weekoverleg (part 2)");
+        assert_eq!(message("nl", "no-such-message", &[]), "no-such-message");
+        assert_eq!(
+            message(
+                "en",
+                "error-not-found",
+                &[("detail", "transcription t1".into())]
+            ),
+            Error::NotFound("transcription t1".into()).to_string()
+        );
+        assert_eq!(
+            missing_detail("watch folder w1"),
+            format!("{} w1", t("missing-watch-folder"))
+        );
+        assert_eq!(missing_detail("voice 'anna'"), "voice 'anna'");
+    }
+}
@@ -0,0 +1,408 @@
+//! Search indexes kept up to date in the background.
+//!
+//! Two indexes cover every transcription's title, text and latest summary:
+//! a full-text index (`transcript_fts`) for keyword search, and one vector
+//! per transcription (`transcript_embeddings`) for finding related ones.
+//! The vectors are hashed word and word-pair counts computed locally, so
+//! they work offline and send nothing anywhere; transcripts that share
+//! vocabulary sit close together.
+//!
+//! Database triggers drop a transcription's entry in `search_index_state`
+//! whenever its text, title or summaries change, which marks it for
+//! indexing again. The indexer picks those up only while no job is queued
+//! or running, nothing is recording and the processor is otherwise quiet,
+//! and stops between transcriptions as soon as interactive work starts.
+//! Progress is emitted as `index://updated`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::Serialize;
+use sysinfo::System;
+use tauri::{AppHandle, Manager};
+
+use crate::error::{Error, Result};
+use crate::jobs::JobQueue;
+use crate::{crash, db, recording};
+
+pub const INDEX_EVENT: &str = "index://updated";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Transcriptions indexed per query for pending work.
+const BATCH: usize = 25;
+/// Processor use, in percent of all cores, above which the machine is busy.
+const IDLE_CPU_PERCENT: f32 = 30.0;
+const DIMS: usize = 256;
+const DEFAULT_LIMIT: usize = 20;
+
+/// Set by [`rebuild_indexes`] to start a pass without waiting for the poll.
+static WAKE: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+static INDEXING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStatus {
+    pub total: u64,
+    pub indexed: u64,
+    /// Transcriptions new or changed since they were last indexed.
+    pub pending: u64,
+    /// A pass is running now.
+    pub indexing: bool,
+    pub last_indexed_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub transcription_id: String,
+    pub title: Option<String>,
+    /// Passage around the match, with matched words in `[` and `]`.
+    pub snippet: String,
+    /// Lower is a better match.
+    pub rank: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedTranscription {
+    pub transcription_id: String,
+    pub title: Option<String>,
+    /// Cosine similarity, 1 for the same vocabulary.
+    pub similarity: f32,
+}
+
+/// FNV-1a, which unlike the standard hasher is the same in every build.
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Unit vector of the hashed words (of three or more letters) and word
+/// pairs in `text`, with counts damped so repeated words do not dominate.
+fn embed(text: &str) -> Vec<f32> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect();
+    let mut counts: HashMap<String, f32> = HashMap::new();
+    for word in &words {
+        *counts.entry(word.clone()).or_default() += 1.0;
+    }
+    for pair in words.windows(2) {
+        *counts.entry(pair.join(" ")).or_default() += 0.5;
+    }
+    let mut vector = vec![0.0f32; DIMS];
+    for (feature, count) in counts {
+        let hash = fnv(feature.as_bytes());
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[(hash % DIMS as u64) as usize] += sign * (1.0 + count.ln());
+    }
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+/// An FTS query matching every word of `input`, the last also as a prefix
+/// while it is still being typed. Words are quoted so operators and
+/// punctuation in them are searched for rather than interpreted.
+fn fts_query(input: &str) -> Option<String> {
+    let words: Vec<String> = input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+    let mut query = words.join(" ");
+    if query.is_empty() {
+        return None;
+    }
+    if !input.ends_with(char::is_whitespace) {
+        query.push('*');
+    }
+    Some(query)
+}
+
+/// Whether interactive work is going on: a job, dictation or a recording.
+fn user_busy(app: &AppHandle) -> bool {
+    app.state::<Arc<JobQueue>>().is_busy() || !recording::active_sessions(app).is_empty()
+}
+
+fn machine_idle(app: &AppHandle) -> bool {
+    if user_busy(app) {
+        return false;
+    }
+    let mut system = System::new();
+    system.refresh_cpu();
+    thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+    system.refresh_cpu();
+    system.global_cpu_info().cpu_usage() < IDLE_CPU_PERCENT
+}
+
+fn pending(conn: &Connection, limit: usize) -> Result<Vec<String>> {
+    let mut statement = conn.prepare(
+        "SELECT t.id FROM transcriptions t
+         LEFT JOIN search_index_state s ON s.transcription_id = t.id
+         WHERE s.transcription_id IS NULL
+         ORDER BY t.created_at DESC LIMIT ?1",
+    )?;
+    let ids = statement
+        .query_map([limit as i64], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(ids)
+}
+
+/// Index one transcription. Reading and writing share one write lock, so a
+/// change saved meanwhile leaves it pending rather than marked current.
+fn index_one(conn: &mut Connection, id: &str) -> Result<()> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let Some((title, text)) = tx
+        .query_row(
+            "SELECT COALESCE(title, ''), text FROM transcriptions WHERE id = ?1",
+            [id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?
+    else {
+        return Ok(());
+    };
+    let summary: String = tx
+        .query_row(
+            "SELECT summary FROM summaries WHERE transcription_id = ?1
+             ORDER BY created_at DESC LIMIT 1",
+            [id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or_default();
+
+    tx.execute(
+        "DELETE FROM transcript_fts WHERE transcription_id = ?1",
+        [id],
+    )?;
+    tx.execute(
+        "INSERT INTO transcript_fts (transcription_id, title, text, summary)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![id, title, text, summary],
+    )?;
+    let vector = embed(&format!("{}\n{}\n{}", title, text, summary));
+    tx.execute(
+        "INSERT OR REPLACE INTO transcript_embeddings (transcription_id, vector) VALUES (?1, ?2)",
+        params![id, to_blob(&vector)],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO search_index_state (transcription_id) VALUES (?1)",
+        [id],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn status(conn: &Connection) -> Result<IndexStatus> {
+    let total: i64 = conn.query_row("SELECT COUNT(*) FROM transcriptions", [], |row| row.get(0))?;
+    let (indexed, last_indexed_at): (i64, Option<String>) = conn.query_row(
+        "SELECT COUNT(*), MAX(indexed_at) FROM search_index_state",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(IndexStatus {
+        total: total as u64,
+        indexed: indexed as u64,
+        pending: (total - indexed).max(0) as u64,
+        indexing: INDEXING.load(Ordering::Relaxed),
+        last_indexed_at,
+    })
+}
+
+/// Index pending transcriptions while the machine stays free.
+fn run_pass(app: &AppHandle) -> Result<()> {
+    if !machine_idle(app) {
+        return Ok(());
+    }
+    let mut conn = db::connect(app)?;
+    let mut indexed = 0;
+    INDEXING.store(true, Ordering::Relaxed);
+    let outcome = (|| -> Result<()> {
+        loop {
+            let batch = pending(&conn, BATCH)?;
+            if batch.is_empty() {
+                return Ok(());
+            }
+            for id in batch {
+                if user_busy(app) {
+                    return Ok(());
+                }
+                index_one(&mut conn, &id)?;
+                indexed += 1;
+            }
+        }
+    })();
+    INDEXING.store(false, Ordering::Relaxed);
+    if indexed > 0 {
+        let _ = app.emit_all(INDEX_EVENT, status(&conn)?);
+    }
+    outcome
+}
+
+/// Start the background indexer. Call once during app setup, after the
+/// job queue.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        {
+            let (woken, wake) = &WAKE;
+            let guard = woken.lock().unwrap();
+            let (mut guard, _) = wake
+                .wait_timeout_while(guard, POLL_INTERVAL, |woken| !*woken)
+                .unwrap();
+            *guard = false;
+        }
+        if let Err(err) = run_pass(&app) {
+            crash::log(format!("search indexing failed: {}", err));
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_index_status(app: AppHandle) -> Result<IndexStatus> {
+    status(&db::connect(&app)?)
+}
+
+/// Drop both indexes so every transcription is indexed again, starting
+/// with the next idle moment.
+#[tauri::command]
+pub fn rebuild_indexes(app: AppHandle) -> Result<IndexStatus> {
+    let mut conn = db::connect(&app)?;
+    let tx = conn.transaction()?;
+    tx.execute_batch(
+        "DELETE FROM transcript_fts;
+         DELETE FROM transcript_embeddings;
+         DELETE FROM search_index_state;",
+    )?;
+    tx.commit()?;
+    let (woken, wake) = &WAKE;
+    *woken.lock().unwrap() = true;
+    wake.notify_one();
+    status(&conn)
+}
+
+/// Transcriptions whose title, text or summary contain every word of
+/// `query`, best first. Ones not indexed yet are not found.
+#[tauri::command]
+pub fn search_transcripts(
+    app: AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>> {
+    let Some(query) = fts_query(&query) else {
+        return Ok(Vec::new());
+    };
+    let conn = db::connect(&app)?;
+    let mut statement = conn.prepare(
+        "SELECT f.transcription_id, t.title,
+                snippet(transcript_fts, -1, '[', ']', '…', 16), bm25(transcript_fts)
+         FROM transcript_fts f JOIN transcriptions t ON t.id = f.transcription_id
+         WHERE transcript_fts MATCH ?1
+         ORDER BY bm25(transcript_fts) LIMIT ?2",
+    )?;
+    let hits = statement
+        .query_map(
+            params![query, limit.unwrap_or(DEFAULT_LIMIT) as i64],
+            |row| {
+                Ok(SearchHit {
+                    transcription_id: row.get(0)?,
+                    title: row.get(1)?,
+                    snippet: row.get(2)?,
+                    rank: row.get(3)?,
+                })
+            },
+        )?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(hits)
+}
+
+/// The indexed transcriptions closest in vocabulary to transcription `id`.
+#[tauri::command]
+pub fn related_transcriptions(
+    app: AppHandle,
+    id: String,
+    limit: Option<usize>,
+) -> Result<Vec<RelatedTranscription>> {
+    let conn = db::connect(&app)?;
+    let target: Vec<u8> = conn
+        .query_row(
+            "SELECT vector FROM transcript_embeddings WHERE transcription_id = ?1",
+            [&id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("search index entry of transcription {}", id)))?;
+    let target = from_blob(&target);
+
+    let mut statement = conn.prepare(
+        "SELECT e.transcription_id, t.title, e.vector FROM transcript_embeddings e
+         JOIN transcriptions t ON t.id = e.transcription_id
+         WHERE e.transcription_id != ?1",
+    )?;
+    let mut related: Vec<RelatedTranscription> = statement
+        .query_map([&id], |row| {
+            let vector: Vec<u8> = row.get(2)?;
+            Ok(RelatedTranscription {
+                transcription_id: row.get(0)?,
+                title: row.get(1)?,
+                similarity: from_blob(&vector)
+                    .iter()
+                    .zip(&target)
+                    .map(|(a, b)| a * b)
+                    .sum(),
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    related.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    related.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(related)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_search_words() {
+        assert_eq!(
+            fts_query("budget \"Q3 OR"),
+            Some("\"budget\" \"\"\"Q3\" \"OR\"*".into())
+        );
+        assert_eq!(fts_query("budget "), Some("\"budget\"".into()));
+        assert_eq!(fts_query("   "), None);
+    }
+
+    #[test]
+    fn shared_vocabulary_scores_closer() {
+        let similarity = |a: &str, b: &str| -> f32 {
+            let (a, b) = (embed(a), embed(b));
+            a.iter().zip(&b).map(|(x, y)| x * y).sum()
+        };
+        let budget = "We reviewed the marketing budget and agreed to cut the travel budget.";
+        let follow_up = "Follow-up on the travel budget: marketing spend is reduced next quarter.";
+        let hiking = "The hiking trail was muddy after the rain, so we turned back early.";
+        assert!(similarity(budget, follow_up) > similarity(budget, hiking) + 0.2);
+        assert!((similarity(budget, budget) - 1.0).abs() < 1e-5);
+        assert_eq!(from_blob(&to_blob(&embed(budget))), embed(budget));
+    }
+}
@@ -0,0 +1,328 @@
+//! Question and answer structure for diarized interviews.
+//!
+//! Consecutive segments from one speaker are merged into turns. A turn that
+//! asks something opens a pair, and the other speakers' turns up to the next
+//! question form its answer.
+
+use std::fs;
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::recording::LabeledSegment;
+use crate::{db, i18n};
+
+/// Longest answer excerpt kept, in characters.
+const EXCERPT_CHARS: usize = 300;
+
+const INTERROGATIVES: &[&str] = &[
+    "who", "what", "when", "where", "why", "how", "which", "do", "does", "did", "can", "could",
+    "would", "will", "should", "is", "are", "was", "were", "have", "has",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QaPair {
+    pub question: String,
+    pub asked_by: String,
+    /// Seconds from the start of the recording.
+    pub asked_at: f64,
+    pub answer: Option<String>,
+    pub answered_by: Option<String>,
+    pub answered_at: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QaDocument {
+    pub id: String,
+    pub transcription_id: String,
+    pub pairs: Vec<QaPair>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QaFormat {
+    Markdown,
+    Text,
+}
+
+struct Turn {
+    speaker: String,
+    text: String,
+    start: f64,
+}
+
+fn turns(segments: &[LabeledSegment]) -> Vec<Turn> {
+    let mut turns: Vec<Turn> = Vec::new();
+    for segment in segments {
+        match turns.last_mut() {
+            Some(turn) if turn.speaker == segment.label => {
+                turn.text.push(' ');
+                turn.text.push_str(segment.text.trim());
+            }
+            _ => turns.push(Turn {
+                speaker: segment.label.clone(),
+                text: segment.text.trim().to_string(),
+                start: segment.start,
+            }),
+        }
+    }
+    turns
+}
+
+fn sentences(text: &str) -> Vec<&str> {
+    text.split_inclusive(['.', '!', '?'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn is_question(sentence: &str) -> bool {
+    if sentence.ends_with('?') {
+        return true;
+    }
+    // Transcripts often drop the question mark, so also accept sentences
+    // that open with an interrogative and have no other terminator.
+    let first = sentence
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    INTERROGATIVES.contains(&first.as_str()) && !sentence.ends_with(['.', '!'])
+}
+
+/// The question sentences of a turn, or `None` if it asks nothing.
+fn question_text(turn: &Turn) -> Option<String> {
+    let questions: Vec<&str> = sentences(&turn.text)
+        .into_iter()
+        .filter(|s| is_question(s))
+        .collect();
+    (!questions.is_empty()).then(|| questions.join(" "))
+}
+
+fn excerpt(text: &str) -> String {
+    if text.chars().count() <= EXCERPT_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(EXCERPT_CHARS).collect();
+    let cut = cut.rsplit_once(' ').map_or(cut.as_str(), |(head, _)| head);
+    format!("{}…", cut.trim_end_matches([',', ';', ' ']))
+}
+
+/// Pair questions with the answers that follow them.
+pub fn structure(segments: &[LabeledSegment]) -> Vec<QaPair> {
+    let turns = turns(segments);
+    let mut pairs = Vec::new();
+    let mut index = 0;
+
+    while index < turns.len() {
+        let asked = &turns[index];
+        index += 1;
+        let Some(question) = question_text(asked) else {
+            continue;
+        };
+
+        let mut answer: Vec<&Turn> = Vec::new();
+        while index < turns.len() {
+            let turn = &turns[index];
+            if question_text(turn).is_some() {
+                break;
+            }
+            // Interjections from the asker ("right", "mm-hmm") don't end an answer.
+            if turn.speaker != asked.speaker {
+                answer.push(turn);
+            }
+            index += 1;
+        }
+
+        let text = answer
+            .iter()
+            .map(|turn| turn.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        pairs.push(QaPair {
+            question,
+            asked_by: asked.speaker.clone(),
+            asked_at: asked.start,
+            answer: answer.first().map(|_| excerpt(&text)),
+            answered_by: answer.first().map(|turn| turn.speaker.clone()),
+            answered_at: answer.first().map(|turn| turn.start),
+        });
+    }
+    pairs
+}
+
+fn timestamp(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        total / 3600,
+        total / 60 % 60,
+        total % 60
+    )
+}
+
+pub fn render(pairs: &[QaPair], format: QaFormat) -> String {
+    let mut out = String::new();
+    if format == QaFormat::Markdown {
+        out.push_str(&format!("# {}\n\n", i18n::t("interview-heading")));
+    }
+    for (n, pair) in pairs.iter().enumerate() {
+        match format {
+            QaFormat::Markdown => out.push_str(&format!(
+                "## Q{}. {}\n\n*{}*\n\n",
+                n + 1,
+                pair.question,
+                i18n::t_args(
+                    "interview-asked",
+                    &[
+                        ("speaker", pair.asked_by.clone()),
+                        ("time", timestamp(pair.asked_at)),
+                    ]
+                )
+            )),
+            QaFormat::Text => out.push_str(&format!(
+                "Q{}. [{}] {}: {}\n",
+                n + 1,
+                timestamp(pair.asked_at),
+                pair.asked_by,
+                pair.question
+            )),
+        }
+        match (&pair.answer, &pair.answered_by, pair.answered_at) {
+            (Some(answer), Some(by), Some(at)) => match format {
+                QaFormat::Markdown => out.push_str(&format!(
+                    "> {}\n>\n> — {}, {}\n\n",
+                    answer,
+                    by,
+                    timestamp(at)
+                )),
+                QaFormat::Text => out.push_str(&format!(
+                    "A{}. [{}] {}: {}\n\n",
+                    n + 1,
+                    timestamp(at),
+                    by,
+                    answer
+                )),
+            },
+            _ => out.push_str(&match format {
+                QaFormat::Markdown => format!("> *{}*\n\n", i18n::t("interview-no-answer")),
+                QaFormat::Text => format!("{}\n\n", i18n::t("interview-no-answer-text")),
+            }),
+        }
+    }
+    out
+}
+
+fn load(conn: &Connection, id: &str) -> Result<QaDocument> {
+    conn.query_row(
+        "SELECT id, transcription_id, pairs, created_at FROM qa_documents WHERE id = ?1",
+        [id],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        },
+    )
+    .optional()?
+    .map(|(id, transcription_id, pairs, created_at)| QaDocument {
+        id,
+        transcription_id,
+        pairs: serde_json::from_str(&pairs).unwrap_or_default(),
+        created_at,
+    })
+    .ok_or_else(|| Error::NotFound(format!("Q&A document {}", id)))
+}
+
+/// Build and store the Q&A document for a diarized interview transcript.
+#[tauri::command]
+pub fn structure_interview(
+    app: AppHandle,
+    transcription_id: String,
+    segments: Vec<LabeledSegment>,
+) -> Result<QaDocument> {
+    let conn = db::connect(&app)?;
+    db::transcription_text(&conn, &transcription_id)?;
+
+    let pairs = structure(&segments);
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO qa_documents (id, transcription_id, pairs) VALUES (?1, ?2, ?3)",
+        params![id, transcription_id, serde_json::to_string(&pairs).unwrap()],
+    )?;
+    load(&conn, &id)
+}
+
+/// The most recent Q&A document for a transcription, if one was made.
+#[tauri::command]
+pub fn get_qa_document(app: AppHandle, transcription_id: String) -> Result<Option<QaDocument>> {
+    let conn = db::connect(&app)?;
+    let id: Option<String> = conn
+        .query_row(
+            "SELECT id FROM qa_documents WHERE transcription_id = ?1
+             ORDER BY created_at DESC LIMIT 1",
+            [&transcription_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    id.map(|id| load(&conn, &id)).transpose()
+}
+
+#[tauri::command]
+pub fn export_qa_document(
+    app: AppHandle,
+    id: String,
+    path: PathBuf,
+    format: Option<QaFormat>,
+) -> Result<()> {
+    let document = load(&db::connect(&app)?, &id)?;
+    fs::write(
+        path,
+        render(&document.pairs, format.unwrap_or(QaFormat::Markdown)),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(label: &str, text: &str, start: f64) -> LabeledSegment {
+        LabeledSegment {
+            label: label.to_string(),
+            text: text.to_string(),
+            start,
+            end: start + 1.0,
+        }
+    }
+
+    #[test]
+    fn pairs_questions_with_the_next_speakers_answer() {
+        let pairs = structure(&[
+            segment("Host", "Thanks for coming.", 0.0),
+            segment("Host", "How did the project start?", 2.0),
+            segment("Guest", "It began as a side project.", 4.0),
+            segment("Host", "Right.", 6.0),
+            segment("Guest", "Then it grew.", 7.0),
+            segment("Host", "what came next", 9.0),
+        ]);
+
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0].question, "How did the project start?");
+        assert_eq!(
+            pairs[0].answer.as_deref(),
+            Some("It began as a side project. Then it grew.")
+        );
+        assert_eq!(pairs[0].answered_at, Some(4.0));
+        assert_eq!(pairs[1].answer, None);
+    }
+}
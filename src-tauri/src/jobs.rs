@@ -0,0 +1,673 @@
+//! Background job queue for batch transcription.
+//!
+//! Queued jobs run one at a time on a worker thread, highest priority lane
+//! first and in submission order within a lane, so a file the user is
+//! waiting on jumps ahead of a long background backlog. Live jobs bypass the
+//! queue entirely and, while any are running, batch jobs start with half the
+//! decoder threads so live work keeps compute headroom.
+//!
+//! Transcriptions nobody is waiting on, such as watch folder files, name a
+//! [`SaveTarget`] and are stored when they finish; others leave the result
+//! on the job for the frontend to save.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::bulk::{self, BatchProgress, BulkAction};
+use crate::diarize::{self, DiarizeOptions};
+use crate::error::{Error, Result};
+use crate::preflight::{self, JobSpec};
+use crate::segments::{self, SegmentInput};
+use crate::transcription::{self, TranscriptionOutput};
+use crate::whisper::{self, AdvancedOptions, DecodeOptions};
+use crate::{
+    audio, audio_files, autoexport, cleanup, crash, db, meeting_types, review, summarize, titles,
+};
+
+pub const JOB_UPDATED_EVENT: &str = "job://updated";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    /// Watch folders and bulk work.
+    Background,
+    /// Something the user just started and is waiting on.
+    Interactive,
+    /// Live transcription; never queued behind batch work.
+    Live,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// How a finished transcription job is stored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SaveTarget {
+    /// Imported audio file the transcript belongs to; without one, the
+    /// job's file is imported when the transcript is saved.
+    pub audio_file_id: Option<String>,
+    /// Watch folder the file arrived through.
+    pub watch_folder_id: Option<String>,
+    /// Summarize the transcript once it is saved.
+    pub summarize: bool,
+    /// Tag the transcript with this series, and start its decoder prompt
+    /// from the names and terms of the previous meeting in it.
+    pub series: Option<String>,
+    /// Project the job's file is imported into, within its quota.
+    pub project: Option<String>,
+    /// Meeting type, by id or name, whose vocabulary biases the decoder and
+    /// whose tags, summary prompt and export template the transcript gets.
+    pub meeting_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobKind {
+    Transcribe {
+        path: PathBuf,
+        model: Option<String>,
+        language: Option<String>,
+        #[serde(default)]
+        advanced: AdvancedOptions,
+        /// Label speakers, with an expected speaker count if known.
+        #[serde(default)]
+        diarize: Option<DiarizeOptions>,
+        /// Store the transcript when the job finishes.
+        #[serde(default)]
+        save: Option<SaveTarget>,
+    },
+    /// One action applied to many stored transcriptions.
+    Bulk {
+        ids: Vec<String>,
+        action: BulkAction,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub priority: JobPriority,
+    pub status: JobStatus,
+    pub result: Option<TranscriptionOutput>,
+    /// The stored transcript, for jobs with a save target.
+    pub transcription_id: Option<String>,
+    /// Items done so far, for bulk jobs.
+    pub progress: Option<BatchProgress>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    seq: u64,
+}
+
+#[derive(Default)]
+struct QueueInner {
+    jobs: Vec<Job>,
+    next_seq: u64,
+    live_running: usize,
+}
+
+impl QueueInner {
+    /// Index of the queued job that should run next.
+    fn next_queued(&self) -> Option<usize> {
+        self.jobs
+            .iter()
+            .enumerate()
+            .filter(|(_, job)| job.status == JobStatus::Queued && job.priority != JobPriority::Live)
+            .max_by(|(_, a), (_, b)| a.priority.cmp(&b.priority).then(b.seq.cmp(&a.seq)))
+            .map(|(index, _)| index)
+    }
+
+    fn job_mut(&mut self, id: &str) -> Result<&mut Job> {
+        self.jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| Error::NotFound(format!("job {}", id)))
+    }
+}
+
+#[derive(Default)]
+pub struct JobQueue {
+    inner: Mutex<QueueInner>,
+    wake: Condvar,
+}
+
+impl JobQueue {
+    fn update(&self, app: &AppHandle, id: &str, apply: impl FnOnce(&mut Job)) {
+        let snapshot = {
+            let mut inner = self.inner.lock().unwrap();
+            match inner.job_mut(id) {
+                Ok(job) => {
+                    apply(job);
+                    job.clone()
+                }
+                Err(_) => return,
+            }
+        };
+        let _ = app.emit_all(JOB_UPDATED_EVENT, snapshot);
+    }
+
+    /// Like [`Self::update`], but only for jobs that have not started yet.
+    fn update_queued(&self, app: &AppHandle, id: &str, apply: impl FnOnce(&mut Job)) -> Result<()> {
+        let snapshot = {
+            let mut inner = self.inner.lock().unwrap();
+            let job = inner.job_mut(id)?;
+            if job.status != JobStatus::Queued {
+                return Err(Error::InvalidInput(format!(
+                    "job {} is no longer queued",
+                    id
+                )));
+            }
+            apply(job);
+            job.clone()
+        };
+        let _ = app.emit_all(JOB_UPDATED_EVENT, snapshot);
+        Ok(())
+    }
+
+    /// Decoder threads for a batch job, reduced while live work is running.
+    fn batch_threads(&self) -> i32 {
+        let threads = whisper::default_threads();
+        if self.inner.lock().unwrap().live_running > 0 {
+            (threads / 2).max(1)
+        } else {
+            threads
+        }
+    }
+}
+
+/// Marks live work as running for as long as it is held.
+pub struct LiveGuard(Arc<JobQueue>);
+
+impl Drop for LiveGuard {
+    fn drop(&mut self) {
+        self.0.inner.lock().unwrap().live_running -= 1;
+    }
+}
+
+impl JobQueue {
+    /// Reserve compute headroom for live work that runs outside the queue,
+    /// such as dictation.
+    pub fn live_guard(self: &Arc<Self>) -> LiveGuard {
+        self.inner.lock().unwrap().live_running += 1;
+        LiveGuard(self.clone())
+    }
+
+    /// Whether any job is queued or running, or live work holds a guard.
+    pub fn is_busy(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.live_running > 0
+            || inner
+                .jobs
+                .iter()
+                .any(|job| matches!(job.status, JobStatus::Queued | JobStatus::Running))
+    }
+
+    /// Jobs that have not finished, in submission order, so they can be
+    /// queued again after a restart. Live jobs belong to a capture that
+    /// ended with the app and are left out.
+    pub fn unfinished(&self) -> Vec<(JobKind, JobPriority)> {
+        let inner = self.inner.lock().unwrap();
+        let mut jobs: Vec<&Job> = inner
+            .jobs
+            .iter()
+            .filter(|job| matches!(job.status, JobStatus::Queued | JobStatus::Running))
+            .filter(|job| job.priority != JobPriority::Live)
+            .collect();
+        jobs.sort_by_key(|job| job.seq);
+        jobs.into_iter()
+            .map(|job| (job.kind.clone(), job.priority))
+            .collect()
+    }
+}
+
+/// Store `output` as a transcript of `audio_file_id`, tagged with the
+/// target's series and its meeting type's tags. Returns the new id.
+fn insert(
+    conn: &mut Connection,
+    audio_file_id: &str,
+    output: &TranscriptionOutput,
+    target: &SaveTarget,
+) -> Result<String> {
+    let segments: Vec<SegmentInput> = output
+        .segments
+        .iter()
+        .enumerate()
+        .map(|(position, segment)| SegmentInput {
+            speaker: output
+                .speakers
+                .as_ref()
+                .and_then(|speakers| speakers.get(position).cloned()),
+            text: segment.text.clone(),
+            start: segment.start,
+            end: segment.end,
+            confidence: segment.confidence.map(f64::from),
+            words: segment.words.clone(),
+        })
+        .collect();
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let meeting_type = output.meeting_type.as_ref();
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO transcriptions
+             (id, audio_file_id, text, language, model_used, duration, title, meeting_type_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT title FROM audio_files WHERE id = ?2), ?7)",
+        rusqlite::params![
+            id,
+            audio_file_id,
+            output.text,
+            output.language,
+            output.model_used,
+            output.duration,
+            meeting_type.map(|meeting_type| &meeting_type.id)
+        ],
+    )?;
+    segments::insert(&tx, &id, 0, &segments)?;
+    let tags = target
+        .series
+        .iter()
+        .chain(meeting_type.into_iter().flat_map(|t| &t.default_tags));
+    for tag in tags {
+        tx.execute(
+            "INSERT OR IGNORE INTO transcription_tags (transcription_id, tag) VALUES (?1, ?2)",
+            rusqlite::params![id, tag],
+        )?;
+    }
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Store a finished transcription as the frontend does, with its segments
+/// and speaker labels, flag it for review, summarize it if asked and name
+/// it if its file name is unhelpful, then schedule cleanup of its source
+/// audio as the settings say.
+/// Returns the id of the new transcription.
+fn save(
+    app: &AppHandle,
+    path: &std::path::Path,
+    output: &TranscriptionOutput,
+    target: &SaveTarget,
+) -> Result<String> {
+    let mut conn = db::connect(app)?;
+    let audio_file_id = match &target.audio_file_id {
+        Some(id) => id.clone(),
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            audio_files::import(&conn, &id, path, target.project.as_deref())?;
+            id
+        }
+    };
+    let id = insert(&mut conn, &audio_file_id, output, target)?;
+    review::flag(&mut conn, &id, None, review::DEFAULT_THRESHOLD)?;
+
+    // A failed summary, e.g. with no provider set up, keeps the transcript.
+    if target.summarize {
+        let meeting_type = output.meeting_type.as_ref().map(|t| t.id.as_str());
+        if let Err(err) = summarize::summarize(app, &id, None, meeting_type, None) {
+            crash::log(format!("summary of {} failed: {}", path.display(), err));
+        }
+    }
+    if let Err(err) = titles::name_if_generic(app, &id) {
+        crash::log(format!("could not name {}: {}", path.display(), err));
+    }
+    if let Err(err) = cleanup::schedule(app, &id, target.watch_folder_id.as_deref()) {
+        crash::log(format!(
+            "could not schedule cleanup of {}: {}",
+            path.display(),
+            err
+        ));
+    }
+    Ok(id)
+}
+
+fn execute(
+    queue: &JobQueue,
+    app: &AppHandle,
+    id: &str,
+    kind: &JobKind,
+    threads: i32,
+) -> Result<Option<TranscriptionOutput>> {
+    match kind {
+        JobKind::Transcribe {
+            path,
+            model,
+            language,
+            advanced,
+            diarize,
+            save: target,
+        } => {
+            let conn = db::connect(app)?;
+            let meeting_type = target
+                .as_ref()
+                .and_then(|target| target.meeting_type.as_deref())
+                .map(|key| meeting_types::find(&conn, key))
+                .transpose()?;
+            let series = target.as_ref().and_then(|target| target.series.as_deref());
+            let options = DecodeOptions {
+                language: language.clone(),
+                threads: Some(threads),
+                advanced: *advanced,
+                initial_prompt: transcription::initial_prompt(
+                    &conn,
+                    meeting_type.as_ref(),
+                    series,
+                )?,
+                ..Default::default()
+            };
+            let pcm = audio::load_pcm(path)?;
+            let mut output = transcription::transcribe_pcm_isolated(
+                app,
+                &pcm,
+                model.as_deref(),
+                &options,
+                true,
+            )?;
+            if let Some(diarize) = diarize {
+                let spans: Vec<(f64, f64)> =
+                    output.segments.iter().map(|s| (s.start, s.end)).collect();
+                output.speakers = Some(diarize::speakers(&pcm, &spans, diarize));
+            }
+            output.meeting_type = meeting_type;
+            autoexport::run(app, path, &output);
+            if let Some(target) = target {
+                let transcription_id = save(app, path, &output, target)?;
+                queue.update(app, id, |job| job.transcription_id = Some(transcription_id));
+            }
+            Ok(Some(output))
+        }
+        JobKind::Bulk { ids, action } => {
+            bulk::run(app, ids, action, |progress| {
+                queue.update(app, id, |job| job.progress = Some(progress.clone()))
+            })?;
+            Ok(None)
+        }
+    }
+}
+
+fn finish(
+    queue: &JobQueue,
+    app: &AppHandle,
+    id: &str,
+    outcome: Result<Option<TranscriptionOutput>>,
+) {
+    queue.update(app, id, |job| match outcome {
+        Ok(output) => {
+            job.status = JobStatus::Completed;
+            job.result = output;
+        }
+        Err(err) => {
+            job.status = JobStatus::Failed;
+            job.error = Some(err.to_string());
+        }
+    });
+}
+
+fn run_live(app: AppHandle, id: String, kind: JobKind) {
+    thread::spawn(move || {
+        let queue = app.state::<Arc<JobQueue>>();
+        queue.update(&app, &id, |job| job.status = JobStatus::Running);
+
+        let outcome = execute(&queue, &app, &id, &kind, whisper::default_threads());
+        queue.inner.lock().unwrap().live_running -= 1;
+        finish(&queue, &app, &id, outcome);
+    });
+}
+
+/// Start the queue worker. Call once during app setup.
+pub fn init(app: &AppHandle) {
+    let queue = Arc::new(JobQueue::default());
+    app.manage(queue.clone());
+
+    let app = app.clone();
+    thread::spawn(move || loop {
+        let (id, kind) = {
+            let mut inner = queue.inner.lock().unwrap();
+            let index = loop {
+                match inner.next_queued() {
+                    Some(index) => break index,
+                    None => inner = queue.wake.wait(inner).unwrap(),
+                }
+            };
+            let job = &mut inner.jobs[index];
+            job.status = JobStatus::Running;
+            (job.id.clone(), job.kind.clone())
+        };
+        queue.update(&app, &id, |_| {});
+
+        let outcome = execute(&queue, &app, &id, &kind, queue.batch_threads());
+        finish(&queue, &app, &id, outcome);
+    });
+}
+
+/// Add a job to the queue and return its id. Live jobs start immediately.
+pub fn enqueue(app: &AppHandle, kind: JobKind, priority: JobPriority) -> String {
+    let queue = app.state::<Arc<JobQueue>>();
+    let id = uuid::Uuid::new_v4().to_string();
+
+    {
+        let mut inner = queue.inner.lock().unwrap();
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+        if priority == JobPriority::Live {
+            inner.live_running += 1;
+        }
+        inner.jobs.push(Job {
+            id: id.clone(),
+            kind: kind.clone(),
+            priority,
+            status: JobStatus::Queued,
+            result: None,
+            transcription_id: None,
+            progress: None,
+            error: None,
+            seq,
+        });
+    }
+
+    if priority == JobPriority::Live {
+        run_live(app.clone(), id.clone(), kind);
+    } else {
+        queue.wake.notify_one();
+    }
+    id
+}
+
+/// Submit a transcription job and return its id. With `save`, the
+/// transcript is stored when the job finishes.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn enqueue_transcription(
+    app: AppHandle,
+    path: PathBuf,
+    model: Option<String>,
+    language: Option<String>,
+    priority: Option<JobPriority>,
+    advanced: Option<AdvancedOptions>,
+    diarize: Option<DiarizeOptions>,
+    save: Option<SaveTarget>,
+) -> Result<String> {
+    let kind = JobKind::Transcribe {
+        path,
+        model,
+        language,
+        advanced: advanced.unwrap_or_default(),
+        diarize,
+        save,
+    };
+    enqueue_checked(&app, kind, priority.unwrap_or(JobPriority::Interactive))
+}
+
+/// Like [`enqueue`], but a transcription is first validated and run
+/// through the pre-flight checks, failing instead of being queued. A file
+/// to be imported into a project must also fit its quota.
+pub fn enqueue_checked(app: &AppHandle, kind: JobKind, priority: JobPriority) -> Result<String> {
+    if let JobKind::Transcribe {
+        path,
+        model,
+        advanced,
+        diarize,
+        save,
+        ..
+    } = &kind
+    {
+        advanced.validate()?;
+        if let Some(diarize) = diarize {
+            diarize.validate()?;
+        }
+        if let Some(SaveTarget {
+            audio_file_id: None,
+            project: Some(project),
+            ..
+        }) = save
+        {
+            audio_files::check_quota(&db::connect(app)?, "", path, project)?;
+        }
+        preflight::check(
+            app,
+            &JobSpec::Transcription {
+                path: path.clone(),
+                model: model.clone(),
+                output_dir: None,
+            },
+        )?
+        .into_result()?;
+    }
+    Ok(enqueue(app, kind, priority))
+}
+
+/// Move a queued job to another lane. Running jobs keep going unchanged.
+#[tauri::command]
+pub fn set_job_priority(
+    app: AppHandle,
+    queue: State<'_, Arc<JobQueue>>,
+    job_id: String,
+    priority: JobPriority,
+) -> Result<()> {
+    if priority == JobPriority::Live {
+        return Err(Error::InvalidInput(
+            "live priority can only be set when submitting a job".into(),
+        ));
+    }
+    queue.update_queued(&app, &job_id, |job| job.priority = priority)
+}
+
+#[tauri::command]
+pub fn cancel_job(app: AppHandle, queue: State<'_, Arc<JobQueue>>, job_id: String) -> Result<()> {
+    queue.update_queued(&app, &job_id, |job| job.status = JobStatus::Cancelled)
+}
+
+/// All jobs, in the order they will run (finished jobs last).
+#[tauri::command]
+pub fn list_jobs(queue: State<'_, Arc<JobQueue>>) -> Vec<Job> {
+    let mut jobs = queue.inner.lock().unwrap().jobs.clone();
+    jobs.sort_by(|a, b| {
+        let pending = |job: &Job| matches!(job.status, JobStatus::Queued | JobStatus::Running);
+        pending(b)
+            .cmp(&pending(a))
+            .then(b.priority.cmp(&a.priority))
+            .then(a.seq.cmp(&b.seq))
+    });
+    jobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, priority: JobPriority, seq: u64) -> Job {
+        Job {
+            id: id.into(),
+            kind: JobKind::Transcribe {
+                path: PathBuf::new(),
+                model: None,
+                language: None,
+                advanced: AdvancedOptions::default(),
+                diarize: None,
+                save: None,
+            },
+            priority,
+            status: JobStatus::Queued,
+            result: None,
+            transcription_id: None,
+            progress: None,
+            error: None,
+            seq,
+        }
+    }
+
+    #[test]
+    fn interactive_jobs_run_before_background_backlog() {
+        let inner = QueueInner {
+            jobs: vec![
+                job("watch-1", JobPriority::Background, 0),
+                job("watch-2", JobPriority::Background, 1),
+                job("dropped", JobPriority::Interactive, 2),
+                job("dropped-later", JobPriority::Interactive, 3),
+            ],
+            next_seq: 4,
+            live_running: 0,
+        };
+        assert_eq!(inner.jobs[inner.next_queued().unwrap()].id, "dropped");
+    }
+
+    #[test]
+    fn saved_transcripts_get_their_meeting_type_and_series_tags() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        for migration in db::migrations() {
+            conn.execute_batch(migration.sql).unwrap();
+        }
+        let output = TranscriptionOutput {
+            text: "Done yesterday.".into(),
+            segments: vec![whisper::Segment {
+                text: "Done yesterday.".into(),
+                start: 0.0,
+                end: 2.0,
+                confidence: None,
+                words: Vec::new(),
+            }],
+            language: "en".into(),
+            duration: 2.0,
+            model_used: "whisper-base".into(),
+            meeting_type: Some(meeting_types::find(&conn, "standup").unwrap()),
+            hallucinations: Default::default(),
+            speakers: None,
+        };
+        let target = SaveTarget {
+            series: Some("team-alpha".into()),
+            ..Default::default()
+        };
+
+        let id = insert(&mut conn, "a1", &output, &target).unwrap();
+        let mut statement = conn
+            .prepare("SELECT tag FROM transcription_tags WHERE transcription_id = ?1 ORDER BY tag")
+            .unwrap();
+        let tags: Vec<String> = statement
+            .query_map([&id], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(tags, ["standup", "team-alpha"]);
+        let meeting_type: Option<String> = conn
+            .query_row(
+                "SELECT meeting_type_id FROM transcriptions WHERE id = ?1",
+                [&id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(meeting_type.as_deref(), Some("standup"));
+    }
+}
@@ -0,0 +1,332 @@
+//! Text generation providers used for summarization.
+//!
+//! The provider is either a local Ollama server or any endpoint speaking the
+//! OpenAI chat completions API. Its settings are stored as JSON in the
+//! `summarization_provider` preference. Both kinds can stream tokens as
+//! they are generated.
+
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::network::Client;
+use crate::{db, network, secrets};
+
+pub const PROVIDER_PREFERENCE: &str = "summarization_provider";
+
+/// Local models can take minutes for a long transcript.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Ollama,
+    OpenAiCompatible,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderConfig {
+    pub kind: ProviderKind,
+    pub base_url: String,
+    pub model: String,
+    /// Keychain entry whose API key authenticates requests, e.g. `openai`.
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        ProviderConfig {
+            kind: ProviderKind::Ollama,
+            base_url: "http://localhost:11434".into(),
+            model: "llama3.2".into(),
+            api_key: None,
+        }
+    }
+}
+
+impl ProviderConfig {
+    /// Name recorded as `model_used` on generated summaries.
+    pub fn label(&self) -> String {
+        match self.kind {
+            ProviderKind::Ollama => format!("ollama:{}", self.model),
+            ProviderKind::OpenAiCompatible => format!("openai:{}", self.model),
+        }
+    }
+
+    /// Whether requests leave the machine, and so may be billed.
+    pub fn is_cloud(&self) -> bool {
+        self.kind == ProviderKind::OpenAiCompatible && !network::is_local(&self.base_url)
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+/// Token counts from a response body or final stream message.
+fn usage_of(kind: ProviderKind, value: &Value) -> Option<TokenUsage> {
+    let (input, output) = match kind {
+        ProviderKind::Ollama => (&value["prompt_eval_count"], &value["eval_count"]),
+        ProviderKind::OpenAiCompatible => (
+            &value["usage"]["prompt_tokens"],
+            &value["usage"]["completion_tokens"],
+        ),
+    };
+    if input.is_null() && output.is_null() {
+        return None;
+    }
+    Some(TokenUsage {
+        input_tokens: input.as_u64().unwrap_or(0),
+        output_tokens: output.as_u64().unwrap_or(0),
+    })
+}
+
+pub fn provider(conn: &Connection) -> Result<ProviderConfig> {
+    Ok(db::get_preference(conn, PROVIDER_PREFERENCE)?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+fn provider_error(err: ureq::Error) -> Error {
+    match err {
+        ureq::Error::Status(code, response) => Error::Provider(format!(
+            "{} returned {}: {}",
+            response.get_url().to_string(),
+            code,
+            response.into_string().unwrap_or_default()
+        )),
+        ureq::Error::Transport(transport) => Error::Provider(transport.to_string()),
+    }
+}
+
+fn send(
+    client: &Client,
+    config: &ProviderConfig,
+    path: &str,
+    body: Value,
+) -> Result<ureq::Response> {
+    let url = config.endpoint(path);
+    let mut request = client.post(&url)?.timeout(REQUEST_TIMEOUT);
+    if let Some(provider) = config.api_key.as_deref() {
+        secrets::check_destination(provider, &url)?;
+        let key = secrets::api_key(provider)?
+            .ok_or_else(|| Error::NotFound(format!("no API key stored for '{}'", provider)))?;
+        request = secrets::authorize(request, provider, &key);
+    }
+    network::no_redirect(request.send_json(body).map_err(provider_error)?)
+}
+
+#[derive(Debug, PartialEq)]
+enum StreamLine {
+    Token(String),
+    Usage(TokenUsage),
+    /// The provider failed part-way through, after the response had started.
+    Error(String),
+    Done,
+    Skip,
+}
+
+/// Parse one line of a streamed response: NDJSON for Ollama, server-sent
+/// events for OpenAI-compatible endpoints.
+fn parse_stream_line(kind: ProviderKind, line: &str) -> StreamLine {
+    let line = line.trim();
+    let payload = match kind {
+        ProviderKind::Ollama => line,
+        ProviderKind::OpenAiCompatible => match line.strip_prefix("data:") {
+            Some(data) if data.trim() == "[DONE]" => return StreamLine::Done,
+            Some(data) => data.trim(),
+            None => return StreamLine::Skip,
+        },
+    };
+    let Ok(value) = serde_json::from_str::<Value>(payload) else {
+        return StreamLine::Skip;
+    };
+    // Ollama sends `{"error": "..."}`, OpenAI-compatible servers an object.
+    let error = &value["error"];
+    if !error.is_null() {
+        let message = error
+            .as_str()
+            .or_else(|| error["message"].as_str())
+            .map_or_else(|| error.to_string(), str::to_string);
+        return StreamLine::Error(message);
+    }
+    let token = match kind {
+        ProviderKind::Ollama => value["response"].as_str(),
+        ProviderKind::OpenAiCompatible => value["choices"][0]["delta"]["content"].as_str(),
+    };
+    match (token, usage_of(kind, &value)) {
+        (Some(token), _) if !token.is_empty() => StreamLine::Token(token.to_string()),
+        (_, Some(usage)) => StreamLine::Usage(usage),
+        _ if value["done"].as_bool() == Some(true) => StreamLine::Done,
+        _ => StreamLine::Skip,
+    }
+}
+
+/// Generate a completion for `prompt` under the `system` instructions,
+/// passing each token to `on_token` as it arrives. Returning `false` from
+/// `on_token` stops generation; dropping the connection tells the server to
+/// stop too.
+///
+/// Tokens the provider reports are added to `usage` as they arrive, so
+/// they are counted even when the request then fails or is stopped.
+pub fn complete_stream(
+    client: &Client,
+    config: &ProviderConfig,
+    system: &str,
+    prompt: &str,
+    usage: &mut TokenUsage,
+    mut on_token: impl FnMut(&str) -> bool,
+) -> Result<String> {
+    let response = match config.kind {
+        ProviderKind::Ollama => send(
+            client,
+            config,
+            "api/generate",
+            json!({
+                "model": config.model,
+                "system": system,
+                "prompt": prompt,
+                "stream": true,
+            }),
+        )?,
+        ProviderKind::OpenAiCompatible => send(
+            client,
+            config,
+            "v1/chat/completions",
+            json!({
+                "model": config.model,
+                "messages": [
+                    { "role": "system", "content": system },
+                    { "role": "user", "content": prompt },
+                ],
+                "stream": true,
+                "stream_options": { "include_usage": true },
+            }),
+        )?,
+    };
+
+    let mut text = String::new();
+    for line in BufReader::new(response.into_reader()).lines() {
+        let line = line.map_err(|e| Error::Provider(e.to_string()))?;
+        match parse_stream_line(config.kind, &line) {
+            StreamLine::Token(token) => {
+                if !on_token(&token) {
+                    return Err(Error::Cancelled("generation stopped".into()));
+                }
+                text.push_str(&token);
+            }
+            StreamLine::Usage(reported) => usage.add(reported),
+            StreamLine::Error(message) => return Err(Error::Provider(message)),
+            StreamLine::Done => break,
+            StreamLine::Skip => {}
+        }
+    }
+    Ok(text.trim().to_string())
+}
+
+#[tauri::command]
+pub fn get_summarization_provider(app: AppHandle) -> Result<ProviderConfig> {
+    provider(&db::connect(&app)?)
+}
+
+#[tauri::command]
+pub fn set_summarization_provider(app: AppHandle, config: ProviderConfig) -> Result<()> {
+    if config.base_url.trim().is_empty() || config.model.trim().is_empty() {
+        return Err(Error::InvalidInput(
+            "provider needs a base URL and a model".into(),
+        ));
+    }
+    if let Some(provider) = config.api_key.as_deref() {
+        secrets::check_destination(provider, &config.base_url)?;
+    }
+    db::set_preference(
+        &db::connect(&app)?,
+        PROVIDER_PREFERENCE,
+        &serde_json::to_string(&config).map_err(|err| Error::InvalidInput(err.to_string()))?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ollama_and_openai_stream_lines() {
+        assert_eq!(
+            parse_stream_line(ProviderKind::Ollama, r#"{"response":"Hel","done":false}"#),
+            StreamLine::Token("Hel".into())
+        );
+        assert_eq!(
+            parse_stream_line(ProviderKind::Ollama, r#"{"response":"","done":true}"#),
+            StreamLine::Done
+        );
+        assert_eq!(
+            parse_stream_line(
+                ProviderKind::OpenAiCompatible,
+                r#"data: {"choices":[{"delta":{"content":"lo"}}]}"#
+            ),
+            StreamLine::Token("lo".into())
+        );
+        assert_eq!(
+            parse_stream_line(
+                ProviderKind::OpenAiCompatible,
+                r#"data: {"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":3}}"#
+            ),
+            StreamLine::Usage(TokenUsage {
+                input_tokens: 12,
+                output_tokens: 3
+            })
+        );
+        assert_eq!(
+            parse_stream_line(ProviderKind::OpenAiCompatible, "data: [DONE]"),
+            StreamLine::Done
+        );
+        assert_eq!(
+            parse_stream_line(ProviderKind::OpenAiCompatible, ": keep-alive"),
+            StreamLine::Skip
+        );
+        assert_eq!(
+            parse_stream_line(ProviderKind::Ollama, r#"{"error":"model not found"}"#),
+            StreamLine::Error("model not found".into())
+        );
+        assert_eq!(
+            parse_stream_line(
+                ProviderKind::OpenAiCompatible,
+                r#"data: {"error":{"message":"rate limited","type":"requests"}}"#
+            ),
+            StreamLine::Error("rate limited".into())
+        );
+    }
+
+    #[test]
+    fn sends_keys_only_to_their_provider() {
+        assert!(secrets::check_destination("openai", "https://api.openai.com/v1/x").is_ok());
+        assert!(secrets::check_destination("groq", "HTTPS://API.GROQ.COM/openai").is_ok());
+        assert!(secrets::check_destination("openai", "http://api.openai.com/v1").is_err());
+        assert!(secrets::check_destination("openai", "https://evil.example/v1").is_err());
+        assert!(
+            secrets::check_destination("openai", "https://api.openai.com.evil.example").is_err()
+        );
+    }
+}
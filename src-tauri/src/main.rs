@@ -1,7 +1,72 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tauri_plugin_sql::{Migration, MigrationKind, SqlitePool};
+mod analytics;
+mod anki;
+mod archive;
+mod audio;
+mod audio_files;
+mod audit;
+mod autoexport;
+mod benchmark;
+mod bulk;
+mod cleanup;
+mod clips;
+mod comments;
+mod companion;
+mod crash;
+mod db;
+mod deeplink;
+mod diarize;
+mod dictation;
+mod editing;
+mod error;
+mod evaluation;
+mod hallucination;
+mod history;
+mod i18n;
+mod indexer;
+mod interview;
+mod jobs;
+mod llm;
+mod maintenance;
+mod meeting_types;
+mod model_cache;
+mod models;
+mod network;
+mod numbers;
+mod parts;
+mod playback;
+mod postprocess;
+mod preflight;
+mod prompts;
+mod pronunciation;
+mod quantize;
+mod quota;
+mod recording;
+mod rerun;
+mod review;
+mod routing;
+mod secrets;
+mod segments;
+mod selftest;
+mod series;
+mod session;
+mod share;
+mod speech;
+mod subtitles;
+mod summarize;
+mod titles;
+mod transcription;
+mod translations;
+mod trim;
+mod usage;
+mod vad;
+mod verbatim;
+mod voice_commands;
+mod watch;
+mod whisper;
+mod worker;
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
@@ -10,55 +75,211 @@ fn greet(name: &str) -> String {
 }
 
 fn main() {
-    let migrations = vec![
-        Migration {
-            version: 1,
-            description: "Create initial tables",
-            sql: "CREATE TABLE IF NOT EXISTS transcriptions (
-                id TEXT PRIMARY KEY,
-                audio_file_id TEXT NOT NULL,
-                text TEXT NOT NULL,
-                language TEXT NOT NULL,
-                model_used TEXT NOT NULL,
-                duration REAL NOT NULL,
-                confidence REAL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            );
-            
-            CREATE TABLE IF NOT EXISTS summaries (
-                id TEXT PRIMARY KEY,
-                transcription_id TEXT NOT NULL,
-                summary TEXT NOT NULL,
-                language TEXT NOT NULL,
-                model_used TEXT NOT NULL,
-                original_length INTEGER NOT NULL,
-                summary_length INTEGER NOT NULL,
-                compression_ratio REAL NOT NULL,
-                processing_time INTEGER NOT NULL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (transcription_id) REFERENCES transcriptions(id)
-            );
-            
-            CREATE TABLE IF NOT EXISTS user_preferences (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL,
-                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            );
-            
-            CREATE INDEX IF NOT EXISTS idx_transcriptions_created_at ON transcriptions(created_at);
-            CREATE INDEX IF NOT EXISTS idx_transcriptions_language ON transcriptions(language);
-            CREATE INDEX IF NOT EXISTS idx_summaries_transcription_id ON summaries(transcription_id);",
-            kind: MigrationKind::Up,
-        },
-    ];
-
+    // Checked first: the worker must not claim the single instance slot
+    // the deep link plugin holds for the app.
+    if std::env::args().nth(1).as_deref() == Some(worker::WORKER_ARG) {
+        worker::serve();
+        return;
+    }
+    tauri_plugin_deep_link::prepare("com.transcriber.app");
     tauri::Builder::default()
         .plugin(tauri_plugin_sql::init_with_migrations(
             "sqlite:transcription_history.db",
-            migrations,
+            db::migrations(),
         ))
-        .invoke_handler(tauri::generate_handler![greet])
+        .manage(recording::RecordingState::default())
+        .manage(worker::Supervisor::default())
+        .manage(dictation::DictationState::default())
+        .manage(voice_commands::VoiceCommandState::default())
+        .manage(summarize::ActiveSummaries::default())
+        .manage(companion::CompanionState::default())
+        .setup(|app| {
+            crash::init(&app.handle());
+            if let Err(err) = db::prepare(&app.handle()) {
+                crash::log(format!("database check failed: {}", err));
+            }
+            i18n::init(&app.handle());
+            model_cache::init(&app.handle());
+            jobs::init(&app.handle());
+            watch::init(&app.handle());
+            dictation::init(&app.handle());
+            voice_commands::init(&app.handle());
+            speech::init(&app.handle());
+            playback::init(&app.handle());
+            maintenance::init(&app.handle());
+            indexer::init(&app.handle());
+            companion::init(&app.handle());
+            cleanup::init(&app.handle());
+            deeplink::init(&app.handle());
+            Ok(())
+        })
+        .on_window_event(|event| {
+            if let tauri::WindowEvent::CloseRequested { .. } = event.event() {
+                session::save_on_close(&tauri::Manager::app_handle(event.window()));
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            db::get_schema_version,
+            recording::start_recording,
+            recording::stop_recording,
+            recording::add_recording_marker,
+            recording::interleave_track_transcripts,
+            transcription::transcribe_file,
+            jobs::enqueue_transcription,
+            jobs::set_job_priority,
+            jobs::cancel_job,
+            jobs::list_jobs,
+            preflight::preflight_check,
+            dictation::start_dictation,
+            dictation::stop_dictation,
+            dictation::is_dictation_active,
+            voice_commands::set_voice_commands_enabled,
+            voice_commands::voice_commands_enabled,
+            speech::speak_text,
+            speech::pause_speech,
+            speech::resume_speech,
+            speech::stop_speech,
+            speech::list_voices,
+            playback::load_playback,
+            playback::resume_playback,
+            playback::pause_playback,
+            playback::seek_playback,
+            playback::set_playback_speed,
+            playback::stop_playback,
+            models::list_downloaded_models,
+            model_cache::get_loaded_models,
+            model_cache::unload_model,
+            model_cache::set_model_memory_budget,
+            quantize::quantize_model,
+            benchmark::benchmark_models,
+            evaluation::evaluate_transcription,
+            comments::add_comment,
+            comments::list_comments,
+            comments::update_comment,
+            comments::delete_comment,
+            meeting_types::list_meeting_types,
+            meeting_types::save_meeting_type,
+            meeting_types::delete_meeting_type,
+            interview::structure_interview,
+            interview::get_qa_document,
+            interview::export_qa_document,
+            segments::save_segments,
+            segments::list_segments,
+            analytics::get_conversation_analytics,
+            verbatim::export_verbatim,
+            review::flag_review_items,
+            review::list_review_items,
+            review::resolve_review_item,
+            review::get_review_progress,
+            editing::update_transcript_text,
+            editing::update_segment_text,
+            editing::get_transcript_version,
+            editing::get_edit_history,
+            editing::set_editor_name,
+            subtitles::reimport_subtitles,
+            share::export_share_page,
+            share::export_read_along_page,
+            diarize::rediarize,
+            indexer::get_index_status,
+            indexer::rebuild_indexes,
+            indexer::search_transcripts,
+            indexer::related_transcriptions,
+            quota::get_project_quotas,
+            quota::set_project_quota,
+            quota::get_storage_breakdown,
+            companion::get_companion_status,
+            companion::set_companion_settings,
+            companion::start_companion_pairing,
+            companion::list_companion_devices,
+            companion::remove_companion_device,
+            watch::add_watch_folder,
+            watch::list_watch_folders,
+            watch::remove_watch_folder,
+            watch::list_watch_presets,
+            watch::add_watch_preset,
+            audio_files::import_audio_file,
+            audio_files::import_audio_data,
+            audio_files::set_audio_file_project,
+            audio_files::get_audio_file,
+            llm::get_summarization_provider,
+            llm::set_summarization_provider,
+            prompts::list_prompts,
+            prompts::save_prompt,
+            prompts::clone_prompt,
+            prompts::delete_prompt,
+            summarize::summarize_transcription,
+            summarize::cancel_summary,
+            titles::generate_title,
+            titles::name_transcription,
+            usage::get_usage_report,
+            secrets::set_api_key,
+            secrets::test_api_key,
+            secrets::delete_api_key,
+            network::set_offline_mode,
+            network::offline_mode,
+            network::get_proxy_settings,
+            network::set_proxy_settings,
+            models::register_custom_model,
+            models::list_model_registry,
+            models::unregister_custom_model,
+            routing::get_language_routes,
+            routing::set_language_route,
+            hallucination::get_hallucination_filter,
+            hallucination::set_hallucination_filter,
+            transcription::get_decoding_defaults,
+            transcription::set_decoding_defaults,
+            bulk::delete_transcriptions,
+            bulk::tag_transcriptions,
+            bulk::export_transcriptions,
+            bulk::summarize_transcriptions,
+            history::export_history_csv,
+            archive::archive_audio,
+            archive::restore_audio,
+            archive::get_audio_archive,
+            maintenance::run_db_maintenance,
+            crash::list_crash_reports,
+            crash::submit_crash_report,
+            crash::delete_crash_report,
+            crash::set_crash_report_endpoint,
+            postprocess::get_post_processing,
+            postprocess::set_post_processing,
+            postprocess::post_process_text,
+            translations::save_translations,
+            translations::list_translations,
+            translations::export_bilingual,
+            clips::extract_clip,
+            anki::export_flashcards,
+            pronunciation::compare_pronunciation,
+            session::save_session,
+            session::restore_session,
+            parts::split_transcription,
+            parts::merge_transcriptions,
+            trim::set_audio_trim,
+            trim::get_audio_trim,
+            transcription::retranscribe,
+            cleanup::source_processed,
+            cleanup::get_source_cleanup,
+            cleanup::set_source_cleanup,
+            cleanup::list_source_cleanups,
+            cleanup::cancel_source_cleanup,
+            watch::set_watch_folder_cleanup,
+            watch::set_watch_folder_series,
+            watch::set_watch_folder_project,
+            deeplink::get_deep_links_enabled,
+            deeplink::set_deep_links_enabled,
+            audit::get_audit_log,
+            bulk::delete_transcription,
+            bulk::clear_all_data,
+            autoexport::get_auto_export,
+            autoexport::set_auto_export,
+            series::get_series_prompt,
+            rerun::retranscribe_range,
+            clips::export_speaker_stems,
+            selftest::run_self_test,
+            i18n::get_ui_language,
+            i18n::set_ui_language,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
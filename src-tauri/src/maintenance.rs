@@ -0,0 +1,168 @@
+//! Routine upkeep of the history database.
+//!
+//! Maintenance checks integrity, rebuilds the file with `VACUUM` to return
+//! pages freed by deletes, and refreshes the query planner's statistics. It
+//! runs on demand and, when the interval preference is set, in the
+//! background once that many days have passed since the last run. Each
+//! report is also emitted as `db://maintenance`.
+
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::{Error, Result};
+use crate::{crash, db};
+
+pub const MAINTENANCE_EVENT: &str = "db://maintenance";
+/// Days between background runs; unset or 0 disables the schedule.
+pub const INTERVAL_PREFERENCE: &str = "db_maintenance_interval_days";
+const LAST_RUN_PREFERENCE: &str = "db_maintenance_last_run";
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// `integrity_check` stops after this many problems.
+const MAX_FINDINGS: u32 = 100;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceReport {
+    pub size_before: u64,
+    pub size_after: u64,
+    pub reclaimed_bytes: u64,
+    /// Problems reported by the integrity and foreign key checks; empty
+    /// when the database is sound.
+    pub findings: Vec<String>,
+    /// Whether the file was vacuumed. Skipped when corruption was found,
+    /// since rebuilding a damaged file can lose more data.
+    pub vacuumed: bool,
+    /// Milliseconds.
+    pub duration: u64,
+    /// Unix seconds.
+    pub ran_at: u64,
+    pub scheduled: bool,
+}
+
+fn size(conn: &Connection) -> Result<u64> {
+    let pages: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok((pages * page_size) as u64)
+}
+
+fn integrity_findings(conn: &Connection) -> Result<Vec<String>> {
+    let mut findings: Vec<String> = conn
+        .prepare(&format!("PRAGMA integrity_check({})", MAX_FINDINGS))?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|line| line != "ok")
+        .collect();
+    let orphans = conn
+        .prepare("PRAGMA foreign_key_check")?
+        .query_map([], |row| {
+            Ok(format!(
+                "row {} of {} references a missing {} row",
+                row.get::<_, Option<i64>>(1)?.unwrap_or_default(),
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    findings.extend(orphans);
+    Ok(findings)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Check, vacuum and optimize the database behind `conn`.
+pub fn run(conn: &Connection, scheduled: bool) -> Result<MaintenanceReport> {
+    let started = Instant::now();
+    let size_before = size(conn)?;
+    let findings = integrity_findings(conn)?;
+    let vacuumed = findings.is_empty();
+    if vacuumed {
+        conn.execute_batch("VACUUM;")?;
+    }
+    conn.execute_batch("ANALYZE; PRAGMA optimize;")?;
+    let size_after = size(conn)?;
+
+    let report = MaintenanceReport {
+        size_before,
+        size_after,
+        reclaimed_bytes: size_before.saturating_sub(size_after),
+        findings,
+        vacuumed,
+        duration: started.elapsed().as_millis() as u64,
+        ran_at: now(),
+        scheduled,
+    };
+    db::set_preference(conn, LAST_RUN_PREFERENCE, &report.ran_at.to_string())?;
+    Ok(report)
+}
+
+/// Whether a scheduled run is due `at` (Unix seconds).
+fn is_due(interval_days: Option<u64>, last_run: Option<u64>, at: u64) -> bool {
+    match (interval_days.filter(|&days| days > 0), last_run) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(days), Some(last)) => at.saturating_sub(last) >= days * 24 * 60 * 60,
+    }
+}
+
+fn run_if_due(app: &AppHandle) -> Result<()> {
+    let conn = db::connect(app)?;
+    let number = |key: &str| -> Result<Option<u64>> {
+        Ok(db::get_preference(&conn, key)?.and_then(|value| value.trim().parse().ok()))
+    };
+    if is_due(
+        number(INTERVAL_PREFERENCE)?,
+        number(LAST_RUN_PREFERENCE)?,
+        now(),
+    ) {
+        let report = run(&conn, true)?;
+        let _ = app.emit_all(MAINTENANCE_EVENT, report);
+    }
+    Ok(())
+}
+
+/// Start the background schedule. Call once during app setup.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(CHECK_INTERVAL);
+        if let Err(err) = run_if_due(&app) {
+            crash::log(format!("database maintenance failed: {}", err));
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn run_db_maintenance(app: AppHandle) -> Result<MaintenanceReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = run(&db::connect(&app)?, false)?;
+        let _ = app.emit_all(MAINTENANCE_EVENT, report.clone());
+        Ok(report)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_runs_once_per_interval() {
+        let day = 24 * 60 * 60;
+        assert!(!is_due(None, None, 10 * day));
+        assert!(!is_due(Some(0), None, 10 * day));
+        assert!(is_due(Some(7), None, 10 * day));
+        assert!(!is_due(Some(7), Some(5 * day), 10 * day));
+        assert!(is_due(Some(7), Some(3 * day), 10 * day));
+    }
+}
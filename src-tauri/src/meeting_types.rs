@@ -0,0 +1,158 @@
+//! Meeting types: named bundles of settings applied to a transcription.
+//!
+//! A type such as "standup" or "lecture" carries a summarization prompt, a
+//! vocabulary that biases the decoder toward domain terms, tags for the
+//! saved record and the export template to use. Transcribing with a meeting
+//! type applies the vocabulary and writes its export template next to the
+//! source. A queued job that saves its transcript also applies the tags,
+//! summarizes with the type's prompt and remembers the type, so later
+//! summaries and exports without a format of their own use it too.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeetingType {
+    pub id: String,
+    pub name: String,
+    pub summary_prompt: Option<String>,
+    pub vocabulary: Vec<String>,
+    pub default_tags: Vec<String>,
+    /// Export format or template name, e.g. `md` or `docx`.
+    pub export_template: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeetingTypeInput {
+    pub name: String,
+    pub summary_prompt: Option<String>,
+    #[serde(default)]
+    pub vocabulary: Vec<String>,
+    #[serde(default)]
+    pub default_tags: Vec<String>,
+    pub export_template: Option<String>,
+}
+
+impl MeetingType {
+    /// Decoder prompt listing the vocabulary, if there is any.
+    pub fn vocabulary_prompt(&self) -> Option<String> {
+        if self.vocabulary.is_empty() {
+            None
+        } else {
+            Some(format!("Glossary: {}.", self.vocabulary.join(", ")))
+        }
+    }
+}
+
+const COLUMNS: &str = "id, name, summary_prompt, vocabulary, default_tags, export_template";
+
+fn json_list(value: String) -> Vec<String> {
+    serde_json::from_str(&value).unwrap_or_default()
+}
+
+fn from_row(row: &Row) -> rusqlite::Result<MeetingType> {
+    Ok(MeetingType {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        summary_prompt: row.get(2)?,
+        vocabulary: json_list(row.get(3)?),
+        default_tags: json_list(row.get(4)?),
+        export_template: row.get(5)?,
+    })
+}
+
+/// Look a meeting type up by id or, case-insensitively, by name.
+pub fn find(conn: &Connection, key: &str) -> Result<MeetingType> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM meeting_types WHERE id = ?1 OR name = ?1 COLLATE NOCASE",
+            COLUMNS
+        ),
+        [key],
+        from_row,
+    )
+    .optional()?
+    .ok_or_else(|| Error::NotFound(format!("meeting type '{}'", key)))
+}
+
+fn clean(list: Vec<String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = list
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect();
+    cleaned.dedup();
+    cleaned
+}
+
+#[tauri::command]
+pub fn list_meeting_types(app: AppHandle) -> Result<Vec<MeetingType>> {
+    let conn = db::connect(&app)?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM meeting_types ORDER BY name COLLATE NOCASE",
+        COLUMNS
+    ))?;
+    let types = statement
+        .query_map([], from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(types)
+}
+
+/// Create a meeting type, or replace the one with `id`.
+#[tauri::command]
+pub fn save_meeting_type(
+    app: AppHandle,
+    id: Option<String>,
+    meeting_type: MeetingTypeInput,
+) -> Result<MeetingType> {
+    let name = meeting_type.name.trim().to_string();
+    if name.is_empty() {
+        return Err(Error::InvalidInput("meeting type name is empty".into()));
+    }
+    let conn = db::connect(&app)?;
+    let id = id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let vocabulary = serde_json::to_string(&clean(meeting_type.vocabulary)).unwrap();
+    let tags = serde_json::to_string(&clean(meeting_type.default_tags)).unwrap();
+
+    conn.execute(
+        "INSERT INTO meeting_types (id, name, summary_prompt, vocabulary, default_tags, export_template)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(id) DO UPDATE SET
+             name = excluded.name,
+             summary_prompt = excluded.summary_prompt,
+             vocabulary = excluded.vocabulary,
+             default_tags = excluded.default_tags,
+             export_template = excluded.export_template,
+             updated_at = CURRENT_TIMESTAMP",
+        params![
+            id,
+            name,
+            meeting_type.summary_prompt,
+            vocabulary,
+            tags,
+            meeting_type.export_template
+        ],
+    )
+    .map_err(|err| match err {
+        rusqlite::Error::SqliteFailure(e, _) if e.code == rusqlite::ErrorCode::ConstraintViolation => {
+            Error::InvalidInput(format!("a meeting type named '{}' already exists", name))
+        }
+        other => other.into(),
+    })?;
+    find(&conn, &id)
+}
+
+#[tauri::command]
+pub fn delete_meeting_type(app: AppHandle, id: String) -> Result<()> {
+    let deleted = db::connect(&app)?.execute("DELETE FROM meeting_types WHERE id = ?1", [&id])?;
+    if deleted == 0 {
+        return Err(Error::NotFound(format!("meeting type '{}'", id)));
+    }
+    Ok(())
+}
@@ -0,0 +1,240 @@
+//! Warm cache of loaded whisper contexts.
+//!
+//! Loading a model takes seconds, so recently used contexts are kept in
+//! memory, least recently used first out, within a configurable budget. A
+//! background watcher also evicts models when the system runs low on memory.
+//! Callers asking for a model that is still loading wait for that load
+//! instead of starting a second one.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use sysinfo::System;
+use tauri::{AppHandle, Manager, State};
+use whisper_rs::WhisperContext;
+
+use crate::error::{Error, Result};
+use crate::{crash, db, models, whisper};
+
+pub const BUDGET_PREFERENCE: &str = "model_memory_budget_mb";
+
+/// Below this share of available memory the watcher starts evicting.
+const PRESSURE_THRESHOLD: f64 = 0.10;
+const PRESSURE_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedModel {
+    pub name: String,
+    /// Estimated from the model file size, which dominates context memory.
+    pub estimated_bytes: u64,
+    /// Whether a transcription is currently holding the context.
+    pub in_use: bool,
+}
+
+struct Entry {
+    name: String,
+    ctx: Arc<WhisperContext>,
+    size_bytes: u64,
+}
+
+struct Inner {
+    budget_bytes: u64,
+    /// Most recently used first.
+    entries: Vec<Entry>,
+    /// Names of the models being loaded.
+    loading: HashSet<String>,
+}
+
+pub struct ModelCache {
+    inner: Mutex<Inner>,
+    /// Signalled whenever a load finishes, successfully or not.
+    loaded: Condvar,
+}
+
+/// Releases a model's loading slot, also if the load fails or panics.
+struct LoadingSlot<'a>(&'a ModelCache, &'a str);
+
+impl Drop for LoadingSlot<'_> {
+    fn drop(&mut self) {
+        self.0.inner.lock().unwrap().loading.remove(self.1);
+        self.0.loaded.notify_all();
+    }
+}
+
+impl ModelCache {
+    pub fn new(budget_bytes: u64) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                budget_bytes,
+                entries: Vec::new(),
+                loading: HashSet::new(),
+            }),
+            loaded: Condvar::new(),
+        }
+    }
+
+    /// Half of physical memory, used until the user configures a budget.
+    pub fn default_budget() -> u64 {
+        let mut system = System::new();
+        system.refresh_memory();
+        system.total_memory() / 2
+    }
+
+    /// Return a warm context for `name`, loading it from `path` on a miss.
+    /// While another caller loads the same model, wait for its result.
+    pub fn get_or_load(&self, name: &str, path: &Path) -> Result<Arc<WhisperContext>> {
+        let mut inner = self.inner.lock().unwrap();
+        loop {
+            if let Some(index) = inner.entries.iter().position(|entry| entry.name == name) {
+                let entry = inner.entries.remove(index);
+                let ctx = entry.ctx.clone();
+                inner.entries.insert(0, entry);
+                return Ok(ctx);
+            }
+            // A failed load leaves no entry, and the next waiter tries.
+            if inner.loading.insert(name.to_string()) {
+                break;
+            }
+            inner = self.loaded.wait(inner).unwrap();
+        }
+        drop(inner);
+        let slot = LoadingSlot(self, name);
+
+        // Load outside the lock so other models stay available meanwhile.
+        let size_bytes = std::fs::metadata(path)?.len();
+        let ctx = Arc::new(whisper::load_context(path)?);
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.retain(|entry| entry.name != name);
+        inner.entries.insert(
+            0,
+            Entry {
+                name: name.to_string(),
+                ctx: ctx.clone(),
+                size_bytes,
+            },
+        );
+        inner.enforce_budget();
+        drop(inner);
+        drop(slot);
+        Ok(ctx)
+    }
+
+    pub fn set_budget(&self, budget_bytes: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.budget_bytes = budget_bytes;
+        inner.enforce_budget();
+    }
+
+    pub fn unload(&self, name: &str) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.entries.len();
+        inner.entries.retain(|entry| entry.name != name);
+        inner.entries.len() != before
+    }
+
+    pub fn loaded(&self) -> Vec<LoadedModel> {
+        self.inner
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|entry| LoadedModel {
+                name: entry.name.clone(),
+                estimated_bytes: entry.size_bytes,
+                in_use: Arc::strong_count(&entry.ctx) > 1,
+            })
+            .collect()
+    }
+
+    /// Drop the least recently used model if the system is short on memory.
+    fn relieve_pressure(&self, system: &mut System) {
+        system.refresh_memory();
+        let total = system.total_memory();
+        if total == 0 || system.available_memory() as f64 / total as f64 >= PRESSURE_THRESHOLD {
+            return;
+        }
+        if let Some(entry) = self.inner.lock().unwrap().entries.pop() {
+            crash::log(format!("memory pressure: unloading model {}", entry.name));
+        }
+    }
+}
+
+impl Inner {
+    fn enforce_budget(&mut self) {
+        // The most recent model is always kept, even if it alone exceeds
+        // the budget; otherwise it could never be used.
+        while self.entries.len() > 1
+            && self
+                .entries
+                .iter()
+                .map(|entry| entry.size_bytes)
+                .sum::<u64>()
+                > self.budget_bytes
+        {
+            self.entries.pop();
+        }
+    }
+}
+
+/// Create the cache with the persisted budget and start the pressure watcher.
+pub fn init(app: &AppHandle) {
+    let budget = db::connect(app)
+        .and_then(|conn| db::get_preference(&conn, BUDGET_PREFERENCE))
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(|megabytes| megabytes * 1024 * 1024)
+        .unwrap_or_else(ModelCache::default_budget);
+    app.manage(ModelCache::new(budget));
+
+    let app = app.clone();
+    thread::spawn(move || {
+        let mut system = System::new();
+        loop {
+            thread::sleep(PRESSURE_POLL_INTERVAL);
+            app.state::<ModelCache>().relieve_pressure(&mut system);
+        }
+    });
+}
+
+/// Load a downloaded model through the cache.
+pub fn context_for(app: &AppHandle, model: &str) -> Result<Arc<WhisperContext>> {
+    let path = models::model_path(app, model)?;
+    app.state::<ModelCache>().get_or_load(model, &path)
+}
+
+#[tauri::command]
+pub fn get_loaded_models(cache: State<'_, ModelCache>) -> Vec<LoadedModel> {
+    cache.loaded()
+}
+
+#[tauri::command]
+pub fn unload_model(cache: State<'_, ModelCache>, model: String) -> Result<()> {
+    if cache.unload(&model) {
+        Ok(())
+    } else {
+        Err(Error::NotFound(format!("model '{}' is not loaded", model)))
+    }
+}
+
+/// Persist and apply the memory budget for cached models, in megabytes.
+#[tauri::command]
+pub fn set_model_memory_budget(
+    app: AppHandle,
+    cache: State<'_, ModelCache>,
+    megabytes: u64,
+) -> Result<()> {
+    db::set_preference(
+        &db::connect(&app)?,
+        BUDGET_PREFERENCE,
+        &megabytes.to_string(),
+    )?;
+    cache.set_budget(megabytes * 1024 * 1024);
+    Ok(())
+}
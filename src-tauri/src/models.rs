@@ -0,0 +1,310 @@
+//! Whisper model files.
+//!
+//! Models are ggml files stored under `<app data>/models`, named the way
+//! whisper.cpp publishes them (`ggml-base.bin`, `ggml-small.en.bin`, ...).
+//! Quantized variants carry the level as a suffix (`ggml-base-q5_0.bin`)
+//! and load through the same code path. Custom and fine-tuned models are
+//! registered in the `models` table with the checksum they were verified
+//! against when added.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::{db, network};
+
+/// Approximate download sizes of the models whisper.cpp publishes, in MB.
+const BUILT_IN_SIZES_MB: &[(&str, u64)] = &[
+    ("tiny", 75),
+    ("tiny.en", 75),
+    ("base", 142),
+    ("base.en", 142),
+    ("small", 466),
+    ("small.en", 466),
+    ("medium", 1_500),
+    ("medium.en", 1_500),
+    ("large-v3", 3_100),
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// Quantization level (`q5_0`, `q8_0`, ...) or `None` for full precision.
+    pub quantization: Option<String>,
+}
+
+pub fn models_dir(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| Error::NotFound("app data directory".into()))?
+        .join("models");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Extract the quantization suffix from a model name such as `base-q5_1`.
+pub fn quantization_of(name: &str) -> Option<&str> {
+    let (_, suffix) = name.rsplit_once('-')?;
+    let level = suffix.strip_prefix('q')?;
+    level
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '_' || c == 'k')
+        .then_some(suffix)
+}
+
+/// Expected size in bytes of a built-in model download.
+pub fn expected_size(name: &str) -> Option<u64> {
+    BUILT_IN_SIZES_MB
+        .iter()
+        .find(|(model, _)| *model == name)
+        .map(|(_, mb)| mb * 1024 * 1024)
+}
+
+pub fn model_file_name(name: &str) -> String {
+    format!("ggml-{}.bin", name)
+}
+
+/// Resolve a model name to its file, failing if it has not been downloaded.
+pub fn model_path(app: &AppHandle, name: &str) -> Result<PathBuf> {
+    let path = models_dir(app)?.join(model_file_name(name));
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(Error::NotFound(format!(
+            "model '{}' is not downloaded",
+            name
+        )))
+    }
+}
+
+/// All model files present in the models directory.
+pub fn downloaded_models(app: &AppHandle) -> Result<Vec<ModelInfo>> {
+    let mut models = Vec::new();
+    for entry in fs::read_dir(models_dir(app)?)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some(name) = file_name
+            .strip_prefix("ggml-")
+            .and_then(|rest| rest.strip_suffix(".bin"))
+        else {
+            continue;
+        };
+
+        models.push(ModelInfo {
+            quantization: quantization_of(name).map(str::to_string),
+            name: name.to_string(),
+            path: entry.path(),
+            size_bytes: entry.metadata()?.len(),
+        });
+    }
+
+    models.sort_by(|a, b| a.size_bytes.cmp(&b.size_bytes));
+    Ok(models)
+}
+
+#[tauri::command]
+pub fn list_downloaded_models(app: AppHandle) -> Result<Vec<ModelInfo>> {
+    downloaded_models(&app)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryEntry {
+    pub name: String,
+    pub custom: bool,
+    /// URL or local path a custom model was added from.
+    pub source: Option<String>,
+    pub sha256: Option<String>,
+    pub size_bytes: Option<u64>,
+    pub downloaded: bool,
+}
+
+/// Normalize a SHA-256 checksum given as hex, optionally `sha256:`-prefixed.
+pub fn parse_checksum(checksum: &str) -> Result<String> {
+    let hex = checksum.trim();
+    let hex = hex
+        .strip_prefix("sha256:")
+        .unwrap_or(hex)
+        .to_ascii_lowercase();
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::InvalidInput(
+            "checksum must be a 64-character SHA-256 hex digest".into(),
+        ));
+    }
+    Ok(hex)
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(Error::InvalidInput(
+            "model names may only contain letters, digits, '-', '_' and '.'".into(),
+        ));
+    }
+    if expected_size(name).is_some() {
+        return Err(Error::InvalidInput(format!(
+            "'{}' is a built-in model name",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Copy `reader` to `target`, returning the SHA-256 hex digest and size.
+fn copy_hashed(mut reader: impl Read, target: &Path) -> Result<(String, u64)> {
+    let mut file = File::create(target)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    let mut size = 0u64;
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        hasher.update(&buffer[..read]);
+        file.write_all(&buffer[..read])?;
+        size += read as u64;
+    }
+    file.sync_all()?;
+    let digest = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    Ok((digest, size))
+}
+
+fn fetch(conn: &Connection, source: &str, target: &Path) -> Result<(String, u64)> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let response = network::client(conn)?.fetch(source).map_err(|e| match e {
+            Error::Provider(message) => Error::Provider(format!("download failed: {}", message)),
+            other => other,
+        })?;
+        copy_hashed(response.into_reader(), target)
+    } else {
+        copy_hashed(File::open(source)?, target)
+    }
+}
+
+/// Add a model from a URL or local file once its checksum matches.
+pub fn register(
+    app: &AppHandle,
+    conn: &Connection,
+    name: &str,
+    source: &str,
+    checksum: &str,
+) -> Result<ModelInfo> {
+    validate_name(name)?;
+    let expected = parse_checksum(checksum)?;
+    let target = models_dir(app)?.join(model_file_name(name));
+    let partial = target.with_extension("bin.part");
+
+    let fetched = fetch(conn, source, &partial);
+    let (digest, size) = match fetched {
+        Ok(result) => result,
+        Err(err) => {
+            let _ = fs::remove_file(&partial);
+            return Err(err);
+        }
+    };
+    if digest != expected {
+        let _ = fs::remove_file(&partial);
+        return Err(Error::InvalidInput(format!(
+            "checksum mismatch for '{}': expected {}, got {}",
+            name, expected, digest
+        )));
+    }
+    fs::rename(&partial, &target)?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO models (name, source, sha256, size_bytes) VALUES (?1, ?2, ?3, ?4)",
+        params![name, source, digest, size as i64],
+    )?;
+    Ok(ModelInfo {
+        quantization: quantization_of(name).map(str::to_string),
+        name: name.to_string(),
+        path: target,
+        size_bytes: size,
+    })
+}
+
+#[tauri::command]
+pub async fn register_custom_model(
+    app: AppHandle,
+    name: String,
+    source: String,
+    checksum: String,
+) -> Result<ModelInfo> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::connect(&app)?;
+        register(&app, &conn, name.trim(), source.trim(), &checksum)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+/// Built-in models followed by registered custom ones.
+#[tauri::command]
+pub fn list_model_registry(app: AppHandle) -> Result<Vec<RegistryEntry>> {
+    let dir = models_dir(&app)?;
+    let downloaded = |name: &str| dir.join(model_file_name(name)).is_file();
+
+    let mut entries: Vec<RegistryEntry> = BUILT_IN_SIZES_MB
+        .iter()
+        .map(|(name, mb)| RegistryEntry {
+            name: name.to_string(),
+            custom: false,
+            source: None,
+            sha256: None,
+            size_bytes: Some(mb * 1024 * 1024),
+            downloaded: downloaded(name),
+        })
+        .collect();
+
+    let conn = db::connect(&app)?;
+    let mut statement =
+        conn.prepare("SELECT name, source, sha256, size_bytes FROM models ORDER BY name")?;
+    let custom = statement.query_map([], |row| {
+        let name: String = row.get(0)?;
+        Ok(RegistryEntry {
+            downloaded: downloaded(&name),
+            name,
+            custom: true,
+            source: row.get(1)?,
+            sha256: row.get(2)?,
+            size_bytes: row.get::<_, Option<i64>>(3)?.map(|size| size as u64),
+        })
+    })?;
+    for entry in custom {
+        entries.push(entry?);
+    }
+    Ok(entries)
+}
+
+/// Remove a custom model and its file.
+#[tauri::command]
+pub fn unregister_custom_model(app: AppHandle, name: String) -> Result<()> {
+    let deleted = db::connect(&app)?.execute("DELETE FROM models WHERE name = ?1", [&name])?;
+    if deleted == 0 {
+        return Err(Error::NotFound(format!("custom model '{}'", name)));
+    }
+    match fs::remove_file(models_dir(&app)?.join(model_file_name(&name))) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,738 @@
+//! Spoken numbers, amounts and dates written the way the locale writes them.
+//!
+//! Dictation often comes back with numbers spelled out ("twenty three
+//! euro", "vijf maart"). This rewrites them as a reader expects them:
+//! amounts with their currency symbol, percentages with `%`, dates in the
+//! locale's order, and other numbers as digits. Lone numbers below ten stay
+//! words, following the usual house style, and ordinals are only read as
+//! part of a date since "second" is just as often a duration. English and
+//! Dutch are supported; other languages pass through unchanged. Line breaks
+//! and other spacing between words are kept as they were.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Locale {
+    En,
+    Nl,
+}
+
+impl Locale {
+    fn for_language(language: &str) -> Option<Locale> {
+        match language.split(['-', '_']).next()?.to_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "nl" => Some(Locale::Nl),
+            _ => None,
+        }
+    }
+}
+
+const EN_SMALL: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const EN_TENS: [&str; 8] = [
+    "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const EN_ORDINALS: [&str; 20] = [
+    "",
+    "first",
+    "second",
+    "third",
+    "fourth",
+    "fifth",
+    "sixth",
+    "seventh",
+    "eighth",
+    "ninth",
+    "tenth",
+    "eleventh",
+    "twelfth",
+    "thirteenth",
+    "fourteenth",
+    "fifteenth",
+    "sixteenth",
+    "seventeenth",
+    "eighteenth",
+    "nineteenth",
+];
+const EN_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const NL_SMALL: [&str; 20] = [
+    "nul",
+    "een",
+    "twee",
+    "drie",
+    "vier",
+    "vijf",
+    "zes",
+    "zeven",
+    "acht",
+    "negen",
+    "tien",
+    "elf",
+    "twaalf",
+    "dertien",
+    "veertien",
+    "vijftien",
+    "zestien",
+    "zeventien",
+    "achttien",
+    "negentien",
+];
+const NL_TENS: [&str; 8] = [
+    "twintig", "dertig", "veertig", "vijftig", "zestig", "zeventig", "tachtig", "negentig",
+];
+const NL_MONTHS: [&str; 12] = [
+    "januari",
+    "februari",
+    "maart",
+    "april",
+    "mei",
+    "juni",
+    "juli",
+    "augustus",
+    "september",
+    "oktober",
+    "november",
+    "december",
+];
+
+/// One whitespace-separated word, split into the part that is matched and
+/// the punctuation around it.
+#[derive(Debug, Clone)]
+struct Token {
+    lead: String,
+    word: String,
+    trail: String,
+    raw: String,
+}
+
+impl Token {
+    fn new(raw: &str) -> Token {
+        let core_start = raw.find(|c: char| c.is_alphanumeric()).unwrap_or(raw.len());
+        let core_end = raw
+            .rfind(|c: char| c.is_alphanumeric())
+            .map_or(core_start, |i| {
+                i + raw[i..].chars().next().unwrap().len_utf8()
+            });
+        Token {
+            lead: raw[..core_start].to_string(),
+            word: raw[core_start..core_end.max(core_start)].to_lowercase(),
+            trail: raw[core_end.max(core_start)..].to_string(),
+            raw: raw.to_string(),
+        }
+    }
+}
+
+/// What a word contributes to a number.
+#[derive(Debug, Clone, Copy)]
+enum Word {
+    Value(u64),
+    Hundred,
+    Scale(u64),
+}
+
+fn position(table: &[&str], word: &str) -> Option<u64> {
+    table.iter().position(|w| *w == word).map(|i| i as u64)
+}
+
+fn en_word(word: &str) -> Option<Word> {
+    if let Some(n) = position(&EN_SMALL, word) {
+        return Some(Word::Value(n));
+    }
+    if let Some(n) = position(&EN_TENS, word) {
+        return Some(Word::Value(20 + n * 10));
+    }
+    // "twenty-three"
+    if let Some((tens, unit)) = word.split_once('-') {
+        let tens = position(&EN_TENS, tens)?;
+        let unit = position(&EN_SMALL, unit).filter(|n| (1..10).contains(n))?;
+        return Some(Word::Value(20 + tens * 10 + unit));
+    }
+    match word {
+        "hundred" => Some(Word::Hundred),
+        "thousand" => Some(Word::Scale(1_000)),
+        "million" => Some(Word::Scale(1_000_000)),
+        "billion" => Some(Word::Scale(1_000_000_000)),
+        _ => None,
+    }
+}
+
+fn nl_below_hundred(word: &str) -> Option<u64> {
+    if let Some(n) = position(&NL_SMALL, word) {
+        return Some(n);
+    }
+    if let Some(n) = position(&NL_TENS, word) {
+        return Some(20 + n * 10);
+    }
+    // Units come first and are joined with "en": drieëntwintig is 3 + 20.
+    NL_TENS.iter().enumerate().find_map(|(i, tens)| {
+        let rest = word.strip_suffix(tens)?;
+        let unit = rest
+            .strip_suffix("ën")
+            .or_else(|| rest.strip_suffix("en"))?;
+        let unit = position(&NL_SMALL, unit).filter(|n| (1..10).contains(n))?;
+        Some(20 + i as u64 * 10 + unit)
+    })
+}
+
+/// Dutch writes numbers below a million as one word, e.g.
+/// tweeduizendvierhonderdvijftig.
+fn nl_compound(
+    word: &str,
+    scale: &str,
+    factor: u64,
+    below: fn(&str) -> Option<u64>,
+) -> Option<u64> {
+    let (left, right) = word.split_once(scale)?;
+    let left = if left.is_empty() { 1 } else { below(left)? };
+    let right = if right.is_empty() { 0 } else { below(right)? };
+    (right < factor).then_some(left * factor + right)
+}
+
+fn nl_below_thousand(word: &str) -> Option<u64> {
+    if word.contains("honderd") {
+        nl_compound(word, "honderd", 100, nl_below_hundred)
+    } else {
+        nl_below_hundred(word)
+    }
+}
+
+fn nl_word(word: &str) -> Option<Word> {
+    match word {
+        // Without the accent it is the article.
+        "een" => None,
+        "één" => Some(Word::Value(1)),
+        "miljoen" => Some(Word::Scale(1_000_000)),
+        "miljard" => Some(Word::Scale(1_000_000_000)),
+        "duizend" => Some(Word::Scale(1_000)),
+        _ if word.contains("duizend") => {
+            nl_compound(word, "duizend", 1_000, nl_below_thousand).map(Word::Value)
+        }
+        _ => nl_below_thousand(word).map(Word::Value),
+    }
+}
+
+fn number_word(word: &str, locale: Locale) -> Option<Word> {
+    match locale {
+        Locale::En => en_word(word),
+        Locale::Nl => nl_word(word),
+    }
+}
+
+/// Builds a number from its words, refusing words that cannot follow the
+/// ones before ("three twenty" is two numbers, not one).
+#[derive(Debug, Default)]
+struct Accumulator {
+    total: u64,
+    current: u64,
+    last_scale: Option<u64>,
+    words: usize,
+}
+
+impl Accumulator {
+    fn push(&mut self, word: Word) -> bool {
+        let started = self.current > 0 || self.total > 0;
+        let accepted = match word {
+            Word::Value(v) => {
+                let fits = self.words == 0
+                    || (v < 100 && self.current.is_multiple_of(100) && started)
+                    || (v < 1_000 && self.current.is_multiple_of(1_000) && started)
+                    || (v < 10
+                        && v > 0
+                        && self.current % 100 >= 20
+                        && self.current.is_multiple_of(10));
+                if fits {
+                    self.current += v;
+                }
+                fits
+            }
+            Word::Hundred => {
+                let fits = (1..100).contains(&self.current);
+                if fits {
+                    self.current *= 100;
+                }
+                fits
+            }
+            Word::Scale(scale) => {
+                let fits = self.current > 0 && self.last_scale.is_none_or(|last| scale < last);
+                if fits {
+                    self.total += self.current * scale;
+                    self.current = 0;
+                    self.last_scale = Some(scale);
+                }
+                fits
+            }
+        };
+        if accepted {
+            self.words += 1;
+        }
+        accepted
+    }
+
+    fn value(&self) -> u64 {
+        self.total + self.current
+    }
+}
+
+/// The longest number starting at `start`, as its value and the number of
+/// tokens it spans. A digit token counts as a one-token number.
+fn cardinal(tokens: &[Token], start: usize, locale: Locale) -> Option<(u64, usize)> {
+    let first = tokens.get(start)?;
+    if is_digits(first) {
+        return first.word.parse().ok().map(|n| (n, 1));
+    }
+
+    let mut acc = Accumulator::default();
+    let mut end = start;
+    let mut i = start;
+    while let Some(token) = tokens.get(i) {
+        // "one hundred and five"
+        if locale == Locale::En && token.word == "and" && acc.words > 0 && token.trail.is_empty() {
+            let follows = tokens
+                .get(i + 1)
+                .and_then(|next| en_word(&next.word))
+                .is_some_and(|w| matches!(w, Word::Value(v) if v < 100));
+            if follows && acc.current.is_multiple_of(100) && tokens[i - 1].trail.is_empty() {
+                i += 1;
+                continue;
+            }
+            break;
+        }
+        if !token.lead.is_empty() && i > start {
+            break;
+        }
+        match number_word(&token.word, locale) {
+            Some(word) if acc.push(word) => {
+                i += 1;
+                end = i;
+                if !token.trail.is_empty() {
+                    break;
+                }
+            }
+            _ => break,
+        }
+    }
+    (acc.words > 0).then(|| (acc.value(), end - start))
+}
+
+fn en_ordinal_word(word: &str) -> Option<u64> {
+    match word {
+        "twentieth" => Some(20),
+        "thirtieth" => Some(30),
+        _ => match word.split_once('-') {
+            // "twenty-third"
+            Some((tens @ ("twenty" | "thirty"), unit)) => {
+                let unit = position(&EN_ORDINALS, unit).filter(|n| (1..10).contains(n))?;
+                Some(if tens == "twenty" { 20 } else { 30 } + unit)
+            }
+            Some(_) => None,
+            None => position(&EN_ORDINALS, word).filter(|&n| n > 0),
+        },
+    }
+}
+
+/// An ordinal day, written as one word or two ("twenty third").
+fn en_ordinal(tokens: &[Token], start: usize) -> Option<(u64, usize)> {
+    let first = tokens.get(start)?;
+    if let Some(n) = en_ordinal_word(&first.word) {
+        return Some((n, 1));
+    }
+    let tens = match first.word.as_str() {
+        "twenty" => 20,
+        "thirty" => 30,
+        _ => return None,
+    };
+    let unit = tokens
+        .get(start + 1)
+        .filter(|_| first.trail.is_empty())
+        .and_then(|next| en_ordinal_word(&next.word))
+        .filter(|n| (1..10).contains(n))?;
+    Some((tens + unit, 2))
+}
+
+fn ordinal_suffix(n: u64) -> &'static str {
+    match (n % 100, n % 10) {
+        (11..=13, _) => "th",
+        (_, 1) => "st",
+        (_, 2) => "nd",
+        (_, 3) => "rd",
+        _ => "th",
+    }
+}
+
+/// A year after a date: a plain number ("two thousand twenty four") or, in
+/// English, two pairs ("twenty twenty four").
+fn year(tokens: &[Token], start: usize, locale: Locale) -> Option<(u64, usize)> {
+    let (first, n) = cardinal(tokens, start, locale)?;
+    if (1_000..3_000).contains(&first) {
+        return Some((first, n));
+    }
+    if locale == Locale::En && (10..100).contains(&first) && tokens[start + n - 1].trail.is_empty()
+    {
+        let (second, m) = cardinal(tokens, start + n, locale)?;
+        if (10..100).contains(&second) {
+            return Some((first * 100 + second, n + m));
+        }
+    }
+    None
+}
+
+/// English months are only read when capitalized, so "may" and "march"
+/// as verbs are left alone.
+fn month(token: &Token, locale: Locale) -> Option<usize> {
+    match locale {
+        Locale::En => EN_MONTHS
+            .iter()
+            .position(|m| token.raw[token.lead.len()..].starts_with(m)),
+        Locale::Nl => NL_MONTHS.iter().position(|m| *m == token.word),
+    }
+}
+
+fn is_digits(token: &Token) -> bool {
+    !token.word.is_empty() && token.word.chars().all(|c| c.is_ascii_digit())
+}
+
+/// A spoken date starting at `start`, rendered, with the tokens it spans.
+fn date(tokens: &[Token], start: usize, locale: Locale) -> Option<(String, usize)> {
+    let valid_day = |day: u64| (1..=31).contains(&day);
+    let first = &tokens[start];
+    let (text, end) = match locale {
+        // "March fifth, twenty twenty four" becomes "March 5, 2024".
+        Locale::En if month(first, locale).is_some() => {
+            let m = month(first, locale)?;
+            let day_token = tokens.get(start + 1)?;
+            if !first.trail.is_empty() || is_digits(day_token) {
+                return None;
+            }
+            let (day, n) = en_ordinal(tokens, start + 1)
+                .or_else(|| cardinal(tokens, start + 1, locale))
+                .filter(|(day, _)| valid_day(*day))?;
+            let end = start + 1 + n;
+            let trail = &tokens[end - 1].trail;
+            match year(tokens, end, locale).filter(|_| trail.is_empty() || trail == ",") {
+                Some((year, n)) => (format!("{} {}, {}", EN_MONTHS[m], day, year), end + n),
+                None => (format!("{} {}", EN_MONTHS[m], day), end),
+            }
+        }
+        // "the fifth of March" keeps its ordinal: "the 5th of March".
+        Locale::En => {
+            let (day, n) = en_ordinal(tokens, start).filter(|(day, _)| valid_day(*day))?;
+            let of = tokens.get(start + n)?;
+            let month_token = tokens.get(start + n + 1)?;
+            if of.word != "of" || !tokens[start + n - 1].trail.is_empty() || !of.trail.is_empty() {
+                return None;
+            }
+            let m = month(month_token, locale)?;
+            let end = start + n + 2;
+            let text = format!("{}{} of {}", day, ordinal_suffix(day), EN_MONTHS[m]);
+            match year(tokens, end, locale).filter(|_| month_token.trail.is_empty()) {
+                Some((year, n)) => (format!("{} {}", text, year), end + n),
+                None => (text, end),
+            }
+        }
+        // "vijf maart tweeduizend vierentwintig" becomes "5 maart 2024".
+        Locale::Nl => {
+            let (day, n) = cardinal(tokens, start, locale).filter(|(day, _)| valid_day(*day))?;
+            let month_token = tokens.get(start + n)?;
+            if is_digits(first) || !tokens[start + n - 1].trail.is_empty() {
+                return None;
+            }
+            let m = month(month_token, locale)?;
+            let end = start + n + 1;
+            let text = format!("{} {}", day, NL_MONTHS[m]);
+            match year(tokens, end, locale).filter(|_| month_token.trail.is_empty()) {
+                Some((year, n)) => (format!("{} {}", text, year), end + n),
+                None => (text, end),
+            }
+        }
+    };
+    Some((
+        format!("{}{}{}", first.lead, text, tokens[end - 1].trail),
+        end - start,
+    ))
+}
+
+/// Digits with the locale's thousands separator from ten thousand up; four
+/// digit numbers are usually years and read better without one.
+fn digits(value: u64, locale: Locale) -> String {
+    let plain = value.to_string();
+    if value < 10_000 {
+        return plain;
+    }
+    let separator = match locale {
+        Locale::En => ',',
+        Locale::Nl => '.',
+    };
+    let mut out = String::new();
+    for (i, c) in plain.chars().enumerate() {
+        if i > 0 && (plain.len() - i).is_multiple_of(3) {
+            out.push(separator);
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn currency_symbol(word: &str) -> Option<char> {
+    match word {
+        "euro" | "euros" => Some('€'),
+        "dollar" | "dollars" => Some('$'),
+        "pound" | "pounds" => Some('£'),
+        _ => None,
+    }
+}
+
+fn amount(symbol: char, units: u64, cents: Option<u64>, locale: Locale) -> String {
+    let units = digits(units, locale);
+    match (locale, cents) {
+        (Locale::En, None) => format!("{}{}", symbol, units),
+        (Locale::En, Some(cents)) => format!("{}{}.{:02}", symbol, units, cents),
+        (Locale::Nl, None) => format!("{} {}", symbol, units),
+        (Locale::Nl, Some(cents)) => format!("{} {},{:02}", symbol, units, cents),
+    }
+}
+
+/// Cents after a currency word: "fifty", "and fifty cents", "vijftig cent".
+fn cents(tokens: &[Token], start: usize, locale: Locale) -> Option<(u64, usize)> {
+    let conjunction = match locale {
+        Locale::En => "and",
+        Locale::Nl => "en",
+    };
+    let skip = tokens
+        .get(start)
+        .is_some_and(|t| t.word == conjunction && t.trail.is_empty()) as usize;
+    let (value, n) = cardinal(tokens, start + skip, locale).filter(|(v, _)| *v < 100)?;
+    let after = start + skip + n;
+    let named = tokens
+        .get(after)
+        .is_some_and(|t| t.word == "cent" || t.word == "cents")
+        && tokens[after - 1].trail.is_empty();
+    if named {
+        Some((value, skip + n + 1))
+    } else if skip == 0 {
+        // "twenty euro fifty" with nothing after the cents but punctuation
+        // or the end of the text.
+        let last = &tokens[after - 1];
+        (!last.trail.is_empty() || after == tokens.len()).then_some((value, n))
+    } else {
+        None
+    }
+}
+
+/// Common words that tell English and Dutch text apart.
+const EN_MARKERS: &[&str] = &[
+    "the", "and", "of", "to", "is", "that", "it", "you", "with", "for", "this", "are",
+];
+const NL_MARKERS: &[&str] = &[
+    "de", "het", "een", "en", "van", "dat", "niet", "met", "voor", "ik", "je", "zijn",
+];
+
+/// `en` or `nl` when `text` reads as one of the supported languages, for
+/// transcripts decoded with automatic language detection.
+pub fn guess_language(text: &str) -> Option<&'static str> {
+    let (mut en, mut nl) = (0, 0);
+    for word in text.split_whitespace() {
+        let word = Token::new(word).word;
+        en += EN_MARKERS.contains(&word.as_str()) as usize;
+        nl += NL_MARKERS.contains(&word.as_str()) as usize;
+    }
+    match en.cmp(&nl) {
+        std::cmp::Ordering::Greater => Some("en"),
+        std::cmp::Ordering::Less => Some("nl"),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// Rewrite spoken numbers, amounts and dates in `text` for `language`.
+/// Text in unsupported languages is returned unchanged.
+pub fn normalize(text: &str, language: &str) -> String {
+    let Some(locale) = Locale::for_language(language) else {
+        return text.to_string();
+    };
+    // Each word with the whitespace after it.
+    let mut words = Vec::new();
+    let mut gaps = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let gap = rest[end..].len() - rest[end..].trim_start().len();
+        words.push(&rest[..end]);
+        gaps.push(&rest[end..end + gap]);
+        rest = &rest[end + gap..];
+    }
+    let tokens: Vec<Token> = words.into_iter().map(Token::new).collect();
+
+    let mut out = String::new();
+    // Writes text standing for `tokens[..end]`, then the gap after them.
+    let mut emit = |rendered: &str, end: usize| {
+        out.push_str(rendered);
+        out.push_str(gaps[end - 1]);
+    };
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some((rendered, n)) = date(&tokens, i, locale) {
+            emit(&rendered, i + n);
+            i += n;
+            continue;
+        }
+        let Some((value, n)) = cardinal(&tokens, i, locale) else {
+            emit(&tokens[i].raw, i + 1);
+            i += 1;
+            continue;
+        };
+        let last = &tokens[i + n - 1];
+        let next = tokens.get(i + n).filter(|_| last.trail.is_empty());
+        let lead = &tokens[i].lead;
+        let spelled = !is_digits(&tokens[i]);
+
+        if let Some(symbol) = next.and_then(|t| currency_symbol(&t.word)) {
+            let unit = &tokens[i + n];
+            let (cents, m) = if unit.trail.is_empty() {
+                cents(&tokens, i + n + 1, locale).map_or((None, 0), |(c, m)| (Some(c), m))
+            } else {
+                (None, 0)
+            };
+            let end = i + n + 1 + m;
+            emit(
+                &format!(
+                    "{}{}{}",
+                    lead,
+                    amount(symbol, value, cents, locale),
+                    tokens[end - 1].trail
+                ),
+                end,
+            );
+            i = end;
+            continue;
+        }
+        let percent = match next.map(|t| t.word.as_str()) {
+            Some("percent" | "procent") => Some(1),
+            Some("per")
+                if tokens[i + n].trail.is_empty()
+                    && tokens.get(i + n + 1).is_some_and(|t| t.word == "cent") =>
+            {
+                Some(2)
+            }
+            _ => None,
+        };
+        if let Some(m) = percent {
+            let end = i + n + m;
+            emit(
+                &format!(
+                    "{}{}%{}",
+                    lead,
+                    digits(value, locale),
+                    tokens[end - 1].trail
+                ),
+                end,
+            );
+            i = end;
+            continue;
+        }
+        if spelled && (value >= 10 || n > 1) {
+            emit(
+                &format!("{}{}{}", lead, digits(value, locale), last.trail),
+                i + n,
+            );
+        } else {
+            for (j, token) in tokens.iter().enumerate().skip(i).take(n) {
+                emit(&token.raw, j + 1);
+            }
+        }
+        i += n;
+    }
+
+    let leading = &text[..text.len() - text.trim_start().len()];
+    format!("{}{}", leading, out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_amounts_and_numbers_as_digits() {
+        assert_eq!(
+            normalize("that is twenty three euro.", "en"),
+            "that is €23."
+        );
+        assert_eq!(
+            normalize("It cost five dollars and fifty cents", "en"),
+            "It cost $5.50"
+        );
+        assert_eq!(
+            normalize("about one hundred and twenty-five thousand people", "en"),
+            "about 125,000 people"
+        );
+        assert_eq!(normalize("up twelve percent", "en"), "up 12%");
+        assert_eq!(
+            normalize("one of three options", "en"),
+            "one of three options"
+        );
+        assert_eq!(normalize("drieëntwintig euro", "nl"), "€ 23");
+        assert_eq!(
+            normalize("tweeduizend vijfhonderd mensen", "nl"),
+            "2500 mensen"
+        );
+        assert_eq!(normalize("een paar", "nl"), "een paar");
+        assert_eq!(normalize("twenty three euro", "fr"), "twenty three euro");
+    }
+
+    #[test]
+    fn keeps_line_breaks_and_guesses_the_language() {
+        assert_eq!(
+            normalize("Ann: twenty three euro.\n\nBob: up twelve percent", "en"),
+            "Ann: €23.\n\nBob: up 12%"
+        );
+        assert_eq!(guess_language("the budget is fine"), Some("en"));
+        assert_eq!(guess_language("het budget is in orde en klaar"), Some("nl"));
+        assert_eq!(guess_language("twenty three"), None);
+    }
+
+    #[test]
+    fn writes_dates_in_locale_order() {
+        assert_eq!(
+            normalize("due March fifth, twenty twenty four.", "en"),
+            "due March 5, 2024."
+        );
+        assert_eq!(
+            normalize("on the twenty-third of May", "en"),
+            "on the 23rd of May"
+        );
+        assert_eq!(
+            normalize("vijf maart tweeduizend vierentwintig", "nl"),
+            "5 maart 2024"
+        );
+    }
+}
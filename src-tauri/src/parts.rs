@@ -0,0 +1,472 @@
+//! Splitting one transcription in two and merging several into one.
+//!
+//! Segment times stay relative to the start of their transcription, so a
+//! split rebases the second half to zero and a merge shifts each later
+//! transcription by the length of those before it. Which stretch of which
+//! audio file each transcription covers is kept in `transcription_parts`;
+//! a transcription without rows there covers its whole audio file.
+//!
+//! Comments, tags and translations follow the segments they belong to.
+//! On a split, summaries stay with the transcription they were written
+//! for; on a merge, they and the edit history move to the merged one.
+//! Review items are recomputed once the segments have moved.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::segments::{self, SegmentInput, StoredSegment};
+use crate::whisper::Word;
+use crate::{audit, db, editing, i18n, review};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioPart {
+    pub audio_file_id: String,
+    /// Where the part begins in the transcription, in seconds.
+    pub start: f64,
+    /// Where the part begins in its audio file, in seconds.
+    pub audio_start: f64,
+    pub duration: f64,
+}
+
+/// The parts recorded for a transcription made by a split or merge, in
+/// playback order; empty for one that covers its whole audio file.
+pub fn stored(conn: &Connection, transcription_id: &str) -> Result<Vec<AudioPart>> {
+    let mut statement = conn.prepare(
+        "SELECT audio_file_id, start_time, audio_start, duration FROM transcription_parts
+         WHERE transcription_id = ?1 ORDER BY position",
+    )?;
+    let parts: Vec<AudioPart> = statement
+        .query_map([transcription_id], |row| {
+            Ok(AudioPart {
+                audio_file_id: row.get(0)?,
+                start: row.get(1)?,
+                audio_start: row.get(2)?,
+                duration: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(parts)
+}
+
+/// The audio a transcription covers, in playback order.
+pub fn for_transcription(conn: &Connection, transcription_id: &str) -> Result<Vec<AudioPart>> {
+    let parts = stored(conn, transcription_id)?;
+    if !parts.is_empty() {
+        return Ok(parts);
+    }
+    conn.query_row(
+        "SELECT audio_file_id, duration FROM transcriptions WHERE id = ?1",
+        [transcription_id],
+        |row| {
+            Ok(vec![AudioPart {
+                audio_file_id: row.get(0)?,
+                start: 0.0,
+                audio_start: 0.0,
+                duration: row.get(1)?,
+            }])
+        },
+    )
+    .optional()?
+    .ok_or_else(|| Error::NotFound(format!("transcription {}", transcription_id)))
+}
+
+fn write_parts(conn: &Connection, transcription_id: &str, parts: &[AudioPart]) -> Result<()> {
+    conn.execute(
+        "DELETE FROM transcription_parts WHERE transcription_id = ?1",
+        [transcription_id],
+    )?;
+    for (position, part) in parts.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO transcription_parts
+                 (transcription_id, position, audio_file_id, start_time, audio_start, duration)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                transcription_id,
+                position as i64,
+                part.audio_file_id,
+                part.start,
+                part.audio_start,
+                part.duration
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// `segment` as `text` from `start` to `end`, rebased by `shift` seconds,
+/// with the word times that fall in it.
+fn input(segment: &StoredSegment, text: &str, start: f64, end: f64, shift: f64) -> SegmentInput {
+    SegmentInput {
+        speaker: segment.speaker.clone(),
+        text: text.to_string(),
+        start,
+        end,
+        confidence: segment.confidence,
+        words: segment
+            .words
+            .iter()
+            .filter(|word| (start..end).contains(&(word.start - shift)))
+            .map(|word| Word {
+                start: word.start - shift,
+                end: (word.end - shift).min(end),
+                ..word.clone()
+            })
+            .collect(),
+    }
+}
+
+/// The segments before and after `at` seconds, the second half rebased to
+/// zero. A segment spanning `at` is divided between its words by their
+/// times, or without them in proportion to time.
+fn split_segments(segments: &[StoredSegment], at: f64) -> (Vec<SegmentInput>, Vec<SegmentInput>) {
+    let mut first = Vec::new();
+    let mut second = Vec::new();
+    for segment in segments {
+        let (start, end) = (segment.start, segment.end);
+        if end <= at {
+            first.push(input(segment, &segment.text, start, end, 0.0));
+            continue;
+        }
+        if start >= at {
+            second.push(input(segment, &segment.text, start - at, end - at, at));
+            continue;
+        }
+        let words: Vec<&str> = segment.text.split_whitespace().collect();
+        let cut = match segments::timed_words(segment) {
+            Some(timed) => timed.iter().filter(|word| word.start < at).count(),
+            None => (words.len() as f64 * (at - start) / (end - start)).round() as usize,
+        };
+        match cut {
+            0 => second.push(input(segment, &segment.text, 0.0, end - at, at)),
+            cut if cut >= words.len() => first.push(input(segment, &segment.text, start, at, 0.0)),
+            cut => {
+                first.push(input(segment, &words[..cut].join(" "), start, at, 0.0));
+                second.push(input(segment, &words[cut..].join(" "), 0.0, end - at, at));
+            }
+        }
+    }
+    (first, second)
+}
+
+fn split_parts(parts: &[AudioPart], at: f64) -> (Vec<AudioPart>, Vec<AudioPart>) {
+    let mut first = Vec::new();
+    let mut second = Vec::new();
+    for part in parts {
+        let end = part.start + part.duration;
+        if end <= at {
+            first.push(part.clone());
+        } else if part.start >= at {
+            second.push(AudioPart {
+                start: part.start - at,
+                ..part.clone()
+            });
+        } else {
+            first.push(AudioPart {
+                duration: at - part.start,
+                ..part.clone()
+            });
+            second.push(AudioPart {
+                start: 0.0,
+                audio_start: part.audio_start + (at - part.start),
+                duration: end - at,
+                ..part.clone()
+            });
+        }
+    }
+    (first, second)
+}
+
+pub fn joined_text(segments: &[SegmentInput]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The RFC 3339 time `offset_ms` after `started_at`.
+fn later(started_at: &str, offset_ms: i64) -> Option<String> {
+    let start = chrono::DateTime::parse_from_rfc3339(started_at).ok()?;
+    Some((start + chrono::Duration::milliseconds(offset_ms)).to_rfc3339())
+}
+
+fn transcription_duration(conn: &Connection, id: &str) -> Result<f64> {
+    conn.query_row(
+        "SELECT duration FROM transcriptions WHERE id = ?1",
+        [id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| Error::NotFound(format!("transcription {}", id)))
+}
+
+/// Split a transcription at `at_ms` into two. The original keeps
+/// everything before the cut; a new transcription, returned by id, gets
+/// the rest.
+#[tauri::command]
+pub fn split_transcription(app: AppHandle, id: String, at_ms: i64) -> Result<String> {
+    let mut conn = db::connect(&app)?;
+    let duration = transcription_duration(&conn, &id)?;
+    let at = at_ms as f64 / 1000.0;
+    if at <= 0.0 || at >= duration {
+        return Err(Error::InvalidInput(format!(
+            "split point {:.2}s is outside the transcription",
+            at
+        )));
+    }
+    let stored = segments::for_transcription(&conn, &id)?;
+    if stored.is_empty() {
+        return Err(Error::InvalidInput(
+            "splitting needs a transcription with timed segments".into(),
+        ));
+    }
+    let (first, second) = split_segments(&stored, at);
+    let (first_parts, second_parts) = split_parts(&for_transcription(&conn, &id)?, at);
+    let divided = first.len() + second.len() > stored.len();
+    let new_id = uuid::Uuid::new_v4().to_string();
+
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    let old_text = db::transcription_text(&tx, &id)?;
+    let (title, started_at): (Option<String>, Option<String>) = tx.query_row(
+        "SELECT title, recording_started_at FROM transcriptions WHERE id = ?1",
+        [&id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    tx.execute(
+        "INSERT INTO transcriptions
+             (id, audio_file_id, text, language, model_used, duration, confidence, title,
+              recording_started_at, recording_timezone)
+         SELECT ?2, COALESCE(?3, audio_file_id), ?4, language, model_used, ?5, confidence, ?6, ?7,
+                recording_timezone
+         FROM transcriptions WHERE id = ?1",
+        params![
+            id,
+            new_id,
+            second_parts.first().map(|part| &part.audio_file_id),
+            joined_text(&second),
+            duration - at,
+            title
+                .as_deref()
+                .map(|title| i18n::t_args("part-two", &[("title", title.to_string())])),
+            started_at.as_deref().and_then(|start| later(start, at_ms))
+        ],
+    )?;
+    let first_text = joined_text(&first);
+    editing::record_edit(&tx, &id, None, "split", Some(&old_text), Some(&first_text))?;
+    tx.execute(
+        "UPDATE transcriptions SET text = ?2, duration = ?3 WHERE id = ?1",
+        params![id, first_text, at],
+    )?;
+
+    tx.execute("DELETE FROM segments WHERE transcription_id = ?1", [&id])?;
+    segments::insert(&tx, &id, 0, &first)?;
+    segments::insert(&tx, &new_id, 0, &second)?;
+    write_parts(&tx, &id, &first_parts)?;
+    write_parts(&tx, &new_id, &second_parts)?;
+
+    tx.execute(
+        "UPDATE comments SET transcription_id = ?2, anchor_ms = anchor_ms - ?3
+         WHERE transcription_id = ?1 AND anchor_ms >= ?3",
+        params![id, new_id, at_ms],
+    )?;
+    tx.execute(
+        "INSERT INTO transcription_tags (transcription_id, tag)
+         SELECT ?2, tag FROM transcription_tags WHERE transcription_id = ?1",
+        params![id, new_id],
+    )?;
+    // Segments wholly after the cut keep their translations; a divided
+    // segment's translation stays with its first half.
+    let kept = first.len() as i64;
+    tx.execute(
+        "UPDATE translations SET transcription_id = ?2, position = position - ?3 + ?4
+         WHERE transcription_id = ?1 AND position >= ?3",
+        params![id, new_id, kept, divided as i64],
+    )?;
+    tx.commit()?;
+
+    for transcription_id in [&id, &new_id] {
+        review::flag(&mut conn, transcription_id, None, review::DEFAULT_THRESHOLD)?;
+    }
+    Ok(new_id)
+}
+
+/// Merge transcriptions, in the given order, into the first of them. The
+/// others are removed once their content has moved. Returns the id of the
+/// merged transcription.
+#[tauri::command]
+pub fn merge_transcriptions(app: AppHandle, ids: Vec<String>) -> Result<String> {
+    if ids.len() < 2 {
+        return Err(Error::InvalidInput(
+            "merging needs at least two transcriptions".into(),
+        ));
+    }
+    if (1..ids.len()).any(|i| ids[..i].contains(&ids[i])) {
+        return Err(Error::InvalidInput(
+            "a transcription cannot be merged with itself".into(),
+        ));
+    }
+    let mut conn = db::connect(&app)?;
+    let target = &ids[0];
+    let languages: Vec<String> = ids
+        .iter()
+        .map(|id| {
+            conn.query_row(
+                "SELECT language FROM transcriptions WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("transcription {}", id)))
+        })
+        .collect::<Result<_>>()?;
+    if languages.iter().any(|language| *language != languages[0]) {
+        return Err(Error::InvalidInput(
+            "only transcriptions in the same language can be merged".into(),
+        ));
+    }
+
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    let old_text = db::transcription_text(&tx, target)?;
+    let mut merged_segments = Vec::new();
+    let mut merged_parts = Vec::new();
+    let mut texts = Vec::new();
+    let mut offset = 0.0;
+    for id in &ids {
+        let duration = transcription_duration(&tx, id)?;
+        let first_position = merged_segments.len() as i64;
+        for segment in segments::for_transcription(&tx, id)? {
+            merged_segments.push(input(
+                &segment,
+                &segment.text,
+                segment.start + offset,
+                segment.end + offset,
+            ));
+        }
+        merged_parts.extend(
+            for_transcription(&tx, id)?
+                .into_iter()
+                .map(|part| AudioPart {
+                    start: part.start + offset,
+                    ..part
+                }),
+        );
+        let text = db::transcription_text(&tx, id)?;
+        if !text.trim().is_empty() {
+            texts.push(text.trim().to_string());
+        }
+
+        if id != target {
+            let offset_ms = (offset * 1000.0).round() as i64;
+            tx.execute(
+                "UPDATE comments SET transcription_id = ?2, anchor_ms = anchor_ms + ?3
+                 WHERE transcription_id = ?1",
+                params![id, target, offset_ms],
+            )?;
+            tx.execute(
+                "INSERT OR IGNORE INTO transcription_tags (transcription_id, tag)
+                 SELECT ?2, tag FROM transcription_tags WHERE transcription_id = ?1",
+                params![id, target],
+            )?;
+            tx.execute(
+                "UPDATE translations SET transcription_id = ?2, position = position + ?3
+                 WHERE transcription_id = ?1",
+                params![id, target, first_position],
+            )?;
+            tx.execute(
+                "UPDATE summaries SET transcription_id = ?2 WHERE transcription_id = ?1",
+                params![id, target],
+            )?;
+            // Versions count per transcription, so moved entries name the
+            // one they were made on.
+            tx.execute(
+                "UPDATE edit_log SET transcription_id = ?2, target = 'merged:' || ?1 || ':' || target
+                 WHERE transcription_id = ?1",
+                params![id, target],
+            )?;
+            tx.execute("DELETE FROM transcriptions WHERE id = ?1", [id])?;
+        }
+        offset += duration;
+    }
+
+    let text = texts.join("\n\n");
+    editing::record_edit(&tx, target, None, "merge", Some(&old_text), Some(&text))?;
+    tx.execute(
+        "UPDATE transcriptions SET text = ?2, duration = ?3 WHERE id = ?1",
+        params![target, text, offset],
+    )?;
+    tx.execute("DELETE FROM segments WHERE transcription_id = ?1", [target])?;
+    segments::insert(&tx, target, 0, &merged_segments)?;
+    write_parts(&tx, target, &merged_parts)?;
+    let merged: Vec<&str> = ids.iter().map(String::as_str).collect();
+    audit::record(
+        &tx,
+        "transcriptions_merged",
+        &merged,
+        Some(&format!("merged into {}", target)),
+    )?;
+    tx.commit()?;
+
+    review::flag(&mut conn, target, None, review::DEFAULT_THRESHOLD)?;
+    Ok(target.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(position: i64, text: &str, start: f64, end: f64) -> StoredSegment {
+        StoredSegment {
+            id: String::new(),
+            transcription_id: "t".into(),
+            position,
+            speaker: Some("Anna".into()),
+            text: text.into(),
+            start,
+            end,
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn divides_the_segment_at_the_cut_by_its_words() {
+        let stored = [
+            segment(0, "Welcome everyone.", 0.0, 2.0),
+            segment(1, "That was the standup and now the review", 2.0, 6.0),
+            segment(2, "First item.", 6.0, 7.0),
+        ];
+        let (first, second) = split_segments(&stored, 4.0);
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[1].text, "That was the standup");
+        assert_eq!(first[1].end, 4.0);
+        assert_eq!(second[0].text, "and now the review");
+        assert_eq!((second[0].start, second[0].end), (0.0, 2.0));
+        assert_eq!((second[1].start, second[1].end), (2.0, 3.0));
+        assert_eq!(second[1].speaker.as_deref(), Some("Anna"));
+    }
+
+    #[test]
+    fn splits_the_audio_part_under_the_cut() {
+        let parts = [AudioPart {
+            audio_file_id: "a".into(),
+            start: 0.0,
+            audio_start: 10.0,
+            duration: 60.0,
+        }];
+        let (first, second) = split_parts(&parts, 20.0);
+        assert_eq!(first[0].duration, 20.0);
+        assert_eq!(
+            second[0],
+            AudioPart {
+                audio_file_id: "a".into(),
+                start: 0.0,
+                audio_start: 30.0,
+                duration: 40.0,
+            }
+        );
+    }
+}
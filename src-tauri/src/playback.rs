@@ -0,0 +1,466 @@
+//! Variable-speed audio playback for reviewing transcripts.
+//!
+//! Speed changes use WSOLA time-stretching: overlapping windows of the
+//! source are re-spaced and each one is nudged to the offset that best
+//! continues the previous window, so speech keeps its pitch at 0.5x–3x.
+//! While playing, the position and the segment under it are emitted so the
+//! transcript view can highlight along.
+
+use std::f32::consts::PI;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rodio::{OutputStream, Sink, Source};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::trim::{self, AudioTrim};
+use crate::{audio, clips, db, parts};
+
+pub const POSITION_EVENT: &str = "playback://position";
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 3.0;
+
+/// Analysis window, in seconds; long enough to hold a couple of pitch periods.
+const WINDOW_SECS: f32 = 0.03;
+/// How far a window may shift to line up with the previous one.
+const SEEK_SECS: f32 = 0.01;
+const POSITION_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentSpan {
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackPosition {
+    /// Seconds into the source audio, independent of speed.
+    pub position: f64,
+    pub duration: f64,
+    pub speed: f32,
+    pub playing: bool,
+    /// Index of the segment being heard, if any.
+    pub segment_index: Option<usize>,
+}
+
+/// State shared between the controls and the audio callback.
+struct Cursor {
+    /// Source position in samples.
+    position: f64,
+    speed: f32,
+    /// Set on seek so the stretcher does not blend across the jump.
+    reset: bool,
+}
+
+/// A mono source that plays `samples` at `cursor.speed` without changing pitch.
+struct Stretcher {
+    samples: Arc<Vec<f32>>,
+    rate: u32,
+    cursor: Arc<Mutex<Cursor>>,
+    window: Vec<f32>,
+    seek: usize,
+    /// Second half of the last windowed frame, waiting to be overlapped.
+    tail: Vec<f32>,
+    /// Where the last frame actually started in the source.
+    previous: usize,
+    block: Vec<f32>,
+    emitted: usize,
+}
+
+impl Stretcher {
+    fn new(samples: Arc<Vec<f32>>, rate: u32, cursor: Arc<Mutex<Cursor>>) -> Self {
+        let len = ((WINDOW_SECS * rate as f32) as usize).max(64) & !1;
+        let window = (0..len)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / len as f32).cos())
+            .collect();
+        Self {
+            samples,
+            rate,
+            cursor,
+            window,
+            seek: (SEEK_SECS * rate as f32) as usize,
+            tail: vec![0.0; len / 2],
+            previous: 0,
+            block: Vec::new(),
+            emitted: 0,
+        }
+    }
+
+    fn hop(&self) -> usize {
+        self.window.len() / 2
+    }
+
+    /// Source offset near `target` whose start best matches the natural
+    /// continuation of the previous frame.
+    fn best_start(&self, target: usize) -> usize {
+        let hop = self.hop();
+        let natural = self.previous + hop;
+        let samples = &self.samples;
+        if natural + hop > samples.len() {
+            return target;
+        }
+        let reference = &samples[natural..natural + hop];
+
+        let low = target.saturating_sub(self.seek);
+        let high = (target + self.seek).min(samples.len().saturating_sub(hop));
+        (low..=high)
+            .max_by(|&a, &b| {
+                let score = |start: usize| -> f32 {
+                    reference
+                        .iter()
+                        .zip(&samples[start..start + hop])
+                        .map(|(x, y)| x * y)
+                        .sum()
+                };
+                score(a).total_cmp(&score(b))
+            })
+            .unwrap_or(target)
+    }
+
+    /// Produce the next `hop` output samples, or `None` at the end of the source.
+    fn next_block(&mut self) -> Option<Vec<f32>> {
+        let hop = self.hop();
+        let (target, speed) = {
+            let mut cursor = self.cursor.lock().unwrap();
+            if cursor.reset {
+                cursor.reset = false;
+                self.tail.iter_mut().for_each(|s| *s = 0.0);
+                self.previous = (cursor.position as usize).saturating_sub(hop);
+            }
+            let target = cursor.position as usize;
+            cursor.position += hop as f64 * cursor.speed as f64;
+            (target, cursor.speed)
+        };
+        if target >= self.samples.len() {
+            return None;
+        }
+
+        // At normal speed the source is passed through untouched.
+        let start = if speed == 1.0 {
+            target
+        } else {
+            self.best_start(target)
+        };
+        self.previous = start;
+
+        let frame: Vec<f32> = self
+            .window
+            .iter()
+            .enumerate()
+            .map(|(i, w)| self.samples.get(start + i).copied().unwrap_or(0.0) * w)
+            .collect();
+        let block = self
+            .tail
+            .iter()
+            .zip(&frame[..hop])
+            .map(|(a, b)| a + b)
+            .collect();
+        self.tail.copy_from_slice(&frame[hop..]);
+        Some(block)
+    }
+}
+
+impl Iterator for Stretcher {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.emitted == self.block.len() {
+            self.block = self.next_block()?;
+            self.emitted = 0;
+        }
+        self.emitted += 1;
+        Some(self.block[self.emitted - 1])
+    }
+}
+
+impl Source for Stretcher {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// What to play: a file as it is, or a transcription's audio already
+/// decoded to 16 kHz, for split and merged transcriptions.
+enum Audio {
+    File(PathBuf),
+    Samples(Vec<f32>),
+}
+
+enum Control {
+    Load {
+        audio: Audio,
+        segments: Vec<SegmentSpan>,
+        trim: AudioTrim,
+        reply: mpsc::Sender<Result<f64>>,
+    },
+    Play,
+    Pause,
+    Seek(f64),
+    SetSpeed(f32),
+    Stop,
+}
+
+pub struct Player(Mutex<mpsc::Sender<Control>>);
+
+struct Loaded {
+    sink: Sink,
+    cursor: Arc<Mutex<Cursor>>,
+    rate: u32,
+    /// Trimmed-off audio before this is never played.
+    start: f64,
+    duration: f64,
+    segments: Vec<SegmentSpan>,
+}
+
+impl Loaded {
+    fn position(&self) -> PlaybackPosition {
+        let cursor = self.cursor.lock().unwrap();
+        let position = (cursor.position / self.rate as f64).min(self.duration);
+        PlaybackPosition {
+            position,
+            duration: self.duration,
+            speed: cursor.speed,
+            playing: !self.sink.is_paused() && position < self.duration,
+            segment_index: self
+                .segments
+                .iter()
+                .position(|segment| position >= segment.start && position < segment.end),
+        }
+    }
+}
+
+fn device_error(err: impl std::fmt::Display) -> Error {
+    Error::AudioDevice(err.to_string())
+}
+
+fn load(
+    output: &mut Option<(OutputStream, rodio::OutputStreamHandle)>,
+    source: Audio,
+    segments: Vec<SegmentSpan>,
+    trim: AudioTrim,
+    speed: f32,
+) -> Result<Loaded> {
+    let (mut samples, rate) = match source {
+        Audio::File(path) => audio::load_mono(&path)?,
+        Audio::Samples(samples) => (samples, audio::WHISPER_SAMPLE_RATE),
+    };
+    let kept = trim.sample_range(samples.len(), rate);
+    samples.truncate(kept.end);
+    if output.is_none() {
+        *output = Some(OutputStream::try_default().map_err(device_error)?);
+    }
+    let sink = Sink::try_new(&output.as_ref().unwrap().1).map_err(device_error)?;
+    sink.pause();
+
+    let duration = samples.len() as f64 / rate as f64;
+    let cursor = Arc::new(Mutex::new(Cursor {
+        position: kept.start as f64,
+        speed,
+        reset: true,
+    }));
+    sink.append(Stretcher::new(Arc::new(samples), rate, cursor.clone()));
+
+    Ok(Loaded {
+        sink,
+        cursor,
+        rate,
+        start: kept.start as f64 / rate as f64,
+        duration,
+        segments,
+    })
+}
+
+fn run(app: AppHandle, control: mpsc::Receiver<Control>) {
+    // rodio's output stream is not `Send`, so it lives on this thread.
+    let mut output = None;
+    let mut loaded: Option<Loaded> = None;
+    let mut speed = 1.0;
+
+    loop {
+        match control.recv_timeout(POSITION_INTERVAL) {
+            Ok(Control::Load {
+                audio,
+                segments,
+                trim,
+                reply,
+            }) => {
+                loaded = None;
+                match load(&mut output, audio, segments, trim, speed) {
+                    Ok(l) => {
+                        let _ = reply.send(Ok(l.duration));
+                        loaded = Some(l);
+                    }
+                    Err(err) => {
+                        let _ = reply.send(Err(err));
+                    }
+                }
+            }
+            Ok(Control::Play) => {
+                if let Some(l) = &loaded {
+                    l.sink.play();
+                }
+            }
+            Ok(Control::Pause) => {
+                if let Some(l) = &loaded {
+                    l.sink.pause();
+                }
+            }
+            Ok(Control::Seek(secs)) => {
+                if let Some(l) = &loaded {
+                    let mut cursor = l.cursor.lock().unwrap();
+                    cursor.position = secs.clamp(l.start, l.duration) * l.rate as f64;
+                    cursor.reset = true;
+                }
+            }
+            Ok(Control::SetSpeed(value)) => {
+                speed = value;
+                if let Some(l) = &loaded {
+                    l.cursor.lock().unwrap().speed = value;
+                }
+            }
+            Ok(Control::Stop) => loaded = None,
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        if let Some(l) = &loaded {
+            if !l.sink.is_paused() {
+                let _ = app.emit_all(POSITION_EVENT, l.position());
+            }
+        }
+    }
+}
+
+/// Start the playback thread.
+pub fn init(app: &AppHandle) {
+    let (sender, receiver) = mpsc::channel();
+    let handle = app.clone();
+    thread::spawn(move || run(handle, receiver));
+    app.manage(Player(Mutex::new(sender)));
+}
+
+impl Player {
+    fn send(&self, control: Control) -> Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .send(control)
+            .map_err(|_| Error::AudioDevice("playback thread exited".into()))
+    }
+}
+
+/// Load an audio file, paused at the start. Returns its duration in seconds.
+///
+/// `segments` are the transcript's time ranges, used for highlight events.
+/// With a `transcription_id`, its trim points apply: playback starts at the
+/// trim start and the returned duration ends at the trim end. A split or
+/// merged transcription plays the audio it covers rather than `path`.
+#[tauri::command]
+pub async fn load_playback(
+    app: AppHandle,
+    player: State<'_, Player>,
+    path: PathBuf,
+    segments: Option<Vec<SegmentSpan>>,
+    transcription_id: Option<String>,
+) -> Result<f64> {
+    let (audio, trim) = tauri::async_runtime::spawn_blocking(move || -> Result<_> {
+        let Some(id) = transcription_id else {
+            return Ok((Audio::File(path), AudioTrim::default()));
+        };
+        let conn = db::connect(&app)?;
+        let audio = if parts::stored(&conn, &id)?.is_empty() {
+            Audio::File(path)
+        } else {
+            Audio::Samples(clips::source_pcm(&conn, &id)?)
+        };
+        Ok((audio, trim::for_transcription(&conn, &id)?))
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))??;
+    let (reply, result) = mpsc::channel();
+    player.send(Control::Load {
+        audio,
+        segments: segments.unwrap_or_default(),
+        trim,
+        reply,
+    })?;
+    tauri::async_runtime::spawn_blocking(move || result.recv())
+        .await
+        .map_err(|e| Error::Transcription(e.to_string()))?
+        .map_err(|_| Error::AudioDevice("playback thread exited".into()))?
+}
+
+#[tauri::command]
+pub fn resume_playback(player: State<'_, Player>) -> Result<()> {
+    player.send(Control::Play)
+}
+
+#[tauri::command]
+pub fn pause_playback(player: State<'_, Player>) -> Result<()> {
+    player.send(Control::Pause)
+}
+
+#[tauri::command]
+pub fn seek_playback(player: State<'_, Player>, position: f64) -> Result<()> {
+    player.send(Control::Seek(position))
+}
+
+/// Change speed without changing pitch; applies to the current and later files.
+#[tauri::command]
+pub fn set_playback_speed(player: State<'_, Player>, speed: f32) -> Result<()> {
+    if !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        return Err(Error::InvalidInput(format!(
+            "speed must be between {}x and {}x",
+            MIN_SPEED, MAX_SPEED
+        )));
+    }
+    player.send(Control::SetSpeed(speed))
+}
+
+#[tauri::command]
+pub fn stop_playback(player: State<'_, Player>) -> Result<()> {
+    player.send(Control::Stop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stretched_len(speed: f32) -> usize {
+        let rate = 16_000;
+        let tone: Vec<f32> = (0..rate)
+            .map(|i| (i as f32 * 440.0 * 2.0 * PI / rate as f32).sin())
+            .collect();
+        let cursor = Arc::new(Mutex::new(Cursor {
+            position: 0.0,
+            speed,
+            reset: true,
+        }));
+        Stretcher::new(Arc::new(tone), rate, cursor).count()
+    }
+
+    #[test]
+    fn output_length_scales_with_speed() {
+        let normal = stretched_len(1.0) as f32;
+        assert!((stretched_len(2.0) as f32 / normal - 0.5).abs() < 0.02);
+        assert!((stretched_len(0.5) as f32 / normal - 2.0).abs() < 0.02);
+    }
+}
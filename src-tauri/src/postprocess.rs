@@ -0,0 +1,93 @@
+//! Rules applied to decoded segments before a transcription is returned.
+//!
+//! The hallucination filter runs first, so dropped segments are never
+//! rewritten, then the optional text rules in the `post_processing`
+//! preference. Exports can apply the text rules again with their own
+//! settings, so a stored transcript can stay verbatim while a profile
+//! renders it for reading.
+
+use std::ops::Range;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{Error, Result};
+use crate::hallucination::{self, HallucinationReport};
+use crate::numbers;
+use crate::whisper::{self, Segment};
+
+pub const RULES_PREFERENCE: &str = "post_processing";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PostProcessing {
+    /// Write spoken numbers, amounts and dates as the language does.
+    pub normalize_numbers: bool,
+}
+
+pub fn rules(conn: &Connection) -> Result<PostProcessing> {
+    Ok(db::get_preference(conn, RULES_PREFERENCE)?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+/// `language`, or for `auto` or no language the one `text` reads as.
+pub fn text_language(language: Option<&str>, text: &str) -> String {
+    match language.filter(|language| !language.is_empty() && *language != "auto") {
+        Some(language) => language.to_string(),
+        None => numbers::guess_language(text).unwrap_or("auto").to_string(),
+    }
+}
+
+/// The text rules of `rules` applied to one piece of text.
+pub fn apply_text(text: &str, language: &str, rules: &PostProcessing) -> String {
+    if rules.normalize_numbers {
+        numbers::normalize(text, &text_language(Some(language), text))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Run the pipeline over freshly decoded segments. `language` is the
+/// decoding language, if one was set; without one, or with `auto`, text
+/// rules use the language the whole transcript reads as.
+pub fn apply(
+    conn: &Connection,
+    segments: Vec<Segment>,
+    speech: Option<&[Range<usize>]>,
+    language: Option<&str>,
+) -> Result<(Vec<Segment>, HallucinationReport)> {
+    let mode = hallucination::filter_mode(conn)?;
+    let (mut segments, report) = hallucination::apply(segments, speech, mode);
+    let rules = rules(conn)?;
+    if rules != PostProcessing::default() {
+        let language = text_language(language, &whisper::join_text(&segments));
+        for segment in &mut segments {
+            segment.text = apply_text(&segment.text, &language, &rules);
+        }
+    }
+    Ok((segments, report))
+}
+
+#[tauri::command]
+pub fn get_post_processing(app: AppHandle) -> Result<PostProcessing> {
+    rules(&db::connect(&app)?)
+}
+
+#[tauri::command]
+pub fn set_post_processing(app: AppHandle, rules: PostProcessing) -> Result<()> {
+    db::set_preference(
+        &db::connect(&app)?,
+        RULES_PREFERENCE,
+        &serde_json::to_string(&rules).map_err(|err| Error::InvalidInput(err.to_string()))?,
+    )
+}
+
+/// Apply `rules` to text for an export, e.g. a profile that normalizes
+/// numbers for a transcript stored verbatim.
+#[tauri::command]
+pub fn post_process_text(text: String, language: String, rules: PostProcessing) -> String {
+    apply_text(&text, &language, &rules)
+}
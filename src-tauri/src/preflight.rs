@@ -0,0 +1,198 @@
+//! Pre-flight checks run before downloads, recordings and transcriptions.
+//!
+//! Problems are reported as structured warnings so the UI can explain them up
+//! front instead of a job failing halfway through.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::{models, recording};
+
+/// Keep this much space free on top of what a job is expected to write.
+const SAFETY_MARGIN_BYTES: u64 = 200 * 1024 * 1024;
+/// 16-bit stereo PCM at 48 kHz, per track.
+const RECORDING_BYTES_PER_MINUTE: u64 = 48_000 * 2 * 2 * 60;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobSpec {
+    Download {
+        model: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Recording {
+        expected_minutes: Option<u64>,
+        tracks: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Transcription {
+        path: PathBuf,
+        model: Option<String>,
+        output_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The job cannot succeed until this is resolved.
+    Blocking,
+    /// The job can run but the user should know.
+    Advisory,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightWarning {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightReport {
+    /// True when there are no blocking warnings.
+    pub ok: bool,
+    pub warnings: Vec<PreflightWarning>,
+}
+
+impl PreflightReport {
+    fn new(warnings: Vec<PreflightWarning>) -> Self {
+        Self {
+            ok: warnings.iter().all(|w| w.severity != Severity::Blocking),
+            warnings,
+        }
+    }
+
+    /// Turn blocking warnings into an error for commands that start work.
+    pub fn into_result(self) -> Result<()> {
+        if self.ok {
+            return Ok(());
+        }
+        let messages: Vec<String> = self
+            .warnings
+            .into_iter()
+            .filter(|w| w.severity == Severity::Blocking)
+            .map(|w| w.message)
+            .collect();
+        Err(Error::PreflightFailed(messages.join("; ")))
+    }
+}
+
+fn warning(code: &'static str, severity: Severity, message: String) -> PreflightWarning {
+    PreflightWarning {
+        code,
+        severity,
+        message,
+    }
+}
+
+fn check_free_space(dir: &Path, needed: u64, warnings: &mut Vec<PreflightWarning>) {
+    let Ok(available) = fs2::available_space(dir) else {
+        return;
+    };
+    if available < needed + SAFETY_MARGIN_BYTES {
+        warnings.push(warning(
+            "LOW_DISK_SPACE",
+            Severity::Blocking,
+            format!(
+                "{} needs about {} MB but only {} MB is free",
+                dir.display(),
+                (needed + SAFETY_MARGIN_BYTES) / 1_048_576,
+                available / 1_048_576
+            ),
+        ));
+    }
+}
+
+fn check_writable(dir: &Path, warnings: &mut Vec<PreflightWarning>) {
+    let probe = dir.join(".transcriber-write-test");
+    let writable = fs::create_dir_all(dir).is_ok() && fs::write(&probe, b"").is_ok();
+    let _ = fs::remove_file(&probe);
+    if !writable {
+        warnings.push(warning(
+            "OUTPUT_NOT_WRITABLE",
+            Severity::Blocking,
+            format!("{} is not writable", dir.display()),
+        ));
+    }
+}
+
+fn check_model(app: &AppHandle, model: &str, warnings: &mut Vec<PreflightWarning>) {
+    if models::model_path(app, model).is_err() {
+        warnings.push(warning(
+            "MODEL_MISSING",
+            Severity::Blocking,
+            format!("model '{}' has not been downloaded", model),
+        ));
+    }
+}
+
+pub fn check(app: &AppHandle, spec: &JobSpec) -> Result<PreflightReport> {
+    let mut warnings = Vec::new();
+
+    match spec {
+        JobSpec::Download { model } => {
+            let dir = models::models_dir(app)?;
+            check_writable(&dir, &mut warnings);
+            match models::expected_size(model) {
+                Some(size) => check_free_space(&dir, size, &mut warnings),
+                None => warnings.push(warning(
+                    "UNKNOWN_MODEL_SIZE",
+                    Severity::Advisory,
+                    format!("download size of '{}' is unknown", model),
+                )),
+            }
+            if models::model_path(app, model).is_ok() {
+                warnings.push(warning(
+                    "MODEL_PRESENT",
+                    Severity::Advisory,
+                    format!("model '{}' is already downloaded", model),
+                ));
+            }
+        }
+        JobSpec::Recording {
+            expected_minutes,
+            tracks,
+        } => {
+            let dir = recording::recordings_dir(app)?;
+            check_writable(&dir, &mut warnings);
+            let minutes = expected_minutes.unwrap_or(60);
+            let needed = minutes * tracks.unwrap_or(2) * RECORDING_BYTES_PER_MINUTE;
+            check_free_space(&dir, needed, &mut warnings);
+        }
+        JobSpec::Transcription {
+            path,
+            model,
+            output_dir,
+        } => {
+            if !path.is_file() {
+                warnings.push(warning(
+                    "INPUT_MISSING",
+                    Severity::Blocking,
+                    format!("{} does not exist", path.display()),
+                ));
+            }
+            let model = model
+                .as_deref()
+                .unwrap_or(crate::transcription::DEFAULT_MODEL);
+            check_model(app, model, &mut warnings);
+            if let Some(dir) = output_dir {
+                check_writable(dir, &mut warnings);
+                check_free_space(dir, 0, &mut warnings);
+            }
+        }
+    }
+
+    Ok(PreflightReport::new(warnings))
+}
+
+#[tauri::command]
+pub fn preflight_check(app: AppHandle, job_spec: JobSpec) -> Result<PreflightReport> {
+    check(&app, &job_spec)
+}
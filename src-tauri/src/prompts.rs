@@ -0,0 +1,196 @@
+//! Library of summarization prompts.
+//!
+//! Prompts are templates with `{{variable}}` placeholders, filled in when a
+//! summary is generated. Built-in prompts cannot be edited or deleted, but
+//! can be cloned into an editable copy.
+
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{Error, Result};
+
+pub const DEFAULT_PROMPT: &str = "executive_summary";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Prompt {
+    pub id: String,
+    pub name: String,
+    pub template: String,
+    pub builtin: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptInput {
+    pub name: String,
+    pub template: String,
+}
+
+/// Replace `{{name}}` placeholders with their values. Unknown placeholders
+/// are left in place so a typo is visible in the output.
+pub fn render(template: &str, variables: &HashMap<&str, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find("{{") {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        match after.find("}}") {
+            Some(close) => {
+                let name = after[..close].trim();
+                match variables.get(name) {
+                    Some(value) => out.push_str(value),
+                    None => out.push_str(&rest[open..open + 2 + close + 2]),
+                }
+                rest = &after[close + 2..];
+            }
+            None => {
+                out.push_str(&rest[open..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+const COLUMNS: &str = "id, name, template, builtin";
+
+fn from_row(row: &Row) -> rusqlite::Result<Prompt> {
+    Ok(Prompt {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        template: row.get(2)?,
+        builtin: row.get(3)?,
+    })
+}
+
+pub fn find(conn: &Connection, id: &str) -> Result<Prompt> {
+    conn.query_row(
+        &format!("SELECT {} FROM prompts WHERE id = ?1", COLUMNS),
+        [id],
+        from_row,
+    )
+    .optional()?
+    .ok_or_else(|| Error::NotFound(format!("prompt '{}'", id)))
+}
+
+fn validate(input: &PromptInput) -> Result<(String, String)> {
+    let name = input.name.trim().to_string();
+    let template = input.template.trim().to_string();
+    if name.is_empty() || template.is_empty() {
+        return Err(Error::InvalidInput(
+            "prompt needs a name and a template".into(),
+        ));
+    }
+    Ok((name, template))
+}
+
+fn duplicate_name(name: &str) -> impl FnOnce(rusqlite::Error) -> Error + '_ {
+    move |err| match err {
+        rusqlite::Error::SqliteFailure(e, _)
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Error::InvalidInput(format!("a prompt named '{}' already exists", name))
+        }
+        other => other.into(),
+    }
+}
+
+#[tauri::command]
+pub fn list_prompts(app: AppHandle) -> Result<Vec<Prompt>> {
+    let conn = db::connect(&app)?;
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM prompts ORDER BY builtin DESC, name COLLATE NOCASE",
+        COLUMNS
+    ))?;
+    let prompts = statement
+        .query_map([], from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(prompts)
+}
+
+/// Create a prompt, or update the custom prompt with `id`.
+#[tauri::command]
+pub fn save_prompt(app: AppHandle, id: Option<String>, prompt: PromptInput) -> Result<Prompt> {
+    let (name, template) = validate(&prompt)?;
+    let conn = db::connect(&app)?;
+    if let Some(existing) = id.as_deref() {
+        if find(&conn, existing)?.builtin {
+            return Err(Error::InvalidInput(
+                "built-in prompts cannot be edited; clone it first".into(),
+            ));
+        }
+    }
+    let id = id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    conn.execute(
+        "INSERT INTO prompts (id, name, template) VALUES (?1, ?2, ?3)
+         ON CONFLICT(id) DO UPDATE SET
+             name = excluded.name,
+             template = excluded.template,
+             updated_at = CURRENT_TIMESTAMP",
+        params![id, name, template],
+    )
+    .map_err(duplicate_name(&name))?;
+    find(&conn, &id)
+}
+
+/// Copy a prompt, built-in or custom, into a new editable prompt.
+#[tauri::command]
+pub fn clone_prompt(app: AppHandle, id: String, name: Option<String>) -> Result<Prompt> {
+    let conn = db::connect(&app)?;
+    let source = find(&conn, &id)?;
+    let name = name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("{} (copy)", source.name));
+    let new_id = uuid::Uuid::new_v4().to_string();
+
+    conn.execute(
+        "INSERT INTO prompts (id, name, template) VALUES (?1, ?2, ?3)",
+        params![new_id, name, source.template],
+    )
+    .map_err(duplicate_name(&name))?;
+    find(&conn, &new_id)
+}
+
+#[tauri::command]
+pub fn delete_prompt(app: AppHandle, id: String) -> Result<()> {
+    let conn = db::connect(&app)?;
+    if find(&conn, &id)?.builtin {
+        return Err(Error::InvalidInput(
+            "built-in prompts cannot be deleted".into(),
+        ));
+    }
+    conn.execute("DELETE FROM prompts WHERE id = ?1", [&id])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_known_variables_and_keeps_unknown_ones() {
+        let variables = HashMap::from([
+            ("language", "Dutch".to_string()),
+            ("meeting_type", "standup".to_string()),
+        ]);
+        assert_eq!(
+            render(
+                "Summarize this {{ meeting_type }} in {{language}} for {{audience}}.",
+                &variables
+            ),
+            "Summarize this standup in Dutch for {{audience}}."
+        );
+        assert_eq!(
+            render("Unclosed {{language", &variables),
+            "Unclosed {{language"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Practice mode: compare a spoken attempt against a reference segment.
+//!
+//! The attempt is transcribed in the reference's language without the
+//! reference as a prompt, since priming the decoder with the expected text
+//! would make it hear what it was told to. The two texts are aligned word
+//! by word and each reference word is scored by how closely the heard word
+//! is spelled, a rough but useful stand-in for how closely it was said.
+
+use std::path::PathBuf;
+
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{Error, Result};
+use crate::evaluation::{self, AlignedWord, AlignmentOp};
+use crate::transcription;
+use crate::whisper::DecodeOptions;
+
+/// Heard words at least this similar to the reference count as close.
+const CLOSE_SIMILARITY: f64 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WordVerdict {
+    Correct,
+    Close,
+    Wrong,
+    Missed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordScore {
+    pub word: String,
+    pub heard: Option<String>,
+    /// 0 to 1.
+    pub score: f64,
+    pub verdict: WordVerdict,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PronunciationReport {
+    pub reference: String,
+    pub heard: String,
+    /// Mean of the word scores.
+    pub score: f64,
+    pub words: Vec<WordScore>,
+    /// Words said that are not in the reference.
+    pub extra: Vec<String>,
+}
+
+/// Spelling similarity from 0 (nothing in common) to 1 (identical).
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - evaluation::edit_distance(&a, &b) as f64 / longest as f64
+}
+
+/// Word alignment where substituting a similarly spelled word is cheap.
+/// Plain edit distance treats every mismatch alike and can pair a
+/// mispronounced word with its neighbour; here a substitution of
+/// unrelated words costs as much as a deletion and an insertion.
+fn align(reference: &[String], heard: &[String]) -> Vec<AlignedWord> {
+    let (n, m) = (reference.len(), heard.len());
+    let substitution = |i: usize, j: usize| 2.0 * (1.0 - similarity(&reference[i], &heard[j]));
+    let mut cost = vec![vec![0.0f64; m + 1]; n + 1];
+    for i in 0..=n {
+        for j in 0..=m {
+            cost[i][j] = match (i, j) {
+                (0, _) => j as f64,
+                (_, 0) => i as f64,
+                _ => (cost[i - 1][j - 1] + substitution(i - 1, j - 1))
+                    .min(cost[i - 1][j] + 1.0)
+                    .min(cost[i][j - 1] + 1.0),
+            };
+        }
+    }
+
+    let step = |op, i: Option<usize>, j: Option<usize>| AlignedWord {
+        op,
+        reference: i.map(|i| reference[i].clone()),
+        hypothesis: j.map(|j| heard[j].clone()),
+    };
+    let mut steps = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 {
+            let cost_here = substitution(i - 1, j - 1);
+            if (cost[i][j] - (cost[i - 1][j - 1] + cost_here)).abs() < 1e-9 {
+                let op = if reference[i - 1] == heard[j - 1] {
+                    AlignmentOp::Equal
+                } else {
+                    AlignmentOp::Substitute
+                };
+                steps.push(step(op, Some(i - 1), Some(j - 1)));
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+        if i > 0 && (cost[i][j] - (cost[i - 1][j] + 1.0)).abs() < 1e-9 {
+            steps.push(step(AlignmentOp::Delete, Some(i - 1), None));
+            i -= 1;
+        } else {
+            steps.push(step(AlignmentOp::Insert, None, Some(j - 1)));
+            j -= 1;
+        }
+    }
+    steps.reverse();
+    steps
+}
+
+fn score_alignment(alignment: &[AlignedWord]) -> (Vec<WordScore>, Vec<String>) {
+    let mut words = Vec::new();
+    let mut extra = Vec::new();
+    for step in alignment {
+        match (step.op, &step.reference, &step.hypothesis) {
+            (AlignmentOp::Insert, _, Some(heard)) => extra.push(heard.clone()),
+            (AlignmentOp::Equal, Some(word), heard) => words.push(WordScore {
+                word: word.clone(),
+                heard: heard.clone(),
+                score: 1.0,
+                verdict: WordVerdict::Correct,
+            }),
+            (AlignmentOp::Substitute, Some(word), Some(heard)) => {
+                let score = similarity(word, heard);
+                words.push(WordScore {
+                    word: word.clone(),
+                    heard: Some(heard.clone()),
+                    score,
+                    verdict: if score >= CLOSE_SIMILARITY {
+                        WordVerdict::Close
+                    } else {
+                        WordVerdict::Wrong
+                    },
+                });
+            }
+            (_, Some(word), _) => words.push(WordScore {
+                word: word.clone(),
+                heard: None,
+                score: 0.0,
+                verdict: WordVerdict::Missed,
+            }),
+            _ => {}
+        }
+    }
+    (words, extra)
+}
+
+pub fn compare(reference: &str, heard: &str) -> PronunciationReport {
+    let alignment = align(
+        &evaluation::normalize_words(reference),
+        &evaluation::normalize_words(heard),
+    );
+    let (words, extra) = score_alignment(&alignment);
+    let score = if words.is_empty() {
+        0.0
+    } else {
+        words.iter().map(|w| w.score).sum::<f64>() / words.len() as f64
+    };
+    PronunciationReport {
+        reference: reference.trim().to_string(),
+        heard: heard.trim().to_string(),
+        score,
+        words,
+        extra,
+    }
+}
+
+/// Transcribe the recording at `recorded_path` and score it against the
+/// text of the reference segment.
+#[tauri::command]
+pub async fn compare_pronunciation(
+    app: AppHandle,
+    reference_segment_id: String,
+    recorded_path: PathBuf,
+) -> Result<PronunciationReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (reference, language): (String, String) = db::connect(&app)?
+            .query_row(
+                "SELECT s.text, t.language FROM segments s
+                 JOIN transcriptions t ON t.id = s.transcription_id
+                 WHERE s.id = ?1",
+                [&reference_segment_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("segment {}", reference_segment_id)))?;
+
+        let options = DecodeOptions {
+            language: Some(language).filter(|language| language != "auto"),
+            ..Default::default()
+        };
+        let attempt = transcription::transcribe_path(&app, &recorded_path, None, &options, false)?;
+        Ok(compare(&reference, &attempt.text))
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_each_reference_word() {
+        let report = compare("Ik heb geen tijd.", "ik hep tijd vandaag");
+        let verdicts: Vec<WordVerdict> = report.words.iter().map(|w| w.verdict).collect();
+        assert_eq!(
+            verdicts,
+            [
+                WordVerdict::Correct,
+                WordVerdict::Close,
+                WordVerdict::Missed,
+                WordVerdict::Correct
+            ]
+        );
+        assert_eq!(report.extra, ["vandaag"]);
+        assert!((report.score - (1.0 + 2.0 / 3.0 + 0.0 + 1.0) / 4.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,334 @@
+//! On-device quantization of whisper ggml models.
+//!
+//! A port of whisper.cpp's `quantize` example: the header, mel filters and
+//! vocabulary are copied as-is, and every 2D weight tensor is rewritten in a
+//! block-quantized format. whisper.cpp loads the result like any other model,
+//! which lets low-memory machines run larger models.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use half::f16;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::models::{self, ModelInfo};
+
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+const GGML_QNT_VERSION: i32 = 2;
+const GGML_QNT_VERSION_FACTOR: i32 = 1000;
+
+const GGML_TYPE_F32: i32 = 0;
+const GGML_TYPE_F16: i32 = 1;
+const GGML_TYPE_Q5_0: i32 = 6;
+const GGML_TYPE_Q8_0: i32 = 8;
+
+const QK: usize = 32;
+
+/// Tensors whisper.cpp keeps in full precision.
+const SKIPPED_TENSORS: &[&str] = &[
+    "encoder.conv1.bias",
+    "encoder.conv2.bias",
+    "encoder.positional_embedding",
+    "decoder.positional_embedding",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantizationLevel {
+    Q5_0,
+    Q8_0,
+}
+
+impl QuantizationLevel {
+    pub fn suffix(self) -> &'static str {
+        match self {
+            QuantizationLevel::Q5_0 => "q5_0",
+            QuantizationLevel::Q8_0 => "q8_0",
+        }
+    }
+
+    fn tensor_type(self) -> i32 {
+        match self {
+            QuantizationLevel::Q5_0 => GGML_TYPE_Q5_0,
+            QuantizationLevel::Q8_0 => GGML_TYPE_Q8_0,
+        }
+    }
+
+    /// `ggml_ftype` recorded in the model header.
+    fn file_type(self) -> i32 {
+        match self {
+            QuantizationLevel::Q5_0 => 8,
+            QuantizationLevel::Q8_0 => 7,
+        }
+    }
+}
+
+fn read_i32(reader: &mut impl Read) -> io::Result<i32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn copy_bytes(reader: &mut impl Read, writer: &mut impl Write, len: usize) -> io::Result<()> {
+    let copied = io::copy(&mut reader.take(len as u64), writer)?;
+    if copied as usize != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn format_error(message: &str) -> Error {
+    Error::InvalidInput(format!("not a whisper ggml model: {}", message))
+}
+
+/// Bytes taken by `counts` multiplied together, of `element_size` bytes
+/// each. A corrupt header fails here, on a negative count or a total the
+/// file of `file_len` bytes cannot hold, before anything is allocated.
+fn checked_len(counts: &[i32], element_size: u64, file_len: u64, what: &str) -> Result<usize> {
+    let bad = || format_error(&format!("bad {} size", what));
+    let mut len = element_size;
+    for &count in counts {
+        let count = u64::try_from(count).map_err(|_| bad())?;
+        len = len.checked_mul(count).ok_or_else(bad)?;
+    }
+    if len > file_len {
+        return Err(bad());
+    }
+    Ok(len as usize)
+}
+
+fn quantize_q8_0(values: &[f32], out: &mut Vec<u8>) {
+    for block in values.chunks(QK) {
+        let amax = block.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+        let d = amax / 127.0;
+        let id = if d != 0.0 { 1.0 / d } else { 0.0 };
+
+        out.extend_from_slice(&f16::from_f32(d).to_le_bytes());
+        out.extend(block.iter().map(|v| (v * id).round() as i8 as u8));
+    }
+}
+
+fn quantize_q5_0(values: &[f32], out: &mut Vec<u8>) {
+    for block in values.chunks(QK) {
+        // The value with the largest magnitude, keeping its sign.
+        let max = block
+            .iter()
+            .copied()
+            .fold(0.0f32, |max, v| if v.abs() > max.abs() { v } else { max });
+        let d = max / -16.0;
+        let id = if d != 0.0 { 1.0 / d } else { 0.0 };
+
+        let mut qh = 0u32;
+        let mut qs = [0u8; QK / 2];
+        for (j, q) in qs.iter_mut().enumerate() {
+            let x0 = ((block[j] * id + 16.5) as i8).min(31) as u8;
+            let x1 = ((block[j + QK / 2] * id + 16.5) as i8).min(31) as u8;
+
+            *q = (x0 & 0x0f) | ((x1 & 0x0f) << 4);
+            qh |= (((x0 & 0x10) >> 4) as u32) << j;
+            qh |= (((x1 & 0x10) >> 4) as u32) << (j + QK / 2);
+        }
+
+        out.extend_from_slice(&f16::from_f32(d).to_le_bytes());
+        out.extend_from_slice(&qh.to_le_bytes());
+        out.extend_from_slice(&qs);
+    }
+}
+
+/// Rewrite `input` as a quantized model at `output`.
+pub fn quantize_file(input: &Path, output: &Path, level: QuantizationLevel) -> Result<()> {
+    let file_len = fs::metadata(input)?.len();
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if u32::from_le_bytes(magic) != GGML_MAGIC {
+        return Err(format_error("bad magic"));
+    }
+    writer.write_all(&magic)?;
+
+    // n_vocab, n_audio_ctx, n_audio_state, n_audio_head, n_audio_layer,
+    // n_text_ctx, n_text_state, n_text_head, n_text_layer, n_mels, ftype
+    let mut hparams = [0i32; 11];
+    for value in hparams.iter_mut() {
+        *value = read_i32(&mut reader)?;
+    }
+    if hparams[10] % GGML_QNT_VERSION_FACTOR > 1 {
+        return Err(Error::InvalidInput("model is already quantized".into()));
+    }
+    hparams[10] = level.file_type() + GGML_QNT_VERSION * GGML_QNT_VERSION_FACTOR;
+    for value in hparams {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    let n_mel = read_i32(&mut reader)?;
+    let n_fft = read_i32(&mut reader)?;
+    writer.write_all(&n_mel.to_le_bytes())?;
+    writer.write_all(&n_fft.to_le_bytes())?;
+    let filters = checked_len(&[n_mel, n_fft], 4, file_len, "mel filter")?;
+    copy_bytes(&mut reader, &mut writer, filters)?;
+
+    let n_vocab = read_i32(&mut reader)?;
+    checked_len(&[n_vocab], 1, file_len, "vocabulary")?;
+    writer.write_all(&n_vocab.to_le_bytes())?;
+    for _ in 0..n_vocab {
+        let len = read_i32(&mut reader)?;
+        writer.write_all(&len.to_le_bytes())?;
+        copy_bytes(
+            &mut reader,
+            &mut writer,
+            checked_len(&[len], 1, file_len, "token")?,
+        )?;
+    }
+
+    loop {
+        let n_dims = match read_i32(&mut reader) {
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        };
+        let name_len = read_i32(&mut reader)?;
+        let tensor_type = read_i32(&mut reader)?;
+        if !(1..=4).contains(&n_dims) {
+            return Err(format_error("bad tensor dimensions"));
+        }
+
+        let mut dims = vec![0i32; n_dims as usize];
+        for dim in dims.iter_mut() {
+            *dim = read_i32(&mut reader)?;
+        }
+        let mut name_bytes = vec![0u8; checked_len(&[name_len], 1, file_len, "tensor name")?];
+        reader.read_exact(&mut name_bytes)?;
+        let name = String::from_utf8_lossy(&name_bytes);
+
+        let element_size = match tensor_type {
+            GGML_TYPE_F32 => 4,
+            GGML_TYPE_F16 => 2,
+            _ => return Err(Error::InvalidInput("model is already quantized".into())),
+        };
+        let mut data = vec![0u8; checked_len(&dims, element_size, file_len, "tensor")?];
+        reader.read_exact(&mut data)?;
+
+        let quantize =
+            n_dims == 2 && dims[0] as usize % QK == 0 && !SKIPPED_TENSORS.contains(&&*name);
+        let out_type = if quantize {
+            level.tensor_type()
+        } else {
+            tensor_type
+        };
+
+        writer.write_all(&n_dims.to_le_bytes())?;
+        writer.write_all(&name_len.to_le_bytes())?;
+        writer.write_all(&out_type.to_le_bytes())?;
+        for dim in &dims {
+            writer.write_all(&dim.to_le_bytes())?;
+        }
+        writer.write_all(&name_bytes)?;
+
+        if !quantize {
+            writer.write_all(&data)?;
+            continue;
+        }
+
+        let values: Vec<f32> = if tensor_type == GGML_TYPE_F16 {
+            data.chunks_exact(2)
+                .map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect()
+        } else {
+            data.chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect()
+        };
+
+        let mut quantized = Vec::with_capacity(values.len());
+        match level {
+            QuantizationLevel::Q5_0 => quantize_q5_0(&values, &mut quantized),
+            QuantizationLevel::Q8_0 => quantize_q8_0(&values, &mut quantized),
+        }
+        writer.write_all(&quantized)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Convert a downloaded full-precision model into a quantized variant.
+///
+/// The result is stored next to the source as `ggml-<model>-<level>.bin`
+/// and shows up in the model list under that name.
+#[tauri::command]
+pub async fn quantize_model(
+    app: AppHandle,
+    model: String,
+    level: QuantizationLevel,
+) -> Result<ModelInfo> {
+    let input = models::model_path(&app, &model)?;
+    let name = format!("{}-{}", model, level.suffix());
+    let output = models::models_dir(&app)?.join(models::model_file_name(&name));
+    let partial = output.with_extension("bin.partial");
+
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(err) = quantize_file(&input, &partial, level) {
+            let _ = fs::remove_file(&partial);
+            return Err(err);
+        }
+        fs::rename(&partial, &output)?;
+
+        Ok(ModelInfo {
+            size_bytes: fs::metadata(&output)?.len(),
+            quantization: Some(level.suffix().to_string()),
+            name,
+            path: output,
+        })
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn q8_0_block_scales_to_full_range() {
+        let mut out = Vec::new();
+        quantize_q8_0(&[1.0; QK], &mut out);
+
+        assert_eq!(out.len(), 2 + QK);
+        assert_eq!(
+            f16::from_le_bytes([out[0], out[1]]).to_f32(),
+            f16::from_f32(1.0 / 127.0).to_f32()
+        );
+        assert!(out[2..].iter().all(|&q| q as i8 == 127));
+    }
+
+    #[test]
+    fn rejects_corrupt_header_sizes() {
+        let input =
+            std::env::temp_dir().join(format!("quantize-test-{}.bin", uuid::Uuid::new_v4()));
+        let output = input.with_extension("out");
+        for n_mel in [-1, 1 << 20] {
+            let mut header = GGML_MAGIC.to_le_bytes().to_vec();
+            for value in [0; 11].into_iter().chain([n_mel, 201]) {
+                header.extend_from_slice(&value.to_le_bytes());
+            }
+            fs::write(&input, &header).unwrap();
+
+            let err = quantize_file(&input, &output, QuantizationLevel::Q8_0).unwrap_err();
+            assert!(err.to_string().contains("bad mel filter size"), "{}", err);
+        }
+        fs::remove_file(&input).unwrap();
+        let _ = fs::remove_file(&output);
+    }
+
+    #[test]
+    fn q5_0_block_has_expected_layout() {
+        let mut out = Vec::new();
+        quantize_q5_0(&[0.5; QK], &mut out);
+        assert_eq!(out.len(), 2 + 4 + QK / 2);
+    }
+}
@@ -0,0 +1,301 @@
+//! Storage quotas per project, and where disk space goes.
+//!
+//! Audio files can be imported into a project. A project's quota caps the
+//! hours of audio and the gigabytes it holds, and an import that would go
+//! over either fails with `QUOTA_EXCEEDED`, whose `quota` field says which
+//! limit and by how much. Files whose container does not declare a length
+//! count only toward the size limit.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::{db, models};
+
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectQuota {
+    pub project: String,
+    pub max_hours: Option<f64>,
+    pub max_gb: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaResource {
+    Hours,
+    Bytes,
+}
+
+/// The limit an import would break, carried by [`Error::QuotaExceeded`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaViolation {
+    pub project: String,
+    pub resource: QuotaResource,
+    /// Already used by the project, in hours or bytes.
+    pub used: f64,
+    /// Added by the import.
+    pub requested: f64,
+    pub limit: f64,
+}
+
+impl QuotaViolation {
+    fn label(&self, amount: f64) -> String {
+        match self.resource {
+            QuotaResource::Hours => format!("{:.1} h", amount),
+            QuotaResource::Bytes => format!("{:.2} GB", amount / BYTES_PER_GB),
+        }
+    }
+
+    /// What the project would hold after the import.
+    pub fn total_label(&self) -> String {
+        self.label(self.used + self.requested)
+    }
+
+    pub fn limit_label(&self) -> String {
+        self.label(self.limit)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectUsage {
+    /// `None` for files imported outside any project.
+    pub project: Option<String>,
+    pub files: u64,
+    pub bytes: u64,
+    pub hours: f64,
+    pub quota: Option<ProjectQuota>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageBreakdown {
+    pub projects: Vec<ProjectUsage>,
+    /// Imported audio, as sized at import.
+    pub audio_bytes: u64,
+    pub model_bytes: u64,
+    /// The history database with its write-ahead log.
+    pub database_bytes: u64,
+    pub total_bytes: u64,
+}
+
+fn from_row(row: &rusqlite::Row) -> rusqlite::Result<ProjectQuota> {
+    Ok(ProjectQuota {
+        project: row.get(0)?,
+        max_hours: row.get(1)?,
+        max_gb: row
+            .get::<_, Option<i64>>(2)?
+            .map(|bytes| bytes as f64 / BYTES_PER_GB),
+    })
+}
+
+fn quota(conn: &Connection, project: &str) -> Result<Option<ProjectQuota>> {
+    Ok(conn
+        .query_row(
+            "SELECT project, max_hours, max_bytes FROM project_quotas WHERE project = ?1",
+            [project],
+            from_row,
+        )
+        .optional()?)
+}
+
+fn quotas(conn: &Connection) -> Result<Vec<ProjectQuota>> {
+    let mut statement =
+        conn.prepare("SELECT project, max_hours, max_bytes FROM project_quotas ORDER BY project")?;
+    let quotas = statement
+        .query_map([], from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(quotas)
+}
+
+/// The first limit broken by adding `bytes` and `secs` to usage of
+/// `used_bytes` and `used_secs`.
+fn violation(
+    quota: &ProjectQuota,
+    (used_bytes, used_secs): (u64, f64),
+    bytes: u64,
+    secs: Option<f64>,
+) -> Option<QuotaViolation> {
+    let hours = secs.map(|secs| secs / 3600.0);
+    let over_hours = quota
+        .max_hours
+        .zip(hours)
+        .filter(|(limit, hours)| used_secs / 3600.0 + hours > *limit)
+        .map(|(limit, hours)| (QuotaResource::Hours, used_secs / 3600.0, hours, limit));
+    let over_bytes = quota
+        .max_gb
+        .map(|gb| gb * BYTES_PER_GB)
+        .filter(|limit| (used_bytes + bytes) as f64 > *limit)
+        .map(|limit| (QuotaResource::Bytes, used_bytes as f64, bytes as f64, limit));
+    over_hours
+        .or(over_bytes)
+        .map(|(resource, used, requested, limit)| QuotaViolation {
+            project: quota.project.clone(),
+            resource,
+            used,
+            requested,
+            limit,
+        })
+}
+
+/// Fail with `QUOTA_EXCEEDED` if importing a file of `bytes` and `secs` as
+/// audio file `id` would put `project` over its quota. A file imported
+/// again under the same id counts once.
+pub fn check(
+    conn: &Connection,
+    project: &str,
+    id: &str,
+    bytes: u64,
+    secs: Option<f64>,
+) -> Result<()> {
+    let Some(quota) = quota(conn, project)? else {
+        return Ok(());
+    };
+    let (used_bytes, used_secs): (i64, f64) = conn.query_row(
+        "SELECT COALESCE(SUM(size), 0), COALESCE(SUM(duration), 0) FROM audio_files
+         WHERE project = ?1 AND id != ?2",
+        params![project, id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    match violation(&quota, (used_bytes as u64, used_secs), bytes, secs) {
+        Some(violation) => Err(Error::QuotaExceeded(violation)),
+        None => Ok(()),
+    }
+}
+
+fn file_size(path: &std::path::Path) -> u64 {
+    std::fs::metadata(path).map_or(0, |metadata| metadata.len())
+}
+
+#[tauri::command]
+pub fn get_project_quotas(app: AppHandle) -> Result<Vec<ProjectQuota>> {
+    quotas(&db::connect(&app)?)
+}
+
+/// Set a project's limits; leaving both unset removes its quota. Existing
+/// files over a new limit stay, but nothing more can be imported.
+#[tauri::command]
+pub fn set_project_quota(
+    app: AppHandle,
+    project: String,
+    max_hours: Option<f64>,
+    max_gb: Option<f64>,
+) -> Result<()> {
+    let project = project.trim();
+    if project.is_empty() {
+        return Err(Error::InvalidInput("a quota needs a project name".into()));
+    }
+    if max_hours
+        .into_iter()
+        .chain(max_gb)
+        .any(|limit| limit <= 0.0 || !limit.is_finite())
+    {
+        return Err(Error::InvalidInput(
+            "quota limits must be positive numbers".into(),
+        ));
+    }
+    let conn = db::connect(&app)?;
+    if max_hours.is_none() && max_gb.is_none() {
+        conn.execute("DELETE FROM project_quotas WHERE project = ?1", [project])?;
+    } else {
+        conn.execute(
+            "INSERT OR REPLACE INTO project_quotas (project, max_hours, max_bytes)
+             VALUES (?1, ?2, ?3)",
+            params![
+                project,
+                max_hours,
+                max_gb.map(|gb| (gb * BYTES_PER_GB) as i64)
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Disk use by project, imported audio, models and the database.
+#[tauri::command]
+pub fn get_storage_breakdown(app: AppHandle) -> Result<StorageBreakdown> {
+    let conn = db::connect(&app)?;
+    let mut statement = conn.prepare(
+        "SELECT project, COUNT(*), COALESCE(SUM(size), 0), COALESCE(SUM(duration), 0)
+         FROM audio_files GROUP BY project ORDER BY project IS NULL, project",
+    )?;
+    let mut projects: Vec<ProjectUsage> = statement
+        .query_map([], |row| {
+            Ok(ProjectUsage {
+                project: row.get(0)?,
+                files: row.get::<_, i64>(1)? as u64,
+                bytes: row.get::<_, i64>(2)? as u64,
+                hours: row.get::<_, f64>(3)? / 3600.0,
+                quota: None,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    for limit in quotas(&conn)? {
+        match projects
+            .iter_mut()
+            .find(|usage| usage.project.as_deref() == Some(&limit.project))
+        {
+            Some(usage) => usage.quota = Some(limit),
+            // A quota set before anything was imported.
+            None => projects.push(ProjectUsage {
+                project: Some(limit.project.clone()),
+                quota: Some(limit),
+                ..Default::default()
+            }),
+        }
+    }
+
+    let audio_bytes = projects.iter().map(|usage| usage.bytes).sum();
+    let model_bytes = models::downloaded_models(&app)
+        .map(|models| models.iter().map(|model| model.size_bytes).sum())
+        .unwrap_or(0);
+    let database = db::db_path(&app)?;
+    let database_bytes = ["", "-wal", "-shm"]
+        .iter()
+        .map(|suffix| {
+            let mut path = database.clone().into_os_string();
+            path.push(suffix);
+            file_size(path.as_ref())
+        })
+        .sum();
+    Ok(StorageBreakdown {
+        projects,
+        audio_bytes,
+        model_bytes,
+        database_bytes,
+        total_bytes: audio_bytes + model_bytes + database_bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_the_limit_an_import_breaks() {
+        let quota = ProjectQuota {
+            project: "Podcast".into(),
+            max_hours: Some(10.0),
+            max_gb: Some(2.0),
+        };
+        let gb = BYTES_PER_GB as u64;
+        assert!(violation(&quota, (gb, 9.0 * 3600.0), gb / 2, Some(1800.0)).is_none());
+
+        let hours = violation(&quota, (gb, 9.5 * 3600.0), gb / 2, Some(3600.0)).unwrap();
+        assert_eq!(hours.resource, QuotaResource::Hours);
+        assert_eq!(
+            (hours.total_label(), hours.limit_label()),
+            ("10.5 h".into(), "10.0 h".into())
+        );
+
+        // Without a declared length only the size counts.
+        let bytes = violation(&quota, (gb + gb / 2, 0.0), gb, None).unwrap();
+        assert_eq!(bytes.resource, QuotaResource::Bytes);
+        assert_eq!(bytes.total_label(), "2.50 GB");
+    }
+}
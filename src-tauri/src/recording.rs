@@ -0,0 +1,619 @@
+//! Multi-source recording.
+//!
+//! A recording session captures one or more sources (the microphone and the
+//! system output) into separate WAV tracks, so each track can be transcribed
+//! on its own and the results interleaved with a per-source label.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, SecondsFormat};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::audio::{self, WHISPER_SAMPLE_RATE};
+use crate::error::{Error, Result};
+use crate::preflight::{self, JobSpec};
+use crate::vad;
+use crate::{crash, i18n};
+
+type WavWriter = hound::WavWriter<BufWriter<File>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioSource {
+    Microphone,
+    System,
+}
+
+impl AudioSource {
+    fn default_label(self) -> String {
+        i18n::t(match self {
+            AudioSource::Microphone => "track-microphone",
+            AudioSource::System => "track-system",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackSpec {
+    pub source: AudioSource,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedTrack {
+    pub label: String,
+    pub source: AudioSource,
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingSession {
+    pub id: String,
+    pub tracks: Vec<RecordedTrack>,
+    /// Marker positions in seconds from the start of the session.
+    pub markers: Vec<f64>,
+    /// Wall-clock time capture started, RFC 3339 with the local UTC offset.
+    pub started_at: String,
+    /// IANA name of the local timezone at the start, when the OS reports
+    /// one.
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackSegment {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackTranscript {
+    pub label: String,
+    pub segments: Vec<TrackSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabeledSegment {
+    pub label: String,
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+struct ActiveRecording {
+    stop: mpsc::Sender<()>,
+    done: mpsc::Receiver<Result<Vec<RecordedTrack>>>,
+    started: Instant,
+    /// Both taken as capture starts, so a timezone change while recording,
+    /// e.g. on a flight, does not shift the session's wall-clock times.
+    started_at: String,
+    timezone: Option<String>,
+    markers: Vec<f64>,
+}
+
+/// Recording sessions that are currently capturing audio, keyed by session id.
+#[derive(Default)]
+pub struct RecordingState(Mutex<HashMap<String, ActiveRecording>>);
+
+/// A track being written while the session is live.
+struct LiveTrack {
+    label: String,
+    source: AudioSource,
+    path: PathBuf,
+    sample_rate: u32,
+    channels: u16,
+    writer: Arc<Mutex<Option<WavWriter>>>,
+}
+
+impl LiveTrack {
+    fn finish(self) -> Result<RecordedTrack> {
+        let writer = self.writer.lock().unwrap().take();
+        let frames = match writer {
+            Some(writer) => {
+                let frames = writer.duration();
+                writer
+                    .finalize()
+                    .map_err(|e| Error::AudioDevice(e.to_string()))?;
+                frames
+            }
+            None => 0,
+        };
+
+        Ok(RecordedTrack {
+            label: self.label,
+            source: self.source,
+            path: self.path,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            duration: frames as f64 / self.sample_rate as f64,
+        })
+    }
+}
+
+pub fn recordings_dir(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| Error::NotFound("app data directory".into()))?
+        .join("recordings");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Find the device that captures the given source, together with its config.
+///
+/// System audio is captured through WASAPI loopback on Windows (an input
+/// stream opened on the output device). Elsewhere it needs a loopback input
+/// device such as a PulseAudio monitor or BlackHole on macOS.
+fn capture_device(source: AudioSource) -> Result<(cpal::Device, cpal::SupportedStreamConfig)> {
+    let host = cpal::default_host();
+
+    let device = match source {
+        AudioSource::Microphone => host.default_input_device(),
+        AudioSource::System if cfg!(target_os = "windows") => host.default_output_device(),
+        AudioSource::System => host
+            .input_devices()
+            .map_err(|e| Error::AudioDevice(e.to_string()))?
+            .find(|device| {
+                device.name().is_ok_and(|name| {
+                    let name = name.to_lowercase();
+                    ["monitor", "loopback", "blackhole", "soundflower"]
+                        .iter()
+                        .any(|hint| name.contains(hint))
+                })
+            }),
+    }
+    .ok_or_else(|| Error::AudioDevice(format!("no capture device for {:?} audio", source)))?;
+
+    let config = if source == AudioSource::System && cfg!(target_os = "windows") {
+        device.default_output_config()
+    } else {
+        device.default_input_config()
+    }
+    .map_err(|e| Error::AudioDevice(e.to_string()))?;
+
+    Ok((device, config))
+}
+
+fn write_samples<T>(writer: &Mutex<Option<WavWriter>>, data: &[T])
+where
+    T: Sample,
+    i16: FromSample<T>,
+{
+    if let Ok(mut guard) = writer.lock() {
+        if let Some(writer) = guard.as_mut() {
+            for &sample in data {
+                let _ = writer.write_sample(i16::from_sample(sample));
+            }
+        }
+    }
+}
+
+fn build_stream(
+    device: &cpal::Device,
+    config: &cpal::SupportedStreamConfig,
+    writer: Arc<Mutex<Option<WavWriter>>>,
+) -> Result<cpal::Stream> {
+    let stream_config: cpal::StreamConfig = config.clone().into();
+    let on_error = |err: cpal::StreamError| crash::log(format!("recording stream error: {}", err));
+
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::F32 => device.build_input_stream(
+            &stream_config,
+            move |data: &[f32], _: &_| write_samples(&writer, data),
+            on_error,
+            None,
+        ),
+        cpal::SampleFormat::I16 => device.build_input_stream(
+            &stream_config,
+            move |data: &[i16], _: &_| write_samples(&writer, data),
+            on_error,
+            None,
+        ),
+        cpal::SampleFormat::U16 => device.build_input_stream(
+            &stream_config,
+            move |data: &[u16], _: &_| write_samples(&writer, data),
+            on_error,
+            None,
+        ),
+        format => {
+            return Err(Error::AudioDevice(format!(
+                "unsupported sample format {:?}",
+                format
+            )))
+        }
+    }
+    .map_err(|e| Error::AudioDevice(e.to_string()))?;
+
+    Ok(stream)
+}
+
+/// Open every requested source and start capturing into its own WAV file.
+fn start_tracks(dir: &Path, specs: &[TrackSpec]) -> Result<(Vec<cpal::Stream>, Vec<LiveTrack>)> {
+    let mut streams = Vec::with_capacity(specs.len());
+    let mut tracks = Vec::with_capacity(specs.len());
+
+    for (index, spec) in specs.iter().enumerate() {
+        let (device, config) = capture_device(spec.source)?;
+        let label = spec
+            .label
+            .clone()
+            .unwrap_or_else(|| spec.source.default_label());
+        let path = dir.join(format!("track-{}-{:?}.wav", index + 1, spec.source).to_lowercase());
+
+        let wav_spec = hound::WavSpec {
+            channels: config.channels(),
+            sample_rate: config.sample_rate().0,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(&path, wav_spec)
+            .map_err(|e| Error::AudioDevice(e.to_string()))?;
+        let writer = Arc::new(Mutex::new(Some(writer)));
+
+        streams.push(build_stream(&device, &config, writer.clone())?);
+        tracks.push(LiveTrack {
+            label,
+            source: spec.source,
+            path,
+            sample_rate: wav_spec.sample_rate,
+            channels: wav_spec.channels,
+            writer,
+        });
+    }
+
+    for stream in &streams {
+        stream
+            .play()
+            .map_err(|e| Error::AudioDevice(e.to_string()))?;
+    }
+
+    Ok((streams, tracks))
+}
+
+/// Start recording the requested sources into a new multi-track session.
+///
+/// Defaults to microphone ("Me") plus system audio ("Others") when no tracks
+/// are given. Returns the session id.
+pub fn start(app: &AppHandle, tracks: Option<Vec<TrackSpec>>) -> Result<String> {
+    let specs = tracks.unwrap_or_else(|| {
+        vec![
+            TrackSpec {
+                source: AudioSource::Microphone,
+                label: None,
+            },
+            TrackSpec {
+                source: AudioSource::System,
+                label: None,
+            },
+        ]
+    });
+    if specs.is_empty() {
+        return Err(Error::InvalidInput("at least one track is required".into()));
+    }
+    preflight::check(
+        app,
+        &JobSpec::Recording {
+            expected_minutes: None,
+            tracks: Some(specs.len() as u64),
+        },
+    )?
+    .into_result()?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let dir = recordings_dir(app)?.join(&id);
+    fs::create_dir_all(&dir)?;
+
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<()>>();
+    let (done_tx, done_rx) = mpsc::channel();
+
+    // cpal streams are not `Send`, so they live on a dedicated thread for
+    // the whole session and are dropped there once a stop is requested.
+    thread::spawn(move || {
+        let (streams, tracks) = match start_tracks(&dir, &specs) {
+            Ok(started) => started,
+            Err(err) => {
+                let _ = ready_tx.send(Err(err));
+                return;
+            }
+        };
+        let _ = ready_tx.send(Ok(()));
+
+        let _ = stop_rx.recv();
+        drop(streams);
+
+        let result = tracks.into_iter().map(LiveTrack::finish).collect();
+        let _ = done_tx.send(result);
+    });
+
+    ready_rx
+        .recv()
+        .map_err(|_| Error::AudioDevice("recording thread exited".into()))??;
+
+    app.state::<RecordingState>().0.lock().unwrap().insert(
+        id.clone(),
+        ActiveRecording {
+            stop: stop_tx,
+            done: done_rx,
+            started: Instant::now(),
+            started_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            timezone: iana_time_zone::get_timezone().ok(),
+            markers: Vec::new(),
+        },
+    );
+
+    Ok(id)
+}
+
+/// Stop a recording session and return the finished tracks.
+pub fn stop(app: &AppHandle, session_id: &str) -> Result<RecordingSession> {
+    let recording = app
+        .state::<RecordingState>()
+        .0
+        .lock()
+        .unwrap()
+        .remove(session_id)
+        .ok_or_else(|| Error::NotFound(format!("recording session {}", session_id)))?;
+
+    let _ = recording.stop.send(());
+    let tracks = recording
+        .done
+        .recv()
+        .map_err(|_| Error::AudioDevice("recording thread exited".into()))??;
+
+    Ok(RecordingSession {
+        id: session_id.to_string(),
+        tracks,
+        markers: recording.markers,
+        started_at: recording.started_at,
+        timezone: recording.timezone,
+    })
+}
+
+/// Ids of the sessions that are currently recording.
+pub fn active_sessions(app: &AppHandle) -> Vec<String> {
+    app.state::<RecordingState>()
+        .0
+        .lock()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Mark the current moment of a session; returns seconds since it started.
+pub fn add_marker(app: &AppHandle, session_id: &str) -> Result<f64> {
+    let state = app.state::<RecordingState>();
+    let mut sessions = state.0.lock().unwrap();
+    let recording = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| Error::NotFound(format!("recording session {}", session_id)))?;
+    let at = recording.started.elapsed().as_secs_f64();
+    recording.markers.push(at);
+    Ok(at)
+}
+
+#[tauri::command]
+pub fn start_recording(app: AppHandle, tracks: Option<Vec<TrackSpec>>) -> Result<String> {
+    start(&app, tracks)
+}
+
+#[tauri::command]
+pub fn stop_recording(app: AppHandle, session_id: String) -> Result<RecordingSession> {
+    stop(&app, &session_id)
+}
+
+#[tauri::command]
+pub fn add_recording_marker(app: AppHandle, session_id: String) -> Result<f64> {
+    add_marker(&app, &session_id)
+}
+
+/// Stream `device` to `sender` as mono `f32` chunks, whatever its format.
+fn mono_input<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    sender: mpsc::Sender<Vec<f32>>,
+) -> std::result::Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &_| {
+            let mono = data
+                .chunks(channels)
+                .map(|frame| {
+                    frame
+                        .iter()
+                        .map(|&sample| sample.to_sample::<f32>())
+                        .sum::<f32>()
+                        / channels as f32
+                })
+                .collect();
+            let _ = sender.send(mono);
+        },
+        |err: cpal::StreamError| crash::log(format!("microphone stream error: {}", err)),
+        None,
+    )
+}
+
+/// Microphone input split into utterances at pauses, resampled to 16 kHz.
+///
+/// Used by live features. Holds a cpal stream, so it must stay on the thread
+/// that opened it.
+pub struct Utterances {
+    _stream: cpal::Stream,
+    samples: mpsc::Receiver<Vec<f32>>,
+    rate: u32,
+    pending: Vec<f32>,
+    buffer: Vec<f32>,
+    min_secs: f64,
+    max_secs: f64,
+}
+
+impl Utterances {
+    /// Trailing silence that ends an utterance.
+    const PAUSE_SECS: f64 = 0.6;
+
+    pub fn open(min_secs: f64, max_secs: f64) -> Result<Self> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| Error::AudioDevice("no microphone available".into()))?;
+        let config = device
+            .default_input_config()
+            .map_err(|e| Error::AudioDevice(e.to_string()))?;
+        let (sender, samples) = mpsc::channel();
+        let rate = config.sample_rate().0;
+        let stream_config: cpal::StreamConfig = config.clone().into();
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => mono_input::<f32>(&device, &stream_config, sender),
+            cpal::SampleFormat::I16 => mono_input::<i16>(&device, &stream_config, sender),
+            cpal::SampleFormat::U16 => mono_input::<u16>(&device, &stream_config, sender),
+            format => {
+                return Err(Error::AudioDevice(format!(
+                    "unsupported microphone sample format {:?}",
+                    format
+                )))
+            }
+        }
+        .map_err(|e| Error::AudioDevice(e.to_string()))?;
+        stream
+            .play()
+            .map_err(|e| Error::AudioDevice(e.to_string()))?;
+
+        Ok(Self {
+            _stream: stream,
+            samples,
+            rate,
+            pending: Vec::new(),
+            buffer: Vec::new(),
+            min_secs,
+            max_secs,
+        })
+    }
+
+    /// Wait up to 100 ms for audio and return a finished, non-silent
+    /// utterance if one is ready.
+    pub fn poll(&mut self) -> Option<Vec<f32>> {
+        if let Ok(chunk) = self.samples.recv_timeout(Duration::from_millis(100)) {
+            self.pending.extend(chunk);
+        }
+        // Resample in blocks so the utterance buffer is always at 16 kHz.
+        if self.pending.len() >= self.rate as usize / 10 {
+            self.buffer.extend(audio::resample(
+                &self.pending,
+                self.rate,
+                WHISPER_SAMPLE_RATE,
+            ));
+            self.pending.clear();
+        }
+
+        let duration = audio::duration_secs(&self.buffer);
+        let ready = duration >= self.max_secs
+            || (duration >= self.min_secs.max(Self::PAUSE_SECS)
+                && vad::ends_in_silence(&self.buffer, Self::PAUSE_SECS));
+        if !ready {
+            return None;
+        }
+
+        let utterance = std::mem::take(&mut self.buffer);
+        (!vad::is_silent(&utterance)).then_some(utterance)
+    }
+}
+
+/// Merge per-track transcripts into a single timeline ordered by start time.
+///
+/// Segments that start at the same moment are ordered by end time, the
+/// shorter first, and segments that also end together keep the order of
+/// the tracks they came from, so the output is deterministic.
+pub fn interleave(tracks: Vec<TrackTranscript>) -> Vec<LabeledSegment> {
+    let mut segments: Vec<LabeledSegment> = tracks
+        .into_iter()
+        .flat_map(|track| {
+            let label = track.label;
+            track
+                .segments
+                .into_iter()
+                .map(move |segment| LabeledSegment {
+                    label: label.clone(),
+                    text: segment.text.trim().to_string(),
+                    start: segment.start,
+                    end: segment.end,
+                })
+        })
+        .filter(|segment| !segment.text.is_empty())
+        .collect();
+
+    segments.sort_by(|a, b| a.start.total_cmp(&b.start).then(a.end.total_cmp(&b.end)));
+    segments
+}
+
+/// Interleave the transcripts of each recorded track into a labeled timeline.
+#[tauri::command]
+pub fn interleave_track_transcripts(tracks: Vec<TrackTranscript>) -> Vec<LabeledSegment> {
+    interleave(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(label: &str, segments: &[(&str, f64, f64)]) -> TrackTranscript {
+        TrackTranscript {
+            label: label.to_string(),
+            segments: segments
+                .iter()
+                .map(|&(text, start, end)| TrackSegment {
+                    text: text.to_string(),
+                    start,
+                    end,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn interleaves_tracks_by_start_time() {
+        let merged = interleave(vec![
+            track("Me", &[("Hi all", 0.0, 1.5), ("Sounds good", 6.0, 7.0)]),
+            track("Others", &[("Hello", 2.0, 3.0), (" ", 4.0, 4.5)]),
+        ]);
+
+        let order: Vec<(&str, &str)> = merged
+            .iter()
+            .map(|s| (s.label.as_str(), s.text.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![("Me", "Hi all"), ("Others", "Hello"), ("Me", "Sounds good")]
+        );
+
+        let tied = interleave(vec![
+            track("Me", &[("Long", 1.0, 3.0), ("Same", 5.0, 6.0)]),
+            track("Others", &[("Short", 1.0, 2.0), ("Same too", 5.0, 6.0)]),
+        ]);
+        let order: Vec<&str> = tied.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(order, vec!["Short", "Long", "Same", "Same too"]);
+    }
+}
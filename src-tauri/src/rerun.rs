@@ -0,0 +1,318 @@
+//! Partial re-transcription of a stored transcript.
+//!
+//! Only the selected time range is decoded again, optionally with a larger
+//! model or cleaned-up audio, and the new segments replace the ones they
+//! overlap. The range is widened to whole segments so no word is cut in
+//! half, and the splice is recorded as a transcript revision that fails
+//! with `EDIT_CONFLICT` if someone saved while decoding ran.
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use crate::error::{Error, Result};
+use crate::segments::{self, SegmentInput, StoredSegment};
+use crate::whisper::{AdvancedOptions, DecodeOptions, Segment, Word};
+use crate::{clips, db, editing, parts, review, transcription, trim};
+
+/// Cut-off of the high-pass filter that removes rumble and hum.
+const HIGH_PASS_HZ: f32 = 80.0;
+/// Peak level enhanced audio is normalized to.
+const TARGET_PEAK: f32 = 0.9;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RangeOptions {
+    /// Model to decode with, e.g. a larger one than the first pass used.
+    pub model: Option<String>,
+    /// Defaults to the transcription's language.
+    pub language: Option<String>,
+    pub advanced: AdvancedOptions,
+    /// Filter out low rumble and normalize the level before decoding.
+    pub enhance: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeOutcome {
+    /// Transcript version produced by the splice.
+    pub version: i64,
+    /// The range decoded, in seconds, after widening to whole segments.
+    pub start: f64,
+    pub end: f64,
+    /// Number of segments replaced.
+    pub replaced: usize,
+    /// Segments of the whole transcript after the splice.
+    pub segments: Vec<StoredSegment>,
+}
+
+/// Remove rumble below [`HIGH_PASS_HZ`] and bring the peak to
+/// [`TARGET_PEAK`], which helps quiet or boomy recordings.
+fn enhance(pcm: &[f32]) -> Vec<f32> {
+    let rc = 1.0 / (2.0 * std::f32::consts::PI * HIGH_PASS_HZ);
+    let dt = 1.0 / WHISPER_SAMPLE_RATE as f32;
+    let alpha = rc / (rc + dt);
+    let mut out = Vec::with_capacity(pcm.len());
+    let (mut previous_in, mut previous_out) = (0.0, 0.0);
+    for &sample in pcm {
+        previous_out = alpha * (previous_out + sample - previous_in);
+        previous_in = sample;
+        out.push(previous_out);
+    }
+    let peak = out.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak > 0.0 {
+        let gain = TARGET_PEAK / peak;
+        out.iter_mut().for_each(|s| *s *= gain);
+    }
+    out
+}
+
+/// The range widened to the segments it overlaps, and their index range.
+fn widen(segments: &[StoredSegment], start: f64, end: f64) -> (f64, f64, std::ops::Range<usize>) {
+    let overlapping: Vec<usize> = (0..segments.len())
+        .filter(|&i| segments[i].end > start && segments[i].start < end)
+        .collect();
+    match (overlapping.first(), overlapping.last()) {
+        (Some(&first), Some(&last)) => (
+            start.min(segments[first].start),
+            end.max(segments[last].end),
+            first..last + 1,
+        ),
+        // Nothing overlaps: insert where the range falls.
+        _ => {
+            let at = segments.iter().take_while(|s| s.end <= start).count();
+            (start, end, at..at)
+        }
+    }
+}
+
+/// Fresh segments for the range, shifted to transcript time. Each keeps
+/// the speaker of the old segment it overlaps most.
+fn splice_inputs(
+    decoded: &[Segment],
+    offset: f64,
+    replaced: &[StoredSegment],
+) -> Vec<SegmentInput> {
+    decoded
+        .iter()
+        .filter(|segment| !segment.text.trim().is_empty())
+        .map(|segment| {
+            let (start, end) = (segment.start + offset, segment.end + offset);
+            let overlap = |old: &StoredSegment| (end.min(old.end) - start.max(old.start)).max(0.0);
+            let speaker = replaced
+                .iter()
+                .filter(|old| overlap(old) > 0.0)
+                .max_by(|a, b| overlap(a).total_cmp(&overlap(b)))
+                .and_then(|old| old.speaker.clone());
+            SegmentInput {
+                speaker,
+                text: segment.text.clone(),
+                start,
+                end,
+                confidence: segment.confidence.map(f64::from),
+                words: segment
+                    .words
+                    .iter()
+                    .map(|word| Word {
+                        start: word.start + offset,
+                        end: word.end + offset,
+                        ..word.clone()
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+fn input(segment: &StoredSegment) -> SegmentInput {
+    SegmentInput {
+        speaker: segment.speaker.clone(),
+        text: segment.text.clone(),
+        start: segment.start,
+        end: segment.end,
+        confidence: segment.confidence,
+        words: segment.words.clone(),
+    }
+}
+
+/// Decode `start_ms`–`end_ms` of a transcription again and splice the
+/// result into its segments and text.
+#[tauri::command]
+pub async fn retranscribe_range(
+    app: AppHandle,
+    id: String,
+    start_ms: i64,
+    end_ms: i64,
+    options: Option<RangeOptions>,
+) -> Result<RangeOutcome> {
+    if start_ms < 0 || end_ms <= start_ms {
+        return Err(Error::InvalidInput(format!(
+            "range ends at {} ms, before it starts at {} ms",
+            end_ms, start_ms
+        )));
+    }
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut conn = db::connect(&app)?;
+        let (language, version): (String, i64) = conn
+            .query_row(
+                "SELECT language, version FROM transcriptions WHERE id = ?1",
+                [&id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("transcription {}", id)))?;
+        let old = segments::for_transcription(&conn, &id)?;
+        let (start, end, replaced) = widen(&old, start_ms as f64 / 1000.0, end_ms as f64 / 1000.0);
+
+        let pcm = clips::source_pcm(&conn, &id)?;
+        let kept =
+            trim::for_transcription(&conn, &id)?.sample_range(pcm.len(), WHISPER_SAMPLE_RATE);
+        let index = |secs: f64| (secs * WHISPER_SAMPLE_RATE as f64) as usize;
+        let from = index(start).clamp(kept.start, kept.end);
+        let to = index(end).clamp(from, kept.end);
+        if to == from {
+            return Err(Error::InvalidInput(
+                "the range is outside the transcription's audio".into(),
+            ));
+        }
+        let slice = if options.enhance {
+            enhance(&pcm[from..to])
+        } else {
+            pcm[from..to].to_vec()
+        };
+
+        let decode = DecodeOptions {
+            language: options
+                .language
+                .clone()
+                .or(Some(language).filter(|language| language != "auto")),
+            advanced: options.advanced,
+            ..Default::default()
+        };
+        let output =
+            transcription::transcribe_pcm(&app, &slice, options.model.as_deref(), &decode, false)?;
+        let offset = from as f64 / WHISPER_SAMPLE_RATE as f64;
+        let fresh = splice_inputs(&output.segments, offset, &old[replaced.clone()]);
+
+        let mut spliced: Vec<SegmentInput> = old[..replaced.start].iter().map(input).collect();
+        spliced.extend(fresh.iter().cloned());
+        spliced.extend(old[replaced.end..].iter().map(input));
+        let old_range: Vec<SegmentInput> = old[replaced.clone()].iter().map(input).collect();
+
+        let tx = conn.transaction()?;
+        let outcome = editing::record_edit(
+            &tx,
+            &id,
+            Some(version),
+            &format!("retranscribe:{}-{}", start_ms, end_ms),
+            Some(&parts::joined_text(&old_range)),
+            Some(&parts::joined_text(&fresh)),
+        )?;
+        tx.execute("DELETE FROM segments WHERE transcription_id = ?1", [&id])?;
+        segments::insert(&tx, &id, 0, &spliced)?;
+        tx.execute(
+            "UPDATE transcriptions SET text = ?2 WHERE id = ?1",
+            params![id, parts::joined_text(&spliced)],
+        )?;
+        // Translations of replaced segments no longer match; later ones
+        // move with their segments. Positions go through negatives so the
+        // shift never collides with a row not yet moved.
+        let (first, last) = (replaced.start as i64, replaced.end as i64);
+        let shift = fresh.len() as i64 - (last - first);
+        tx.execute(
+            "DELETE FROM translations
+             WHERE transcription_id = ?1 AND position >= ?2 AND position < ?3",
+            params![id, first, last],
+        )?;
+        tx.execute(
+            "UPDATE translations SET position = -(position + ?3) - 1
+             WHERE transcription_id = ?1 AND position >= ?2",
+            params![id, last, shift],
+        )?;
+        tx.execute(
+            "UPDATE translations SET position = -position - 1
+             WHERE transcription_id = ?1 AND position < 0",
+            [&id],
+        )?;
+        tx.commit()?;
+
+        review::flag(&mut conn, &id, None, review::DEFAULT_THRESHOLD)?;
+        Ok(RangeOutcome {
+            version: outcome.version,
+            start,
+            end,
+            replaced: replaced.len(),
+            segments: segments::for_transcription(&conn, &id)?,
+        })
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(position: i64, speaker: &str, start: f64, end: f64) -> StoredSegment {
+        StoredSegment {
+            id: String::new(),
+            transcription_id: "t".into(),
+            position,
+            speaker: Some(speaker.into()),
+            text: format!("segment {}", position),
+            start,
+            end,
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn widens_to_whole_segments_and_keeps_speakers() {
+        let old = [
+            segment(0, "Anna", 0.0, 4.0),
+            segment(1, "Anna", 4.0, 9.0),
+            segment(2, "Pieter", 9.0, 12.0),
+            segment(3, "Anna", 12.0, 15.0),
+        ];
+        let (start, end, replaced) = widen(&old, 5.0, 10.0);
+        assert_eq!((start, end, replaced.clone()), (4.0, 12.0, 1..3));
+        assert_eq!(widen(&old, 20.0, 21.0).2, 4..4);
+
+        let decoded = [
+            Segment {
+                text: " Better words.".into(),
+                start: 0.0,
+                end: 4.5,
+                confidence: Some(0.9),
+                words: Vec::new(),
+            },
+            Segment {
+                text: " Pieter again.".into(),
+                start: 5.5,
+                end: 8.0,
+                confidence: Some(0.8),
+                words: Vec::new(),
+            },
+        ];
+        let fresh = splice_inputs(&decoded, start, &old[replaced]);
+        assert_eq!(fresh[0].speaker.as_deref(), Some("Anna"));
+        assert_eq!((fresh[1].start, fresh[1].end), (9.5, 12.0));
+        assert_eq!(fresh[1].speaker.as_deref(), Some("Pieter"));
+    }
+
+    #[test]
+    fn enhancement_removes_offset_and_normalizes() {
+        let pcm: Vec<f32> = (0..WHISPER_SAMPLE_RATE as usize)
+            .map(|n| 0.3 + 0.05 * (n as f32 * 0.2).sin())
+            .collect();
+        let out = enhance(&pcm);
+        let tail = &out[out.len() / 2..];
+        let mean = tail.iter().sum::<f32>() / tail.len() as f32;
+        let peak = out.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(mean.abs() < 0.02);
+        assert!((peak - TARGET_PEAK).abs() < 1e-4);
+    }
+}
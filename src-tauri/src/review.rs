@@ -0,0 +1,425 @@
+//! Review queue for regions of a transcript that need a human listen.
+//!
+//! Segments decoded with low confidence are flagged whenever segments are
+//! saved. Given the audio, flagging also compares the transcript with voice
+//! activity: text over silence is likely hallucinated, and speech with no
+//! text was likely missed. Saving without the audio keeps those findings
+//! for the segments that are still there.
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::audio::{self, WHISPER_SAMPLE_RATE};
+use crate::error::{Error, Result};
+use crate::segments::{self, StoredSegment};
+use crate::{db, editing, vad};
+
+pub const DEFAULT_THRESHOLD: f64 = 0.5;
+/// Segments with less detected speech than this share are flagged.
+const MIN_SPEECH_COVERAGE: f64 = 0.2;
+/// Shorter untranscribed speech is usually a cough or a breath.
+const MIN_MISSED_SECS: f64 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewReason {
+    LowConfidence,
+    /// Text was decoded where no speech was detected.
+    NoSpeechDetected,
+    /// Speech was detected where nothing was decoded.
+    UntranscribedSpeech,
+}
+
+impl ReviewReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReviewReason::LowConfidence => "low_confidence",
+            ReviewReason::NoSpeechDetected => "no_speech_detected",
+            ReviewReason::UntranscribedSpeech => "untranscribed_speech",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "no_speech_detected" => ReviewReason::NoSpeechDetected,
+            "untranscribed_speech" => ReviewReason::UntranscribedSpeech,
+            _ => ReviewReason::LowConfidence,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewItem {
+    pub id: String,
+    pub transcription_id: String,
+    pub segment_id: Option<String>,
+    pub start: f64,
+    pub end: f64,
+    pub reason: ReviewReason,
+    pub resolved: bool,
+    pub note: Option<String>,
+    pub created_at: String,
+    pub resolved_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewProgress {
+    pub total: usize,
+    pub resolved: usize,
+    /// 100 when nothing needed review.
+    pub reviewed_percent: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flag {
+    pub segment_id: Option<String>,
+    pub start: f64,
+    pub end: f64,
+    pub reason: ReviewReason,
+}
+
+/// Find regions needing review; `speech` enables the VAD comparison.
+pub fn find_flags(
+    segments: &[StoredSegment],
+    speech: Option<&[Range<usize>]>,
+    threshold: f64,
+) -> Vec<Flag> {
+    let mut flags = Vec::new();
+    for segment in segments {
+        let reason = if segment.confidence.is_some_and(|c| c < threshold) {
+            Some(ReviewReason::LowConfidence)
+        } else if speech.is_some_and(|regions| {
+            vad::speech_coverage(regions, segment.start, segment.end) < MIN_SPEECH_COVERAGE
+        }) {
+            Some(ReviewReason::NoSpeechDetected)
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            flags.push(Flag {
+                segment_id: Some(segment.id.clone()),
+                start: segment.start,
+                end: segment.end,
+                reason,
+            });
+        }
+    }
+
+    let rate = WHISPER_SAMPLE_RATE as f64;
+    for region in speech.unwrap_or_default() {
+        let (start, end) = (region.start as f64 / rate, region.end as f64 / rate);
+        let covered = segments.iter().any(|s| s.start < end && s.end > start);
+        if !covered && end - start >= MIN_MISSED_SECS {
+            flags.push(Flag {
+                segment_id: None,
+                start,
+                end,
+                reason: ReviewReason::UntranscribedSpeech,
+            });
+        }
+    }
+
+    flags.sort_by(|a, b| a.start.total_cmp(&b.start));
+    flags
+}
+
+/// An open voice-activity item, moved onto `segments` after they were
+/// saved again without the audio to re-check. Low-confidence items are
+/// found afresh instead, and items whose region was edited away are dropped.
+fn carry_over(item: &ReviewItem, segments: &[StoredSegment]) -> Option<Flag> {
+    match item.reason {
+        ReviewReason::LowConfidence => None,
+        ReviewReason::NoSpeechDetected => segments
+            .iter()
+            .find(|segment| (segment.start - item.start).abs() < 0.01)
+            .map(|segment| Flag {
+                segment_id: Some(segment.id.clone()),
+                start: segment.start,
+                end: segment.end,
+                reason: item.reason,
+            }),
+        ReviewReason::UntranscribedSpeech => {
+            let covered = segments
+                .iter()
+                .any(|s| s.start < item.end && s.end > item.start);
+            (!covered).then(|| Flag {
+                segment_id: None,
+                start: item.start,
+                end: item.end,
+                reason: item.reason,
+            })
+        }
+    }
+}
+
+const COLUMNS: &str =
+    "id, transcription_id, segment_id, start_time, end_time, reason, status, note, created_at, resolved_at";
+
+fn from_row(row: &Row) -> rusqlite::Result<ReviewItem> {
+    Ok(ReviewItem {
+        id: row.get(0)?,
+        transcription_id: row.get(1)?,
+        segment_id: row.get(2)?,
+        start: row.get(3)?,
+        end: row.get(4)?,
+        reason: ReviewReason::parse(&row.get::<_, String>(5)?),
+        resolved: row.get::<_, String>(6)? == "resolved",
+        note: row.get(7)?,
+        created_at: row.get(8)?,
+        resolved_at: row.get(9)?,
+    })
+}
+
+fn items(
+    conn: &Connection,
+    transcription_id: &str,
+    include_resolved: bool,
+) -> Result<Vec<ReviewItem>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM review_items WHERE transcription_id = ?1 AND (?2 OR status = 'open')
+         ORDER BY start_time",
+        COLUMNS
+    ))?;
+    let items = statement
+        .query_map(params![transcription_id, include_resolved], from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(items)
+}
+
+/// Replace the open items of a transcription with freshly found flags.
+///
+/// Resolved items are kept, and a flag matching one is not raised again.
+/// Without `speech`, open voice-activity items are carried over.
+pub fn flag(
+    conn: &mut Connection,
+    transcription_id: &str,
+    speech: Option<&[Range<usize>]>,
+    threshold: f64,
+) -> Result<Vec<ReviewItem>> {
+    let segments = segments::for_transcription(conn, transcription_id)?;
+    let mut flags = find_flags(&segments, speech, threshold);
+    let (resolved, open): (Vec<ReviewItem>, Vec<ReviewItem>) = items(conn, transcription_id, true)?
+        .into_iter()
+        .partition(|item| item.resolved);
+    if speech.is_none() {
+        flags.extend(open.iter().filter_map(|item| carry_over(item, &segments)));
+        flags.sort_by(|a, b| a.start.total_cmp(&b.start));
+    }
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM review_items WHERE transcription_id = ?1 AND status = 'open'",
+        [transcription_id],
+    )?;
+    for flag in flags {
+        let already_reviewed = resolved
+            .iter()
+            .any(|item| item.reason == flag.reason && (item.start - flag.start).abs() < 0.01);
+        if already_reviewed {
+            continue;
+        }
+        tx.execute(
+            "INSERT INTO review_items (id, transcription_id, segment_id, start_time, end_time, reason)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                uuid::Uuid::new_v4().to_string(),
+                transcription_id,
+                flag.segment_id,
+                flag.start,
+                flag.end,
+                flag.reason.as_str()
+            ],
+        )?;
+    }
+    tx.commit()?;
+    items(conn, transcription_id, false)
+}
+
+/// Flag a transcript for review, checking voice activity if `audio_path` is given.
+#[tauri::command]
+pub async fn flag_review_items(
+    app: AppHandle,
+    id: String,
+    audio_path: Option<PathBuf>,
+    threshold: Option<f64>,
+) -> Result<Vec<ReviewItem>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let speech = audio_path
+            .map(|path| audio::load_pcm(&path).map(|pcm| vad::speech_regions(&pcm)))
+            .transpose()?;
+        let mut conn = db::connect(&app)?;
+        db::transcription_text(&conn, &id)?;
+        flag(
+            &mut conn,
+            &id,
+            speech.as_deref(),
+            threshold.unwrap_or(DEFAULT_THRESHOLD),
+        )
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[tauri::command]
+pub fn list_review_items(
+    app: AppHandle,
+    id: String,
+    include_resolved: Option<bool>,
+) -> Result<Vec<ReviewItem>> {
+    items(&db::connect(&app)?, &id, include_resolved.unwrap_or(false))
+}
+
+/// Mark an item reviewed, optionally correcting the flagged segment's text.
+///
+/// Corrections are attributed edits; pass `expected_version` to have them
+/// rejected if the transcript changed since it was loaded.
+#[tauri::command]
+pub fn resolve_review_item(
+    app: AppHandle,
+    item_id: String,
+    note: Option<String>,
+    corrected_text: Option<String>,
+    expected_version: Option<i64>,
+) -> Result<ReviewItem> {
+    let mut conn = db::connect(&app)?;
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    let (transcription_id, segment_id): (String, Option<String>) = tx
+        .query_row(
+            "SELECT transcription_id, segment_id FROM review_items WHERE id = ?1",
+            [&item_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("review item {}", item_id)))?;
+
+    if let Some(text) = corrected_text {
+        let segment_id = segment_id
+            .ok_or_else(|| Error::InvalidInput("this item has no segment to correct".into()))?;
+        let old: String = tx.query_row(
+            "SELECT text FROM segments WHERE id = ?1",
+            [&segment_id],
+            |row| row.get(0),
+        )?;
+        editing::record_edit(
+            &tx,
+            &transcription_id,
+            expected_version,
+            &format!("segment:{}", segment_id),
+            Some(&old),
+            Some(text.trim()),
+        )?;
+        tx.execute(
+            "UPDATE segments SET text = ?2 WHERE id = ?1",
+            params![segment_id, text.trim()],
+        )?;
+        segments::sync_text(&tx, &transcription_id)?;
+    }
+    tx.execute(
+        "UPDATE review_items SET status = 'resolved', note = ?2, resolved_at = CURRENT_TIMESTAMP
+         WHERE id = ?1",
+        params![item_id, note],
+    )?;
+    let item = tx.query_row(
+        &format!("SELECT {} FROM review_items WHERE id = ?1", COLUMNS),
+        [&item_id],
+        from_row,
+    )?;
+    tx.commit()?;
+    Ok(item)
+}
+
+#[tauri::command]
+pub fn get_review_progress(app: AppHandle, id: String) -> Result<ReviewProgress> {
+    let all = items(&db::connect(&app)?, &id, true)?;
+    let resolved = all.iter().filter(|item| item.resolved).count();
+    Ok(ReviewProgress {
+        total: all.len(),
+        resolved,
+        reviewed_percent: if all.is_empty() {
+            100.0
+        } else {
+            resolved as f64 * 100.0 / all.len() as f64
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(id: &str, start: f64, end: f64, confidence: f64) -> StoredSegment {
+        StoredSegment {
+            id: id.into(),
+            transcription_id: "t".into(),
+            position: 0,
+            speaker: None,
+            text: "words".into(),
+            start,
+            end,
+            confidence: Some(confidence),
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn flags_low_confidence_and_vad_disagreement() {
+        let rate = WHISPER_SAMPLE_RATE as usize;
+        // Speech from 0-2 s and 6-9 s.
+        let speech = vec![0..2 * rate, 6 * rate..9 * rate];
+        let flags = find_flags(
+            &[segment("a", 0.0, 2.0, 0.3), segment("b", 3.0, 5.0, 0.9)],
+            Some(&speech),
+            DEFAULT_THRESHOLD,
+        );
+
+        let reasons: Vec<ReviewReason> = flags.iter().map(|f| f.reason).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                ReviewReason::LowConfidence,
+                ReviewReason::NoSpeechDetected,
+                ReviewReason::UntranscribedSpeech
+            ]
+        );
+        assert_eq!(flags[2].start, 6.0);
+    }
+
+    #[test]
+    fn carries_voice_activity_items_over_resaved_segments() {
+        let item = |reason, start, end| ReviewItem {
+            id: "i".into(),
+            transcription_id: "t".into(),
+            segment_id: Some("old".into()),
+            start,
+            end,
+            reason,
+            resolved: false,
+            note: None,
+            created_at: String::new(),
+            resolved_at: None,
+        };
+        let saved = [segment("new", 3.0, 5.0, 0.9)];
+
+        let moved = carry_over(&item(ReviewReason::NoSpeechDetected, 3.0, 5.0), &saved).unwrap();
+        assert_eq!(moved.segment_id.as_deref(), Some("new"));
+        assert_eq!(
+            carry_over(&item(ReviewReason::NoSpeechDetected, 8.0, 9.0), &saved),
+            None
+        );
+        assert!(carry_over(&item(ReviewReason::UntranscribedSpeech, 6.0, 9.0), &saved).is_some());
+        assert_eq!(
+            carry_over(&item(ReviewReason::UntranscribedSpeech, 4.0, 7.0), &saved),
+            None
+        );
+        assert_eq!(
+            carry_over(&item(ReviewReason::LowConfidence, 3.0, 5.0), &saved),
+            None
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Per-language model routing.
+//!
+//! Rules such as "nl → large-v3, en → distil-medium" pick the model for a
+//! file once its language is known. They apply only when the caller did not
+//! ask for a specific model, and a rule whose model is not downloaded falls
+//! back to the default.
+
+use std::collections::BTreeMap;
+
+use rusqlite::Connection;
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{Error, Result};
+
+pub const ROUTES_PREFERENCE: &str = "language_model_routes";
+
+/// Model name by ISO 639-1 language code.
+pub type Routes = BTreeMap<String, String>;
+
+pub fn routes(conn: &Connection) -> Result<Routes> {
+    Ok(db::get_preference(conn, ROUTES_PREFERENCE)?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+/// The routed model for `language`, matching regional tags such as `en-GB`
+/// by their base language.
+pub fn route<'a>(routes: &'a Routes, language: &str) -> Option<&'a str> {
+    let language = language.to_ascii_lowercase();
+    let base = language.split(['-', '_']).next().unwrap_or_default();
+    routes
+        .get(&language)
+        .or_else(|| routes.get(base))
+        .map(String::as_str)
+}
+
+#[tauri::command]
+pub fn get_language_routes(app: AppHandle) -> Result<Routes> {
+    routes(&db::connect(&app)?)
+}
+
+/// Route `language` to `model`, or remove its rule when `model` is `None`.
+#[tauri::command]
+pub fn set_language_route(
+    app: AppHandle,
+    language: String,
+    model: Option<String>,
+) -> Result<Routes> {
+    let language = language.trim().to_ascii_lowercase();
+    if language.is_empty() || language == "auto" {
+        return Err(Error::InvalidInput("a route needs a language code".into()));
+    }
+    let conn = db::connect(&app)?;
+    let mut routes = routes(&conn)?;
+    match model.map(|model| model.trim().to_string()) {
+        Some(model) if !model.is_empty() => {
+            routes.insert(language, model);
+        }
+        _ => {
+            routes.remove(&language);
+        }
+    }
+    db::set_preference(
+        &conn,
+        ROUTES_PREFERENCE,
+        &serde_json::to_string(&routes).unwrap(),
+    )?;
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regional_tags_fall_back_to_base_language() {
+        let routes = Routes::from([
+            ("nl".to_string(), "large-v3".to_string()),
+            ("en".to_string(), "distil-medium".to_string()),
+            ("en-gb".to_string(), "medium".to_string()),
+        ]);
+        assert_eq!(route(&routes, "NL"), Some("large-v3"));
+        assert_eq!(route(&routes, "en-GB"), Some("medium"));
+        assert_eq!(route(&routes, "en-US"), Some("distil-medium"));
+        assert_eq!(route(&routes, "de"), None);
+    }
+}
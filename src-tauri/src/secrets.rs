@@ -0,0 +1,186 @@
+//! API keys for cloud providers, kept in the OS keychain.
+//!
+//! Keys, and the proxy password, are never written to SQLite or preferences. Saving a key first
+//! checks it against the provider with a cheap authenticated request.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::network::{self, Client};
+use crate::{audit, db};
+
+const SERVICE: &str = "com.transcriber.app";
+const PING_TIMEOUT: Duration = Duration::from_secs(15);
+const PROXY_ENTRY: &str = "proxy";
+
+/// Providers we hold keys for, with the endpoint used to validate a key.
+const PROVIDERS: &[(&str, &str)] = &[
+    ("openai", "https://api.openai.com/v1/models"),
+    ("groq", "https://api.groq.com/openai/v1/models"),
+    ("mistral", "https://api.mistral.ai/v1/models"),
+    ("anthropic", "https://api.anthropic.com/v1/models"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyStatus {
+    pub provider: String,
+    pub stored: bool,
+    pub valid: Option<bool>,
+}
+
+fn ping_url(provider: &str) -> Result<&'static str> {
+    PROVIDERS
+        .iter()
+        .find(|(name, _)| *name == provider)
+        .map(|(_, url)| *url)
+        .ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "unknown provider '{}'; expected one of: {}",
+                provider,
+                PROVIDERS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })
+}
+
+fn keychain_entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name).map_err(|e| Error::Keychain(e.to_string()))
+}
+
+fn entry(provider: &str) -> Result<keyring::Entry> {
+    ping_url(provider)?;
+    keychain_entry(provider)
+}
+
+fn read(entry: keyring::Entry) -> Result<Option<String>> {
+    match entry.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(Error::Keychain(err.to_string())),
+    }
+}
+
+fn remove(entry: keyring::Entry) -> Result<()> {
+    match entry.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(Error::Keychain(err.to_string())),
+    }
+}
+
+/// The stored key for `provider`, if there is one.
+pub fn api_key(provider: &str) -> Result<Option<String>> {
+    read(entry(provider)?)
+}
+
+pub fn proxy_password() -> Result<Option<String>> {
+    read(keychain_entry(PROXY_ENTRY)?)
+}
+
+/// Store the proxy password, or remove it when `None`.
+pub fn set_proxy_password(password: Option<&str>) -> Result<()> {
+    let entry = keychain_entry(PROXY_ENTRY)?;
+    match password {
+        Some(password) => entry
+            .set_password(password)
+            .map_err(|e| Error::Keychain(e.to_string())),
+        None => remove(entry),
+    }
+}
+
+/// Refuse to send `provider`'s key anywhere but that provider's own host,
+/// over HTTPS, so a mistyped or hostile base URL cannot collect it.
+pub fn check_destination(provider: &str, url: &str) -> Result<()> {
+    let expected = network::host(ping_url(provider)?);
+    let secure = url
+        .trim_start()
+        .get(..8)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("https://"));
+    if secure && network::host(url.trim_start()).eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(Error::InvalidInput(format!(
+            "the {} API key is only sent to https://{}, not to {}",
+            provider, expected, url
+        )))
+    }
+}
+
+/// Attach `key` to a request the way `provider` expects it.
+pub fn authorize(request: ureq::Request, provider: &str, key: &str) -> ureq::Request {
+    if provider == "anthropic" {
+        request
+            .set("x-api-key", key)
+            .set("anthropic-version", "2023-06-01")
+    } else {
+        request.set("Authorization", &format!("Bearer {}", key))
+    }
+}
+
+/// Whether the provider accepts `key`. Network failures are errors, since
+/// they say nothing about the key.
+fn ping(client: &Client, provider: &str, key: &str) -> Result<bool> {
+    let request = client.get(ping_url(provider)?)?.timeout(PING_TIMEOUT);
+    match authorize(request, provider, key).call() {
+        Ok(response) => network::no_redirect(response).map(|_| true),
+        Err(ureq::Error::Status(401 | 403, _)) => Ok(false),
+        Err(ureq::Error::Status(code, _)) => Err(Error::Provider(format!(
+            "{} returned {} while checking the key",
+            provider, code
+        ))),
+        Err(ureq::Error::Transport(transport)) => Err(Error::Provider(transport.to_string())),
+    }
+}
+
+/// Validate `key` against the provider and store it in the keychain.
+#[tauri::command]
+pub async fn set_api_key(app: AppHandle, provider: String, key: String) -> Result<()> {
+    let key = key.trim().to_string();
+    if key.is_empty() {
+        return Err(Error::InvalidInput("API key is empty".into()));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let client = network::client(&db::connect(&app)?)?;
+        if !ping(&client, &provider, &key)? {
+            return Err(Error::InvalidInput(format!(
+                "{} rejected this API key",
+                provider
+            )));
+        }
+        entry(&provider)?
+            .set_password(&key)
+            .map_err(|e| Error::Keychain(e.to_string()))?;
+        audit::record(&db::connect(&app)?, "api_key_changed", &[&provider], None)
+    })
+    .await
+    .map_err(|e| Error::Provider(e.to_string()))?
+}
+
+/// Check the stored key still works.
+#[tauri::command]
+pub async fn test_api_key(app: AppHandle, provider: String) -> Result<KeyStatus> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let client = network::client(&db::connect(&app)?)?;
+        let key = api_key(&provider)?;
+        let valid = key.map(|key| ping(&client, &provider, &key)).transpose()?;
+        Ok(KeyStatus {
+            stored: valid.is_some(),
+            valid,
+            provider,
+        })
+    })
+    .await
+    .map_err(|e| Error::Provider(e.to_string()))?
+}
+
+#[tauri::command]
+pub fn delete_api_key(app: AppHandle, provider: String) -> Result<()> {
+    remove(entry(&provider)?)?;
+    audit::record(&db::connect(&app)?, "api_key_deleted", &[&provider], None)
+}
@@ -0,0 +1,258 @@
+//! Timed, optionally speaker-labeled segments of stored transcriptions.
+
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::whisper::Word;
+use crate::{db, review};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredSegment {
+    pub id: String,
+    pub transcription_id: String,
+    pub position: i64,
+    pub speaker: Option<String>,
+    pub text: String,
+    /// Start time in seconds.
+    pub start: f64,
+    /// End time in seconds.
+    pub end: f64,
+    pub confidence: Option<f64>,
+    /// Word times recorded by the decoder, if any; see [`timed_words`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<Word>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentInput {
+    pub speaker: Option<String>,
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+    pub confidence: Option<f64>,
+    #[serde(default)]
+    pub words: Vec<Word>,
+}
+
+const COLUMNS: &str =
+    "id, transcription_id, position, speaker, text, start_time, end_time, confidence, words";
+
+fn from_row(row: &Row) -> rusqlite::Result<StoredSegment> {
+    Ok(StoredSegment {
+        id: row.get(0)?,
+        transcription_id: row.get(1)?,
+        position: row.get(2)?,
+        speaker: row.get(3)?,
+        text: row.get(4)?,
+        start: row.get(5)?,
+        end: row.get(6)?,
+        confidence: row.get(7)?,
+        words: row
+            .get::<_, Option<String>>(8)?
+            .and_then(|words| serde_json::from_str(&words).ok())
+            .unwrap_or_default(),
+    })
+}
+
+/// The segment's word times while they still spell its text, which an edit
+/// since decoding may have changed.
+pub fn timed_words(segment: &StoredSegment) -> Option<&[Word]> {
+    let spelled = segment
+        .words
+        .iter()
+        .flat_map(|word| word.text.split_whitespace());
+    (!segment.words.is_empty() && spelled.eq(segment.text.split_whitespace()))
+        .then_some(segment.words.as_slice())
+}
+
+/// Segments of a transcription in playback order.
+pub fn for_transcription(conn: &Connection, transcription_id: &str) -> Result<Vec<StoredSegment>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM segments WHERE transcription_id = ?1 ORDER BY position",
+        COLUMNS
+    ))?;
+    let segments = statement
+        .query_map([transcription_id], from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(segments)
+}
+
+/// Append `segments` to a transcription, numbered from `first_position`.
+/// Callers run this inside their own transaction.
+pub fn insert(
+    conn: &Connection,
+    transcription_id: &str,
+    first_position: i64,
+    segments: &[SegmentInput],
+) -> Result<()> {
+    for segment in segments {
+        if segment.end < segment.start {
+            return Err(Error::InvalidInput(format!(
+                "segment ends before it starts at {:.2}s",
+                segment.start
+            )));
+        }
+    }
+
+    let mut insert = conn.prepare(
+        "INSERT INTO segments (id, transcription_id, position, speaker, text, start_time, end_time, confidence, words)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?;
+    for (offset, segment) in segments.iter().enumerate() {
+        insert.execute(params![
+            uuid::Uuid::new_v4().to_string(),
+            transcription_id,
+            first_position + offset as i64,
+            segment.speaker,
+            segment.text.trim(),
+            segment.start,
+            segment.end,
+            segment.confidence,
+            (!segment.words.is_empty()).then(|| serde_json::to_string(&segment.words).unwrap())
+        ])?;
+    }
+    Ok(())
+}
+
+/// Set a transcription's text to its segments' text, after a segment was
+/// edited. Transcriptions without segments keep their text.
+pub fn sync_text(conn: &Connection, transcription_id: &str) -> Result<()> {
+    let segments = for_transcription(conn, transcription_id)?;
+    if segments.is_empty() {
+        return Ok(());
+    }
+    let text = segments
+        .iter()
+        .map(|segment| segment.text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    conn.execute(
+        "UPDATE transcriptions SET text = ?2 WHERE id = ?1",
+        params![transcription_id, text],
+    )?;
+    Ok(())
+}
+
+/// The stored segment `keep` accepts that overlaps `segment` the most.
+fn overlapping<'a>(
+    stored: &'a [StoredSegment],
+    segment: &SegmentInput,
+    keep: impl Fn(&StoredSegment) -> bool,
+) -> Option<&'a StoredSegment> {
+    stored
+        .iter()
+        .filter(|old| keep(old))
+        .map(|old| (old.end.min(segment.end) - old.start.max(segment.start), old))
+        .filter(|(overlap, _)| *overlap > 0.0)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, old)| old)
+}
+
+/// The speaker of the stored segment overlapping `segment` the most.
+fn overlapping_speaker(stored: &[StoredSegment], segment: &SegmentInput) -> Option<String> {
+    overlapping(stored, segment, |old| old.speaker.is_some()).and_then(|old| old.speaker.clone())
+}
+
+/// Replace every segment of a transcription in one transaction. Segments
+/// given without a speaker or word times keep those of the stored segment
+/// they overlap most, so saving edited text does not drop speaker labels.
+/// Carried word times apply only while they still spell the text.
+pub fn replace(
+    conn: &mut Connection,
+    transcription_id: &str,
+    segments: &[SegmentInput],
+) -> Result<()> {
+    let tx = conn.transaction()?;
+    let stored = for_transcription(&tx, transcription_id)?;
+    let segments: Vec<SegmentInput> = segments
+        .iter()
+        .map(|segment| SegmentInput {
+            speaker: segment
+                .speaker
+                .clone()
+                .or_else(|| overlapping_speaker(&stored, segment)),
+            words: if segment.words.is_empty() {
+                overlapping(&stored, segment, |old| !old.words.is_empty())
+                    .map(|old| old.words.clone())
+                    .unwrap_or_default()
+            } else {
+                segment.words.clone()
+            },
+            ..segment.clone()
+        })
+        .collect();
+    tx.execute(
+        "DELETE FROM segments WHERE transcription_id = ?1",
+        [transcription_id],
+    )?;
+    insert(&tx, transcription_id, 0, &segments)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Replace a transcription's segments and re-flag them for review.
+#[tauri::command]
+pub fn save_segments(
+    app: AppHandle,
+    transcription_id: String,
+    segments: Vec<SegmentInput>,
+) -> Result<()> {
+    let mut conn = db::connect(&app)?;
+    db::transcription_text(&conn, &transcription_id)?;
+    replace(&mut conn, &transcription_id, &segments)?;
+    review::flag(
+        &mut conn,
+        &transcription_id,
+        None,
+        review::DEFAULT_THRESHOLD,
+    )?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_segments(app: AppHandle, transcription_id: String) -> Result<Vec<StoredSegment>> {
+    for_transcription(&db::connect(&app)?, &transcription_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carries_speakers_over_by_overlap() {
+        let stored = |speaker: &str, start: f64, end: f64| StoredSegment {
+            id: String::new(),
+            transcription_id: "t1".into(),
+            position: 0,
+            speaker: Some(speaker.into()),
+            text: String::new(),
+            start,
+            end,
+            confidence: None,
+            words: Vec::new(),
+        };
+        let input = |start: f64, end: f64| SegmentInput {
+            speaker: None,
+            text: String::new(),
+            start,
+            end,
+            confidence: None,
+            words: Vec::new(),
+        };
+        let stored = [stored("Ann", 0.0, 4.0), stored("Bob", 4.0, 9.0)];
+        assert_eq!(
+            overlapping_speaker(&stored, &input(3.0, 8.0)),
+            Some("Bob".into())
+        );
+        assert_eq!(
+            overlapping_speaker(&stored, &input(0.5, 4.5)),
+            Some("Ann".into())
+        );
+        assert_eq!(overlapping_speaker(&stored, &input(10.0, 12.0)), None);
+    }
+}
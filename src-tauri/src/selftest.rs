@@ -0,0 +1,294 @@
+//! End-to-end self-test of an install, for support.
+//!
+//! A bundled five-second sample goes through every stage a real file does:
+//! decoding, voice activity detection, transcription with the smallest
+//! downloaded model, storage and export. Each stage reports its timing and
+//! whether it passed; once one fails, the ones after it are skipped.
+//!
+//! The sample is synthesized voiced audio rather than a recording, so the
+//! test checks that each stage completes, not what the transcript says.
+//! The stored rows are rolled back and the exported file removed, so the
+//! test leaves nothing behind.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::bulk::{self, ExportFormat};
+use crate::error::{Error, Result};
+use crate::segments::{self, SegmentInput};
+use crate::transcription::{self, TranscriptionOutput};
+use crate::whisper::DecodeOptions;
+use crate::{audio, audio_files, db, models, vad};
+
+const SAMPLE_RESOURCE: &str = "resources/self-test.wav";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Decode,
+    Vad,
+    Transcribe,
+    Store,
+    Export,
+}
+
+const STAGES: [Stage; 5] = [
+    Stage::Decode,
+    Stage::Vad,
+    Stage::Transcribe,
+    Stage::Store,
+    Stage::Export,
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageResult {
+    pub stage: Stage,
+    pub passed: bool,
+    /// Not run because an earlier stage failed.
+    pub skipped: bool,
+    pub duration_ms: u64,
+    /// What the stage produced, e.g. the number of segments.
+    pub detail: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub app_version: String,
+    /// Model the transcription stage used.
+    pub model: Option<String>,
+    pub total_ms: u64,
+    pub stages: Vec<StageResult>,
+}
+
+/// Results of the stages run so far.
+#[derive(Default)]
+struct Stages(Vec<StageResult>);
+
+impl Stages {
+    /// Run `stage` unless an earlier one failed. `run` returns its output
+    /// and a short description of it.
+    fn run<T>(&mut self, stage: Stage, run: impl FnOnce() -> Result<(T, String)>) -> Option<T> {
+        if self.0.iter().any(|result| !result.passed) {
+            return None;
+        }
+        let started = Instant::now();
+        let outcome = run();
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let (output, detail, error) = match outcome {
+            Ok((output, detail)) => (Some(output), Some(detail), None),
+            Err(err) => (None, None, Some(err.to_string())),
+        };
+        self.0.push(StageResult {
+            stage,
+            passed: output.is_some(),
+            skipped: false,
+            duration_ms,
+            detail,
+            error,
+        });
+        output
+    }
+
+    /// Every stage in order, with the ones not run marked skipped.
+    fn finish(mut self) -> Vec<StageResult> {
+        for stage in STAGES {
+            if !self.0.iter().any(|result| result.stage == stage) {
+                self.0.push(StageResult {
+                    stage,
+                    passed: false,
+                    skipped: true,
+                    duration_ms: 0,
+                    detail: None,
+                    error: None,
+                });
+            }
+        }
+        self.0
+    }
+}
+
+/// Store the output as a transcription inside a transaction, read it back
+/// and roll it back.
+fn store(app: &AppHandle, sample: &Path, output: &TranscriptionOutput) -> Result<String> {
+    let mut conn = db::connect(app)?;
+    let tx = conn.transaction()?;
+    let (audio_file_id, id) = (
+        uuid::Uuid::new_v4().to_string(),
+        uuid::Uuid::new_v4().to_string(),
+    );
+    audio_files::import(&tx, &audio_file_id, sample, None)?;
+    tx.execute(
+        "INSERT INTO transcriptions (id, audio_file_id, text, language, model_used, duration)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![
+            id,
+            audio_file_id,
+            output.text,
+            output.language,
+            output.model_used,
+            output.duration
+        ],
+    )?;
+    let inputs: Vec<SegmentInput> = output
+        .segments
+        .iter()
+        .map(|segment| SegmentInput {
+            speaker: None,
+            text: segment.text.clone(),
+            start: segment.start,
+            end: segment.end,
+            confidence: segment.confidence.map(f64::from),
+            words: segment.words.clone(),
+        })
+        .collect();
+    segments::insert(&tx, &id, 0, &inputs)?;
+    let stored = segments::for_transcription(&tx, &id)?.len();
+    if db::transcription_text(&tx, &id)? != output.text || stored != inputs.len() {
+        return Err(Error::Io(std::io::Error::other(
+            "the stored transcription did not read back as written",
+        )));
+    }
+    tx.rollback()?;
+    Ok(format!("{} segments written and read back", stored))
+}
+
+/// Export the output to a scratch folder and remove it again.
+fn export(sample: &Path, output: &TranscriptionOutput) -> Result<String> {
+    let dir = std::env::temp_dir().join(format!("transcriber-self-test-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir)?;
+    let written = bulk::write_output(
+        output,
+        sample,
+        ExportFormat::Json,
+        &dir,
+        "{source}",
+        &Default::default(),
+    )
+    .and_then(|path| Ok(fs::metadata(path)?.len()));
+    let _ = fs::remove_dir_all(&dir);
+    Ok(format!("{} bytes of JSON", written?))
+}
+
+fn sample_path(app: &AppHandle) -> Result<PathBuf> {
+    app.path_resolver()
+        .resolve_resource(SAMPLE_RESOURCE)
+        .filter(|path| path.is_file())
+        .ok_or_else(|| Error::NotFound(format!("bundled sample {}", SAMPLE_RESOURCE)))
+}
+
+/// Run the sample through the whole pipeline and report each stage.
+#[tauri::command]
+pub async fn run_self_test(app: AppHandle) -> Result<SelfTestReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let started = Instant::now();
+        let mut stages = Stages::default();
+        let mut model = None;
+
+        let decoded = stages.run(Stage::Decode, || {
+            let sample = sample_path(&app)?;
+            let pcm = audio::load_pcm(&sample)?;
+            let detail = format!("{:.1}s of audio", audio::duration_secs(&pcm));
+            Ok(((sample, pcm), detail))
+        });
+        let Some((sample, pcm)) = decoded else {
+            return Ok(report(&app, None, started, stages));
+        };
+        stages.run(Stage::Vad, || {
+            let regions = vad::speech_regions(&pcm);
+            let speech: usize = regions.iter().map(|region| region.len()).sum();
+            if regions.is_empty() {
+                return Err(Error::Decode("no speech detected in the sample".into()));
+            }
+            let detail = format!(
+                "{} speech regions, {:.1}s of speech",
+                regions.len(),
+                audio::duration_secs(&pcm[..speech])
+            );
+            Ok(((), detail))
+        });
+        let output = stages.run(Stage::Transcribe, || {
+            let name = models::downloaded_models(&app)?
+                .into_iter()
+                .min_by_key(|model| model.size_bytes)
+                .map(|model| model.name)
+                .ok_or_else(|| Error::NotFound("a downloaded whisper model".into()))?;
+            model = Some(name.clone());
+            let decode_started = Instant::now();
+            let options = DecodeOptions {
+                language: Some("en".into()),
+                ..Default::default()
+            };
+            let output = transcription::transcribe_pcm(&app, &pcm, Some(&name), &options, false)?;
+            let detail = format!(
+                "{} segments, {:.2}x real time",
+                output.segments.len(),
+                decode_started.elapsed().as_secs_f64() / output.duration.max(0.001)
+            );
+            Ok((output, detail))
+        });
+        if let Some(output) = output {
+            stages.run(Stage::Store, || Ok(((), store(&app, &sample, &output)?)));
+            stages.run(Stage::Export, || Ok(((), export(&sample, &output)?)));
+        }
+        Ok(report(&app, model, started, stages))
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+fn report(
+    app: &AppHandle,
+    model: Option<String>,
+    started: Instant,
+    stages: Stages,
+) -> SelfTestReport {
+    let stages = stages.finish();
+    SelfTestReport {
+        passed: stages.iter().all(|stage| stage.passed),
+        app_version: app.package_info().version.to_string(),
+        model,
+        total_ms: started.elapsed().as_millis() as u64,
+        stages,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_stages_after_a_failure() {
+        let mut stages = Stages::default();
+        assert_eq!(stages.run(Stage::Decode, || Ok((1, "ok".into()))), Some(1));
+        let failed: Option<()> = stages.run(Stage::Vad, || {
+            Err(Error::Decode("no speech detected in the sample".into()))
+        });
+        assert!(failed.is_none());
+        assert_eq!(stages.run(Stage::Transcribe, || Ok((2, "ok".into()))), None);
+
+        let results = stages.finish();
+        let summary: Vec<_> = results
+            .iter()
+            .map(|result| (result.stage, result.passed, result.skipped))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Stage::Decode, true, false),
+                (Stage::Vad, false, false),
+                (Stage::Transcribe, false, true),
+                (Stage::Store, false, true),
+                (Stage::Export, false, true),
+            ]
+        );
+        assert!(results[1].error.as_deref().unwrap().contains("no speech"));
+    }
+}
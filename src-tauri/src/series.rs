@@ -0,0 +1,237 @@
+//! Decoder prompts carried over from the previous meeting in a series.
+//!
+//! A series is the set of transcriptions sharing a tag, such as a project
+//! name. Names and terms from the latest one (its speaker labels, and the
+//! capitalized words and acronyms used mid-sentence) become the initial
+//! prompt of the next transcription, so the decoder spells people and
+//! products the same way from one meeting to the next.
+//!
+//! Queued jobs take their series from their save target, watch folders
+//! from the folder's configured series, and a re-transcription from the
+//! tags the transcription already has.
+
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::AppHandle;
+
+use crate::error::Result;
+use crate::{db, segments};
+
+/// Terms kept in a prompt; whisper reads at most about 224 prompt tokens.
+const MAX_TERMS: usize = 40;
+const MAX_PROMPT_CHARS: usize = 600;
+
+/// Capitalized words that are not names.
+const COMMON: &[&str] = &[
+    "I",
+    "I'm",
+    "I'll",
+    "I've",
+    "I'd",
+    "OK",
+    "Okay",
+    "Mr",
+    "Mrs",
+    "Ms",
+    "Dr",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+fn is_generic_speaker(label: &str) -> bool {
+    let lower = label.to_lowercase();
+    lower.starts_with("speaker") || lower.starts_with("spreker") || lower == "unknown"
+}
+
+fn is_term(word: &str) -> bool {
+    let mut letters = word.chars().filter(|c| c.is_alphabetic());
+    let Some(first) = letters.next() else {
+        return false;
+    };
+    first.is_uppercase() && word.chars().count() > 1 && !COMMON.contains(&word)
+}
+
+/// Names and terms in `text`, most frequent first, after `speakers`.
+///
+/// Every sentence starts with a capital, so a run of capitalized words at
+/// the start of one only counts whole if it also appears mid-sentence, and
+/// otherwise without its first word.
+pub fn terms(text: &str, speakers: &[String]) -> Vec<String> {
+    // Runs of capitalized words: the words, whether the run opened a
+    // sentence, and where it ended.
+    let mut runs: Vec<(Vec<&str>, bool, usize)> = Vec::new();
+    let mut run: Vec<&str> = Vec::new();
+    let mut opens_sentence = false;
+    let mut sentence_start = true;
+    for (order, token) in text.split_whitespace().enumerate() {
+        let word = token.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'');
+        if is_term(word) {
+            if run.is_empty() {
+                opens_sentence = sentence_start;
+            }
+            run.push(word);
+        } else if !run.is_empty() {
+            runs.push((std::mem::take(&mut run), opens_sentence, order));
+        }
+        // Punctuation after a word ends its run.
+        if !run.is_empty() && token.ends_with(|c: char| !c.is_alphanumeric() && c != '\'') {
+            runs.push((std::mem::take(&mut run), opens_sentence, order));
+        }
+        sentence_start = token.ends_with(['.', '?', '!']);
+    }
+    if !run.is_empty() {
+        runs.push((run, opens_sentence, usize::MAX));
+    }
+
+    let known: Vec<String> = runs
+        .iter()
+        .filter(|(_, opens, _)| !opens)
+        .map(|(words, _, _)| words.join(" "))
+        .collect();
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+    for (words, opens, order) in &runs {
+        let joined = words.join(" ");
+        let term = if !opens || known.contains(&joined) {
+            joined
+        } else {
+            words[1..].join(" ")
+        };
+        if !term.is_empty() {
+            counts.entry(term).or_insert((0, *order)).0 += 1;
+        }
+    }
+
+    let mut ranked: Vec<(String, (usize, usize))> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.1 .1.cmp(&b.1 .1)));
+
+    let mut out: Vec<String> = Vec::new();
+    let named = speakers
+        .iter()
+        .map(|speaker| speaker.trim().to_string())
+        .filter(|speaker| !speaker.is_empty() && !is_generic_speaker(speaker));
+    for term in named.chain(ranked.into_iter().map(|(term, _)| term)) {
+        if !out.contains(&term) && out.len() < MAX_TERMS {
+            out.push(term);
+        }
+    }
+    out
+}
+
+/// Decoder prompt listing `terms`, cut to whole terms within the limit.
+pub fn prompt_for(terms: &[String]) -> Option<String> {
+    let mut listed = String::new();
+    for term in terms {
+        if listed.len() + term.len() + 2 > MAX_PROMPT_CHARS {
+            break;
+        }
+        if !listed.is_empty() {
+            listed.push_str(", ");
+        }
+        listed.push_str(term);
+    }
+    (!listed.is_empty()).then(|| format!("Names and terms: {}.", listed))
+}
+
+/// The latest transcription tagged `series`, other than `except`.
+fn previous(conn: &Connection, series: &str, except: Option<&str>) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT t.id FROM transcriptions t
+             JOIN transcription_tags g ON g.transcription_id = t.id
+             WHERE g.tag = ?1 AND t.id IS NOT ?2
+             ORDER BY COALESCE(t.recording_started_at, t.created_at) DESC LIMIT 1",
+            params![series, except],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Prompt from the previous meeting in `series`, if it has one.
+pub fn prompt(conn: &Connection, series: &str) -> Result<Option<String>> {
+    match previous(conn, series, None)? {
+        Some(id) => prompt_from(conn, &id),
+        None => Ok(None),
+    }
+}
+
+/// Prompt for transcribing `transcription_id` again: from the latest other
+/// meeting sharing one of its tags, if there is one.
+pub fn prompt_for_transcription(
+    conn: &Connection,
+    transcription_id: &str,
+) -> Result<Option<String>> {
+    let tags: Vec<String> = conn
+        .prepare("SELECT tag FROM transcription_tags WHERE transcription_id = ?1 ORDER BY tag")?
+        .query_map([transcription_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for tag in tags {
+        if let Some(id) = previous(conn, &tag, Some(transcription_id))? {
+            return prompt_from(conn, &id);
+        }
+    }
+    Ok(None)
+}
+
+/// Prompt listing the speakers, names and terms of transcription `id`.
+fn prompt_from(conn: &Connection, id: &str) -> Result<Option<String>> {
+    let text = db::transcription_text(conn, id)?;
+    let mut speakers: Vec<String> = Vec::new();
+    for speaker in segments::for_transcription(conn, id)?
+        .into_iter()
+        .filter_map(|segment| segment.speaker)
+    {
+        if !speakers.contains(&speaker) {
+            speakers.push(speaker);
+        }
+    }
+    Ok(prompt_for(&terms(&text, &speakers)))
+}
+
+/// The prompt the next transcription in `series` would start with.
+#[tauri::command]
+pub fn get_series_prompt(app: AppHandle, series: String) -> Result<Option<String>> {
+    prompt(&db::connect(&app)?, &series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_names_used_mid_sentence() {
+        let text = "Thanks everyone. I spoke with Anna Jansen about the OKR review. \
+                    Anna thinks Kubernetes can wait until March. So Pieter, over to you. \
+                    Anna Jansen will follow up.";
+        assert_eq!(
+            terms(text, &["Pieter".into(), "Speaker 2".into()]),
+            ["Pieter", "Anna Jansen", "OKR", "Kubernetes"]
+        );
+    }
+
+    #[test]
+    fn prompt_keeps_whole_terms() {
+        let terms: Vec<String> = (0..200).map(|n| format!("Term{}", n)).collect();
+        let prompt = prompt_for(&terms).unwrap();
+        assert!(prompt.len() <= MAX_PROMPT_CHARS + "Names and terms: .".len());
+        assert!(prompt.ends_with(|c: char| c.is_ascii_digit() || c == '.'));
+        assert_eq!(prompt_for(&[]), None);
+    }
+}
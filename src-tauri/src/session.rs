@@ -0,0 +1,251 @@
+//! Where the user left off, restored on the next launch.
+//!
+//! The frontend saves the open transcript, playback position and its own
+//! view state whenever they change; the backend adds the jobs still waiting
+//! in the queue and the recordings still capturing, and refreshes its part
+//! once more when the main window closes. On restore, the jobs are queued
+//! again if they still pass the pre-flight checks, and recordings cut short
+//! by the app closing are made playable, since their WAV headers were
+//! never finalized.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::jobs::{self, JobKind, JobPriority, JobQueue};
+use crate::{crash, recording};
+
+const SESSION_FILE: &str = "session.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackPosition {
+    pub path: PathBuf,
+    /// Seconds.
+    pub position: f64,
+}
+
+/// A recording that was still capturing when the session was saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingDraft {
+    pub session_id: String,
+    pub tracks: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingJob {
+    pub kind: JobKind,
+    pub priority: JobPriority,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SessionState {
+    pub open_transcription_id: Option<String>,
+    pub playback: Option<PlaybackPosition>,
+    /// Filled in by the backend when saving.
+    pub recording_drafts: Vec<RecordingDraft>,
+    /// Filled in by the backend when saving.
+    pub pending_jobs: Vec<PendingJob>,
+    /// View state only the frontend interprets, such as open panels.
+    pub ui: serde_json::Value,
+    pub saved_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoredSession {
+    pub state: SessionState,
+    /// Ids of the jobs queued again, in the order they were saved.
+    pub requeued_jobs: Vec<String>,
+    pub rejected_jobs: Vec<RejectedJob>,
+}
+
+/// A saved job that was not queued again, e.g. because its file is gone.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedJob {
+    pub kind: JobKind,
+    pub error: String,
+}
+
+fn session_path(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| Error::NotFound("app data directory".into()))?;
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(SESSION_FILE))
+}
+
+/// Write through a temporary file so a crash mid-write keeps the last
+/// complete session.
+fn write(path: &Path, state: &SessionState) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(state).unwrap())?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+fn drafts(app: &AppHandle) -> Result<Vec<RecordingDraft>> {
+    let dir = recording::recordings_dir(app)?;
+    let mut drafts = Vec::new();
+    for session_id in recording::active_sessions(app) {
+        let mut tracks: Vec<PathBuf> = fs::read_dir(dir.join(&session_id))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "wav"))
+            .collect();
+        tracks.sort();
+        drafts.push(RecordingDraft { session_id, tracks });
+    }
+    Ok(drafts)
+}
+
+/// Patch the RIFF and data sizes of a WAV file whose writer never
+/// finalized it. Only the plain 44-byte PCM header the recorder writes is
+/// handled; returns whether the header was patched. The sizes of files
+/// past the 4 GB a WAV header can describe are set to the maximum, which
+/// most players read as "until the end of the file".
+pub fn repair_wav(path: &Path) -> Result<bool> {
+    let len = fs::metadata(path)?.len();
+    let mut header = [0u8; 44];
+    if len < 44 {
+        return Ok(false);
+    }
+    File::open(path)?.read_exact(&mut header)?;
+    let plain_header = &header[0..4] == b"RIFF"
+        && &header[8..16] == b"WAVEfmt "
+        && header[16..20] == 16u32.to_le_bytes()
+        && &header[36..40] == b"data";
+    if !plain_header {
+        return Ok(false);
+    }
+    let size = |bytes: u64| u32::try_from(bytes).unwrap_or(u32::MAX).to_le_bytes();
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&size(len - 8))?;
+    file.seek(SeekFrom::Start(40))?;
+    file.write_all(&size(len - 44))?;
+    Ok(true)
+}
+
+fn save(app: &AppHandle, queue: &JobQueue, mut state: SessionState) -> Result<()> {
+    state.recording_drafts = drafts(app)?;
+    state.pending_jobs = queue
+        .unfinished()
+        .into_iter()
+        .map(|(kind, priority)| PendingJob { kind, priority })
+        .collect();
+    state.saved_at = Some(Local::now().to_rfc3339_opts(SecondsFormat::Secs, false));
+    write(&session_path(app)?, &state)
+}
+
+/// Refresh the backend's part of the saved session as the window closes,
+/// keeping what the frontend last saved.
+pub fn save_on_close(app: &AppHandle) {
+    let saved = session_path(app).and_then(|path| Ok(fs::read(path)?));
+    let state = saved
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+    if let Err(err) = save(app, &app.state::<Arc<JobQueue>>(), state) {
+        crash::log(format!("could not save the session: {}", err));
+    }
+}
+
+/// Save where the user is. The backend's part of the state replaces
+/// whatever the frontend sent for it.
+#[tauri::command]
+pub fn save_session(
+    app: AppHandle,
+    queue: State<'_, Arc<JobQueue>>,
+    state: SessionState,
+) -> Result<()> {
+    save(&app, &queue, state)
+}
+
+/// The last saved session, if any, with its pending jobs queued again.
+/// Jobs that no longer pass the pre-flight checks are reported instead.
+/// Restoring twice does not queue them twice.
+#[tauri::command]
+pub fn restore_session(app: AppHandle) -> Result<Option<RestoredSession>> {
+    let path = session_path(&app)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut state: SessionState = match serde_json::from_slice(&fs::read(&path)?) {
+        Ok(state) => state,
+        Err(err) => {
+            crash::log(format!("discarding unreadable session: {}", err));
+            fs::remove_file(&path)?;
+            return Ok(None);
+        }
+    };
+
+    let active = recording::active_sessions(&app);
+    state
+        .recording_drafts
+        .retain(|draft| !active.contains(&draft.session_id));
+    for track in state.recording_drafts.iter().flat_map(|d| &d.tracks) {
+        if let Err(err) = repair_wav(track) {
+            crash::log(format!("could not repair {}: {}", track.display(), err));
+        }
+    }
+
+    let mut requeued_jobs = Vec::new();
+    let mut rejected_jobs = Vec::new();
+    for job in state.pending_jobs.drain(..) {
+        match jobs::enqueue_checked(&app, job.kind.clone(), job.priority) {
+            Ok(id) => requeued_jobs.push(id),
+            Err(err) => rejected_jobs.push(RejectedJob {
+                kind: job.kind,
+                error: err.to_string(),
+            }),
+        }
+    }
+    write(&path, &state)?;
+    Ok(Some(RestoredSession {
+        state,
+        requeued_jobs,
+        rejected_jobs,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patches_sizes_of_an_unfinalized_wav() {
+        let path = std::env::temp_dir().join(format!("draft-{}.wav", uuid::Uuid::new_v4()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for n in 0..1600 {
+            writer.write_sample(n as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        // Zero the sizes, as left by a writer that never finalized.
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[4..8].fill(0);
+        bytes[40..44].fill(0);
+        fs::write(&path, bytes).unwrap();
+
+        assert!(repair_wav(&path).unwrap());
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.duration(), 1600);
+        fs::remove_file(path).unwrap();
+    }
+}
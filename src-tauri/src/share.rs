@@ -0,0 +1,498 @@
+//! Self-contained HTML pages for sharing a transcript read-only.
+//!
+//! The page needs no app or network access: styles and script are inline,
+//! and audio, when included, is embedded as a data URI. Clicking a
+//! timestamp seeks the embedded player. Reviewer comments are listed after
+//! the transcript, each linked to the point it refers to.
+//!
+//! A read-along page also highlights each word as it is spoken and keeps
+//! it in view, at the word times whisper decoded. Segments without them,
+//! from older transcripts or edited since, have their words spread over
+//! the segment in proportion to length: there the highlight can run ahead
+//! of or behind the speaker, most in segments with pauses.
+//!
+//! Embedded audio is refused past [`MAX_EMBED_BYTES`], which browsers
+//! struggle to load as a data URI.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use rusqlite::OptionalExtension;
+use tauri::AppHandle;
+
+use crate::comments::{self, Comment};
+use crate::error::{Error, Result};
+use crate::segments::{self, StoredSegment};
+use crate::{archive, audio_files, clips, db, i18n, parts};
+
+/// Largest audio file embedded in a page; about 80 minutes of the WAV
+/// assembled for split and merged transcriptions.
+const MAX_EMBED_BYTES: u64 = 150 * 1024 * 1024;
+
+const STYLE: &str = "body{font-family:-apple-system,Segoe UI,Roboto,sans-serif;max-width:760px;\
+margin:2rem auto;padding:0 1rem;color:#222;line-height:1.55}\
+h1{font-size:1.5rem}h2{font-size:1.15rem;margin-top:2rem}\
+.meta{color:#666;font-size:.9rem}.summary{background:#f5f7fa;padding:1rem;border-radius:6px}\
+audio{width:100%;position:sticky;top:0;background:#fff;padding:.5rem 0}\
+.segment{margin:.4rem 0}.segment.active{background:#fff6d5}\
+.time{font-family:monospace;color:#06c;text-decoration:none;margin-right:.5rem}\
+.speaker{font-weight:600;margin-right:.35rem}\
+.w{cursor:pointer;border-radius:3px}.w.active{background:#ffd95a}";
+
+const SCRIPT: &str = "const a=document.querySelector('audio');\
+document.querySelectorAll('.time').forEach(t=>t.addEventListener('click',e=>{\
+if(!a)return;e.preventDefault();a.currentTime=+t.dataset.start;a.play();}));\
+if(a)a.addEventListener('timeupdate',()=>document.querySelectorAll('.segment').forEach(s=>\
+s.classList.toggle('active',a.currentTime>=+s.dataset.start&&a.currentTime<+s.dataset.end)));";
+
+/// Highlights the word being spoken, checked every frame while playing
+/// since `timeupdate` fires only a few times a second.
+const READ_ALONG_SCRIPT: &str = "const ws=[...document.querySelectorAll('.w')];let cur=null;\
+ws.forEach(w=>w.addEventListener('click',()=>{if(!a)return;a.currentTime=+w.dataset.start;a.play();}));\
+function tick(){const t=a.currentTime;let lo=0,hi=ws.length-1,hit=null;\
+while(lo<=hi){const m=(lo+hi)>>1;if(+ws[m].dataset.start>t)hi=m-1;else{hit=ws[m];lo=m+1;}}\
+if(hit&&t>=+hit.dataset.end)hit=null;if(hit!==cur){if(cur)cur.classList.remove('active');\
+if(hit){hit.classList.add('active');hit.scrollIntoView({block:'center',behavior:'smooth'});}cur=hit;}\
+if(!a.paused)requestAnimationFrame(tick);}\
+if(a){a.addEventListener('play',()=>requestAnimationFrame(tick));a.addEventListener('seeked',tick);}";
+
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn timestamp(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    if total >= 3600 {
+        format!("{}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60)
+    } else {
+        format!("{:02}:{:02}", total / 60, total % 60)
+    }
+}
+
+/// Words of a segment with start and end times: the decoded ones when
+/// they still match the text, otherwise spread over the segment in
+/// proportion to their length plus one character for the space after.
+/// That is an estimate: silences inside the segment are spread over its
+/// words.
+fn word_times(segment: &StoredSegment) -> Vec<(&str, f64, f64)> {
+    if let Some(timed) = segments::timed_words(segment) {
+        return timed
+            .iter()
+            .flat_map(|word| {
+                word.text
+                    .split_whitespace()
+                    .map(|text| (text, word.start, word.end))
+            })
+            .collect();
+    }
+    let words: Vec<&str> = segment.text.split_whitespace().collect();
+    let total: usize = words.iter().map(|word| word.chars().count() + 1).sum();
+    let per_char = (segment.end - segment.start).max(0.0) / total.max(1) as f64;
+    let mut at = segment.start;
+    words
+        .into_iter()
+        .map(|word| {
+            let end = at + (word.chars().count() + 1) as f64 * per_char;
+            let timed = (word, at, end);
+            at = end;
+            timed
+        })
+        .collect()
+}
+
+/// Refuse audio of `bytes` too large to embed.
+fn check_embed_size(bytes: u64) -> Result<()> {
+    if bytes > MAX_EMBED_BYTES {
+        return Err(Error::InvalidInput(format!(
+            "the audio is {} MB, more than the {} MB a shared page can embed; \
+             choose a compressed or shorter audio file",
+            bytes / (1024 * 1024),
+            MAX_EMBED_BYTES / (1024 * 1024)
+        )));
+    }
+    Ok(())
+}
+
+/// Bytes of `path`, once its size is checked.
+fn read_embed(path: &Path) -> Result<Vec<u8>> {
+    check_embed_size(fs::metadata(path)?.len())?;
+    Ok(fs::read(path)?)
+}
+
+fn audio_mime(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("mp3") => "audio/mpeg",
+        Some("m4a") | Some("mp4") => "audio/mp4",
+        Some("ogg") => "audio/ogg",
+        Some("flac") => "audio/flac",
+        Some("webm") => "audio/webm",
+        _ => "audio/wav",
+    }
+}
+
+pub struct SharePage<'a> {
+    pub title: &'a str,
+    pub created_at: &'a str,
+    pub text: &'a str,
+    pub segments: &'a [StoredSegment],
+    pub summary: Option<&'a str>,
+    pub comments: &'a [Comment],
+    /// Data URI of the embedded audio.
+    pub audio: Option<String>,
+    /// Highlight each word in time with the audio.
+    pub read_along: bool,
+}
+
+pub fn render(page: &SharePage) -> String {
+    let mut body = format!(
+        "<h1>{}</h1>\n<p class=\"meta\">{}</p>\n",
+        escape_html(page.title),
+        escape_html(page.created_at)
+    );
+    if let Some(audio) = &page.audio {
+        body.push_str(&format!(
+            "<audio controls preload=\"metadata\" src=\"{}\"></audio>\n",
+            audio
+        ));
+    }
+    if let Some(summary) = page.summary {
+        body.push_str(&format!(
+            "<h2>{}</h2>\n<div class=\"summary\">{}</div>\n",
+            escape_html(&i18n::t("heading-summary")),
+            escape_html(summary).replace('\n', "<br>")
+        ));
+    }
+
+    body.push_str(&format!(
+        "<h2>{}</h2>\n",
+        escape_html(&i18n::t("heading-transcript"))
+    ));
+    if page.segments.is_empty() {
+        for paragraph in page.text.split("\n\n").filter(|p| !p.trim().is_empty()) {
+            body.push_str(&format!("<p>{}</p>\n", escape_html(paragraph.trim())));
+        }
+    }
+    for segment in page.segments {
+        let text = if page.read_along {
+            word_times(segment)
+                .into_iter()
+                .map(|(word, start, end)| {
+                    format!(
+                        "<span class=\"w\" data-start=\"{:.2}\" data-end=\"{:.2}\">{}</span>",
+                        start,
+                        end,
+                        escape_html(word)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        } else {
+            escape_html(&segment.text)
+        };
+        let speaker = segment
+            .speaker
+            .as_deref()
+            .map(|name| format!("<span class=\"speaker\">{}:</span>", escape_html(name)))
+            .unwrap_or_default();
+        body.push_str(&format!(
+            "<p class=\"segment\" data-start=\"{start}\" data-end=\"{end}\">\
+             <a class=\"time\" href=\"#t={start}\" data-start=\"{start}\">{stamp}</a>{speaker}{text}</p>\n",
+            start = segment.start,
+            end = segment.end,
+            stamp = timestamp(segment.start),
+            speaker = speaker,
+            text = text,
+        ));
+    }
+    if !page.comments.is_empty() {
+        body.push_str(&format!(
+            "<h2>{}</h2>\n<ul class=\"comments\">\n",
+            escape_html(&i18n::t("heading-comments"))
+        ));
+        for comment in page.comments {
+            let start = comment.anchor_ms as f64 / 1000.0;
+            body.push_str(&format!(
+                "<li><a class=\"time\" href=\"#t={start}\" data-start=\"{start}\">{stamp}</a>\
+                 <span class=\"speaker\">{author}:</span>{text}</li>\n",
+                start = start,
+                stamp = timestamp(start),
+                author = escape_html(&comment.author),
+                text = escape_html(&comment.text),
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+    let script = if page.read_along {
+        format!("{}{}", SCRIPT, READ_ALONG_SCRIPT)
+    } else {
+        SCRIPT.to_string()
+    };
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}<script>{}</script>\n</body>\n</html>\n",
+        i18n::language(),
+        escape_html(page.title),
+        STYLE,
+        body,
+        script
+    )
+}
+
+/// Write a page for transcription `id` to `path`.
+fn write_page(
+    app: &AppHandle,
+    id: &str,
+    path: &Path,
+    audio_path: Option<PathBuf>,
+    title: Option<String>,
+    read_along: bool,
+) -> Result<()> {
+    let conn = db::connect(app)?;
+    let (text, created_at): (String, String) = conn
+        .query_row(
+            "SELECT text, created_at FROM transcriptions WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("transcription {}", id)))?;
+    let summary: Option<String> = conn
+        .query_row(
+            "SELECT summary FROM summaries WHERE transcription_id = ?1
+             ORDER BY created_at DESC LIMIT 1",
+            [id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let segments = segments::for_transcription(&conn, id)?;
+    let comments = comments::for_transcription(&conn, id)?;
+    if read_along && segments.is_empty() {
+        return Err(Error::InvalidInput(
+            "a read-along page needs a transcription with timed segments".into(),
+        ));
+    }
+    // Read-along pages are nothing without audio, so they fall back to
+    // the transcription's own source file, or for a split or merged
+    // transcription to the audio it covers.
+    let audio = match audio_path {
+        Some(audio_path) => Some((read_embed(&audio_path)?, audio_mime(&audio_path))),
+        None if read_along && !parts::stored(&conn, id)?.is_empty() => {
+            let pcm = clips::source_pcm(&conn, id)?;
+            // 16-bit samples after a 44-byte header.
+            check_embed_size(pcm.len() as u64 * 2 + 44)?;
+            let wav = std::env::temp_dir().join(format!("share-{}.wav", uuid::Uuid::new_v4()));
+            archive::write_wav(&pcm, &wav)?;
+            let bytes = fs::read(&wav);
+            let _ = fs::remove_file(&wav);
+            Some((bytes?, "audio/wav"))
+        }
+        None if read_along => {
+            let audio_file_id: String = conn.query_row(
+                "SELECT audio_file_id FROM transcriptions WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )?;
+            let source = audio_files::get(&conn, &audio_file_id)?.path;
+            if !source.is_file() {
+                return Err(Error::NotFound(format!("audio file {}", source.display())));
+            }
+            Some((read_embed(&source)?, audio_mime(&source)))
+        }
+        None => None,
+    };
+
+    let audio = audio.map(|(bytes, mime)| {
+        format!(
+            "data:{};base64,{}",
+            mime,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        )
+    });
+
+    let default_title = i18n::t("default-title");
+    let html = render(&SharePage {
+        title: title.as_deref().unwrap_or(&default_title),
+        created_at: &created_at,
+        text: &text,
+        segments: &segments,
+        summary: summary.as_deref(),
+        comments: &comments,
+        audio,
+        read_along,
+    });
+    fs::write(path, html)?;
+    Ok(())
+}
+
+/// Write a read-only HTML page for a transcription to `path`.
+///
+/// `audio_path` embeds the recording, which makes the file roughly as large
+/// as the audio itself; audio over 150 MB is refused.
+#[tauri::command]
+pub async fn export_share_page(
+    app: AppHandle,
+    id: String,
+    path: PathBuf,
+    audio_path: Option<PathBuf>,
+    title: Option<String>,
+) -> Result<PathBuf> {
+    tauri::async_runtime::spawn_blocking(move || {
+        write_page(&app, &id, &path, audio_path, title, false)?;
+        Ok(path)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+/// Write an HTML page whose words light up as the embedded audio plays.
+/// The audio defaults to the transcription's source file. Word times are
+/// estimated in segments decoded without them, so the highlight there is
+/// approximate.
+#[tauri::command]
+pub async fn export_read_along_page(
+    app: AppHandle,
+    id: String,
+    path: PathBuf,
+    audio_path: Option<PathBuf>,
+    title: Option<String>,
+) -> Result<PathBuf> {
+    tauri::async_runtime::spawn_blocking(move || {
+        write_page(&app, &id, &path, audio_path, title, true)?;
+        Ok(path)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::whisper::Word;
+
+    #[test]
+    fn escapes_transcript_text_and_links_timestamps() {
+        let segments = [StoredSegment {
+            id: "s".into(),
+            transcription_id: "t".into(),
+            position: 0,
+            speaker: Some("Ana".into()),
+            text: "<b>hi</b> & bye".into(),
+            start: 65.0,
+            end: 67.5,
+            confidence: None,
+            words: Vec::new(),
+        }];
+        let html = render(&SharePage {
+            title: "Q3 review",
+            created_at: "2024-01-01",
+            text: "",
+            segments: &segments,
+            summary: None,
+            comments: &[Comment {
+                id: "c".into(),
+                transcription_id: "t".into(),
+                anchor_ms: 66_000,
+                author: "Bo".into(),
+                text: "<sic>".into(),
+                created_at: String::new(),
+                updated_at: String::new(),
+            }],
+            audio: None,
+            read_along: false,
+        });
+
+        assert!(html.contains("&lt;b&gt;hi&lt;/b&gt; &amp; bye"));
+        assert!(html.contains("data-start=\"65\">01:05</a>"));
+        assert!(!html.contains("<audio"));
+        assert!(html.contains(
+            "data-start=\"66\">01:06</a><span class=\"speaker\">Bo:</span>&lt;sic&gt;</li>"
+        ));
+    }
+
+    #[test]
+    fn spreads_words_over_their_segment() {
+        let segment = StoredSegment {
+            id: "s".into(),
+            transcription_id: "t".into(),
+            position: 0,
+            speaker: None,
+            text: " Hi there, <you>".into(),
+            start: 10.0,
+            end: 14.0,
+            confidence: None,
+            words: Vec::new(),
+        };
+        let words = word_times(&segment);
+        assert_eq!(
+            words,
+            [
+                ("Hi", 10.0, 10.75),
+                ("there,", 10.75, 12.5),
+                ("<you>", 12.5, 14.0)
+            ]
+        );
+
+        let html = render(&SharePage {
+            title: "Lecture",
+            created_at: "2024-01-01",
+            text: "",
+            segments: &[segment],
+            summary: None,
+            comments: &[],
+            audio: Some("data:audio/wav;base64,".into()),
+            read_along: true,
+        });
+        assert!(html
+            .contains("<span class=\"w\" data-start=\"10.75\" data-end=\"12.50\">there,</span>"));
+        assert!(html.contains("&lt;you&gt;</span>"));
+        assert!(html.contains("requestAnimationFrame"));
+    }
+
+    #[test]
+    fn uses_decoded_word_times_while_they_match_the_text() {
+        let word = |text: &str, start, end| Word {
+            text: text.into(),
+            start,
+            end,
+        };
+        let mut segment = StoredSegment {
+            id: "s".into(),
+            transcription_id: "t".into(),
+            position: 0,
+            speaker: None,
+            text: "Hi there".into(),
+            start: 10.0,
+            end: 14.0,
+            confidence: None,
+            words: vec![word("Hi", 10.2, 10.5), word("there", 12.0, 12.4)],
+        };
+        assert_eq!(
+            word_times(&segment),
+            [("Hi", 10.2, 10.5), ("there", 12.0, 12.4)]
+        );
+
+        // Edited since decoding: estimated from the segment start.
+        segment.text = "Hi there, you".into();
+        let words = word_times(&segment);
+        assert_eq!((words.len(), words[0].1), (3, 10.0));
+    }
+}
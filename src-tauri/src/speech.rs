@@ -0,0 +1,580 @@
+//! Spoken readback of transcripts and summaries.
+//!
+//! System voices go through the OS speech engine. Voices named `piper:<name>`
+//! are synthesized offline by a Piper binary from a `<name>.onnx` model in
+//! the app's `voices` directory, then played back as audio.
+//!
+//! OS engines cannot pause mid-utterance, so text is queued sentence by
+//! sentence: pausing stops the current sentence and resuming starts it over.
+//! Engines that cannot report whether they are still speaking are assumed
+//! to be for as long as the sentence takes at an average speaking pace.
+//! Piper runs on its own thread, so pause and stop apply while it works.
+
+use std::fs::{self, File};
+use std::io::{BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rodio::{Decoder, OutputStream, Sink};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use tts::Tts;
+
+use crate::crash;
+use crate::error::{Error, Result};
+
+pub const STATE_EVENT: &str = "speech://state";
+const PIPER_PREFIX: &str = "piper:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeechState {
+    Speaking,
+    Paused,
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceInfo {
+    /// Pass this as `voice` to `speak_text`.
+    pub id: String,
+    pub name: String,
+    pub language: Option<String>,
+    /// Synthesized on-device without the OS speech engine.
+    pub offline_neural: bool,
+}
+
+enum Control {
+    Speak {
+        text: String,
+        voice: Option<String>,
+        /// Multiplier of the voice's normal speed.
+        rate: f32,
+        reply: mpsc::Sender<Result<()>>,
+    },
+    /// A Piper synthesis started by the `Speak` numbered `generation` ended.
+    Synthesized {
+        generation: u64,
+        file: Result<PathBuf>,
+        reply: mpsc::Sender<Result<()>>,
+    },
+    Pause,
+    Resume,
+    Stop,
+}
+
+pub struct Speaker(Mutex<mpsc::Sender<Control>>);
+
+/// Words per second at a voice's normal rate, for engines that cannot
+/// report the end of a sentence.
+const WORDS_PER_SEC: f32 = 2.5;
+
+/// About how long an OS voice takes to say `sentence` at `rate`.
+fn sentence_duration(sentence: &str, rate: f32) -> Duration {
+    let words = sentence.split_whitespace().count() as f32;
+    Duration::from_secs_f32(words / (WORDS_PER_SEC * rate) + 0.5)
+}
+
+fn speech_error(err: impl std::fmt::Display) -> Error {
+    Error::AudioDevice(format!("speech: {}", err))
+}
+
+fn voices_dir(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| Error::NotFound("app data directory".into()))?
+        .join("voices");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// A bundled Piper binary in the voices directory, else one on `PATH`.
+fn piper_binary(voices: &std::path::Path) -> PathBuf {
+    let bundled = voices.join(if cfg!(windows) { "piper.exe" } else { "piper" });
+    if bundled.exists() {
+        bundled
+    } else {
+        PathBuf::from("piper")
+    }
+}
+
+/// Split text into sentences, keeping the terminating punctuation.
+fn sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        current.push(c);
+        let at_break = matches!(c, '.' | '!' | '?' | '\n')
+            && chars.peek().is_none_or(|next| next.is_whitespace());
+        if at_break && !current.trim().is_empty() {
+            sentences.push(current.trim().to_string());
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        sentences.push(current.trim().to_string());
+    }
+    sentences
+}
+
+/// Run Piper over `text` and return the path of the synthesized WAV.
+fn synthesize_piper(
+    voices: &std::path::Path,
+    name: &str,
+    text: &str,
+    rate: f32,
+) -> Result<PathBuf> {
+    let model = voices.join(format!("{}.onnx", name));
+    if !model.exists() {
+        return Err(Error::NotFound(format!("piper voice '{}'", name)));
+    }
+    let output =
+        std::env::temp_dir().join(format!("transcriber-speech-{}.wav", uuid::Uuid::new_v4()));
+
+    let mut child = Command::new(piper_binary(voices))
+        .arg("--model")
+        .arg(&model)
+        .arg("--output_file")
+        .arg(&output)
+        // Piper's length scale is the inverse of speed.
+        .arg("--length_scale")
+        .arg((1.0 / rate).to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| Error::NotFound(format!("piper executable: {}", e)))?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(speech_error(format!("piper exited with {}", status)));
+    }
+    Ok(output)
+}
+
+/// Playback owned by the speaker thread; neither engine is `Send`.
+enum Playback {
+    System {
+        queue: Vec<String>,
+        next: usize,
+        rate: f32,
+        /// When the sentence before `next` was handed to the engine.
+        started: Option<Instant>,
+    },
+    Synthesizing {
+        generation: u64,
+    },
+    Piper {
+        sink: Sink,
+        file: PathBuf,
+    },
+}
+
+struct Engine {
+    app: AppHandle,
+    control: mpsc::Sender<Control>,
+    tts: Option<Tts>,
+    audio: Option<(OutputStream, rodio::OutputStreamHandle)>,
+    playback: Option<Playback>,
+    /// Counts `Speak` requests, so a late Piper result can tell it is stale.
+    generation: u64,
+    paused: bool,
+}
+
+/// Whether the OS engine is still saying the sentence before `next`.
+fn still_speaking(
+    tts: &Tts,
+    queue: &[String],
+    next: usize,
+    rate: f32,
+    started: Option<Instant>,
+) -> bool {
+    tts.is_speaking().unwrap_or_else(|_| {
+        let sentence = next.checked_sub(1).and_then(|last| queue.get(last));
+        match (sentence, started) {
+            (Some(sentence), Some(started)) => {
+                started.elapsed() < sentence_duration(sentence, rate)
+            }
+            _ => false,
+        }
+    })
+}
+
+impl Engine {
+    fn tts(&mut self) -> Result<&mut Tts> {
+        if self.tts.is_none() {
+            self.tts = Some(Tts::default().map_err(speech_error)?);
+        }
+        Ok(self.tts.as_mut().unwrap())
+    }
+
+    fn emit(&self, state: SpeechState) {
+        let _ = self.app.emit_all(STATE_EVENT, state);
+    }
+
+    /// Start reading `text`. With a Piper voice, `reply` is answered once
+    /// synthesis ends, from `synthesized`; otherwise before this returns.
+    fn speak(
+        &mut self,
+        text: String,
+        voice: Option<&str>,
+        rate: f32,
+        reply: mpsc::Sender<Result<()>>,
+    ) {
+        self.stop();
+        self.generation += 1;
+        let rate = rate.clamp(0.25, 4.0);
+
+        if let Some(name) = voice.and_then(|v| v.strip_prefix(PIPER_PREFIX)) {
+            let voices = match voices_dir(&self.app) {
+                Ok(voices) => voices,
+                Err(err) => {
+                    let _ = reply.send(Err(err));
+                    return;
+                }
+            };
+            let (name, generation, control) =
+                (name.to_string(), self.generation, self.control.clone());
+            thread::spawn(move || {
+                let file = synthesize_piper(&voices, &name, &text, rate);
+                let _ = control.send(Control::Synthesized {
+                    generation,
+                    file,
+                    reply,
+                });
+            });
+            self.playback = Some(Playback::Synthesizing {
+                generation: self.generation,
+            });
+            self.paused = false;
+            self.emit(SpeechState::Speaking);
+        } else {
+            let result = self.speak_system(&text, voice, rate);
+            let _ = reply.send(result);
+        }
+    }
+
+    fn speak_system(&mut self, text: &str, voice: Option<&str>, rate: f32) -> Result<()> {
+        let tts = self.tts()?;
+        if let Some(wanted) = voice {
+            let voices = tts.voices().map_err(speech_error)?;
+            let found = voices
+                .iter()
+                .find(|v| v.id() == wanted || v.name() == wanted)
+                .ok_or_else(|| Error::NotFound(format!("voice '{}'", wanted)))?;
+            tts.set_voice(found).map_err(speech_error)?;
+        }
+        let scaled = (tts.normal_rate() * rate).clamp(tts.min_rate(), tts.max_rate());
+        tts.set_rate(scaled).map_err(speech_error)?;
+        self.playback = Some(Playback::System {
+            queue: sentences(text),
+            next: 0,
+            rate,
+            started: None,
+        });
+
+        self.paused = false;
+        self.emit(SpeechState::Speaking);
+        Ok(())
+    }
+
+    /// Play a finished Piper synthesis, unless speech was stopped or
+    /// replaced while it ran.
+    fn synthesized(
+        &mut self,
+        generation: u64,
+        file: Result<PathBuf>,
+        reply: mpsc::Sender<Result<()>>,
+    ) {
+        let current = matches!(
+            self.playback,
+            Some(Playback::Synthesizing { generation: waiting }) if waiting == generation
+        );
+        if !current {
+            if let Ok(file) = file {
+                let _ = fs::remove_file(file);
+            }
+            let _ = reply.send(Ok(()));
+            return;
+        }
+        let result = file.and_then(|file| self.play_file(file));
+        if result.is_err() {
+            self.playback = None;
+            self.paused = false;
+            self.emit(SpeechState::Stopped);
+        }
+        let _ = reply.send(result);
+    }
+
+    fn play_file(&mut self, file: PathBuf) -> Result<()> {
+        if self.audio.is_none() {
+            self.audio = Some(OutputStream::try_default().map_err(speech_error)?);
+        }
+        let handle = &self.audio.as_ref().unwrap().1;
+        let sink = Sink::try_new(handle).map_err(speech_error)?;
+        sink.append(Decoder::new(BufReader::new(File::open(&file)?)).map_err(speech_error)?);
+        if self.paused {
+            sink.pause();
+        }
+        self.playback = Some(Playback::Piper { sink, file });
+        Ok(())
+    }
+
+    fn pause(&mut self) {
+        match &mut self.playback {
+            Some(Playback::Piper { sink, .. }) => sink.pause(),
+            Some(Playback::System {
+                queue,
+                next,
+                rate,
+                started,
+            }) => {
+                if let Some(tts) = self.tts.as_mut() {
+                    // Repeat the interrupted sentence on resume.
+                    if still_speaking(tts, queue, *next, *rate, *started) {
+                        *next = next.saturating_sub(1);
+                    }
+                    *started = None;
+                    let _ = tts.stop();
+                }
+            }
+            Some(Playback::Synthesizing { .. }) => {}
+            None => return,
+        }
+        self.paused = true;
+        self.emit(SpeechState::Paused);
+    }
+
+    fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+        if let Some(Playback::Piper { sink, .. }) = &self.playback {
+            sink.play();
+        }
+        self.paused = false;
+        self.emit(SpeechState::Speaking);
+    }
+
+    fn stop(&mut self) {
+        match self.playback.take() {
+            Some(Playback::Piper { sink, file }) => {
+                sink.stop();
+                let _ = fs::remove_file(file);
+            }
+            Some(Playback::System { .. }) => {
+                if let Some(tts) = self.tts.as_mut() {
+                    let _ = tts.stop();
+                }
+            }
+            // The late result is discarded by `synthesized`.
+            Some(Playback::Synthesizing { .. }) => {}
+            None => return,
+        }
+        self.paused = false;
+        self.emit(SpeechState::Stopped);
+    }
+
+    /// Feed the next sentence to the OS engine and detect the end of playback.
+    fn tick(&mut self) {
+        if self.paused {
+            return;
+        }
+        let finished = match &mut self.playback {
+            Some(Playback::Piper { sink, .. }) => sink.empty(),
+            Some(Playback::System {
+                queue,
+                next,
+                rate,
+                started,
+            }) => {
+                let Some(tts) = self.tts.as_mut() else {
+                    return;
+                };
+                if still_speaking(tts, queue, *next, *rate, *started) {
+                    false
+                } else if let Some(sentence) = queue.get(*next) {
+                    *next += 1;
+                    *started = Some(Instant::now());
+                    if let Err(err) = tts.speak(sentence.as_str(), false) {
+                        crash::log(format!("speech failed: {}", err));
+                    }
+                    false
+                } else {
+                    true
+                }
+            }
+            Some(Playback::Synthesizing { .. }) | None => false,
+        };
+        if finished {
+            self.stop();
+        }
+    }
+}
+
+fn run(app: AppHandle, sender: mpsc::Sender<Control>, control: mpsc::Receiver<Control>) {
+    let mut engine = Engine {
+        app,
+        control: sender,
+        tts: None,
+        audio: None,
+        playback: None,
+        generation: 0,
+        paused: false,
+    };
+    loop {
+        match control.recv_timeout(Duration::from_millis(100)) {
+            Ok(Control::Speak {
+                text,
+                voice,
+                rate,
+                reply,
+            }) => engine.speak(text, voice.as_deref(), rate, reply),
+            Ok(Control::Synthesized {
+                generation,
+                file,
+                reply,
+            }) => engine.synthesized(generation, file, reply),
+            Ok(Control::Pause) => engine.pause(),
+            Ok(Control::Resume) => engine.resume(),
+            Ok(Control::Stop) => engine.stop(),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        engine.tick();
+    }
+}
+
+/// Start the speaker thread.
+pub fn init(app: &AppHandle) {
+    let (sender, receiver) = mpsc::channel();
+    let (handle, own) = (app.clone(), sender.clone());
+    thread::spawn(move || run(handle, own, receiver));
+    app.manage(Speaker(Mutex::new(sender)));
+}
+
+impl Speaker {
+    fn send(&self, control: Control) -> Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .send(control)
+            .map_err(|_| speech_error("speaker thread exited"))
+    }
+}
+
+/// Read `text` aloud, replacing anything currently being spoken.
+///
+/// `rate` is relative to the voice's normal speed (1.0). Synthesis with a
+/// Piper voice finishes before this returns, so it runs off the main thread;
+/// stopping speech meanwhile makes it return without playing anything.
+#[tauri::command]
+pub async fn speak_text(
+    speaker: State<'_, Speaker>,
+    text: String,
+    voice: Option<String>,
+    rate: Option<f32>,
+) -> Result<()> {
+    let (reply, result) = mpsc::channel();
+    speaker.send(Control::Speak {
+        text,
+        voice,
+        rate: rate.unwrap_or(1.0),
+        reply,
+    })?;
+    tauri::async_runtime::spawn_blocking(move || result.recv())
+        .await
+        .map_err(|e| Error::Transcription(e.to_string()))?
+        .map_err(|_| speech_error("speaker thread exited"))?
+}
+
+#[tauri::command]
+pub fn pause_speech(speaker: State<'_, Speaker>) -> Result<()> {
+    speaker.send(Control::Pause)
+}
+
+#[tauri::command]
+pub fn resume_speech(speaker: State<'_, Speaker>) -> Result<()> {
+    speaker.send(Control::Resume)
+}
+
+#[tauri::command]
+pub fn stop_speech(speaker: State<'_, Speaker>) -> Result<()> {
+    speaker.send(Control::Stop)
+}
+
+/// System voices plus any Piper voices installed in the voices directory.
+#[tauri::command]
+pub async fn list_voices(app: AppHandle) -> Result<Vec<VoiceInfo>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut voices: Vec<VoiceInfo> = Tts::default()
+            .and_then(|tts| tts.voices())
+            .map_err(speech_error)?
+            .into_iter()
+            .map(|voice| VoiceInfo {
+                id: voice.id(),
+                name: voice.name(),
+                language: Some(voice.language().to_string()),
+                offline_neural: false,
+            })
+            .collect();
+
+        for entry in fs::read_dir(voices_dir(&app)?)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("onnx") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                voices.push(VoiceInfo {
+                    id: format!("{}{}", PIPER_PREFIX, name),
+                    name: name.to_string(),
+                    // Piper voice names start with the locale, e.g. en_US-amy-low.
+                    language: name
+                        .split('-')
+                        .next()
+                        .map(|locale| locale.replace('_', "-")),
+                    offline_neural: true,
+                });
+            }
+        }
+        Ok(voices)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_sentences_but_not_decimals() {
+        assert_eq!(
+            sentences("Revenue grew 2.5 percent. Next steps? Ship it"),
+            vec!["Revenue grew 2.5 percent.", "Next steps?", "Ship it"]
+        );
+    }
+
+    #[test]
+    fn estimates_sentence_length_from_words_and_rate() {
+        let sentence = "one two three four five";
+        assert_eq!(
+            sentence_duration(sentence, 1.0),
+            Duration::from_millis(2500)
+        );
+        assert_eq!(
+            sentence_duration(sentence, 2.0),
+            Duration::from_millis(1500)
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Corrections made to exported subtitles, read back into the transcript.
+//!
+//! Users often fix an SRT or WebVTT file in a subtitle editor rather than in
+//! the app. Re-importing it matches each cue to the segment it was exported
+//! from and saves the changed texts as one new transcript version, logged
+//! per segment. A cue is matched to the segment starting at about the same
+//! time, or, if the file was retimed but still has one cue per segment, to
+//! the segment in the same place. Timings and cues that match no segment
+//! are left alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::params;
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::segments::{self, StoredSegment};
+use crate::{db, editing};
+
+/// How far a cue's start may drift from its segment's and still match.
+const MATCH_TOLERANCE_SECS: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cue {
+    start: f64,
+    text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleReimport {
+    pub cues: usize,
+    pub matched: usize,
+    /// Segments whose text changed.
+    pub changed: usize,
+    /// 1-based places in the file of cues that matched no segment.
+    pub unmatched_cues: Vec<usize>,
+    /// The transcript version after the import; unchanged if no text was.
+    pub version: i64,
+    /// The transcript text after the import.
+    pub text: String,
+    pub segments: Vec<StoredSegment>,
+}
+
+/// Seconds from `00:01:02,500`, `00:01:02.500` or WebVTT's `01:02.500`.
+fn parse_time(text: &str) -> Option<f64> {
+    let (clock, millis) = text.trim().split_once([',', '.'])?;
+    let mut secs = 0.0;
+    for part in clock.split(':') {
+        secs = secs * 60.0 + part.parse::<u32>().ok()? as f64;
+    }
+    Some(secs + millis.parse::<u32>().ok()? as f64 / 10f64.powi(millis.len() as i32))
+}
+
+/// Cue text as plain words: lines joined, and formatting such as `<i>`,
+/// `<v Speaker>` or `{\an8}` dropped.
+fn plain_text(lines: &[&str]) -> String {
+    let mut text = String::new();
+    let mut markup = None;
+    for c in lines.join(" ").chars() {
+        match (markup, c) {
+            (None, '<') => markup = Some('>'),
+            (None, '{') => markup = Some('}'),
+            (Some(close), c) if c == close => markup = None,
+            (Some(_), _) => {}
+            (None, c) => text.push(c),
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Cues of an SRT or WebVTT file, in file order. Blocks without a timing
+/// line, such as the WebVTT header and notes, are skipped.
+fn parse(source: &str) -> Vec<Cue> {
+    let source = source.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    source
+        .split("\n\n")
+        .filter_map(|block| {
+            let lines: Vec<&str> = block.lines().collect();
+            let timing = lines.iter().position(|line| line.contains("-->"))?;
+            let (start, end) = lines[timing].split_once("-->")?;
+            // WebVTT cue settings follow the end time.
+            parse_time(end.split_whitespace().next()?)?;
+            Some(Cue {
+                start: parse_time(start)?,
+                text: plain_text(&lines[timing + 1..]),
+            })
+        })
+        .collect()
+}
+
+/// For each cue, the index of the segment it was exported from.
+fn match_cues(cues: &[Cue], segments: &[StoredSegment]) -> Vec<Option<usize>> {
+    let mut taken = vec![false; segments.len()];
+    let mut matches: Vec<Option<usize>> = cues
+        .iter()
+        .enumerate()
+        .map(|(place, cue)| {
+            let distance = |index: usize| (segments[index].start - cue.start).abs();
+            let near = |index: &usize| !taken[*index] && distance(*index) <= MATCH_TOLERANCE_SECS;
+            let found = Some(place)
+                .filter(|place| *place < segments.len() && near(place))
+                .or_else(|| {
+                    (0..segments.len())
+                        .filter(near)
+                        .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+                });
+            if let Some(index) = found {
+                taken[index] = true;
+            }
+            found
+        })
+        .collect();
+    if cues.len() == segments.len() {
+        for (place, found) in matches.iter_mut().enumerate() {
+            if found.is_none() && !taken[place] {
+                taken[place] = true;
+                *found = Some(place);
+            }
+        }
+    }
+    matches
+}
+
+/// Apply the text of an edited SRT or WebVTT export of a transcription.
+/// Cues left empty do not clear their segment.
+fn reimport(
+    app: &AppHandle,
+    transcription_id: &str,
+    path: &Path,
+    expected_version: Option<i64>,
+) -> Result<SubtitleReimport> {
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    let cues = parse(&fs::read_to_string(path)?);
+    if cues.is_empty() {
+        return Err(Error::InvalidInput(format!(
+            "{} has no subtitle cues",
+            name
+        )));
+    }
+
+    let mut conn = db::connect(app)?;
+    let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+    db::transcription_text(&tx, transcription_id)?;
+    let stored = segments::for_transcription(&tx, transcription_id)?;
+    let matches = match_cues(&cues, &stored);
+    if matches.iter().all(Option::is_none) {
+        return Err(Error::InvalidInput(format!(
+            "no cue in {} matches a segment of this transcription",
+            name
+        )));
+    }
+
+    let changes: Vec<(&StoredSegment, &str)> = matches
+        .iter()
+        .zip(&cues)
+        .filter_map(|(index, cue)| Some((&stored[(*index)?], cue.text.as_str())))
+        .filter(|(segment, text)| !text.is_empty() && segment.text.trim() != *text)
+        .collect();
+    let version = if changes.is_empty() {
+        let version = tx.query_row(
+            "SELECT version FROM transcriptions WHERE id = ?1",
+            [transcription_id],
+            |row| row.get(0),
+        )?;
+        if expected_version.is_some_and(|expected| expected != version) {
+            return Err(Error::EditConflict {
+                current: version,
+                editor: None,
+            });
+        }
+        version
+    } else {
+        let outcome = editing::bump_version(&tx, transcription_id, expected_version)?;
+        for (segment, text) in &changes {
+            editing::log_edit(
+                &tx,
+                transcription_id,
+                &outcome,
+                &format!("segment:{}", segment.id),
+                Some(&segment.text),
+                Some(text),
+            )?;
+            tx.execute(
+                "UPDATE segments SET text = ?2 WHERE id = ?1",
+                params![segment.id, text],
+            )?;
+        }
+        segments::sync_text(&tx, transcription_id)?;
+        outcome.version
+    };
+    let changed = changes.len();
+    let segments = segments::for_transcription(&tx, transcription_id)?;
+    let text = db::transcription_text(&tx, transcription_id)?;
+    tx.commit()?;
+
+    Ok(SubtitleReimport {
+        cues: cues.len(),
+        matched: matches.iter().flatten().count(),
+        changed,
+        unmatched_cues: matches
+            .iter()
+            .enumerate()
+            .filter(|(_, index)| index.is_none())
+            .map(|(place, _)| place + 1)
+            .collect(),
+        version,
+        text,
+        segments,
+    })
+}
+
+/// Apply the text of an edited SRT or WebVTT export of a transcription.
+#[tauri::command]
+pub async fn reimport_subtitles(
+    app: AppHandle,
+    transcription_id: String,
+    path: PathBuf,
+    expected_version: Option<i64>,
+) -> Result<SubtitleReimport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        reimport(&app, &transcription_id, &path, expected_version)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, text: &str) -> StoredSegment {
+        StoredSegment {
+            id: format!("s{}", start),
+            transcription_id: "t1".into(),
+            position: 0,
+            speaker: None,
+            text: text.into(),
+            start,
+            end: start + 2.0,
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    fn cue(start: f64, text: &str) -> Cue {
+        Cue {
+            start,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn reads_srt_and_webvtt_cues() {
+        let srt = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hello</i> there,\r\ngeneral.\r\n\r\n2\r\n00:01:02,250 --> 00:01:04,000\r\nBye &amp; thanks.\r\n";
+        assert_eq!(
+            parse(srt),
+            vec![
+                Cue {
+                    start: 1.0,
+                    text: "Hello there, general.".into()
+                },
+                Cue {
+                    start: 62.25,
+                    text: "Bye & thanks.".into()
+                },
+            ]
+        );
+
+        let vtt = "WEBVTT\n\nNOTE edited\n\nintro\n01:02.250 --> 01:04.000 align:start\n<v Ann>{\\an8}Bye.</v>\n";
+        assert_eq!(
+            parse(vtt),
+            vec![Cue {
+                start: 62.25,
+                text: "Bye.".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn matches_cues_by_start_time_then_by_place() {
+        let stored = [
+            segment(0.0, "One."),
+            segment(3.0, "Two."),
+            segment(6.0, "Three."),
+        ];
+        // The second cue was deleted and a new one added at the end.
+        let edited = [cue(0.0, "One."), cue(6.2, "Three!"), cue(20.0, "Four.")];
+        assert_eq!(match_cues(&edited, &stored), vec![Some(0), Some(2), None]);
+        assert_eq!(match_cues(&edited[..2], &stored), vec![Some(0), Some(2)]);
+
+        // Every cue kept, but shifted by a second.
+        let retimed = [cue(1.0, "One."), cue(4.0, "Two."), cue(7.0, "Three.")];
+        assert_eq!(
+            match_cues(&retimed, &stored),
+            vec![Some(0), Some(1), Some(2)]
+        );
+    }
+}
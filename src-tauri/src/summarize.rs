@@ -0,0 +1,657 @@
+//! Transcript summarization through the configured text generation provider.
+//!
+//! Transcripts that do not fit in one request are summarized in sections
+//! first, and the section summaries are then combined with the same prompt.
+//! The final pass is streamed to the frontend as `summary://token` events.
+//!
+//! Passages decoded with low confidence are marked in the transcript sent
+//! to the model, which is asked to tag statements resting on them with
+//! `[unverified]` rather than repeat a misheard word as fact.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{Error, Result};
+use crate::llm::{self, ProviderConfig, TokenUsage};
+use crate::meeting_types::{self, MeetingType};
+use crate::network::{self, Client};
+use crate::segments::{self, StoredSegment};
+use crate::usage::{self, Operation, UsageEntry};
+use crate::{db, prompts, review};
+
+/// Characters of transcript sent per request; fits small local models.
+const SECTION_CHARS: usize = 12_000;
+
+pub const TOKEN_EVENT: &str = "summary://token";
+
+const SYSTEM_PROMPT: &str =
+    "You summarize transcripts of recorded speech. Follow the instructions exactly and only use facts from the transcript.";
+
+const LOW_CONFIDENCE_OPEN: &str = "[low confidence]";
+const LOW_CONFIDENCE_CLOSE: &str = "[/low confidence]";
+
+const GROUNDING_INSTRUCTIONS: &str =
+    "Passages between [low confidence] and [/low confidence] were transcribed unreliably and may be wrong. Mark every statement that relies on such a passage with [unverified], and keep [unverified] on statements that already carry it.";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    pub id: String,
+    pub transcription_id: String,
+    pub summary: String,
+    pub language: String,
+    pub model_used: String,
+    pub prompt_id: Option<String>,
+    pub original_length: usize,
+    pub summary_length: usize,
+    pub compression_ratio: f64,
+    /// Milliseconds.
+    pub processing_time: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryToken {
+    pub transcription_id: String,
+    pub token: String,
+}
+
+/// Cancel flags of summaries being generated, by transcription id.
+#[derive(Default)]
+pub struct ActiveSummaries(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+/// Removes a summary's cancel flag when generation ends.
+struct ActiveGuard<'a>(&'a ActiveSummaries, String);
+
+impl Drop for ActiveGuard<'_> {
+    fn drop(&mut self) {
+        self.0 .0.lock().unwrap().remove(&self.1);
+    }
+}
+
+/// Readable name of a language code for use inside prompts.
+pub fn language_name(code: &str) -> String {
+    match code {
+        "en" => "English".into(),
+        "nl" => "Dutch".into(),
+        "de" => "German".into(),
+        "fr" => "French".into(),
+        "es" => "Spanish".into(),
+        "" | "auto" => "the language of the transcript".into(),
+        other => other.into(),
+    }
+}
+
+/// Whether a line opens a speaker turn: `Speaker 1:`, `[Alice]` or `- Bob:`,
+/// as recognised by the frontend's chunker.
+fn starts_turn(line: &str) -> bool {
+    let line = line.trim_start();
+    let line = line
+        .strip_prefix(['-', '\u{2013}'])
+        .map_or(line, str::trim_start);
+    let label_end = if let Some(rest) = line.strip_prefix('[') {
+        match rest.find(']') {
+            Some(close)
+                if (1..=40).contains(&rest[..close].chars().count())
+                    && !rest[..close].contains('\n') =>
+            {
+                close + 2
+            }
+            _ => return false,
+        }
+    } else {
+        let mut chars = line.char_indices();
+        if !chars.next().is_some_and(|(_, c)| c.is_uppercase()) {
+            return false;
+        }
+        match chars
+            .take(31)
+            .find(|(_, c)| !(c.is_alphanumeric() || matches!(c, ' ' | '.' | '\'' | '-')))
+        {
+            Some((colon, ':')) => colon + 1,
+            _ => return false,
+        }
+    };
+    line[label_end..].starts_with(char::is_whitespace)
+}
+
+/// Whether `text[..cut]` leaves a low-confidence passage open.
+fn opens_low_confidence(text: &str, cut: usize) -> bool {
+    text[..cut].matches(LOW_CONFIDENCE_OPEN).count()
+        > text[..cut].matches(LOW_CONFIDENCE_CLOSE).count()
+}
+
+/// Whether `cut` falls within a low-confidence marker itself.
+fn splits_marker(text: &str, cut: usize) -> bool {
+    [LOW_CONFIDENCE_OPEN, LOW_CONFIDENCE_CLOSE]
+        .iter()
+        .any(|marker| {
+            text.match_indices(marker)
+                .any(|(start, _)| start < cut && cut < start + marker.len())
+        })
+}
+
+/// Split text into sections of at most `max_chars`. Like the frontend's
+/// chunker, a section ends at a speaker turn or paragraph once it is at
+/// least half full, else at a sentence end, else between words. Cuts
+/// avoid low-confidence passages, and never split their markers.
+pub fn sections(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut sections = Vec::new();
+    let mut rest = text.trim();
+    while rest.len() > max_chars {
+        let mut limit = max_chars;
+        while !rest.is_char_boundary(limit) {
+            limit -= 1;
+        }
+        let window = &rest[..limit];
+        let pick = |inside_passage: bool| {
+            let allowed = |cut: &usize| {
+                *cut > 0
+                    && !splits_marker(rest, *cut)
+                    && (inside_passage || !opens_low_confidence(rest, *cut))
+            };
+            let turn = window
+                .match_indices('\n')
+                .map(|(i, _)| i + 1)
+                .filter(|&start| start >= limit / 2)
+                .filter(|&start| {
+                    let line = rest[start..].lines().next().unwrap_or("");
+                    line.trim().is_empty()
+                        || window[..start - 1].ends_with('\n')
+                        || starts_turn(line)
+                })
+                .filter(allowed)
+                .last();
+            turn.or_else(|| {
+                window
+                    .match_indices(['.', '!', '?'])
+                    .map(|(i, _)| i + 1)
+                    .filter(|&end| window[end..].starts_with(char::is_whitespace))
+                    .filter(allowed)
+                    .last()
+            })
+            .or_else(|| {
+                window
+                    .match_indices(' ')
+                    .map(|(i, _)| i)
+                    .filter(allowed)
+                    .last()
+            })
+        };
+        let cut = pick(false).or_else(|| pick(true)).unwrap_or(limit);
+        sections.push(rest[..cut].trim());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        sections.push(rest);
+    }
+    sections
+}
+
+/// `parts` with a low-confidence passage that spans a cut closed at the
+/// end of one section and reopened at the start of the next, so every
+/// section is marked on its own.
+fn balance_markers(parts: &[&str]) -> Vec<String> {
+    let mut open = false;
+    parts
+        .iter()
+        .map(|part| {
+            let mut section = String::new();
+            if open {
+                section.push_str(LOW_CONFIDENCE_OPEN);
+                section.push(' ');
+            }
+            section.push_str(part);
+            open = opens_low_confidence(&section, section.len());
+            if open {
+                section.push(' ');
+                section.push_str(LOW_CONFIDENCE_CLOSE);
+            }
+            section
+        })
+        .collect()
+}
+
+pub struct SummaryRequest {
+    pub instructions: String,
+    pub prompt_id: Option<String>,
+    pub language: String,
+}
+
+/// Resolve the instructions for a summary: an explicit prompt, else the
+/// meeting type's own prompt, else the default library prompt. Without a
+/// `meeting_type`, the one the transcription was made with applies.
+pub fn request(
+    conn: &Connection,
+    transcription_id: &str,
+    prompt_id: Option<&str>,
+    meeting_type: Option<&str>,
+    language: Option<String>,
+) -> Result<SummaryRequest> {
+    let meeting_type: Option<MeetingType> = match meeting_type {
+        Some(key) => Some(meeting_types::find(conn, key)?),
+        // A type deleted since the transcription was made no longer applies.
+        None => conn
+            .query_row(
+                "SELECT meeting_type_id FROM transcriptions WHERE id = ?1",
+                [transcription_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+            .flatten()
+            .and_then(|key| meeting_types::find(conn, &key).ok()),
+    };
+    let language = match language {
+        Some(language) => language,
+        None => conn.query_row(
+            "SELECT language FROM transcriptions WHERE id = ?1",
+            [transcription_id],
+            |row| row.get(0),
+        )?,
+    };
+
+    let (template, prompt_id) = match (prompt_id, meeting_type.as_ref()) {
+        (Some(id), _) => (prompts::find(conn, id)?.template, Some(id.to_string())),
+        (
+            None,
+            Some(MeetingType {
+                summary_prompt: Some(prompt),
+                ..
+            }),
+        ) => (prompt.clone(), None),
+        (None, _) => (
+            prompts::find(conn, prompts::DEFAULT_PROMPT)?.template,
+            Some(prompts::DEFAULT_PROMPT.to_string()),
+        ),
+    };
+    let variables = HashMap::from([
+        ("language", language_name(&language)),
+        (
+            "meeting_type",
+            meeting_type
+                .map(|t| t.name.to_lowercase())
+                .unwrap_or_else(|| "recording".into()),
+        ),
+    ]);
+
+    Ok(SummaryRequest {
+        instructions: prompts::render(&template, &variables),
+        prompt_id,
+        language,
+    })
+}
+
+/// The transcript with runs of segments below `threshold` confidence
+/// wrapped in low-confidence markers, or `None` when no segment is below
+/// it and the plain `text` can be used. Also `None` when `text` was edited
+/// apart from the segments, whose confidences then no longer describe it.
+pub fn grounded_text(text: &str, segments: &[StoredSegment], threshold: f64) -> Option<String> {
+    let low = |segment: &StoredSegment| segment.confidence.is_some_and(|c| c < threshold);
+    if !segments.iter().any(low) {
+        return None;
+    }
+    let words = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let joined: Vec<&str> = segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .collect();
+    if words(&joined.join(" ")) != words(text) {
+        return None;
+    }
+    let mut out = String::new();
+    let mut in_low = false;
+    for segment in segments {
+        let text = segment.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match (in_low, low(segment)) {
+            (false, true) => {
+                out.push_str(if out.is_empty() { "" } else { " " });
+                out.push_str(LOW_CONFIDENCE_OPEN);
+                out.push(' ');
+            }
+            (true, false) => {
+                out.push(' ');
+                out.push_str(LOW_CONFIDENCE_CLOSE);
+                out.push(' ');
+            }
+            _ if !out.is_empty() => out.push(' '),
+            _ => {}
+        }
+        in_low = low(segment);
+        out.push_str(text);
+    }
+    if in_low {
+        out.push(' ');
+        out.push_str(LOW_CONFIDENCE_CLOSE);
+    }
+    Some(out)
+}
+
+fn message(instructions: &str, transcript: &str) -> String {
+    format!("{}\n\nTranscript:\n{}", instructions, transcript)
+}
+
+fn check_cancelled(cancel: &AtomicBool) -> Result<()> {
+    if cancel.load(Ordering::Relaxed) {
+        Err(Error::Cancelled("summary generation was cancelled".into()))
+    } else {
+        Ok(())
+    }
+}
+
+fn cancelled(err: Error) -> Error {
+    match err {
+        Error::Cancelled(_) => Error::Cancelled("summary generation was cancelled".into()),
+        other => other,
+    }
+}
+
+/// Summarize `text`, combining section summaries for long transcripts.
+/// Tokens of the final summary are passed to `on_token` as they arrive, and
+/// setting `cancel` stops generation at the next token of any pass. Tokens
+/// the provider reports for every request made are added to `usage`, also
+/// when generation fails or is cancelled part-way.
+pub fn generate(
+    client: &Client,
+    config: &ProviderConfig,
+    instructions: &str,
+    text: &str,
+    cancel: &AtomicBool,
+    usage: &mut TokenUsage,
+    mut on_token: impl FnMut(&str),
+) -> Result<String> {
+    let ground = |instructions: &str| {
+        if text.contains(LOW_CONFIDENCE_OPEN) {
+            format!("{}\n\n{}", instructions, GROUNDING_INSTRUCTIONS)
+        } else {
+            instructions.to_string()
+        }
+    };
+    let parts = sections(text, SECTION_CHARS);
+    let input = if parts.len() <= 1 {
+        text.to_string()
+    } else {
+        let mut partial = Vec::with_capacity(parts.len());
+        for part in balance_markers(&parts) {
+            check_cancelled(cancel)?;
+            // Streamed only so that cancelling stops a long section pass
+            // instead of waiting for it to finish.
+            let section = llm::complete_stream(
+                client,
+                config,
+                SYSTEM_PROMPT,
+                &message(
+                    &ground("Summarize this section of a longer transcript in detail."),
+                    &part,
+                ),
+                usage,
+                |_| !cancel.load(Ordering::Relaxed),
+            )
+            .map_err(cancelled)?;
+            partial.push(section);
+        }
+        partial.join("\n\n")
+    };
+
+    check_cancelled(cancel)?;
+    llm::complete_stream(
+        client,
+        config,
+        SYSTEM_PROMPT,
+        &message(&ground(instructions), &input),
+        usage,
+        |token| {
+            if cancel.load(Ordering::Relaxed) {
+                return false;
+            }
+            on_token(token);
+            true
+        },
+    )
+    .map_err(cancelled)
+}
+
+pub fn save(conn: &Connection, summary: &Summary) -> Result<()> {
+    conn.execute(
+        "INSERT INTO summaries (id, transcription_id, summary, language, model_used, original_length,
+             summary_length, compression_ratio, processing_time, prompt_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            summary.id,
+            summary.transcription_id,
+            summary.summary,
+            summary.language,
+            summary.model_used,
+            summary.original_length as i64,
+            summary.summary_length as i64,
+            summary.compression_ratio,
+            summary.processing_time as i64,
+            summary.prompt_id
+        ],
+    )?;
+    Ok(())
+}
+
+/// Summarize a stored transcription on the current thread and save the
+/// result, streaming tokens to the frontend.
+pub fn summarize(
+    app: &AppHandle,
+    id: &str,
+    prompt_id: Option<&str>,
+    meeting_type: Option<&str>,
+    language: Option<String>,
+) -> Result<Summary> {
+    let started = Instant::now();
+    let conn = db::connect(app)?;
+    let text = db::transcription_text(&conn, id)?;
+    let grounded = grounded_text(
+        &text,
+        &segments::for_transcription(&conn, id)?,
+        review::DEFAULT_THRESHOLD,
+    );
+    let request = request(&conn, id, prompt_id, meeting_type, language)?;
+    let config = llm::provider(&conn)?;
+
+    let active = app.state::<ActiveSummaries>();
+    let cancel = Arc::new(AtomicBool::new(false));
+    {
+        let mut running = active.0.lock().unwrap();
+        if running.contains_key(id) {
+            return Err(Error::InvalidInput(format!(
+                "a summary of {} is already being generated",
+                id
+            )));
+        }
+        running.insert(id.to_string(), cancel.clone());
+    }
+    let _guard = ActiveGuard(&active, id.to_string());
+
+    let client = network::client(&conn)?;
+    let mut tokens = TokenUsage::default();
+    let generated = generate(
+        &client,
+        &config,
+        &request.instructions,
+        grounded.as_deref().unwrap_or(&text),
+        &cancel,
+        &mut tokens,
+        |token| {
+            let _ = app.emit_all(
+                TOKEN_EVENT,
+                SummaryToken {
+                    transcription_id: id.to_string(),
+                    token: token.to_string(),
+                },
+            );
+        },
+    );
+    // Billed whether or not the summary was finished.
+    if config.is_cloud() && tokens != TokenUsage::default() {
+        usage::record(
+            &conn,
+            &UsageEntry {
+                provider: &config.base_url,
+                model: &config.model,
+                operation: Operation::Summarization,
+                transcription_id: Some(id),
+                tokens,
+                audio_seconds: 0.0,
+            },
+        )?;
+    }
+
+    let text_summary = generated?;
+    let summary = Summary {
+        id: uuid::Uuid::new_v4().to_string(),
+        transcription_id: id.to_string(),
+        original_length: text.chars().count(),
+        summary_length: text_summary.chars().count(),
+        compression_ratio: text_summary.chars().count() as f64 / text.chars().count().max(1) as f64,
+        summary: text_summary,
+        language: request.language,
+        model_used: config.label(),
+        prompt_id: request.prompt_id,
+        processing_time: started.elapsed().as_millis() as u64,
+    };
+    save(&conn, &summary)?;
+    Ok(summary)
+}
+
+/// Summarize a stored transcription with a library prompt and save the result.
+#[tauri::command]
+pub async fn summarize_transcription(
+    app: AppHandle,
+    id: String,
+    prompt_id: Option<String>,
+    meeting_type: Option<String>,
+    language: Option<String>,
+) -> Result<Summary> {
+    tauri::async_runtime::spawn_blocking(move || {
+        summarize(
+            &app,
+            &id,
+            prompt_id.as_deref(),
+            meeting_type.as_deref(),
+            language,
+        )
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+/// Stop a summary that is being generated; its command returns CANCELLED.
+#[tauri::command]
+pub fn cancel_summary(active: State<'_, ActiveSummaries>, transcription_id: String) -> Result<()> {
+    let running = active.0.lock().unwrap();
+    let cancel = running
+        .get(&transcription_id)
+        .ok_or_else(|| Error::NotFound(format!("summary in progress for {}", transcription_id)))?;
+    cancel.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_break_at_sentence_ends() {
+        let text = "First sentence here. Second sentence here. Third one.";
+        let parts = sections(text, 25);
+        assert_eq!(parts[0], "First sentence here.");
+        assert!(parts.iter().all(|part| part.len() <= 25));
+        assert_eq!(parts.concat().replace(' ', ""), text.replace(' ', ""));
+    }
+
+    #[test]
+    fn sections_prefer_speaker_turns() {
+        let text =
+            "Ann: We ship on Friday. The budget is fine.\nBob: Good. I will tell the team today.";
+        let parts = sections(text, 60);
+        assert_eq!(
+            parts,
+            [
+                "Ann: We ship on Friday. The budget is fine.",
+                "Bob: Good. I will tell the team today."
+            ]
+        );
+        assert!(starts_turn("[Alice] hello"));
+        assert!(starts_turn("- Speaker 2: yes"));
+        assert!(!starts_turn("the time was 10:30 today"));
+    }
+
+    #[test]
+    fn marks_low_confidence_runs() {
+        let segment = |text: &str, confidence: Option<f64>| StoredSegment {
+            id: String::new(),
+            transcription_id: "t".into(),
+            position: 0,
+            speaker: None,
+            text: format!(" {}", text),
+            start: 0.0,
+            end: 1.0,
+            confidence,
+            words: Vec::new(),
+        };
+        let segments = [
+            segment("We ship on Friday.", Some(0.9)),
+            segment("The budget is", Some(0.3)),
+            segment("forty thousand.", Some(0.2)),
+            segment("Any questions?", None),
+            segment("Call Ms Okafor.", Some(0.4)),
+        ];
+        let text =
+            "We ship on Friday. The budget is forty thousand. Any questions? Call Ms Okafor.";
+        assert_eq!(
+            grounded_text(text, &segments, 0.5).unwrap(),
+            "We ship on Friday. [low confidence] The budget is forty thousand. \
+             [/low confidence] Any questions? [low confidence] Call Ms Okafor. [/low confidence]"
+        );
+        assert_eq!(
+            grounded_text("We ship on Friday.", &segments[..1], 0.5),
+            None
+        );
+        assert_eq!(grounded_text("Any questions?", &segments[3..4], 0.5), None);
+        // Edited since, so the confidences no longer apply.
+        assert_eq!(
+            grounded_text(&text.replace("forty", "fifty"), &segments, 0.5),
+            None
+        );
+    }
+
+    #[test]
+    fn sections_keep_low_confidence_passages_whole() {
+        let text = "We ship on Friday. [low confidence] The budget is forty. [/low confidence] Any questions?";
+        let parts = sections(text, 70);
+        assert_eq!(
+            parts,
+            [
+                "We ship on Friday.",
+                "[low confidence] The budget is forty. [/low confidence] Any questions?"
+            ]
+        );
+
+        let text =
+            "[low confidence] The budget is forty thousand. Call Ms Okafor. [/low confidence]";
+        let parts = sections(text, 50);
+        assert!(parts
+            .iter()
+            .all(|part| part.matches('[').count() == part.matches(']').count()));
+        let balanced = balance_markers(&parts);
+        assert_eq!(
+            balanced,
+            [
+                "[low confidence] The budget is forty thousand. [/low confidence]",
+                "[low confidence] Call Ms Okafor. [/low confidence]"
+            ]
+        );
+    }
+}
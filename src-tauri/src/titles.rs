@@ -0,0 +1,212 @@
+//! Titles for transcripts of files named the way recorders name them by
+//! default ("Voice 014.m4a", "REC_20240305.wav"), generated from the
+//! opening of the transcript with the summarization provider.
+//!
+//! Queued jobs name their transcripts as they are saved; the window asks
+//! for the same step with [`name_transcription`] once it saved its own.
+//! A transcript that took an embedded title from its file keeps it.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::llm::{self, TokenUsage};
+use crate::usage::{self, Operation, UsageEntry};
+use crate::{db, network};
+
+/// Roughly the first three minutes of speech, enough to name a recording.
+const TITLE_SOURCE_WORDS: usize = 450;
+const TITLE_MAX_WORDS: usize = 8;
+
+const SYSTEM_PROMPT: &str =
+    "You name transcripts of recorded speech. Answer with the title only, without quotes.";
+
+const INSTRUCTIONS: &str =
+    "Write a short descriptive title of at most eight words for the recording this transcript opens.";
+
+/// Words recorders start default file names with, before a number or date.
+const GENERIC_NAMES: &[&str] = &[
+    "voice memo",
+    "voice",
+    "audio",
+    "new recording",
+    "recording",
+    "rec",
+    "memo",
+    "track",
+    "untitled",
+    "sound",
+    "gmt",
+];
+
+/// Whether `file_name` says nothing about the content: empty, or a generic
+/// word followed by nothing but digits and separators.
+pub fn is_unhelpful_file_name(file_name: &str) -> bool {
+    let stem = file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(stem, _)| stem)
+        .trim()
+        .to_lowercase();
+    let filler = |rest: &str| {
+        rest.chars()
+            .all(|c| c.is_ascii_digit() || c.is_whitespace() || "_.-".contains(c))
+    };
+    filler(&stem)
+        || GENERIC_NAMES
+            .iter()
+            .any(|name| stem.strip_prefix(name).is_some_and(filler))
+}
+
+/// A headline from the model's answer: its first clause, without trailing
+/// punctuation, at most [`TITLE_MAX_WORDS`] words and capitalized.
+fn headline(answer: &str) -> Option<String> {
+    let answer = answer.trim().trim_matches(|c| c == '"' || c == '\'');
+    let clause = answer
+        .char_indices()
+        .find(|&(i, c)| ".!?;:".contains(c) && answer[i + 1..].starts_with(char::is_whitespace))
+        .map_or(answer, |(i, _)| &answer[..i]);
+    let words: Vec<&str> = clause
+        .trim_end_matches(|c| ".!?;:,".contains(c))
+        .split_whitespace()
+        .take(TITLE_MAX_WORDS)
+        .collect();
+    let title = words.join(" ");
+    let mut chars = title.chars();
+    let first = chars.next()?;
+    Some(first.to_uppercase().chain(chars).collect())
+}
+
+fn set_title(conn: &Connection, id: &str, title: &str) -> Result<()> {
+    let updated = conn.execute(
+        "UPDATE transcriptions SET title = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+        params![title, id],
+    )?;
+    if updated == 0 {
+        return Err(Error::NotFound(format!("transcription {}", id)));
+    }
+    Ok(())
+}
+
+/// Generate a title from the opening of transcription `id`, store it and
+/// return it.
+pub fn generate(app: &AppHandle, id: &str) -> Result<String> {
+    let conn = db::connect(app)?;
+    let text = db::transcription_text(&conn, id)?;
+    let opening = text
+        .split_whitespace()
+        .take(TITLE_SOURCE_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if opening.is_empty() {
+        return Err(Error::InvalidInput(
+            "cannot generate a title for an empty transcript".into(),
+        ));
+    }
+
+    let config = llm::provider(&conn)?;
+    let client = network::client(&conn)?;
+    let mut tokens = TokenUsage::default();
+    let answer = llm::complete_stream(
+        &client,
+        &config,
+        SYSTEM_PROMPT,
+        &format!("{}\n\nTranscript:\n{}", INSTRUCTIONS, opening),
+        &mut tokens,
+        |_| true,
+    );
+    if config.is_cloud() && tokens != TokenUsage::default() {
+        usage::record(
+            &conn,
+            &UsageEntry {
+                provider: &config.base_url,
+                model: &config.model,
+                operation: Operation::Summarization,
+                transcription_id: Some(id),
+                tokens,
+                audio_seconds: 0.0,
+            },
+        )?;
+    }
+
+    let title = headline(&answer?)
+        .ok_or_else(|| Error::Provider("the model produced no usable title".into()))?;
+    set_title(&conn, id, &title)?;
+    Ok(title)
+}
+
+/// Generate a title for transcription `id` if it has none and the name of
+/// its source file is unhelpful. Returns the title generated, if any.
+pub fn name_if_generic(app: &AppHandle, id: &str) -> Result<Option<String>> {
+    let conn = db::connect(app)?;
+    let (title, file_name): (Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT t.title, a.file_name FROM transcriptions t
+             LEFT JOIN audio_files a ON a.id = t.audio_file_id
+             WHERE t.id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("transcription {}", id)))?;
+    match (title, file_name) {
+        (None, Some(file_name)) if is_unhelpful_file_name(&file_name) => {
+            generate(app, id).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Generate and store a title for a transcription, whatever its file name.
+#[tauri::command]
+pub async fn generate_title(app: AppHandle, id: String) -> Result<String> {
+    tauri::async_runtime::spawn_blocking(move || generate(&app, &id))
+        .await
+        .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+/// Name a transcription saved in the window after its content when its
+/// file name is unhelpful; see [`name_if_generic`].
+#[tauri::command]
+pub async fn name_transcription(app: AppHandle, id: String) -> Result<Option<String>> {
+    tauri::async_runtime::spawn_blocking(move || name_if_generic(&app, &id))
+        .await
+        .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_default_recorder_names() {
+        for name in [
+            "Voice 014.m4a",
+            "REC_20240305.wav",
+            "audio1.mp3",
+            "2024-03-05 14.32.11.m4a",
+            "Voice Memo 3.m4a",
+        ] {
+            assert!(is_unhelpful_file_name(name), "{}", name);
+        }
+        for name in [
+            "Weekly sync.m4a",
+            "interview-jane-doe.wav",
+            "recordings of bats.wav",
+        ] {
+            assert!(!is_unhelpful_file_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn headlines_keep_the_first_clause() {
+        assert_eq!(
+            headline("\"budget review for the third quarter. Next steps follow\"").as_deref(),
+            Some("Budget review for the third quarter")
+        );
+        assert_eq!(
+            headline("One two three four five six seven eight nine,").as_deref(),
+            Some("One two three four five six seven eight")
+        );
+        assert_eq!(headline(" ... "), None);
+    }
+}
@@ -0,0 +1,344 @@
+//! File transcription through the native whisper engine.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{Error, Result};
+use crate::hallucination::HallucinationReport;
+use crate::meeting_types::{self, MeetingType};
+use crate::preflight::{self, JobSpec};
+use crate::whisper::{self, AdvancedOptions, DecodeOptions, Segment};
+use crate::{
+    audio, autoexport, clips, db, model_cache, models, postprocess, routing, series, trim, vad,
+    worker,
+};
+
+pub const DEFAULT_MODEL: &str = "base";
+
+/// Advanced decoding options applied when a job leaves them unset.
+pub const DECODING_PREFERENCE: &str = "decoding_defaults";
+
+/// Files shorter than this are decoded in one pass.
+const PARALLEL_MIN_SECS: f64 = 120.0;
+const CHUNK_TARGET_SECS: f64 = 60.0;
+const CHUNK_MAX_SECS: f64 = 90.0;
+/// Each worker holds its own decoder state, so memory grows with the count.
+const MAX_WORKERS: usize = 4;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionOutput {
+    pub text: String,
+    pub segments: Vec<Segment>,
+    pub language: String,
+    /// Audio duration in seconds.
+    pub duration: f64,
+    pub model_used: String,
+    /// The meeting type applied, whose prompt, tags and export template a
+    /// caller saving the result applies.
+    pub meeting_type: Option<MeetingType>,
+    /// Segments detected as hallucinated, and how many were removed.
+    pub hallucinations: HallucinationReport,
+    /// Speaker of each segment, when the job was asked to label them.
+    pub speakers: Option<Vec<String>>,
+}
+
+/// Per-run settings for [`transcribe_file`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TranscribeOptions {
+    pub language: Option<String>,
+    /// Split long audio across cores; on unless turned off.
+    pub parallel: Option<bool>,
+    pub meeting_type: Option<String>,
+    pub advanced: Option<AdvancedOptions>,
+    /// Series tag whose previous meeting's names and terms join the prompt.
+    pub series: Option<String>,
+}
+
+pub fn decoding_defaults(conn: &rusqlite::Connection) -> Result<AdvancedOptions> {
+    Ok(db::get_preference(conn, DECODING_PREFERENCE)?
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default())
+}
+
+/// Decoder prompt from a meeting type's vocabulary and the names and terms
+/// of the previous meeting in `series`.
+pub fn initial_prompt(
+    conn: &rusqlite::Connection,
+    meeting_type: Option<&MeetingType>,
+    series: Option<&str>,
+) -> Result<Option<String>> {
+    let series_prompt = series
+        .map(|series| series::prompt(conn, series))
+        .transpose()?
+        .flatten();
+    let prompts: Vec<String> = meeting_type
+        .and_then(MeetingType::vocabulary_prompt)
+        .into_iter()
+        .chain(series_prompt)
+        .collect();
+    Ok((!prompts.is_empty()).then(|| prompts.join(" ")))
+}
+
+/// Decode samples, splitting long batch audio at silences and running the
+/// chunks in parallel across cores when `parallel` allows it.
+pub fn decode(
+    ctx: &whisper_rs::WhisperContext,
+    pcm: &[f32],
+    options: &DecodeOptions,
+    parallel: bool,
+) -> Result<Vec<Segment>> {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if !parallel || cores < 4 || audio::duration_secs(pcm) < PARALLEL_MIN_SECS {
+        return whisper::transcribe(ctx, pcm, options);
+    }
+
+    let chunks = vad::split_at_silence(pcm, CHUNK_TARGET_SECS, CHUNK_MAX_SECS);
+    let workers = (cores / 2).min(MAX_WORKERS);
+    whisper::transcribe_parallel(ctx, pcm, options, &chunks, workers)
+}
+
+/// Choose the model for `pcm`: the requested one, else the language
+/// route, else the default. Detecting the language for routing also fixes
+/// it in the returned options so it is not detected twice; it runs in the
+/// inference worker, so a crash there does not take down the app.
+fn resolve_model(
+    app: &AppHandle,
+    pcm: &[f32],
+    requested: Option<&str>,
+    options: &DecodeOptions,
+) -> Result<(String, DecodeOptions)> {
+    let mut options = options.clone();
+    if let Some(model) = requested {
+        return Ok((model.to_string(), options));
+    }
+    let routes = routing::routes(&db::connect(app)?)?;
+    if routes.is_empty() {
+        return Ok((DEFAULT_MODEL.to_string(), options));
+    }
+
+    let language = match options.language.clone().filter(|lang| lang != "auto") {
+        Some(language) => Some(language),
+        // Without a detection model, fall back to the default rather than fail.
+        None => {
+            let threads = options.threads.unwrap_or_else(whisper::default_threads);
+            models::model_path(app, DEFAULT_MODEL)
+                .and_then(|path| {
+                    app.state::<worker::Supervisor>()
+                        .detect_language(&path, pcm, threads)
+                })
+                .ok()
+        }
+    };
+    let model = language
+        .as_deref()
+        .and_then(|language| routing::route(&routes, language))
+        .filter(|model| models::model_path(app, model).is_ok())
+        .unwrap_or(DEFAULT_MODEL)
+        .to_string();
+    options.language = language;
+    Ok((model, options))
+}
+
+/// Decode and transcribe an audio file on the current thread. Without a
+/// `model`, the language routing rules choose one.
+pub fn transcribe_path(
+    app: &AppHandle,
+    path: &std::path::Path,
+    model: Option<&str>,
+    options: &DecodeOptions,
+    parallel: bool,
+) -> Result<TranscriptionOutput> {
+    transcribe_pcm(app, &audio::load_pcm(path)?, model, options, parallel)
+}
+
+/// Transcribe 16 kHz mono samples on the current thread.
+pub fn transcribe_pcm(
+    app: &AppHandle,
+    pcm: &[f32],
+    model: Option<&str>,
+    options: &DecodeOptions,
+    parallel: bool,
+) -> Result<TranscriptionOutput> {
+    transcribe_with(app, pcm, model, options, |model, options| {
+        let ctx = model_cache::context_for(app, model)?;
+        decode(&ctx, pcm, options, parallel)
+    })
+}
+
+/// Like [`transcribe_pcm`], but decoding in the inference worker process,
+/// so a crash in whisper.cpp fails only this call. The worker loads its
+/// own copy of the model, apart from the app's model cache.
+pub fn transcribe_pcm_isolated(
+    app: &AppHandle,
+    pcm: &[f32],
+    model: Option<&str>,
+    options: &DecodeOptions,
+    parallel: bool,
+) -> Result<TranscriptionOutput> {
+    transcribe_with(app, pcm, model, options, |model, options| {
+        app.state::<worker::Supervisor>().decode(
+            &models::model_path(app, model)?,
+            pcm,
+            options,
+            parallel,
+        )
+    })
+}
+
+fn transcribe_with(
+    app: &AppHandle,
+    pcm: &[f32],
+    model: Option<&str>,
+    options: &DecodeOptions,
+    run_decoder: impl FnOnce(&str, &DecodeOptions) -> Result<Vec<Segment>>,
+) -> Result<TranscriptionOutput> {
+    let mut options = options.clone();
+    options.advanced = options.advanced.or(decoding_defaults(&db::connect(app)?)?);
+    options.advanced.validate()?;
+
+    let (model, options) = resolve_model(app, pcm, model, &options)?;
+    let options = &options;
+    let segments = run_decoder(&model, options)?;
+    let speech = vad::speech_regions(pcm);
+    let (segments, hallucinations) = postprocess::apply(
+        &db::connect(app)?,
+        segments,
+        Some(&speech),
+        options.language.as_deref(),
+    )?;
+
+    Ok(TranscriptionOutput {
+        text: whisper::join_text(&segments),
+        segments,
+        language: options.language.clone().unwrap_or_else(|| "auto".into()),
+        duration: audio::duration_secs(pcm),
+        model_used: format!("whisper-{}", model),
+        meeting_type: None,
+        hallucinations,
+        speakers: None,
+    })
+}
+
+/// Transcribe a file once the pre-flight checks pass. With a `series` tag
+/// in the options, names and terms from the latest transcription carrying
+/// that tag join the decoder prompt.
+#[tauri::command]
+pub async fn transcribe_file(
+    app: AppHandle,
+    path: PathBuf,
+    model: Option<String>,
+    options: Option<TranscribeOptions>,
+) -> Result<TranscriptionOutput> {
+    let TranscribeOptions {
+        language,
+        parallel,
+        meeting_type,
+        advanced,
+        series,
+    } = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        preflight::check(
+            &app,
+            &JobSpec::Transcription {
+                path: path.clone(),
+                model: model.clone(),
+                output_dir: None,
+            },
+        )?
+        .into_result()?;
+        let conn = db::connect(&app)?;
+        let meeting_type = meeting_type
+            .map(|key| meeting_types::find(&conn, &key))
+            .transpose()?;
+        let options = DecodeOptions {
+            language,
+            initial_prompt: initial_prompt(&conn, meeting_type.as_ref(), series.as_deref())?,
+            advanced: advanced.unwrap_or_default(),
+            ..Default::default()
+        };
+
+        let mut output = transcribe_pcm_isolated(
+            &app,
+            &audio::load_pcm(&path)?,
+            model.as_deref(),
+            &options,
+            parallel.unwrap_or(true),
+        )?;
+        output.meeting_type = meeting_type;
+        autoexport::run(&app, &path, &output);
+        Ok(output)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+/// Transcribe a stored transcription's audio again, e.g. with a larger
+/// model, skipping the audio outside its trim points, in the inference
+/// worker. Names and terms from the previous meeting sharing one of its
+/// tags join the decoder prompt. Segment times stay relative to the start
+/// of the original recording. The result is returned for the caller to
+/// save, like a new transcription. As for new files, the pre-flight checks
+/// run before any audio is decoded.
+#[tauri::command]
+pub async fn retranscribe(
+    app: AppHandle,
+    transcription_id: String,
+    model: Option<String>,
+    language: Option<String>,
+    advanced: Option<AdvancedOptions>,
+) -> Result<TranscriptionOutput> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::connect(&app)?;
+        for path in clips::source_files(&conn, &transcription_id)? {
+            preflight::check(
+                &app,
+                &JobSpec::Transcription {
+                    path,
+                    model: model.clone(),
+                    output_dir: None,
+                },
+            )?
+            .into_result()?;
+        }
+        let pcm = clips::source_pcm(&conn, &transcription_id)?;
+        let trim = trim::for_transcription(&conn, &transcription_id)?;
+        let offset = trim.offset_secs();
+        let options = DecodeOptions {
+            language,
+            initial_prompt: series::prompt_for_transcription(&conn, &transcription_id)?,
+            advanced: advanced.unwrap_or_default(),
+            ..Default::default()
+        };
+
+        let mut output =
+            transcribe_pcm_isolated(&app, trim.apply(&pcm), model.as_deref(), &options, true)?;
+        for segment in &mut output.segments {
+            segment.start += offset;
+            segment.end += offset;
+        }
+        output.duration = audio::duration_secs(&pcm);
+        Ok(output)
+    })
+    .await
+    .map_err(|e| Error::Transcription(e.to_string()))?
+}
+
+#[tauri::command]
+pub fn get_decoding_defaults(app: AppHandle) -> Result<AdvancedOptions> {
+    decoding_defaults(&db::connect(&app)?)
+}
+
+/// Store advanced decoding defaults after checking every value is in range.
+#[tauri::command]
+pub fn set_decoding_defaults(app: AppHandle, options: AdvancedOptions) -> Result<()> {
+    options.validate()?;
+    db::set_preference(
+        &db::connect(&app)?,
+        DECODING_PREFERENCE,
+        &serde_json::to_string(&options).unwrap(),
+    )
+}
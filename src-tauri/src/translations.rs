@@ -0,0 +1,256 @@
+//! Translated segment text and bilingual export.
+//!
+//! Translations are stored per segment position rather than segment id,
+//! since saving edited segments replaces their rows. A bilingual export
+//! pairs each segment with its translation: interleaved lines, a two-column
+//! Markdown table, or SRT cues carrying both lines.
+
+use std::fs;
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::bulk::srt_time;
+use crate::error::{Error, Result};
+use crate::segments::{self, StoredSegment};
+use crate::verbatim::timestamp;
+use crate::{db, i18n};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Translation {
+    pub position: i64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BilingualFormat {
+    /// Markdown, each original line followed by its translation.
+    Interleaved,
+    /// Markdown table with the original and translation side by side.
+    Table,
+    /// SRT with the original and translation as two lines of each cue.
+    Srt,
+}
+
+pub fn for_language(
+    conn: &Connection,
+    transcription_id: &str,
+    language: &str,
+) -> Result<Vec<Translation>> {
+    let mut statement = conn.prepare(
+        "SELECT position, text FROM translations
+         WHERE transcription_id = ?1 AND language = ?2 ORDER BY position",
+    )?;
+    let translations = statement
+        .query_map(params![transcription_id, language], |row| {
+            Ok(Translation {
+                position: row.get(0)?,
+                text: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(translations)
+}
+
+fn table_cell(text: &str) -> String {
+    text.trim().replace('|', "\\|").replace('\n', " ")
+}
+
+/// Render segments next to their translations. Segments without a
+/// translation keep an empty translation line or cell.
+fn render(
+    segments: &[StoredSegment],
+    translations: &[Translation],
+    source_language: &str,
+    language: &str,
+    format: BilingualFormat,
+) -> String {
+    let translated = |segment: &StoredSegment| {
+        translations
+            .iter()
+            .find(|t| t.position == segment.position)
+            .map_or("", |t| t.text.trim())
+    };
+    match format {
+        BilingualFormat::Interleaved => segments
+            .iter()
+            .map(|segment| {
+                format!(
+                    "**[{}]** {}\n> {}\n\n",
+                    timestamp(segment.start),
+                    segment.text.trim(),
+                    translated(segment)
+                )
+            })
+            .collect(),
+        BilingualFormat::Table => {
+            let mut out = format!(
+                "| {} | {} | {} |\n| --- | --- | --- |\n",
+                i18n::t("heading-time"),
+                source_language,
+                language
+            );
+            for segment in segments {
+                out.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    timestamp(segment.start),
+                    table_cell(&segment.text),
+                    table_cell(translated(segment))
+                ));
+            }
+            out
+        }
+        BilingualFormat::Srt => segments
+            .iter()
+            .enumerate()
+            .map(|(n, segment)| {
+                format!(
+                    "{}\n{} --> {}\n{}\n{}\n\n",
+                    n + 1,
+                    srt_time(segment.start),
+                    srt_time(segment.end),
+                    segment.text.trim(),
+                    translated(segment)
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Store a transcription's translation into `language`, replacing any
+/// earlier translation into that language.
+#[tauri::command]
+pub fn save_translations(
+    app: AppHandle,
+    transcription_id: String,
+    language: String,
+    translations: Vec<Translation>,
+) -> Result<()> {
+    let mut conn = db::connect(&app)?;
+    db::transcription_text(&conn, &transcription_id)?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM translations WHERE transcription_id = ?1 AND language = ?2",
+        params![transcription_id, language],
+    )?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO translations (transcription_id, language, position, text)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for translation in &translations {
+            insert.execute(params![
+                transcription_id,
+                language,
+                translation.position,
+                translation.text.trim()
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_translations(
+    app: AppHandle,
+    transcription_id: String,
+    language: String,
+) -> Result<Vec<Translation>> {
+    for_language(&db::connect(&app)?, &transcription_id, &language)
+}
+
+/// Write the transcription and its translation into `language` to `path`.
+#[tauri::command]
+pub fn export_bilingual(
+    app: AppHandle,
+    transcription_id: String,
+    language: String,
+    format: BilingualFormat,
+    path: PathBuf,
+) -> Result<()> {
+    let conn = db::connect(&app)?;
+    let source_language: String = conn
+        .query_row(
+            "SELECT language FROM transcriptions WHERE id = ?1",
+            [&transcription_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("transcription {}", transcription_id)))?;
+    let segments = segments::for_transcription(&conn, &transcription_id)?;
+    if segments.is_empty() {
+        return Err(Error::InvalidInput(
+            "bilingual export needs a transcription with timed segments".into(),
+        ));
+    }
+    let translations = for_language(&conn, &transcription_id, &language)?;
+    if translations.is_empty() {
+        return Err(Error::NotFound(format!(
+            "{} translation of transcription {}",
+            language, transcription_id
+        )));
+    }
+    fs::write(
+        path,
+        render(
+            &segments,
+            &translations,
+            &source_language,
+            &language,
+            format,
+        ),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(position: i64, text: &str, start: f64, end: f64) -> StoredSegment {
+        StoredSegment {
+            id: String::new(),
+            transcription_id: "t".into(),
+            position,
+            speaker: None,
+            text: text.into(),
+            start,
+            end,
+            confidence: None,
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn pairs_segments_with_translations_by_position() {
+        let segments = [
+            segment(0, " Goedemorgen.", 0.0, 1.5),
+            segment(1, " Hoe gaat het | vandaag?", 1.5, 3.0),
+        ];
+        let translations = [Translation {
+            position: 1,
+            text: "How are you | today?".into(),
+        }];
+        assert_eq!(
+            render(&segments, &translations, "nl", "en", BilingualFormat::Table),
+            "| Time | nl | en |\n| --- | --- | --- |\n\
+             | 00:00:00 | Goedemorgen. |  |\n\
+             | 00:00:01 | Hoe gaat het \\| vandaag? | How are you \\| today? |\n"
+        );
+        assert_eq!(
+            render(
+                &segments[1..],
+                &translations,
+                "nl",
+                "en",
+                BilingualFormat::Srt
+            ),
+            "1\n00:00:01,500 --> 00:00:03,000\nHoe gaat het | vandaag?\nHow are you | today?\n\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Non-destructive trim points on a transcription's source audio.
+//!
+//! Trim points are stored with the transcription and the audio file is
+//! never rewritten. Playback, clip export and re-transcription read the
+//! whole file and keep only the samples inside the window, so positions
+//! stay in seconds from the start of the original recording.
+
+use std::ops::Range;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use crate::db;
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioTrim {
+    /// Audio before this offset is skipped; `None` keeps the start.
+    pub start_ms: Option<i64>,
+    /// Audio after this offset is skipped; `None` keeps the end.
+    pub end_ms: Option<i64>,
+}
+
+impl AudioTrim {
+    /// Samples kept out of `len` samples at `rate` Hz.
+    pub fn sample_range(&self, len: usize, rate: u32) -> Range<usize> {
+        let index = |ms: i64| ((ms.max(0) as u64 * rate as u64 / 1000) as usize).min(len);
+        let start = self.start_ms.map_or(0, index);
+        let end = self.end_ms.map_or(len, index).max(start);
+        start..end
+    }
+
+    /// Seconds before the kept audio, for shifting timestamps of audio
+    /// decoded from the trimmed samples.
+    pub fn offset_secs(&self) -> f64 {
+        self.start_ms.unwrap_or(0).max(0) as f64 / 1000.0
+    }
+
+    /// The kept part of 16 kHz samples.
+    pub fn apply<'a>(&self, pcm: &'a [f32]) -> &'a [f32] {
+        &pcm[self.sample_range(pcm.len(), WHISPER_SAMPLE_RATE)]
+    }
+}
+
+pub fn for_transcription(conn: &Connection, transcription_id: &str) -> Result<AudioTrim> {
+    conn.query_row(
+        "SELECT trim_start_ms, trim_end_ms FROM transcriptions WHERE id = ?1",
+        [transcription_id],
+        |row| {
+            Ok(AudioTrim {
+                start_ms: row.get(0)?,
+                end_ms: row.get(1)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| Error::NotFound(format!("transcription {}", transcription_id)))
+}
+
+/// Set the trim points of a transcription. Passing neither restores the
+/// full recording.
+#[tauri::command]
+pub fn set_audio_trim(
+    app: AppHandle,
+    transcription_id: String,
+    start_ms: Option<i64>,
+    end_ms: Option<i64>,
+) -> Result<AudioTrim> {
+    let trim = AudioTrim { start_ms, end_ms };
+    if start_ms.is_some_and(|ms| ms < 0) || end_ms.is_some_and(|ms| ms <= 0) {
+        return Err(Error::InvalidInput(
+            "trim points must be after the start of the audio".into(),
+        ));
+    }
+    if let (Some(start), Some(end)) = (start_ms, end_ms) {
+        if end <= start {
+            return Err(Error::InvalidInput(format!(
+                "trim ends at {} ms, before it starts at {} ms",
+                end, start
+            )));
+        }
+    }
+
+    let conn = db::connect(&app)?;
+    let duration: Option<f64> = conn
+        .query_row(
+            "SELECT duration FROM transcriptions WHERE id = ?1",
+            [&transcription_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| Error::NotFound(format!("transcription {}", transcription_id)))?;
+    if let (Some(start), Some(duration)) = (start_ms, duration) {
+        if start as f64 >= duration * 1000.0 {
+            return Err(Error::InvalidInput(format!(
+                "trim starts at {} ms, after the audio ends",
+                start
+            )));
+        }
+    }
+
+    conn.execute(
+        "UPDATE transcriptions SET trim_start_ms = ?1, trim_end_ms = ?2 WHERE id = ?3",
+        params![trim.start_ms, trim.end_ms, transcription_id],
+    )?;
+    Ok(trim)
+}
+
+#[tauri::command]
+pub fn get_audio_trim(app: AppHandle, transcription_id: String) -> Result<AudioTrim> {
+    for_transcription(&db::connect(&app)?, &transcription_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_samples_inside_the_window() {
+        let trim = AudioTrim {
+            start_ms: Some(500),
+            end_ms: Some(1_500),
+        };
+        assert_eq!(trim.sample_range(32_000, 16_000), 8_000..24_000);
+        // An end past the audio is cut at the last sample.
+        assert_eq!(trim.sample_range(16_000, 16_000), 8_000..16_000);
+        assert_eq!(AudioTrim::default().sample_range(100, 16_000), 0..100);
+        assert_eq!(
+            AudioTrim {
+                start_ms: Some(2_000),
+                end_ms: None
+            }
+            .sample_range(16_000, 16_000),
+            16_000..16_000
+        );
+    }
+}
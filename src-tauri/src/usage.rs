@@ -0,0 +1,183 @@
+//! Usage and estimated cost of cloud provider requests.
+//!
+//! Each billed request is recorded in `provider_usage` with its token or
+//! audio counts. Costs are estimated from published list prices; models we
+//! have no price for are recorded without a cost.
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use tauri::AppHandle;
+
+use crate::db;
+use crate::error::{Error, Result};
+use crate::llm::TokenUsage;
+
+/// USD per million input and output tokens. More specific names first, as
+/// models are matched by prefix.
+const TOKEN_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+];
+
+/// USD per minute of audio.
+const AUDIO_PRICES: &[(&str, f64)] = &[
+    ("gpt-4o-mini-transcribe", 0.003),
+    ("gpt-4o-transcribe", 0.006),
+    ("whisper-1", 0.006),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Summarization,
+    Transcription,
+}
+
+impl Operation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Operation::Summarization => "summarization",
+            Operation::Transcription => "transcription",
+        }
+    }
+}
+
+pub struct UsageEntry<'a> {
+    pub provider: &'a str,
+    pub model: &'a str,
+    pub operation: Operation,
+    pub transcription_id: Option<&'a str>,
+    pub tokens: TokenUsage,
+    pub audio_seconds: f64,
+}
+
+/// Estimated cost in USD, or `None` for models without a known price.
+pub fn estimate_cost(model: &str, tokens: TokenUsage, audio_seconds: f64) -> Option<f64> {
+    if audio_seconds > 0.0 {
+        let (_, per_minute) = AUDIO_PRICES
+            .iter()
+            .find(|(name, _)| model.starts_with(name))?;
+        return Some(audio_seconds / 60.0 * per_minute);
+    }
+    let (_, input, output) = TOKEN_PRICES
+        .iter()
+        .find(|(name, _, _)| model.starts_with(name))?;
+    Some((tokens.input_tokens as f64 * input + tokens.output_tokens as f64 * output) / 1_000_000.0)
+}
+
+pub fn record(conn: &Connection, entry: &UsageEntry) -> Result<()> {
+    conn.execute(
+        "INSERT INTO provider_usage (provider, model, operation, transcription_id,
+             input_tokens, output_tokens, audio_seconds, estimated_cost)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            entry.provider,
+            entry.model,
+            entry.operation.as_str(),
+            entry.transcription_id,
+            entry.tokens.input_tokens as i64,
+            entry.tokens.output_tokens as i64,
+            entry.audio_seconds,
+            estimate_cost(entry.model, entry.tokens, entry.audio_seconds)
+        ],
+    )?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageLine {
+    pub provider: String,
+    pub model: String,
+    pub operation: String,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub audio_minutes: f64,
+    pub estimated_cost: f64,
+    /// Requests to models without a known price, not included in the cost.
+    pub unpriced_requests: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub month: String,
+    pub lines: Vec<UsageLine>,
+    pub total_cost: f64,
+}
+
+fn valid_month(month: &str) -> bool {
+    match month.split_once('-') {
+        Some((year, number)) => {
+            year.len() == 4
+                && year.bytes().all(|b| b.is_ascii_digit())
+                && number.len() == 2
+                && matches!(number.parse::<u32>(), Ok(1..=12))
+        }
+        None => false,
+    }
+}
+
+/// Provider usage and estimated spend for a month given as `YYYY-MM`.
+#[tauri::command]
+pub fn get_usage_report(app: AppHandle, month: String) -> Result<UsageReport> {
+    if !valid_month(&month) {
+        return Err(Error::InvalidInput(format!(
+            "month must look like 2024-03, got '{}'",
+            month
+        )));
+    }
+    let conn = db::connect(&app)?;
+    let mut statement = conn.prepare(
+        "SELECT provider, model, operation, COUNT(*), SUM(input_tokens), SUM(output_tokens),
+                SUM(audio_seconds) / 60.0, COALESCE(SUM(estimated_cost), 0),
+                SUM(estimated_cost IS NULL)
+         FROM provider_usage
+         WHERE strftime('%Y-%m', created_at) = ?1
+         GROUP BY provider, model, operation
+         ORDER BY 8 DESC",
+    )?;
+    let lines: Vec<UsageLine> = statement
+        .query_map([&month], |row| {
+            Ok(UsageLine {
+                provider: row.get(0)?,
+                model: row.get(1)?,
+                operation: row.get(2)?,
+                requests: row.get(3)?,
+                input_tokens: row.get(4)?,
+                output_tokens: row.get(5)?,
+                audio_minutes: row.get(6)?,
+                estimated_cost: row.get(7)?,
+                unpriced_requests: row.get(8)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(UsageReport {
+        total_cost: lines.iter().map(|line| line.estimated_cost).sum(),
+        lines,
+        month,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_by_most_specific_model_prefix() {
+        let tokens = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 500_000,
+        };
+        let cost = |model, tokens, audio| estimate_cost(model, tokens, audio).unwrap();
+        assert!((cost("gpt-4o-mini-2024-07-18", tokens, 0.0) - 0.45).abs() < 1e-9);
+        assert!((cost("gpt-4o", tokens, 0.0) - 7.5).abs() < 1e-9);
+        assert!((cost("whisper-1", TokenUsage::default(), 120.0) - 0.012).abs() < 1e-9);
+        assert_eq!(estimate_cost("llama3.2", tokens, 0.0), None);
+    }
+}
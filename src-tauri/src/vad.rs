@@ -0,0 +1,145 @@
+//! Energy-based voice activity detection.
+//!
+//! Works on short-time frame energy, which is cheap enough to run over whole
+//! files before decoding and needs no separate model.
+
+use std::ops::Range;
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+
+/// 30 ms analysis frames at 16 kHz.
+pub const FRAME_LEN: usize = 480;
+
+/// Root-mean-square energy of each frame.
+pub fn frame_energies(pcm: &[f32]) -> Vec<f32> {
+    pcm.chunks(FRAME_LEN)
+        .map(|frame| (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt())
+        .collect()
+}
+
+/// Frame energy below which audio counts as silence for live capture.
+pub const SILENCE_RMS: f32 = 0.01;
+
+pub fn is_silent(pcm: &[f32]) -> bool {
+    frame_energies(pcm)
+        .iter()
+        .all(|energy| *energy < SILENCE_RMS)
+}
+
+/// Whether the last `secs` of `pcm` are silent.
+pub fn ends_in_silence(pcm: &[f32], secs: f64) -> bool {
+    let tail = ((secs * WHISPER_SAMPLE_RATE as f64) as usize).min(pcm.len());
+    is_silent(&pcm[pcm.len() - tail..])
+}
+
+/// Speech must be this many times louder than the noise floor.
+const SPEECH_FACTOR: f32 = 3.0;
+/// Keeps near-digital silence from making every breath count as speech.
+const MIN_THRESHOLD: f32 = 0.005;
+
+/// Energy threshold adapted to the recording: a multiple of the noise
+/// floor, estimated as the 20th percentile frame energy.
+fn speech_threshold(energies: &[f32]) -> f32 {
+    if energies.is_empty() {
+        return MIN_THRESHOLD;
+    }
+    let mut sorted = energies.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let floor = sorted[sorted.len() / 5];
+    (floor * SPEECH_FACTOR).max(MIN_THRESHOLD)
+}
+
+/// Sample ranges that contain speech, with short gaps bridged.
+pub fn speech_regions(pcm: &[f32]) -> Vec<Range<usize>> {
+    let energies = frame_energies(pcm);
+    let threshold = speech_threshold(&energies);
+    // Pauses shorter than 300 ms stay inside the surrounding region.
+    let max_gap = 10;
+
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    for (index, energy) in energies.iter().enumerate() {
+        if *energy < threshold {
+            continue;
+        }
+        let start = index * FRAME_LEN;
+        let end = ((index + 1) * FRAME_LEN).min(pcm.len());
+        match regions.last_mut() {
+            Some(last) if start <= last.end + max_gap * FRAME_LEN => last.end = end,
+            _ => regions.push(start..end),
+        }
+    }
+    regions
+}
+
+/// Fraction of `range` (in seconds) covered by detected speech.
+pub fn speech_coverage(regions: &[Range<usize>], start: f64, end: f64) -> f64 {
+    let rate = WHISPER_SAMPLE_RATE as f64;
+    let (from, to) = ((start * rate) as usize, (end * rate) as usize);
+    if to <= from {
+        return 0.0;
+    }
+    let covered: usize = regions
+        .iter()
+        .map(|region| region.end.min(to).saturating_sub(region.start.max(from)))
+        .sum();
+    covered as f64 / (to - from) as f64
+}
+
+/// Split audio into chunks of roughly `target_secs`, cutting at the quietest
+/// point (smoothed over 300 ms) between the target and `max_secs`.
+pub fn split_at_silence(pcm: &[f32], target_secs: f64, max_secs: f64) -> Vec<Range<usize>> {
+    let rate = WHISPER_SAMPLE_RATE as f64;
+    let target = (target_secs * rate) as usize / FRAME_LEN;
+    let max = ((max_secs * rate) as usize / FRAME_LEN).max(target + 1);
+
+    let energies = frame_energies(pcm);
+    let window = 10;
+    let smoothed: Vec<f32> = (0..energies.len())
+        .map(|i| {
+            let lo = i.saturating_sub(window / 2);
+            let hi = (i + window / 2 + 1).min(energies.len());
+            energies[lo..hi].iter().sum::<f32>() / (hi - lo) as f32
+        })
+        .collect();
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < energies.len() {
+        if energies.len() - start <= max {
+            chunks.push(start * FRAME_LEN..pcm.len());
+            break;
+        }
+        let cut = (start + target..start + max)
+            .min_by(|&a, &b| smoothed[a].total_cmp(&smoothed[b]))
+            .unwrap_or(start + max);
+        chunks.push(start * FRAME_LEN..cut * FRAME_LEN);
+        start = cut;
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|i| (i as f32 * 0.05).sin() * 0.5)
+            .collect()
+    }
+
+    #[test]
+    fn splits_at_the_quiet_gap() {
+        // 8 s of signal, 1 s of silence, 8 s of signal.
+        let mut pcm = tone(8 * 16_000);
+        pcm.extend(vec![0.0; 16_000]);
+        pcm.extend(tone(8 * 16_000));
+
+        let chunks = split_at_silence(&pcm, 6.0, 12.0);
+        assert_eq!(chunks.len(), 2);
+        let cut = chunks[0].end;
+        assert!((8 * 16_000..9 * 16_000).contains(&cut));
+        assert_eq!(chunks[1].start, cut);
+        assert_eq!(chunks[1].end, pcm.len());
+    }
+}
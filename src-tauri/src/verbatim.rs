@@ -0,0 +1,219 @@
+//! Verbatim export for legal and medical transcription.
+//!
+//! Every utterance is kept as decoded, fillers included. Unintelligible
+//! stretches become `[inaudible HH:MM:SS]`, lines are numbered on fixed-size
+//! pages, and an optional certification page closes the document.
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use tauri::AppHandle;
+
+use crate::error::{Error, Result};
+use crate::segments::{self, StoredSegment};
+use crate::{db, i18n};
+
+/// Markers whisper and human editors use for audio that could not be made out.
+const INAUDIBLE_MARKERS: &[&str] = &[
+    "[inaudible]",
+    "(inaudible)",
+    "[blank_audio]",
+    "[unintelligible]",
+    "(unintelligible)",
+    "[crosstalk]",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Certification {
+    pub transcriber: String,
+    /// Defaults to a standard statement of accuracy.
+    pub statement: Option<String>,
+    pub date: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VerbatimOptions {
+    /// Characters per line before wrapping.
+    pub line_width: usize,
+    /// Numbered lines per page; legal transcripts conventionally use 25.
+    pub lines_per_page: usize,
+    /// Segments below this confidence are marked inaudible.
+    pub inaudible_below: f64,
+    pub title: Option<String>,
+    pub certification: Option<Certification>,
+}
+
+impl Default for VerbatimOptions {
+    fn default() -> Self {
+        Self {
+            line_width: 60,
+            lines_per_page: 25,
+            inaudible_below: 0.35,
+            title: None,
+            certification: None,
+        }
+    }
+}
+
+pub fn timestamp(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        total / 3600,
+        total / 60 % 60,
+        total % 60
+    )
+}
+
+fn is_inaudible(segment: &StoredSegment, threshold: f64) -> bool {
+    let text = segment.text.trim().to_lowercase();
+    text.is_empty()
+        || INAUDIBLE_MARKERS.contains(&text.as_str())
+        || segment.confidence.is_some_and(|c| c < threshold)
+}
+
+/// Greedy word wrap; words longer than `width` get a line of their own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+pub fn render(segments: &[StoredSegment], options: &VerbatimOptions) -> String {
+    let width = options.line_width.max(20);
+    let per_page = options.lines_per_page.max(1);
+
+    let mut body = Vec::new();
+    let mut previous_speaker: Option<&str> = None;
+    for segment in segments {
+        let text = if is_inaudible(segment, options.inaudible_below) {
+            i18n::t_args("verbatim-inaudible", &[("time", timestamp(segment.start))])
+        } else {
+            segment.text.trim().to_string()
+        };
+        // Each change of speaker starts a new, labeled paragraph.
+        let speaker = segment.speaker.as_deref();
+        let text = match speaker {
+            Some(name) if speaker != previous_speaker => {
+                format!("{}: {}", name.to_uppercase(), text)
+            }
+            _ => text,
+        };
+        previous_speaker = speaker;
+        body.extend(wrap(&text, width));
+    }
+
+    let mut out = String::new();
+    if let Some(title) = &options.title {
+        out.push_str(&format!("{}\n\n", title));
+    }
+    let pages = body.len().div_ceil(per_page).max(1);
+    for (page, lines) in body.chunks(per_page).enumerate() {
+        if page > 0 {
+            out.push('\u{c}');
+        }
+        out.push_str(&format!(
+            "{:>w$}\n\n",
+            i18n::t_args(
+                "verbatim-page",
+                &[
+                    ("page", (page + 1).to_string()),
+                    ("pages", pages.to_string())
+                ]
+            ),
+            w = width + 4
+        ));
+        for (n, line) in lines.iter().enumerate() {
+            out.push_str(&format!("{:>2}  {}\n", n + 1, line));
+        }
+        out.push('\n');
+    }
+
+    if let Some(cert) = &options.certification {
+        out.push('\u{c}');
+        out.push_str(&format!("{}\n\n", i18n::t("verbatim-certification")));
+        let statement = cert
+            .statement
+            .clone()
+            .unwrap_or_else(|| i18n::t("verbatim-statement"));
+        for line in wrap(&statement, width) {
+            out.push_str(&format!("{}\n", line));
+        }
+        out.push_str(&format!(
+            "\n\n______________________________\n{}\n{}\n",
+            cert.transcriber, cert.date
+        ));
+    }
+    out
+}
+
+/// Write a stored transcription in the verbatim profile as plain text.
+#[tauri::command]
+pub fn export_verbatim(
+    app: AppHandle,
+    id: String,
+    path: PathBuf,
+    options: Option<VerbatimOptions>,
+) -> Result<()> {
+    let conn = db::connect(&app)?;
+    db::transcription_text(&conn, &id)?;
+    let segments = segments::for_transcription(&conn, &id)?;
+    if segments.is_empty() {
+        return Err(Error::InvalidInput(
+            "verbatim export needs a transcription with timed segments".into(),
+        ));
+    }
+    fs::write(path, render(&segments, &options.unwrap_or_default()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(speaker: &str, text: &str, start: f64, confidence: f64) -> StoredSegment {
+        StoredSegment {
+            id: String::new(),
+            transcription_id: String::new(),
+            position: 0,
+            speaker: Some(speaker.into()),
+            text: text.into(),
+            start,
+            end: start + 1.0,
+            confidence: Some(confidence),
+            words: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn marks_inaudible_and_numbers_lines() {
+        let text = render(
+            &[
+                segment("Witness", "Um, I was, uh, at home.", 0.0, 0.9),
+                segment("Witness", "mumble", 872.0, 0.1),
+                segment("Counsel", "[BLANK_AUDIO]", 880.0, 0.9),
+            ],
+            &VerbatimOptions::default(),
+        );
+
+        assert!(text.contains(" 1  WITNESS: Um, I was, uh, at home.\n"));
+        assert!(text.contains(" 2  [inaudible 00:14:32]\n"));
+        assert!(text.contains(" 3  COUNSEL: [inaudible 00:14:40]\n"));
+        assert!(!text.contains("CERTIFICATION"));
+    }
+}
@@ -0,0 +1,230 @@
+//! Hands-free control of recording through spoken commands.
+//!
+//! When enabled, a listener keeps the microphone open and runs the smallest
+//! downloaded whisper model over short utterances, looking for a fixed set
+//! of phrases. The model is loaded on its own rather than through the model
+//! cache, so it never competes with the engine used for transcription.
+
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::crash;
+use crate::error::{Error, Result};
+use crate::evaluation::normalize_words;
+use crate::recording::{self, Utterances};
+use crate::whisper::{self, DecodeOptions};
+use crate::{db, models};
+
+pub const ENABLED_PREFERENCE: &str = "voice_commands_enabled";
+pub const RECOGNIZED_EVENT: &str = "voice-command://recognized";
+pub const RECORDING_STOPPED_EVENT: &str = "recording://stopped";
+
+/// Keyword models in order of preference; the first one downloaded is used.
+const KEYWORD_MODELS: &[&str] = &["tiny.en", "tiny", "base.en", "base"];
+/// Commands are short, so long utterances are cut early.
+const MIN_UTTERANCE_SECS: f64 = 0.5;
+const MAX_UTTERANCE_SECS: f64 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceCommand {
+    StartRecording,
+    StopRecording,
+    AddMarker,
+}
+
+const PHRASES: &[(&str, VoiceCommand)] = &[
+    ("start recording", VoiceCommand::StartRecording),
+    ("stop recording", VoiceCommand::StopRecording),
+    ("add marker", VoiceCommand::AddMarker),
+];
+
+/// Find the first command phrase spoken as consecutive words in `text`.
+pub fn match_command(text: &str) -> Option<VoiceCommand> {
+    let words = normalize_words(text);
+    PHRASES.iter().find_map(|(phrase, command)| {
+        let phrase: Vec<&str> = phrase.split(' ').collect();
+        words
+            .windows(phrase.len())
+            .any(|window| window.iter().zip(&phrase).all(|(w, p)| w == p))
+            .then_some(*command)
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecognizedCommand {
+    pub command: VoiceCommand,
+    /// Session the command acted on, if any.
+    pub session_id: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct VoiceCommandState(Mutex<Option<mpsc::Sender<()>>>);
+
+/// Carry out `command`; returns the recording session it acted on.
+pub fn perform(app: &AppHandle, command: VoiceCommand) -> Result<Option<String>> {
+    match command {
+        VoiceCommand::StartRecording => {
+            if let Some(active) = recording::active_sessions(app).into_iter().next() {
+                return Ok(Some(active));
+            }
+            recording::start(app, None).map(Some)
+        }
+        VoiceCommand::StopRecording => {
+            let Some(id) = recording::active_sessions(app).into_iter().next() else {
+                return Ok(None);
+            };
+            let session = recording::stop(app, &id)?;
+            let _ = app.emit_all(RECORDING_STOPPED_EVENT, &session);
+            Ok(Some(id))
+        }
+        VoiceCommand::AddMarker => {
+            let sessions = recording::active_sessions(app);
+            for id in &sessions {
+                recording::add_marker(app, id)?;
+            }
+            Ok(sessions.into_iter().next())
+        }
+    }
+}
+
+fn keyword_model(app: &AppHandle) -> Result<std::path::PathBuf> {
+    KEYWORD_MODELS
+        .iter()
+        .find_map(|name| models::model_path(app, name).ok())
+        .ok_or_else(|| {
+            Error::NotFound("voice commands need the tiny or base model downloaded".into())
+        })
+}
+
+fn run(app: AppHandle, stop: mpsc::Receiver<()>, ready: mpsc::Sender<Result<()>>) {
+    let setup = || -> Result<_> {
+        let ctx = whisper::load_context(&keyword_model(&app)?)?;
+        let utterances = Utterances::open(MIN_UTTERANCE_SECS, MAX_UTTERANCE_SECS)?;
+        Ok((ctx, utterances))
+    };
+    let (ctx, mut utterances) = match setup() {
+        Ok(started) => started,
+        Err(err) => {
+            let _ = ready.send(Err(err));
+            return;
+        }
+    };
+    let _ = ready.send(Ok(()));
+
+    // Keep the listener light: it runs alongside recording and transcription.
+    let options = DecodeOptions {
+        language: Some("en".into()),
+        threads: Some(2),
+        initial_prompt: Some("Start recording. Stop recording. Add marker.".into()),
+        ..Default::default()
+    };
+
+    while stop.try_recv().is_err() {
+        let Some(utterance) = utterances.poll() else {
+            continue;
+        };
+        let text = match whisper::transcribe(&ctx, &utterance, &options) {
+            Ok(segments) => whisper::join_text(&segments),
+            Err(err) => {
+                crash::log(format!("voice command decode failed: {}", err));
+                continue;
+            }
+        };
+        let Some(command) = match_command(&text) else {
+            continue;
+        };
+
+        let (session_id, error) = match perform(&app, command) {
+            Ok(id) => (id, None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        let _ = app.emit_all(
+            RECOGNIZED_EVENT,
+            RecognizedCommand {
+                command,
+                session_id,
+                error,
+            },
+        );
+    }
+}
+
+fn start(app: &AppHandle) -> Result<()> {
+    let state = app.state::<VoiceCommandState>();
+    let mut listener = state.0.lock().unwrap();
+    if listener.is_some() {
+        return Ok(());
+    }
+
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let handle = app.clone();
+    thread::spawn(move || run(handle, stop_rx, ready_tx));
+    ready_rx
+        .recv()
+        .map_err(|_| Error::AudioDevice("voice command listener exited".into()))??;
+
+    *listener = Some(stop_tx);
+    Ok(())
+}
+
+fn stop(app: &AppHandle) {
+    if let Some(stop) = app.state::<VoiceCommandState>().0.lock().unwrap().take() {
+        let _ = stop.send(());
+    }
+}
+
+/// Start the listener on launch if the user has opted in.
+pub fn init(app: &AppHandle) {
+    let enabled = db::connect(app)
+        .and_then(|conn| db::get_preference(&conn, ENABLED_PREFERENCE))
+        .ok()
+        .flatten()
+        .is_some_and(|value| value == "true");
+    if enabled {
+        if let Err(err) = start(app) {
+            crash::log(format!("voice commands unavailable: {}", err));
+        }
+    }
+}
+
+/// Turn voice commands on or off and remember the choice.
+#[tauri::command]
+pub fn set_voice_commands_enabled(app: AppHandle, enabled: bool) -> Result<()> {
+    if enabled {
+        start(&app)?;
+    } else {
+        stop(&app);
+    }
+    db::set_preference(
+        &db::connect(&app)?,
+        ENABLED_PREFERENCE,
+        if enabled { "true" } else { "false" },
+    )
+}
+
+#[tauri::command]
+pub fn voice_commands_enabled(state: State<'_, VoiceCommandState>) -> bool {
+    state.0.lock().unwrap().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_phrases_inside_longer_utterances() {
+        assert_eq!(
+            match_command("Okay, stop recording."),
+            Some(VoiceCommand::StopRecording)
+        );
+        assert_eq!(match_command("Add marker"), Some(VoiceCommand::AddMarker));
+        assert_eq!(match_command("start the recording"), None);
+    }
+}
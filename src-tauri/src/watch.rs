@@ -0,0 +1,513 @@
+//! Watch folders that feed new recordings into the job queue.
+//!
+//! Folders are polled rather than watched through OS notifications, which
+//! behave inconsistently on network and cloud-synced drives. A file is
+//! queued once its size has stopped changing between polls, so recordings
+//! that are still being written or converted are left alone. A file the
+//! queue refuses, e.g. over a project quota or short of disk space, is
+//! tried again only once it changes, rather than on every poll.
+//! Finished transcripts are saved, and summarized if the folder asks for it.
+//!
+//! Presets locate the local recording folders of Zoom and Teams.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::cleanup::SourceCleanup;
+use crate::diarize::DiarizeOptions;
+use crate::error::{Error, Result};
+use crate::jobs::{self, JobPriority, SaveTarget};
+use crate::{crash, db};
+
+pub const QUEUED_EVENT: &str = "watch://queued";
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+pub const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "m4a", "mp4", "flac", "ogg", "webm"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchPresetKind {
+    Zoom,
+    Teams,
+}
+
+impl WatchPresetKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            WatchPresetKind::Zoom => "zoom",
+            WatchPresetKind::Teams => "teams",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            WatchPresetKind::Zoom => "Zoom recordings",
+            WatchPresetKind::Teams => "Teams recordings",
+        }
+    }
+
+    /// Default local recording folders, most likely first.
+    fn candidates(self) -> Vec<PathBuf> {
+        let home = tauri::api::path::home_dir();
+        let documents = tauri::api::path::document_dir();
+        match self {
+            // Zoom saves each meeting in a subfolder of Documents/Zoom on
+            // every desktop platform.
+            WatchPresetKind::Zoom => documents.into_iter().map(|dir| dir.join("Zoom")).collect(),
+            // Teams saves recordings to the Recordings folder of the user's
+            // OneDrive, whose folder name includes the organization for
+            // work accounts ("OneDrive - Contoso").
+            WatchPresetKind::Teams => {
+                let mut roots = Vec::new();
+                if let Some(home) = &home {
+                    roots.push(home.clone());
+                    roots.push(home.join("Library").join("CloudStorage"));
+                }
+                roots
+                    .iter()
+                    .flat_map(|root| fs::read_dir(root).into_iter().flatten().flatten())
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        path.file_name()
+                            .and_then(|name| name.to_str())
+                            .is_some_and(|name| name.starts_with("OneDrive"))
+                    })
+                    .map(|onedrive| onedrive.join("Recordings"))
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchPreset {
+    pub preset: WatchPresetKind,
+    pub name: String,
+    /// First existing default folder, if the app is installed and has recorded.
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchFolder {
+    pub id: String,
+    pub path: PathBuf,
+    pub preset: Option<String>,
+    pub recursive: bool,
+    pub model: Option<String>,
+    pub language: Option<String>,
+    /// Label speakers in transcripts from this folder.
+    pub diarize: bool,
+    /// Summarize transcripts from this folder when they finish.
+    pub auto_summary: bool,
+    pub enabled: bool,
+    /// Source audio cleanup for this folder, overriding the global setting.
+    pub cleanup: Option<SourceCleanup>,
+    /// Tag given to transcripts from this folder; each one's decoder
+    /// prompt starts from the previous one's names and terms.
+    pub series: Option<String>,
+    /// Project files from this folder are imported into; files that would
+    /// break its quota are not queued.
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatchOptions {
+    pub recursive: Option<bool>,
+    pub model: Option<String>,
+    pub language: Option<String>,
+    pub diarize: bool,
+    pub auto_summary: bool,
+    /// Also transcribe files already in the folder; otherwise only new ones.
+    pub include_existing: bool,
+    pub cleanup: Option<SourceCleanup>,
+    pub series: Option<String>,
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedFile {
+    pub folder_id: String,
+    pub path: PathBuf,
+    pub job_id: String,
+    pub diarize: bool,
+    pub auto_summary: bool,
+}
+
+const COLUMNS: &str =
+    "id, path, preset, recursive, model, language, diarize, auto_summary, enabled, cleanup, series, project";
+
+fn from_row(row: &Row) -> rusqlite::Result<WatchFolder> {
+    Ok(WatchFolder {
+        id: row.get(0)?,
+        path: PathBuf::from(row.get::<_, String>(1)?),
+        preset: row.get(2)?,
+        recursive: row.get(3)?,
+        model: row.get(4)?,
+        language: row.get(5)?,
+        diarize: row.get(6)?,
+        auto_summary: row.get(7)?,
+        enabled: row.get(8)?,
+        cleanup: row
+            .get::<_, Option<String>>(9)?
+            .and_then(|value| serde_json::from_str(&value).ok()),
+        series: row.get(10)?,
+        project: row.get(11)?,
+    })
+}
+
+pub fn folders(conn: &Connection) -> Result<Vec<WatchFolder>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT {} FROM watch_folders ORDER BY created_at",
+        COLUMNS
+    ))?;
+    let folders = statement
+        .query_map([], from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(folders)
+}
+
+pub fn is_audio(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Audio files under `dir` with their sizes.
+fn audio_files(dir: &Path, recursive: bool, out: &mut Vec<(PathBuf, u64)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            if recursive {
+                audio_files(&path, recursive, out);
+            }
+        } else if is_audio(&path) {
+            out.push((path, metadata.len()));
+        }
+    }
+}
+
+/// Record a folder's current files as skipped so only later ones are queued.
+fn skip_existing(conn: &Connection, folder: &WatchFolder) -> Result<()> {
+    let mut files = Vec::new();
+    audio_files(&folder.path, folder.recursive, &mut files);
+    for (path, size) in files {
+        conn.execute(
+            "INSERT OR IGNORE INTO watch_files (folder_id, path, size, status)
+             VALUES (?1, ?2, ?3, 'skipped')",
+            params![folder.id, path.to_string_lossy(), size as i64],
+        )?;
+    }
+    Ok(())
+}
+
+/// When `path` was last modified, in milliseconds since the epoch, or 0
+/// where the file system does not say.
+fn modified_ms(path: &Path) -> i64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_millis() as i64)
+}
+
+fn poll_folder(app: &AppHandle, conn: &Connection, folder: &WatchFolder) -> Result<()> {
+    let mut files = Vec::new();
+    audio_files(&folder.path, folder.recursive, &mut files);
+
+    for (path, size) in files {
+        let key = path.to_string_lossy().to_string();
+        let known: Option<(i64, String, Option<i64>)> = conn
+            .query_row(
+                "SELECT size, status, modified_ms FROM watch_files WHERE folder_id = ?1 AND path = ?2",
+                params![folder.id, key],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        match known {
+            None => {
+                conn.execute(
+                    "INSERT INTO watch_files (folder_id, path, size, status)
+                     VALUES (?1, ?2, ?3, 'pending')",
+                    params![folder.id, key, size as i64],
+                )?;
+            }
+            Some((previous, status, modified)) if status == "rejected" => {
+                // Changed since it was refused: wait for it to settle again.
+                if previous as u64 != size || modified != Some(modified_ms(&path)) {
+                    conn.execute(
+                        "UPDATE watch_files SET status = 'pending', size = ?3, modified_ms = NULL
+                         WHERE folder_id = ?1 AND path = ?2",
+                        params![folder.id, key, size as i64],
+                    )?;
+                }
+            }
+            Some((previous, status, _)) if status == "pending" => {
+                if previous as u64 != size || size == 0 {
+                    conn.execute(
+                        "UPDATE watch_files SET size = ?3 WHERE folder_id = ?1 AND path = ?2",
+                        params![folder.id, key, size as i64],
+                    )?;
+                    continue;
+                }
+                let queued = jobs::enqueue_transcription(
+                    app.clone(),
+                    path.clone(),
+                    folder.model.clone(),
+                    folder.language.clone(),
+                    Some(JobPriority::Background),
+                    None,
+                    folder.diarize.then(DiarizeOptions::default),
+                    Some(SaveTarget {
+                        audio_file_id: None,
+                        watch_folder_id: Some(folder.id.clone()),
+                        summarize: folder.auto_summary,
+                        series: folder.series.clone(),
+                        project: folder.project.clone(),
+                        meeting_type: None,
+                    }),
+                );
+                let job_id = match queued {
+                    Ok(job_id) => job_id,
+                    Err(err) => {
+                        crash::log(format!("not queuing {}: {}", path.display(), err));
+                        conn.execute(
+                            "UPDATE watch_files SET status = 'rejected', modified_ms = ?3
+                             WHERE folder_id = ?1 AND path = ?2",
+                            params![folder.id, key, modified_ms(&path)],
+                        )?;
+                        continue;
+                    }
+                };
+                conn.execute(
+                    "UPDATE watch_files SET status = 'queued', job_id = ?3
+                     WHERE folder_id = ?1 AND path = ?2",
+                    params![folder.id, key, job_id],
+                )?;
+                let _ = app.emit_all(
+                    QUEUED_EVENT,
+                    QueuedFile {
+                        folder_id: folder.id.clone(),
+                        path,
+                        job_id,
+                        diarize: folder.diarize,
+                        auto_summary: folder.auto_summary,
+                    },
+                );
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Start polling the enabled watch folders.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+        let result = db::connect(&app).and_then(|conn| {
+            for folder in folders(&conn)?.iter().filter(|folder| folder.enabled) {
+                if let Err(err) = poll_folder(&app, &conn, folder) {
+                    crash::log(format!(
+                        "watch folder {} failed: {}",
+                        folder.path.display(),
+                        err
+                    ));
+                }
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            crash::log(format!("watch folders unavailable: {}", err));
+        }
+    });
+}
+
+/// A series or project as stored: trimmed, and none if blank.
+fn non_blank(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+fn insert_folder(
+    conn: &Connection,
+    path: PathBuf,
+    preset: Option<WatchPresetKind>,
+    options: WatchOptions,
+) -> Result<WatchFolder> {
+    if !path.is_dir() {
+        return Err(Error::NotFound(format!("folder {}", path.display())));
+    }
+    if let Some(cleanup) = &options.cleanup {
+        cleanup.validate()?;
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO watch_folders
+             (id, path, preset, recursive, model, language, diarize, auto_summary, cleanup, series, project)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            id,
+            path.to_string_lossy(),
+            preset.map(WatchPresetKind::as_str),
+            options.recursive.unwrap_or(true),
+            options.model,
+            options.language,
+            options.diarize,
+            options.auto_summary,
+            options
+                .cleanup
+                .as_ref()
+                .map(|cleanup| serde_json::to_string(cleanup).unwrap()),
+            non_blank(options.series.as_deref()),
+            non_blank(options.project.as_deref())
+        ],
+    )
+    .map_err(|err| match err {
+        rusqlite::Error::SqliteFailure(e, _)
+            if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            Error::InvalidInput(format!("{} is already watched", path.display()))
+        }
+        other => other.into(),
+    })?;
+
+    let folder = conn.query_row(
+        &format!("SELECT {} FROM watch_folders WHERE id = ?1", COLUMNS),
+        [&id],
+        from_row,
+    )?;
+    if !options.include_existing {
+        skip_existing(conn, &folder)?;
+    }
+    Ok(folder)
+}
+
+#[tauri::command]
+pub fn add_watch_folder(
+    app: AppHandle,
+    path: PathBuf,
+    options: Option<WatchOptions>,
+) -> Result<WatchFolder> {
+    insert_folder(&db::connect(&app)?, path, None, options.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn list_watch_folders(app: AppHandle) -> Result<Vec<WatchFolder>> {
+    folders(&db::connect(&app)?)
+}
+
+#[tauri::command]
+pub fn remove_watch_folder(app: AppHandle, id: String) -> Result<()> {
+    let conn = db::connect(&app)?;
+    conn.execute("DELETE FROM watch_files WHERE folder_id = ?1", [&id])?;
+    if conn.execute("DELETE FROM watch_folders WHERE id = ?1", [&id])? == 0 {
+        return Err(Error::NotFound(format!("watch folder {}", id)));
+    }
+    Ok(())
+}
+
+/// Set or clear a folder's own source audio cleanup; cleared folders use
+/// the global setting.
+#[tauri::command]
+pub fn set_watch_folder_cleanup(
+    app: AppHandle,
+    id: String,
+    cleanup: Option<SourceCleanup>,
+) -> Result<()> {
+    if let Some(cleanup) = &cleanup {
+        cleanup.validate()?;
+    }
+    let updated = db::connect(&app)?.execute(
+        "UPDATE watch_folders SET cleanup = ?2 WHERE id = ?1",
+        params![
+            id,
+            cleanup.map(|cleanup| serde_json::to_string(&cleanup).unwrap())
+        ],
+    )?;
+    if updated == 0 {
+        return Err(Error::NotFound(format!("watch folder {}", id)));
+    }
+    Ok(())
+}
+
+/// Set or clear the series tag of a folder's transcripts.
+#[tauri::command]
+pub fn set_watch_folder_series(app: AppHandle, id: String, series: Option<String>) -> Result<()> {
+    let updated = db::connect(&app)?.execute(
+        "UPDATE watch_folders SET series = ?2 WHERE id = ?1",
+        params![id, non_blank(series.as_deref())],
+    )?;
+    if updated == 0 {
+        return Err(Error::NotFound(format!("watch folder {}", id)));
+    }
+    Ok(())
+}
+
+/// Set or clear the project a folder's files are imported into.
+#[tauri::command]
+pub fn set_watch_folder_project(app: AppHandle, id: String, project: Option<String>) -> Result<()> {
+    let updated = db::connect(&app)?.execute(
+        "UPDATE watch_folders SET project = ?2 WHERE id = ?1",
+        params![id, non_blank(project.as_deref())],
+    )?;
+    if updated == 0 {
+        return Err(Error::NotFound(format!("watch folder {}", id)));
+    }
+    Ok(())
+}
+
+/// The Zoom and Teams presets with the folder each would watch on this machine.
+#[tauri::command]
+pub fn list_watch_presets() -> Vec<WatchPreset> {
+    [WatchPresetKind::Zoom, WatchPresetKind::Teams]
+        .into_iter()
+        .map(|preset| WatchPreset {
+            preset,
+            name: preset.name().to_string(),
+            path: preset.candidates().into_iter().find(|path| path.is_dir()),
+        })
+        .collect()
+}
+
+/// Watch a preset's folder with meeting defaults: speaker labels and an
+/// automatic summary for every recording.
+#[tauri::command]
+pub fn add_watch_preset(app: AppHandle, preset: WatchPresetKind) -> Result<WatchFolder> {
+    let path = preset
+        .candidates()
+        .into_iter()
+        .find(|path| path.is_dir())
+        .ok_or_else(|| {
+            Error::NotFound(format!(
+                "no {} folder found on this computer",
+                preset.name().to_lowercase()
+            ))
+        })?;
+    insert_folder(
+        &db::connect(&app)?,
+        path,
+        Some(preset),
+        WatchOptions {
+            recursive: Some(true),
+            diarize: true,
+            auto_summary: true,
+            ..Default::default()
+        },
+    )
+}
@@ -0,0 +1,355 @@
+//! Local speech recognition through whisper.cpp.
+
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Segment {
+    pub text: String,
+    /// Start time in seconds.
+    pub start: f64,
+    /// End time in seconds.
+    pub end: f64,
+    /// Mean token probability, between 0 and 1.
+    pub confidence: Option<f32>,
+    /// Words with their times, from whisper's token timestamps.
+    #[serde(default)]
+    pub words: Vec<Word>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    /// Start time in seconds.
+    pub start: f64,
+    /// End time in seconds.
+    pub end: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeOptions {
+    /// ISO 639-1 code, or `None` to let whisper detect the language.
+    pub language: Option<String>,
+    pub threads: Option<i32>,
+    /// Text the decoder is conditioned on, biasing it toward these words.
+    pub initial_prompt: Option<String>,
+    #[serde(default)]
+    pub advanced: AdvancedOptions,
+}
+
+/// Decoder tuning for difficult audio. Unset fields keep whisper.cpp's
+/// defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AdvancedOptions {
+    /// Beam search width; greedy decoding when unset.
+    pub beam_size: Option<i32>,
+    /// Sampling temperature of the first attempt at each window.
+    pub temperature: Option<f32>,
+    /// Added to the temperature each time a window fails the compression or
+    /// log-probability checks and is decoded again; 0 disables fallback.
+    pub temperature_increment: Option<f32>,
+    /// No-speech probability above which a window is skipped as silence.
+    pub no_speech_threshold: Option<f32>,
+    /// Condition each window on the text decoded before it. Turning this
+    /// off stops a mistake from repeating, at some cost in consistency.
+    pub condition_on_previous_text: Option<bool>,
+}
+
+/// whisper.cpp runs at most this many decoders at once.
+const MAX_BEAM_SIZE: i32 = 8;
+
+impl AdvancedOptions {
+    /// Fill unset fields from `defaults`.
+    pub fn or(self, defaults: AdvancedOptions) -> AdvancedOptions {
+        AdvancedOptions {
+            beam_size: self.beam_size.or(defaults.beam_size),
+            temperature: self.temperature.or(defaults.temperature),
+            temperature_increment: self
+                .temperature_increment
+                .or(defaults.temperature_increment),
+            no_speech_threshold: self.no_speech_threshold.or(defaults.no_speech_threshold),
+            condition_on_previous_text: self
+                .condition_on_previous_text
+                .or(defaults.condition_on_previous_text),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let unit = |name: &str, value: Option<f32>| match value {
+            Some(value) if !(0.0..=1.0).contains(&value) => Err(Error::InvalidInput(format!(
+                "{} must be between 0 and 1, got {}",
+                name, value
+            ))),
+            _ => Ok(()),
+        };
+        if let Some(size) = self.beam_size {
+            if !(1..=MAX_BEAM_SIZE).contains(&size) {
+                return Err(Error::InvalidInput(format!(
+                    "beam size must be between 1 and {}, got {}",
+                    MAX_BEAM_SIZE, size
+                )));
+            }
+        }
+        unit("temperature", self.temperature)?;
+        unit("temperature increment", self.temperature_increment)?;
+        unit("no-speech threshold", self.no_speech_threshold)
+    }
+}
+
+fn engine_error(err: whisper_rs::WhisperError) -> Error {
+    Error::Transcription(err.to_string())
+}
+
+pub fn load_context(model_path: &Path) -> Result<WhisperContext> {
+    let path = model_path
+        .to_str()
+        .ok_or_else(|| Error::InvalidInput("model path is not valid UTF-8".into()))?;
+    WhisperContext::new_with_params(path, WhisperContextParameters::default()).map_err(engine_error)
+}
+
+/// Run a full decode of 16 kHz mono samples.
+pub fn transcribe(
+    ctx: &WhisperContext,
+    pcm: &[f32],
+    options: &DecodeOptions,
+) -> Result<Vec<Segment>> {
+    let mut state = ctx.create_state().map_err(engine_error)?;
+
+    let advanced = &options.advanced;
+    let strategy = match advanced.beam_size {
+        Some(beam_size) => SamplingStrategy::BeamSearch {
+            beam_size,
+            patience: -1.0,
+        },
+        None => SamplingStrategy::Greedy { best_of: 1 },
+    };
+    let mut params = FullParams::new(strategy);
+    params.set_language(options.language.as_deref().filter(|lang| *lang != "auto"));
+    params.set_n_threads(options.threads.unwrap_or_else(default_threads));
+    if let Some(prompt) = options.initial_prompt.as_deref() {
+        params.set_initial_prompt(prompt);
+    }
+    if let Some(temperature) = advanced.temperature {
+        params.set_temperature(temperature);
+    }
+    if let Some(increment) = advanced.temperature_increment {
+        params.set_temperature_inc(increment);
+    }
+    if let Some(threshold) = advanced.no_speech_threshold {
+        params.set_no_speech_thold(threshold);
+    }
+    if let Some(condition) = advanced.condition_on_previous_text {
+        params.set_no_context(!condition);
+    }
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+    params.set_token_timestamps(true);
+
+    state.full(params, pcm).map_err(engine_error)?;
+
+    let count = state.full_n_segments().map_err(engine_error)?;
+    let mut segments = Vec::with_capacity(count.max(0) as usize);
+    for i in 0..count {
+        let text = state.full_get_segment_text(i).map_err(engine_error)?;
+        // whisper.cpp reports timestamps in 10 ms units.
+        let start = state.full_get_segment_t0(i).map_err(engine_error)? as f64 / 100.0;
+        let end = state.full_get_segment_t1(i).map_err(engine_error)? as f64 / 100.0;
+
+        let tokens = state.full_n_tokens(i).map_err(engine_error)?;
+        let confidence = if tokens > 0 {
+            let mut total = 0.0;
+            for j in 0..tokens {
+                total += state.full_get_token_prob(i, j).map_err(engine_error)?;
+            }
+            Some(total / tokens as f32)
+        } else {
+            None
+        };
+
+        segments.push(Segment {
+            text: text.trim().to_string(),
+            start,
+            end,
+            confidence,
+            // Word times are a bonus; without them read-along estimates.
+            words: words(ctx, &state, i).unwrap_or_default(),
+        });
+    }
+
+    Ok(segments)
+}
+
+/// Words of segment `i` from its token times. A token starting with a
+/// space begins a word and others continue it; special tokens such as
+/// timestamps are skipped.
+fn words(
+    ctx: &WhisperContext,
+    state: &whisper_rs::WhisperState,
+    i: i32,
+) -> std::result::Result<Vec<Word>, whisper_rs::WhisperError> {
+    let mut words: Vec<Word> = Vec::new();
+    for j in 0..state.full_n_tokens(i)? {
+        if state.full_get_token_id(i, j)? >= ctx.token_eot() {
+            continue;
+        }
+        let text = state.full_get_token_text(i, j)?;
+        let data = state.full_get_token_data(i, j)?;
+        // In 10 ms units, like segment times.
+        let (start, end) = (data.t0 as f64 / 100.0, data.t1 as f64 / 100.0);
+        match words.last_mut() {
+            Some(word) if !text.starts_with(' ') => {
+                word.text.push_str(&text);
+                word.end = end;
+            }
+            _ if !text.trim().is_empty() => words.push(Word {
+                text: text.trim_start().to_string(),
+                start,
+                end,
+            }),
+            _ => {}
+        }
+    }
+    Ok(words)
+}
+
+/// Decode `chunks` of `pcm` concurrently and merge them into one timeline.
+///
+/// Each worker owns a whisper state on the shared context. Chunk timestamps
+/// are shifted by the chunk offset and clamped to the chunk end so segments
+/// never overlap across a boundary.
+pub fn transcribe_parallel(
+    ctx: &WhisperContext,
+    pcm: &[f32],
+    options: &DecodeOptions,
+    chunks: &[Range<usize>],
+    workers: usize,
+) -> Result<Vec<Segment>> {
+    let workers = workers.clamp(1, chunks.len().max(1));
+    let worker_options = DecodeOptions {
+        threads: Some((options.threads.unwrap_or_else(default_threads) / workers as i32).max(1)),
+        ..options.clone()
+    };
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<Vec<Segment>>>>> =
+        Mutex::new((0..chunks.len()).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(range) = chunks.get(index) else {
+                    break;
+                };
+                let result = transcribe(ctx, &pcm[range.clone()], &worker_options);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    let mut merged = Vec::new();
+    for (range, result) in chunks.iter().zip(results.into_inner().unwrap()) {
+        let offset = range.start as f64 / WHISPER_SAMPLE_RATE as f64;
+        let chunk_end = range.end as f64 / WHISPER_SAMPLE_RATE as f64;
+        let segments = result.unwrap_or_else(|| Ok(Vec::new()))?;
+        merged.extend(segments.into_iter().map(|segment| {
+            Segment {
+                start: (segment.start + offset).min(chunk_end),
+                end: (segment.end + offset).min(chunk_end),
+                words: segment
+                    .words
+                    .into_iter()
+                    .map(|word| Word {
+                        start: (word.start + offset).min(chunk_end),
+                        end: (word.end + offset).min(chunk_end),
+                        ..word
+                    })
+                    .collect(),
+                ..segment
+            }
+        }));
+    }
+    Ok(merged)
+}
+
+pub fn join_text(segments: &[Segment]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Detect the spoken language from the first 30 seconds of `pcm`.
+pub fn detect_language(ctx: &WhisperContext, pcm: &[f32], threads: i32) -> Result<String> {
+    let window = &pcm[..pcm.len().min(30 * WHISPER_SAMPLE_RATE as usize)];
+    let mut state = ctx.create_state().map_err(engine_error)?;
+    state
+        .pcm_to_mel(window, threads as usize)
+        .map_err(engine_error)?;
+    let probabilities = state
+        .lang_detect(0, threads as usize)
+        .map_err(engine_error)?;
+    let id = probabilities
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map_or(-1, |(id, _)| id as i32);
+    whisper_rs::get_lang_str(id)
+        .map(str::to_string)
+        .ok_or_else(|| Error::Transcription(format!("unknown language id {}", id)))
+}
+
+pub fn default_threads() -> i32 {
+    std::thread::available_parallelism()
+        .map(|n| n.get().min(8) as i32)
+        .unwrap_or(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_options_override_defaults_and_are_range_checked() {
+        let defaults = AdvancedOptions {
+            beam_size: Some(5),
+            no_speech_threshold: Some(0.6),
+            ..Default::default()
+        };
+        let job = AdvancedOptions {
+            beam_size: Some(2),
+            ..Default::default()
+        };
+        let merged = job.or(defaults);
+        assert_eq!(merged.beam_size, Some(2));
+        assert_eq!(merged.no_speech_threshold, Some(0.6));
+        assert!(merged.validate().is_ok());
+
+        let too_wide = AdvancedOptions {
+            beam_size: Some(32),
+            ..Default::default()
+        };
+        assert!(too_wide.validate().is_err());
+        let too_hot = AdvancedOptions {
+            temperature: Some(1.5),
+            ..Default::default()
+        };
+        assert!(too_hot.validate().is_err());
+    }
+}
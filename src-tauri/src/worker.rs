@@ -0,0 +1,383 @@
+//! Whisper inference in a child process.
+//!
+//! File transcription decodes in a copy of the app started with
+//! [`WORKER_ARG`], which reads one JSON request per line on stdin and
+//! answers each on stdout. A crash or out-of-memory kill inside whisper.cpp
+//! then ends only the worker: the supervisor starts a fresh one and tries
+//! the request once more before failing the job. A worker that hangs is
+//! killed once its answer is overdue, replaced, and the job fails without
+//! a retry. Samples travel through a temporary file rather than the pipe,
+//! and the worker keeps the last model loaded between requests. Language
+//! detection for model routing runs there too, ahead of the decode.
+//!
+//! Live dictation still decodes in the app, where the extra model load
+//! would cost more than the isolation is worth. Its models stay in the
+//! app's model cache, so a model used both there and in the worker is held
+//! in memory twice; the cache's memory budget does not count the worker's
+//! copy.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use whisper_rs::WhisperContext;
+
+use crate::audio::WHISPER_SAMPLE_RATE;
+use crate::error::{Error, Result};
+use crate::whisper::{self, DecodeOptions, Segment};
+use crate::{crash, transcription};
+
+/// Command-line argument that makes the binary serve requests instead of
+/// opening the app.
+pub const WORKER_ARG: &str = "--inference-worker";
+const MAX_ATTEMPTS: usize = 2;
+/// How long an answer may take: this much, plus [`TIMEOUT_PER_AUDIO_SEC`]
+/// for every second of audio, which covers large models on slow CPUs.
+const BASE_TIMEOUT: Duration = Duration::from_secs(300);
+const TIMEOUT_PER_AUDIO_SEC: f64 = 4.0;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    model_path: PathBuf,
+    /// Raw little-endian `f32` samples at 16 kHz.
+    pcm_path: PathBuf,
+    task: Task,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Task {
+    Decode {
+        options: DecodeOptions,
+        parallel: bool,
+    },
+    DetectLanguage {
+        threads: i32,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Response {
+    Segments(Vec<Segment>),
+    /// Code of the language detected.
+    Language(String),
+    /// The decode failed in an orderly way, so retrying will not help.
+    Error(String),
+}
+
+fn write_pcm(path: &Path, pcm: &[f32]) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    for sample in pcm {
+        out.write_all(&sample.to_le_bytes())?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn read_pcm(path: &Path) -> Result<Vec<f32>> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// Time allowed to decode `samples` of 16 kHz audio.
+fn timeout_for(samples: usize) -> Duration {
+    BASE_TIMEOUT + Duration::from_secs_f64(samples as f64 / 16_000.0 * TIMEOUT_PER_AUDIO_SEC)
+}
+
+/// Why an exchange with the worker failed.
+enum Failure {
+    /// The worker stopped or sent something unreadable; a fresh one may
+    /// succeed.
+    Lost(Error),
+    /// No answer in time; the request itself is likely to hang again.
+    TimedOut(Duration),
+}
+
+impl From<io::Error> for Failure {
+    fn from(err: io::Error) -> Self {
+        Failure::Lost(err.into())
+    }
+}
+
+struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines the worker writes, read on a thread of their own so waiting
+    /// for one can time out.
+    answers: Receiver<io::Result<String>>,
+}
+
+impl Worker {
+    fn spawn() -> Result<Worker> {
+        let mut child = Command::new(std::env::current_exe()?)
+            .arg(WORKER_ARG)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(Error::Transcription(
+                "the inference worker has no pipes".into(),
+            ));
+        };
+        let (sender, answers) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            loop {
+                let mut line = String::new();
+                match stdout.read_line(&mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if sender.send(Ok(line)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        let _ = sender.send(Err(err));
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Worker {
+            child,
+            stdin,
+            answers,
+        })
+    }
+
+    /// Send one request and wait up to `timeout` for its answer. A failure
+    /// here means the worker is gone, stuck or out of step and must be
+    /// replaced.
+    fn exchange(
+        &mut self,
+        request: &Request,
+        timeout: Duration,
+    ) -> std::result::Result<Response, Failure> {
+        let line = serde_json::to_string(request).map_err(io::Error::other)?;
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()?;
+        match self.answers.recv_timeout(timeout) {
+            Ok(answer) => Ok(serde_json::from_str(&answer?).map_err(io::Error::other)?),
+            Err(RecvTimeoutError::Timeout) => Err(Failure::TimedOut(timeout)),
+            Err(RecvTimeoutError::Disconnected) => {
+                let status = self.child.wait()?;
+                Err(Failure::Lost(Error::Transcription(format!(
+                    "the inference worker stopped ({})",
+                    status
+                ))))
+            }
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Owns the worker process, started on first use. Requests are served one
+/// at a time.
+#[derive(Default)]
+pub struct Supervisor(Mutex<Option<Worker>>);
+
+impl Supervisor {
+    /// Decode `pcm` with the model at `model_path` in the worker.
+    pub fn decode(
+        &self,
+        model_path: &Path,
+        pcm: &[f32],
+        options: &DecodeOptions,
+        parallel: bool,
+    ) -> Result<Vec<Segment>> {
+        let task = Task::Decode {
+            options: options.clone(),
+            parallel,
+        };
+        match self.run(model_path, pcm, task)? {
+            Response::Segments(segments) => Ok(segments),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Detect the spoken language of `pcm` with the model at `model_path`
+    /// in the worker. Only the first 30 seconds are sent, which is all
+    /// detection listens to.
+    pub fn detect_language(&self, model_path: &Path, pcm: &[f32], threads: i32) -> Result<String> {
+        let window = &pcm[..pcm.len().min(30 * WHISPER_SAMPLE_RATE as usize)];
+        match self.run(model_path, window, Task::DetectLanguage { threads })? {
+            Response::Language(language) => Ok(language),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    fn run(&self, model_path: &Path, pcm: &[f32], task: Task) -> Result<Response> {
+        let pcm_path =
+            std::env::temp_dir().join(format!("transcriber-pcm-{}.f32", uuid::Uuid::new_v4()));
+        write_pcm(&pcm_path, pcm)?;
+        let request = Request {
+            model_path: model_path.to_path_buf(),
+            pcm_path: pcm_path.clone(),
+            task,
+        };
+        let outcome = self.send(&request, timeout_for(pcm.len()));
+        let _ = fs::remove_file(&pcm_path);
+        outcome
+    }
+
+    fn send(&self, request: &Request, timeout: Duration) -> Result<Response> {
+        let mut worker = self.0.lock().unwrap();
+        let mut failure = None;
+        for attempt in 1..=MAX_ATTEMPTS {
+            let outcome = match worker.as_mut() {
+                Some(running) => running.exchange(request, timeout),
+                None => Worker::spawn()
+                    .map_err(Failure::Lost)
+                    .and_then(|spawned| worker.insert(spawned).exchange(request, timeout)),
+            };
+            match outcome {
+                Ok(response) => return Ok(response),
+                Err(Failure::TimedOut(timeout)) => {
+                    // Dropping the stuck worker kills it; its replacement
+                    // is ready for the next job.
+                    *worker = None;
+                    *worker = Worker::spawn()
+                        .map_err(|err| {
+                            crash::log(format!("inference worker restart failed: {}", err))
+                        })
+                        .ok();
+                    return Err(Error::Transcription(format!(
+                        "the inference worker did not answer within {} minutes and was restarted",
+                        timeout.as_secs().div_ceil(60)
+                    )));
+                }
+                Err(Failure::Lost(err)) => {
+                    crash::log(format!(
+                        "inference worker failed on attempt {}: {}",
+                        attempt, err
+                    ));
+                    *worker = None;
+                    failure = Some(err);
+                }
+            }
+        }
+        Err(Error::Transcription(format!(
+            "the inference worker failed {} times: {}",
+            MAX_ATTEMPTS,
+            failure.map_or_else(String::new, |err| err.to_string())
+        )))
+    }
+}
+
+/// The error for `response`, which answers a different task or reports
+/// one that failed.
+fn unexpected(response: Response) -> Error {
+    match response {
+        Response::Error(message) => Error::Transcription(message),
+        other => Error::Transcription(format!(
+            "the inference worker answered out of step: {:?}",
+            other
+        )),
+    }
+}
+
+fn handle(loaded: &mut Option<(PathBuf, WhisperContext)>, request: Request) -> Result<Response> {
+    let pcm = read_pcm(&request.pcm_path)?;
+    let ctx = match loaded.take() {
+        Some((path, ctx)) if path == request.model_path => ctx,
+        previous => {
+            // Free the old model before loading the next.
+            drop(previous);
+            whisper::load_context(&request.model_path)?
+        }
+    };
+    let response = match request.task {
+        Task::Decode { options, parallel } => {
+            transcription::decode(&ctx, &pcm, &options, parallel).map(Response::Segments)
+        }
+        Task::DetectLanguage { threads } => {
+            whisper::detect_language(&ctx, &pcm, threads).map(Response::Language)
+        }
+    };
+    *loaded = Some((request.model_path, ctx));
+    response
+}
+
+/// Serve requests until stdin closes, which happens when the app exits.
+pub fn serve() {
+    let mut loaded = None;
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                handle(&mut loaded, request).unwrap_or_else(|err| Response::Error(err.to_string()))
+            }
+            Err(err) => Response::Error(format!("unreadable request: {}", err)),
+        };
+        let Ok(answer) = serde_json::to_string(&response) else {
+            break;
+        };
+        if writeln!(stdout, "{}", answer)
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_and_samples_round_trip() {
+        let path = std::env::temp_dir().join(format!("worker-test-{}.f32", uuid::Uuid::new_v4()));
+        let pcm = [0.0, -1.0, 0.25, f32::MIN_POSITIVE];
+        write_pcm(&path, &pcm).unwrap();
+        assert_eq!(read_pcm(&path).unwrap(), pcm);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            timeout_for(16_000 * 60),
+            BASE_TIMEOUT + Duration::from_secs(240)
+        );
+
+        let response = Response::Segments(vec![Segment {
+            text: " Hello.".into(),
+            start: 0.0,
+            end: 1.5,
+            confidence: Some(0.5),
+            words: Vec::new(),
+        }]);
+        let line = serde_json::to_string(&response).unwrap();
+        assert!(!line.contains('\n'));
+        let parsed: Response = serde_json::from_str(&line).unwrap();
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), line);
+
+        let request = Request {
+            model_path: "ggml-base.bin".into(),
+            pcm_path: "samples.f32".into(),
+            task: Task::DetectLanguage { threads: 4 },
+        };
+        let line = serde_json::to_string(&request).unwrap();
+        let parsed: Request = serde_json::from_str(&line).unwrap();
+        assert!(matches!(parsed.task, Task::DetectLanguage { threads: 4 }));
+    }
+}
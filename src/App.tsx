@@ -1,7 +1,8 @@
-import { useCallback, useEffect, useState } from 'react';
+import { useCallback, useEffect, useRef, useState } from 'react';
 import { AudioUploader, AudioPlayer, Navigation, HistoryView } from '@/components';
 import { SUPPORTED_AUDIO_FORMATS, initializePlatform } from '@/utils';
 import { AudioFilesProvider, useAudioFiles } from '@/contexts';
+import { restoreSession, saveSession } from '@/services';
 import type { AudioFile } from '@/models';
 import './App.css';
 
@@ -10,6 +11,35 @@ function AppContent() {
   const { files: uploadedFiles } = state;
   const [currentView, setCurrentView] = useState<'transcription' | 'history'>('transcription');
   const message = 'Welcome to Transcriber';
+  const sessionRestored = useRef(false);
+
+  // Pick up where the last session left off; saving waits for this so an
+  // early save cannot replace the session before it is read
+  useEffect(() => {
+    restoreSession()
+      .then(restored => {
+        const view = restored?.state.ui?.view;
+        if (view === 'transcription' || view === 'history') {
+          setCurrentView(view);
+        }
+        restored?.rejectedJobs.forEach(job => {
+          console.warn('Queued job not resumed:', job.error);
+        });
+      })
+      .catch(error => {
+        console.warn('Failed to restore session:', error);
+      })
+      .finally(() => {
+        sessionRestored.current = true;
+      });
+  }, []);
+
+  useEffect(() => {
+    if (!sessionRestored.current) return;
+    saveSession({ ui: { view: currentView } }).catch(error => {
+      console.warn('Failed to save session:', error);
+    });
+  }, [currentView]);
 
   // Initialize platform detection on app start
   useEffect(() => {
@@ -3,7 +3,7 @@
  */
 
 import { useState } from 'react';
-import { ExportService, type ExportOptions, type ExportProgress } from '@/services';
+import { ExportService, databaseService, type ExportOptions, type ExportProgress } from '@/services';
 import type { TranscriptionJobResult, SummarizationResult } from '@/models';
 
 interface ExportDialogProps {
@@ -42,6 +42,17 @@ export function ExportDialog({
     setExportOptions(prev => ({ ...prev, includeMarkdown: !prev.includeMarkdown }));
   };
 
+  const handleAbsoluteTimestampsToggle = () => {
+    setExportOptions(prev => ({ ...prev, absoluteTimestamps: !prev.absoluteTimestamps }));
+  };
+
+  const handleNormalizeNumbersToggle = () => {
+    setExportOptions(prev => ({
+      ...prev,
+      postProcessing: { normalizeNumbers: !prev.postProcessing?.normalizeNumbers }
+    }));
+  };
+
   const handleExport = async () => {
     if (isExporting) return;
 
@@ -54,10 +65,12 @@ export function ExportDialog({
     });
 
     try {
+      // Output that was never saved has no comments to include
+      const comments = await databaseService.getComments(transcription.id).catch(() => []);
       await ExportService.exportTranscription(
         transcription,
         summary,
-        exportOptions,
+        { ...exportOptions, comments },
         (progressUpdate) => {
           setProgress(progressUpdate);
           if (progressUpdate.status === 'complete') {
@@ -185,6 +198,32 @@ export function ExportDialog({
                   📝 Use markdown formatting (for better readability)
                 </span>
               </label>
+
+              {transcription.recordingStartedAt && (
+                <label className="checkbox-option">
+                  <input
+                    type="checkbox"
+                    checked={exportOptions.absoluteTimestamps ?? false}
+                    onChange={handleAbsoluteTimestampsToggle}
+                    disabled={isExporting}
+                  />
+                  <span className="option-label">
+                    🕒 Show clock times (e.g. 14:32:05) instead of offsets
+                  </span>
+                </label>
+              )}
+
+              <label className="checkbox-option">
+                <input
+                  type="checkbox"
+                  checked={exportOptions.postProcessing?.normalizeNumbers ?? false}
+                  onChange={handleNormalizeNumbersToggle}
+                  disabled={isExporting}
+                />
+                <span className="option-label">
+                  🔢 Write spoken numbers and dates as digits (e.g. €23)
+                </span>
+              </label>
             </div>
           </div>
 
@@ -94,6 +94,8 @@ export function HistoryView({ className = '' }: HistoryViewProps) {
       modelUsed: transcription.model_used,
       duration: transcription.duration,
       confidence: transcription.confidence,
+      recordingStartedAt: transcription.recording_started_at,
+      recordingTimezone: transcription.recording_timezone,
       createdAt: new Date(transcription.created_at),
       updatedAt: new Date(transcription.updated_at)
     };
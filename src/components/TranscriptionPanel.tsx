@@ -3,6 +3,7 @@
  */
 
 import { useState, useEffect } from 'react';
+import { open } from '@tauri-apps/api/dialog';
 import { useTranscription, useLanguageDetection } from '@/hooks';
 import { databaseService } from '@/services';
 import { SummarizationPanel } from './SummarizationPanel';
@@ -39,6 +40,16 @@ export function TranscriptionPanel({ audioFile, className = '' }: TranscriptionP
     modelSize: 'base',
     returnTimestamps: true,
   });
+  // Saved edits, and the transcript version the next edit is based on
+  const [editedText, setEditedText] = useState<string | null>(null);
+  const [version, setVersion] = useState<number | null>(null);
+  const [editError, setEditError] = useState<string | null>(null);
+
+  useEffect(() => {
+    setEditedText(null);
+    setVersion(null);
+    setEditError(null);
+  }, [result?.id]);
 
   // Auto-detect language and save to database when transcription completes
   useEffect(() => {
@@ -59,6 +70,14 @@ export function TranscriptionPanel({ audioFile, className = '' }: TranscriptionP
         try {
           await databaseService.saveTranscription(result);
           console.log('Transcription saved to database');
+          setVersion(await databaseService.getTranscriptVersion(result.id));
+
+          // Name recordings like "Voice 014.m4a" after their content, as
+          // queued jobs do
+          const title = await databaseService.nameTranscription(result.id);
+          if (title) {
+            console.log(`Generated transcript title: ${title}`);
+          }
         } catch (error) {
           console.error('Failed to save transcription to database:', error);
         }
@@ -68,6 +87,42 @@ export function TranscriptionPanel({ audioFile, className = '' }: TranscriptionP
     }
   }, [result?.text, options.language, detectLanguage]);
 
+  const handleEditTranscript = async (newText: string) => {
+    if (!result || version === null) return;
+    try {
+      const outcome = await databaseService.updateTranscriptText(result.id, newText, version);
+      setVersion(outcome.version);
+      setEditedText(newText);
+      setEditError(null);
+    } catch (err) {
+      // EDIT_CONFLICT names who saved in the meantime; reload to see their changes
+      const message = typeof err === 'object' && err !== null && 'message' in err
+        ? String((err as { message: unknown }).message)
+        : String(err);
+      setEditError(message);
+    }
+  };
+
+  const handleReimportSubtitles = async () => {
+    if (!result || version === null) return;
+    const path = await open({
+      title: 'Choose the corrected subtitles',
+      filters: [{ name: 'Subtitles', extensions: ['srt', 'vtt'] }],
+    });
+    if (typeof path !== 'string') return;
+    try {
+      const outcome = await databaseService.reimportSubtitles(result.id, path, version);
+      setVersion(outcome.version);
+      setEditedText(outcome.text);
+      setEditError(null);
+    } catch (err) {
+      const message = typeof err === 'object' && err !== null && 'message' in err
+        ? String((err as { message: unknown }).message)
+        : String(err);
+      setEditError(message);
+    }
+  };
+
   const handleStartTranscription = () => {
     startTranscription(audioFile, options);
   };
@@ -205,21 +260,34 @@ export function TranscriptionPanel({ audioFile, className = '' }: TranscriptionP
         </div>
       )}
 
+      {editError && (
+        <div className="transcription-error">
+          <span className="error-icon">⚠️</span>
+          <span className="error-text">{editError}</span>
+          <button className="clear-error-button" onClick={() => setEditError(null)}>
+            ✕
+          </button>
+        </div>
+      )}
+
       {/* Enhanced Results Display */}
       {result && (
         <TranscriptDisplay
-          transcription={result}
+          transcription={editedText === null ? result : { ...result, text: editedText }}
           onSegmentClick={(segment) => {
             console.log('Segment clicked:', segment);
             // Could integrate with audio player to jump to timestamp
           }}
-          onEditTranscript={(newText) => {
-            console.log('Transcript edited:', newText);
-            // Could implement transcript editing functionality
-          }}
+          onEditTranscript={handleEditTranscript}
         />
       )}
 
+      {result && version !== null && (
+        <button className="reimport-button" onClick={handleReimportSubtitles}>
+          Re-import corrected subtitles
+        </button>
+      )}
+
       {/* Summarization Panel - Show when transcription is complete */}
       {result && (
         <SummarizationPanel 
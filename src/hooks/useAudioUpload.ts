@@ -5,10 +5,12 @@
 import { useState, useCallback } from 'react';
 import type { AudioFile, FileUploadProgress } from '@/models';
 import { validateAudioFile, getAudioFormat, extractAudioMetadata } from '@/utils';
+import { databaseService, quotaViolation } from '@/services';
 
 interface UseAudioUploadReturn {
   uploadProgress: FileUploadProgress[];
-  uploadFiles: (files: File[]) => Promise<AudioFile[]>;
+  /** Import files, into `project` if given */
+  uploadFiles: (files: File[], project?: string) => Promise<AudioFile[]>;
   isUploading: boolean;
   clearProgress: () => void;
 }
@@ -17,7 +19,7 @@ export function useAudioUpload(): UseAudioUploadReturn {
   const [uploadProgress, setUploadProgress] = useState<FileUploadProgress[]>([]);
   const [isUploading, setIsUploading] = useState(false);
 
-  const uploadFiles = useCallback(async (files: File[]): Promise<AudioFile[]> => {
+  const uploadFiles = useCallback(async (files: File[], project?: string): Promise<AudioFile[]> => {
     setIsUploading(true);
     const uploadedFiles: AudioFile[] = [];
 
@@ -78,6 +80,19 @@ export function useAudioUpload(): UseAudioUploadReturn {
 
           const metadata = await extractAudioMetadata(file);
 
+          // Keep a copy with its embedded tags, whose title becomes the
+          // title of transcripts made from it
+          const stored = await databaseService
+            .importAudioData(fileId, file, project)
+            .catch(error => {
+              // A file over its project's quota is refused, not kept unstored
+              if (quotaViolation(error)) {
+                throw error;
+              }
+              console.warn(`Could not store ${file.name}:`, error);
+              return null;
+            });
+
           setUploadProgress(prev =>
             prev.map(p => (p.fileId === fileId ? { ...p, progress: 75, status: 'validating' } : p))
           );
@@ -92,6 +107,7 @@ export function useAudioUpload(): UseAudioUploadReturn {
             file: file, // Store the actual File object for transcription
             size: file.size,
             ...(metadata.duration && { duration: metadata.duration }),
+            ...(stored?.title && { title: stored.title }),
             format: getAudioFormat(file)!,
             uploadedAt: new Date(),
             lastModified: new Date(file.lastModified),
@@ -110,7 +126,10 @@ export function useAudioUpload(): UseAudioUploadReturn {
                 ? {
                     ...p,
                     status: 'error',
-                    error: error instanceof Error ? error.message : 'Unknown error',
+                    error:
+                      error instanceof Error
+                        ? error.message
+                        : ((error as { message?: string } | null)?.message ?? 'Unknown error'),
                   }
                 : p
             )
@@ -17,6 +17,8 @@ export interface AudioFile {
   file?: File; // Actual File object for transcription
   size: number;
   duration?: number;
+  /** Title embedded in the file's tags */
+  title?: string;
   format: SupportedAudioFormat;
   uploadedAt: Date;
   lastModified: Date;
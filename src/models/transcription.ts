@@ -40,6 +40,8 @@ export interface TranscriptionSegment {
   startTime: number;
   endTime: number;
   confidence?: number;
+  /** Speaker label, when speakers were told apart */
+  speaker?: string;
 }
 
 export interface TranscriptionJobResult {
@@ -51,6 +53,10 @@ export interface TranscriptionJobResult {
   modelUsed: string;
   duration: number;
   confidence?: number;
+  /** Wall-clock time recording started, ISO 8601 with its UTC offset */
+  recordingStartedAt?: string | undefined;
+  /** IANA timezone the recording was made in */
+  recordingTimezone?: string | undefined;
   createdAt: Date;
   updatedAt: Date;
 }
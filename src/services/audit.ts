@@ -0,0 +1,35 @@
+/**
+ * Audit log of destructive operations: deletions, merges, archived or
+ * removed audio and API key changes, with who ran them
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+
+export interface AuditEntry {
+  id: string;
+  /** e.g. `transcription_deleted`, `audio_deleted`, `api_key_changed` */
+  action: string;
+  targetIds: string[];
+  actor: string;
+  detail: string | null;
+  createdAt: string;
+}
+
+export interface AuditFilter {
+  action?: string;
+  /** Entries that affected this id */
+  targetId?: string;
+  actor?: string;
+  /** First day to include, YYYY-MM-DD in UTC */
+  dateFrom?: string;
+  /** Last day to include, YYYY-MM-DD in UTC */
+  dateTo?: string;
+  limit?: number;
+}
+
+/**
+ * Matching entries, newest first
+ */
+export async function getAuditLog(filter?: AuditFilter): Promise<AuditEntry[]> {
+  return invoke<AuditEntry[]>('get_audit_log', { filter: filter ?? null });
+}
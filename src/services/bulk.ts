@@ -0,0 +1,135 @@
+/**
+ * Batch operations over the transcription history
+ *
+ * Each call queues one backend job and returns its id. The job's `progress`
+ * is updated on every `job://updated` event as items finish.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { PostProcessingRules } from './export.js';
+
+export type BulkExportFormat = 'txt' | 'markdown' | 'srt' | 'json';
+
+export interface BatchProgress {
+  total: number;
+  succeeded: number;
+  failed: { id: string; error: string }[];
+}
+
+export interface BulkJobUpdate {
+  id: string;
+  status: 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
+  progress: BatchProgress | null;
+  error: string | null;
+}
+
+export async function deleteTranscriptions(ids: string[]): Promise<string> {
+  return invoke<string>('delete_transcriptions', { ids });
+}
+
+export async function tagTranscriptions(ids: string[], tag: string): Promise<string> {
+  return invoke<string>('tag_transcriptions', { ids, tag });
+}
+
+/**
+ * `fileName` is a template such as `{date}_{title}_{lang}`; the fields are
+ * date, time, title, lang, model, id, source and speaker. With `bySpeaker`,
+ * each speaker of a diarized transcript gets a file of their own. A null
+ * `format` writes each in its meeting type's export template, else as text
+ */
+export async function exportTranscriptions(
+  ids: string[],
+  format: BulkExportFormat | null,
+  dir: string,
+  rules?: PostProcessingRules,
+  fileName?: string,
+  bySpeaker = false
+): Promise<string> {
+  return invoke<string>('export_transcriptions', {
+    ids,
+    format,
+    dir,
+    rules,
+    fileName: fileName ?? null,
+    bySpeaker,
+  });
+}
+
+/**
+ * Write one WAV per speaker, silent outside their turns. `channels` maps
+ * speakers to the channel (from 0) that carries them in the original file,
+ * for recordings with one party per channel. Returns the files written
+ */
+export async function exportSpeakerStems(
+  transcriptionId: string,
+  dir: string,
+  channels?: Record<string, number>
+): Promise<string[]> {
+  return invoke<string[]>('export_speaker_stems', {
+    transcriptionId,
+    dir,
+    channels: channels ?? null,
+  });
+}
+
+/**
+ * Write an HTML page that highlights each word as the embedded audio plays.
+ * The audio defaults to the transcription's source file; audio over 150 MB
+ * is refused. Word times are estimated within each segment, so the
+ * highlight is approximate. Returns the path
+ */
+export async function exportReadAlongPage(
+  id: string,
+  path: string,
+  audioPath?: string,
+  title?: string
+): Promise<string> {
+  return invoke<string>('export_read_along_page', {
+    id,
+    path,
+    audioPath: audioPath ?? null,
+    title: title ?? null,
+  });
+}
+
+/**
+ * An export written as soon as a transcription completes
+ */
+export interface AutoExportRule {
+  format: BulkExportFormat;
+  /** Folder to write into; null writes next to the source file */
+  dir?: string | null;
+  /** File name template; defaults to `{source}` */
+  fileName?: string | null;
+  rules?: PostProcessingRules;
+}
+
+export async function getAutoExportRules(): Promise<AutoExportRule[]> {
+  return invoke<AutoExportRule[]>('get_auto_export');
+}
+
+export async function setAutoExportRules(rules: AutoExportRule[]): Promise<void> {
+  return invoke('set_auto_export', { rules });
+}
+
+export async function summarizeTranscriptions(ids: string[]): Promise<string> {
+  return invoke<string>('summarize_transcriptions', { ids });
+}
+
+/**
+ * Follow one batch job until it stops running
+ */
+export async function watchBulkJob(
+  jobId: string,
+  onUpdate: (update: BulkJobUpdate) => void
+): Promise<UnlistenFn> {
+  const unlisten = await listen<BulkJobUpdate>('job://updated', event => {
+    if (event.payload.id !== jobId) return;
+    onUpdate(event.payload);
+    if (!['queued', 'running'].includes(event.payload.status)) {
+      unlisten();
+    }
+  });
+  return unlisten;
+}
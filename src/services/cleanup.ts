@@ -0,0 +1,73 @@
+/**
+ * Opt-in cleanup of source audio once a transcript is done
+ *
+ * After a transcription (and its summary, when one is expected) is saved,
+ * report it with `sourceProcessed`. If the cleanup settings allow it and
+ * the transcript is confident enough, the recording is deleted or moved
+ * after a grace period, which `cancelSourceCleanup` can interrupt.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+
+export type CleanupAction = 'keep' | 'delete' | 'move';
+
+export interface SourceCleanup {
+  action: CleanupAction;
+  /** Folder recordings are moved into, for `move` */
+  destination?: string | null;
+  /** Between 0 and 1 */
+  minConfidence: number;
+  graceHours: number;
+  requireSummary: boolean;
+}
+
+export interface ScheduledCleanup {
+  audioFileId: string;
+  transcriptionId: string;
+  action: CleanupAction;
+  destination: string | null;
+  /** Unix seconds */
+  dueAt: number;
+  status: 'scheduled' | 'done' | 'cancelled' | 'failed';
+  error: string | null;
+}
+
+export async function getSourceCleanup(): Promise<SourceCleanup> {
+  return invoke<SourceCleanup>('get_source_cleanup');
+}
+
+export async function setSourceCleanup(settings: SourceCleanup): Promise<void> {
+  return invoke('set_source_cleanup', { settings });
+}
+
+/**
+ * Set a watch folder's own cleanup settings; null falls back to the global ones
+ */
+export async function setWatchFolderCleanup(
+  id: string,
+  cleanup: SourceCleanup | null
+): Promise<void> {
+  return invoke('set_watch_folder_cleanup', { id, cleanup });
+}
+
+/**
+ * Report a saved transcription; resolves to the scheduled removal, or null
+ * when the source audio is kept
+ */
+export async function sourceProcessed(
+  transcriptionId: string,
+  watchFolderId?: string
+): Promise<ScheduledCleanup | null> {
+  return invoke<ScheduledCleanup | null>('source_processed', {
+    transcriptionId,
+    watchFolderId: watchFolderId ?? null,
+  });
+}
+
+export async function listSourceCleanups(): Promise<ScheduledCleanup[]> {
+  return invoke<ScheduledCleanup[]>('list_source_cleanups');
+}
+
+export async function cancelSourceCleanup(audioFileId: string): Promise<void> {
+  return invoke('cancel_source_cleanup', { audioFileId });
+}
@@ -0,0 +1,89 @@
+/**
+ * Voice memos pushed from a phone over the local network
+ *
+ * Turning uploads on starts an HTTPS server that phones find over mDNS as
+ * `_transcriber._tcp`. A phone pairs once with the code shown by
+ * `startCompanionPairing`, pinning the certificate fingerprint shown with
+ * it; its memos are then imported into the chosen project and queued.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export interface CompanionSettings {
+  enabled: boolean;
+  port: number;
+  /** Project memos are imported into; null keeps them outside any */
+  project: string | null;
+  model: string | null;
+  language: string | null;
+}
+
+export interface CompanionStatus {
+  settings: CompanionSettings;
+  /** The server is accepting uploads */
+  listening: boolean;
+  hostName: string;
+  /** SHA-256 of the server certificate, once one has been made */
+  fingerprint: string | null;
+}
+
+export interface PairingInfo {
+  /** Six digits, valid once */
+  code: string;
+  expiresInSecs: number;
+  hostName: string;
+  port: number;
+  fingerprint: string;
+}
+
+export interface CompanionDevice {
+  id: string;
+  name: string;
+  pairedAt: string;
+  lastSeenAt: string | null;
+}
+
+export interface ReceivedMemo {
+  audioFileId: string;
+  jobId: string;
+  deviceName: string;
+  project: string | null;
+  fileName: string;
+}
+
+export async function getCompanionStatus(): Promise<CompanionStatus> {
+  return invoke<CompanionStatus>('get_companion_status');
+}
+
+/**
+ * Save the settings, starting or stopping the server to match
+ */
+export async function setCompanionSettings(settings: CompanionSettings): Promise<CompanionStatus> {
+  return invoke<CompanionStatus>('set_companion_settings', { settings });
+}
+
+/**
+ * Open pairing for a few minutes; needs uploads to be on
+ */
+export async function startCompanionPairing(): Promise<PairingInfo> {
+  return invoke<PairingInfo>('start_companion_pairing');
+}
+
+export async function listCompanionDevices(): Promise<CompanionDevice[]> {
+  return invoke<CompanionDevice[]>('list_companion_devices');
+}
+
+/**
+ * Forget a paired phone; its uploads are refused from then on
+ */
+export async function removeCompanionDevice(id: string): Promise<void> {
+  await invoke('remove_companion_device', { id });
+}
+
+/**
+ * Call `onMemo` for each memo a phone uploads, once it is queued
+ */
+export function onMemoReceived(onMemo: (memo: ReceivedMemo) => void): Promise<UnlistenFn> {
+  return listen<ReceivedMemo>('companion://memo', event => onMemo(event.payload));
+}
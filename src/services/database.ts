@@ -3,6 +3,7 @@
  */
 
 import Database from '@tauri-apps/plugin-sql';
+import { invoke } from '@tauri-apps/api/tauri';
 import type { 
   TranscriptionJobResult, 
   SummarizationResult
@@ -16,8 +17,82 @@ export interface TranscriptionRecord {
   model_used: string;
   duration: number;
   confidence?: number;
+  /** Defaults to the title embedded in the source audio file */
+  title?: string | undefined;
+  /** Wall-clock recording start, ISO 8601 with its UTC offset */
+  recording_started_at?: string | undefined;
+  recording_timezone?: string | undefined;
   created_at: string;
   updated_at: string;
+  /** Metadata read from the source audio file when it was imported */
+  audio?: AudioFileMetadata | undefined;
+}
+
+export interface AudioFileMetadata {
+  title?: string | undefined;
+  artist?: string | undefined;
+  album?: string | undefined;
+  recordedAt?: string | undefined;
+  device?: string | undefined;
+}
+
+/** Non-destructive trim points; unset ends keep the full recording */
+export interface AudioTrim {
+  startMs?: number | undefined;
+  endMs?: number | undefined;
+}
+
+export interface RetranscribeRangeOptions {
+  model?: string;
+  language?: string;
+  /** Filter out rumble and normalize the level before decoding */
+  enhance?: boolean;
+}
+
+export interface RetranscribeRangeOutcome {
+  version: number;
+  /** Seconds, widened to whole segments */
+  start: number;
+  end: number;
+  replaced: number;
+  segments: Array<{
+    id: string;
+    position: number;
+    speaker: string | null;
+    text: string;
+    start: number;
+    end: number;
+    confidence: number | null;
+  }>;
+}
+
+export interface DiarizeOptions {
+  /** The number of speakers, when it is known */
+  speakers?: number;
+  minSpeakers?: number;
+  maxSpeakers?: number;
+  /** How different two voices must sound to be told apart; lower splits more readily */
+  threshold?: number;
+}
+
+export interface SubtitleReimport {
+  cues: number;
+  matched: number;
+  /** Segments whose text changed */
+  changed: number;
+  /** 1-based places in the file of cues that matched no segment */
+  unmatchedCues: number[];
+  /** Unchanged if no text was */
+  version: number;
+  /** The transcript text after the import */
+  text: string;
+  segments: RetranscribeRangeOutcome['segments'];
+}
+
+export interface EditOutcome {
+  /** The transcript version the edit produced */
+  version: number;
+  editor: string;
 }
 
 export interface SummaryRecord {
@@ -39,12 +114,55 @@ export interface UserPreference {
   updated_at: string;
 }
 
+export interface SchemaVersion {
+  current: number;
+  supported: number;
+  compatible: boolean;
+}
+
+export interface UsageLine {
+  provider: string;
+  model: string;
+  operation: 'summarization' | 'transcription';
+  requests: number;
+  inputTokens: number;
+  outputTokens: number;
+  audioMinutes: number;
+  /** Estimated from list prices, in USD */
+  estimatedCost: number;
+  /** Requests to models without a known price, not included in the cost */
+  unpricedRequests: number;
+}
+
+export interface UsageReport {
+  month: string;
+  lines: UsageLine[];
+  totalCost: number;
+}
+
+export interface TranscriptComment {
+  id: string;
+  transcriptionId: string;
+  /** Position in the audio the comment refers to, in milliseconds */
+  anchorMs: number;
+  author: string;
+  text: string;
+  createdAt: string;
+  updatedAt: string;
+}
+
 export interface TranscriptionHistoryFilters {
   language?: string | undefined;
   modelUsed?: string | undefined;
   dateFrom?: string | undefined;
   dateTo?: string | undefined;
   searchText?: string | undefined;
+  /** Filters on metadata embedded in the source audio file */
+  artist?: string | undefined;
+  device?: string | undefined;
+  recordedFrom?: string | undefined;
+  recordedTo?: string | undefined;
+  tag?: string | undefined;
   limit?: number | undefined;
   offset?: number | undefined;
 }
@@ -65,6 +183,14 @@ class DatabaseService {
   async initialize(): Promise<void> {
     if (this.isInitialized) return;
 
+    // Refuse to open a database written by a newer build of the app
+    const schema = await this.getSchemaVersion();
+    if (!schema.compatible) {
+      throw new Error(
+        `This database uses schema version ${schema.current}, but this version of the app only supports up to ${schema.supported}. Please update the app.`
+      );
+    }
+
     try {
       // Use the v1 API - Database.load for initialization
       this.db = await Database.load('sqlite:transcription_history.db');
@@ -77,16 +203,145 @@ class DatabaseService {
   }
 
   /**
-   * Save a transcription result to the database
+   * Get the schema version of the database and whether this build supports it
+   */
+  async getSchemaVersion(): Promise<SchemaVersion> {
+    return invoke<SchemaVersion>('get_schema_version');
+  }
+
+  /**
+   * Cloud provider usage and estimated spend for a month (`YYYY-MM`)
+   */
+  async getUsageReport(month: string): Promise<UsageReport> {
+    return invoke<UsageReport>('get_usage_report', { month });
+  }
+
+  /**
+   * Write every transcription matching the filters (ignoring paging) to a
+   * CSV file; resolves to the number of rows written
+   */
+  async exportHistoryCsv(filters: TranscriptionHistoryFilters, path: string): Promise<number> {
+    return invoke<number>('export_history_csv', { filter: filters, path });
+  }
+
+  /**
+   * Split a transcription at `atMs`; resolves to the id of the new
+   * transcription holding everything after the cut
+   */
+  async splitTranscription(id: string, atMs: number): Promise<string> {
+    return invoke<string>('split_transcription', { id, atMs: Math.round(atMs) });
+  }
+
+  /**
+   * Merge transcriptions, in order, into the first; the others are removed.
+   * Resolves to the id of the merged transcription
+   */
+  async mergeTranscriptions(ids: string[]): Promise<string> {
+    return invoke<string>('merge_transcriptions', { ids });
+  }
+
+  /**
+   * Set trim points applied to playback, clips and re-transcription; the
+   * source audio is left untouched. Pass neither to clear them
+   */
+  async setAudioTrim(transcriptionId: string, startMs?: number, endMs?: number): Promise<AudioTrim> {
+    return invoke<AudioTrim>('set_audio_trim', {
+      transcriptionId,
+      startMs: startMs === undefined ? null : Math.round(startMs),
+      endMs: endMs === undefined ? null : Math.round(endMs),
+    });
+  }
+
+  async getAudioTrim(transcriptionId: string): Promise<AudioTrim> {
+    return invoke<AudioTrim>('get_audio_trim', { transcriptionId });
+  }
+
+  /**
+   * Decode a time range again, optionally with another model or enhanced
+   * audio, and splice the new segments into the transcript
+   */
+  async retranscribeRange(
+    id: string,
+    startMs: number,
+    endMs: number,
+    options?: RetranscribeRangeOptions
+  ): Promise<RetranscribeRangeOutcome> {
+    return invoke<RetranscribeRangeOutcome>('retranscribe_range', {
+      id,
+      startMs: Math.round(startMs),
+      endMs: Math.round(endMs),
+      options: options ?? null,
+    });
+  }
+
+  /**
+   * Group a transcript's segments into speakers again, e.g. with the right
+   * speaker count after two voices were merged. Speaker names are replaced
+   * by numbered labels
+   */
+  async rediarize(
+    id: string,
+    options?: DiarizeOptions
+  ): Promise<RetranscribeRangeOutcome['segments']> {
+    return invoke<RetranscribeRangeOutcome['segments']>('rediarize', {
+      id,
+      options: options ?? null,
+    });
+  }
+
+  /**
+   * Read back an SRT or WebVTT export corrected in another editor and save
+   * the changed cue texts as one new version. Timings are left alone; show
+   * the returned text and segments in place of the old ones
+   */
+  async reimportSubtitles(
+    transcriptionId: string,
+    path: string,
+    expectedVersion?: number
+  ): Promise<SubtitleReimport> {
+    return invoke<SubtitleReimport>('reimport_subtitles', {
+      transcriptionId,
+      path,
+      expectedVersion: expectedVersion ?? null,
+    });
+  }
+
+  /**
+   * The decoder prompt the next meeting tagged `series` would start with:
+   * names and terms from the latest transcription with that tag
+   */
+  async getSeriesPrompt(series: string): Promise<string | null> {
+    return invoke<string | null>('get_series_prompt', { series });
+  }
+
+  /**
+   * Language of text the backend writes: export headings, default labels
+   * and error messages. Set it here rather than as a plain preference so
+   * the backend picks it up at once
+   */
+  async getUiLanguage(): Promise<string> {
+    return invoke<string>('get_ui_language');
+  }
+
+  async setUiLanguage(language: string): Promise<void> {
+    await invoke('set_ui_language', { language });
+  }
+
+  /**
+   * Save a new transcription result to the database. A result that was
+   * already saved is left alone: change it with updateTranscriptText or
+   * updateSegmentText, which check and bump its version
    */
   async saveTranscription(transcription: TranscriptionJobResult): Promise<void> {
     await this.ensureInitialized();
 
     try {
-      await this.db!.execute(
-        `INSERT OR REPLACE INTO transcriptions (
-          id, audio_file_id, text, language, model_used, duration, confidence, created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)`,
+      const inserted = await this.db!.execute(
+        `INSERT INTO transcriptions (
+          id, audio_file_id, text, language, model_used, duration, confidence, title,
+          recording_started_at, recording_timezone, created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, (SELECT title FROM audio_files WHERE id = ?), ?, ?, ?, ?)
+        ON CONFLICT(id) DO NOTHING`,
         [
           transcription.id,
           transcription.audioFileId,
@@ -95,16 +350,78 @@ class DatabaseService {
           transcription.modelUsed,
           transcription.duration,
           transcription.confidence || null,
+          transcription.audioFileId,
+          transcription.recordingStartedAt ?? null,
+          transcription.recordingTimezone ?? null,
           new Date().toISOString(),
           new Date().toISOString()
         ]
       );
+      if (inserted.rowsAffected === 0) {
+        return;
+      }
+
+      // Segments are kept separately so the backend can analyze the timeline
+      await invoke('save_segments', {
+        transcriptionId: transcription.id,
+        segments: transcription.segments.map(segment => ({
+          speaker: segment.speaker ?? null,
+          text: segment.text,
+          start: segment.startTime,
+          end: segment.endTime,
+          confidence: segment.confidence ?? null
+        }))
+      });
     } catch (error) {
       console.error('Failed to save transcription:', error);
       throw new Error('Failed to save transcription to database');
     }
   }
 
+  /**
+   * Store a file added in the window with the metadata embedded in it, so
+   * its transcripts take the embedded title. Importing into a project fails
+   * with `QUOTA_EXCEEDED` if the file does not fit its quota
+   */
+  async importAudioData(id: string, file: File, project?: string): Promise<AudioFileMetadata> {
+    const data = Array.from(new Uint8Array(await file.arrayBuffer()));
+    return invoke<AudioFileMetadata>('import_audio_data', {
+      id,
+      fileName: file.name,
+      data,
+      project: project ?? null,
+    });
+  }
+
+  /**
+   * Move an imported file into a project, or out of any with null
+   */
+  async setAudioFileProject(id: string, project: string | null): Promise<AudioFileMetadata> {
+    return invoke<AudioFileMetadata>('set_audio_file_project', { id, project });
+  }
+
+  /**
+   * The version a transcript edit must be based on
+   */
+  async getTranscriptVersion(id: string): Promise<number> {
+    return invoke<number>('get_transcript_version', { id });
+  }
+
+  /**
+   * Replace a transcript's text as an attributed edit. Fails with
+   * EDIT_CONFLICT if someone saved since `expectedVersion`
+   */
+  async updateTranscriptText(id: string, text: string, expectedVersion: number): Promise<EditOutcome> {
+    return invoke<EditOutcome>('update_transcript_text', { id, text, expectedVersion });
+  }
+
+  /**
+   * Change one segment's text as an attributed edit
+   */
+  async updateSegmentText(segmentId: string, text: string, expectedVersion: number): Promise<EditOutcome> {
+    return invoke<EditOutcome>('update_segment_text', { segmentId, text, expectedVersion });
+  }
+
   /**
    * Save a summary result to the database
    */
@@ -143,10 +460,14 @@ class DatabaseService {
     await this.ensureInitialized();
 
     try {
+      const columns = `t.*, s.id as summary_id, s.summary, s.compression_ratio, s.processing_time,
+        a.title as audio_title, a.artist as audio_artist, a.album as audio_album,
+        a.recorded_at as audio_recorded_at, a.device as audio_device`;
       let query = `
-        SELECT t.*, s.id as summary_id, s.summary, s.compression_ratio, s.processing_time
+        SELECT ${columns}
         FROM transcriptions t
         LEFT JOIN summaries s ON t.id = s.transcription_id
+        LEFT JOIN audio_files a ON t.audio_file_id = a.id
         WHERE 1=1
       `;
       const params: any[] = [];
@@ -173,13 +494,38 @@ class DatabaseService {
       }
 
       if (filters.searchText) {
-        query += ' AND (t.text LIKE ? OR s.summary LIKE ?)';
+        query += ' AND (t.text LIKE ? OR s.summary LIKE ? OR t.title LIKE ? OR a.title LIKE ?)';
         const searchPattern = `%${filters.searchText}%`;
-        params.push(searchPattern, searchPattern);
+        params.push(searchPattern, searchPattern, searchPattern, searchPattern);
+      }
+
+      if (filters.artist) {
+        query += ' AND a.artist LIKE ?';
+        params.push(`%${filters.artist}%`);
+      }
+
+      if (filters.device) {
+        query += ' AND a.device LIKE ?';
+        params.push(`%${filters.device}%`);
+      }
+
+      if (filters.recordedFrom) {
+        query += ' AND a.recorded_at >= ?';
+        params.push(filters.recordedFrom);
+      }
+
+      if (filters.recordedTo) {
+        query += ' AND a.recorded_at <= ?';
+        params.push(filters.recordedTo);
+      }
+
+      if (filters.tag) {
+        query += ' AND t.id IN (SELECT transcription_id FROM transcription_tags WHERE tag = ?)';
+        params.push(filters.tag);
       }
 
       // Get total count
-      const countQuery = query.replace('SELECT t.*, s.id as summary_id, s.summary, s.compression_ratio, s.processing_time', 'SELECT COUNT(*) as total');
+      const countQuery = query.replace(`SELECT ${columns}`, 'SELECT COUNT(*) as total');
       const countResult = await this.db!.select(countQuery, params) as any[];
       const total = countResult[0]?.total || 0;
 
@@ -207,8 +553,18 @@ class DatabaseService {
         model_used: row.model_used,
         duration: row.duration,
         confidence: row.confidence,
+        title: row.title ?? row.audio_title ?? undefined,
+        recording_started_at: row.recording_started_at ?? undefined,
+        recording_timezone: row.recording_timezone ?? undefined,
         created_at: row.created_at,
         updated_at: row.updated_at,
+        audio: row.audio_title || row.audio_artist || row.audio_album || row.audio_recorded_at || row.audio_device ? {
+          title: row.audio_title ?? undefined,
+          artist: row.audio_artist ?? undefined,
+          album: row.audio_album ?? undefined,
+          recordedAt: row.audio_recorded_at ?? undefined,
+          device: row.audio_device ?? undefined
+        } : undefined,
         summary: row.summary_id ? {
           id: row.summary_id,
           transcription_id: row.id,
@@ -253,6 +609,22 @@ class DatabaseService {
     }
   }
 
+  /**
+   * Generate a title from the opening of a transcription with the
+   * summarization provider, store it and return it
+   */
+  async generateTitle(id: string): Promise<string> {
+    return invoke<string>('generate_title', { id });
+  }
+
+  /**
+   * Generate a title for a transcription without one whose file name, like
+   * "Voice 014.m4a", says nothing about it. Returns the title, if any
+   */
+  async nameTranscription(id: string): Promise<string | null> {
+    return invoke<string | null>('name_transcription', { id });
+  }
+
   /**
    * Get summary for a transcription
    */
@@ -273,14 +645,12 @@ class DatabaseService {
   }
 
   /**
-   * Delete a transcription and its associated summary
+   * Delete a transcription and its associated summary. The backend does the
+   * delete so it is recorded in the audit log
    */
   async deleteTranscription(id: string): Promise<void> {
-    await this.ensureInitialized();
-
     try {
-      await this.db!.execute('DELETE FROM summaries WHERE transcription_id = ?', [id]);
-      await this.db!.execute('DELETE FROM transcriptions WHERE id = ?', [id]);
+      await invoke('delete_transcription', { id });
     } catch (error) {
       console.error('Failed to delete transcription:', error);
       throw new Error('Failed to delete transcription');
@@ -439,6 +809,31 @@ class DatabaseService {
     }
   }
 
+  /**
+   * Add a comment anchored to a point in a transcription
+   */
+  async addComment(transcriptionId: string, anchorMs: number, author: string, text: string): Promise<TranscriptComment> {
+    return invoke<TranscriptComment>('add_comment', { transcriptionId, anchorMs, author, text });
+  }
+
+  /**
+   * Get the comments on a transcription in timeline order
+   */
+  async getComments(transcriptionId: string): Promise<TranscriptComment[]> {
+    return invoke<TranscriptComment[]>('list_comments', { transcriptionId });
+  }
+
+  /**
+   * Edit a comment, optionally moving its anchor
+   */
+  async updateComment(id: string, text: string, anchorMs?: number): Promise<TranscriptComment> {
+    return invoke<TranscriptComment>('update_comment', { id, text, anchorMs });
+  }
+
+  async deleteComment(id: string): Promise<void> {
+    await invoke('delete_comment', { id });
+  }
+
   /**
    * Clear all data (for testing or reset purposes)
    */
@@ -446,9 +841,8 @@ class DatabaseService {
     await this.ensureInitialized();
 
     try {
-      await this.db!.execute('DELETE FROM summaries');
-      await this.db!.execute('DELETE FROM transcriptions');
-      await this.db!.execute('DELETE FROM user_preferences');
+      // The backend records the reset in the audit log, which survives it
+      await invoke('clear_all_data');
     } catch (error) {
       console.error('Failed to clear database:', error);
       throw new Error('Failed to clear database');
@@ -0,0 +1,40 @@
+/**
+ * Links opened from outside the app
+ *
+ * Launchers and macro keys can open `transcriber://transcribe?path=...`,
+ * `transcriber://record`, `transcriber://stop` or `transcriber://marker`.
+ * The backend carries the link out and reports it here, so the interface
+ * can follow the queued job or recording session. Links are refused until
+ * the user turns them on with `setDeepLinksEnabled`; queued files are saved
+ * as new transcriptions.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export interface HandledDeepLink {
+  url: string;
+  /** Job queued by a `transcribe` link */
+  jobId: string | null;
+  /** Recording session a command acted on */
+  sessionId: string | null;
+  error: string | null;
+}
+
+/**
+ * Call `onLink` for every link the backend handles
+ */
+export function onDeepLink(onLink: (link: HandledDeepLink) => void): Promise<UnlistenFn> {
+  return listen<HandledDeepLink>('deep-link://handled', event => onLink(event.payload));
+}
+
+export async function getDeepLinksEnabled(): Promise<boolean> {
+  return invoke<boolean>('get_deep_links_enabled');
+}
+
+/**
+ * Allow or refuse links that record and transcribe
+ */
+export async function setDeepLinksEnabled(enabled: boolean): Promise<void> {
+  return invoke('set_deep_links_enabled', { enabled });
+}
@@ -4,10 +4,13 @@
  */
 
 import { writeTextFile, writeBinaryFile } from '@tauri-apps/api/fs';
+import { invoke } from '@tauri-apps/api/tauri';
 import { save } from '@tauri-apps/api/dialog';
 import { Document, Packer, Paragraph, TextRun, HeadingLevel, AlignmentType } from 'docx';
 import { jsPDF } from 'jspdf';
 import type { TranscriptionJobResult, SummarizationResult } from '@/models';
+import type { TranscriptComment } from './database.js';
+import { formatClockTime } from '@/utils/date';
 
 export interface ExportMetadata {
   title: string;
@@ -18,6 +21,15 @@ export interface ExportMetadata {
   date: string;
   compressionRatio?: number | undefined;
   processingTime?: number | undefined;
+  /** Wall-clock recording start, ISO 8601 with the recording's UTC offset */
+  recordingStartedAt?: string | undefined;
+  recordingTimezone?: string | undefined;
+}
+
+/** Text rules from the backend post-processing pipeline */
+export interface PostProcessingRules {
+  /** Write spoken numbers, amounts and dates the way the language does ("€23") */
+  normalizeNumbers: boolean;
 }
 
 export interface ExportOptions {
@@ -25,6 +37,15 @@ export interface ExportOptions {
   includeMarkdown: boolean;
   format: 'txt' | 'docx' | 'pdf';
   filename?: string;
+  /** Reviewer comments, rendered as numbered footnotes */
+  comments?: TranscriptComment[];
+  /**
+   * Render segment times as clock times ("14:32:05") from the recording
+   * start instead of offsets into the audio
+   */
+  absoluteTimestamps?: boolean;
+  /** Rules applied to this export only; the stored transcript is unchanged */
+  postProcessing?: PostProcessingRules;
 }
 
 export interface ExportProgress {
@@ -36,6 +57,24 @@ export interface ExportProgress {
 export type ExportProgressCallback = (progress: ExportProgress) => void;
 
 export class ExportService {
+  /**
+   * Apply post-processing rules to the text and each segment
+   */
+  private static async postProcess(
+    transcription: TranscriptionJobResult,
+    rules: PostProcessingRules
+  ): Promise<TranscriptionJobResult> {
+    const apply = (text: string) =>
+      invoke<string>('post_process_text', { text, language: transcription.language, rules });
+    return {
+      ...transcription,
+      text: await apply(transcription.text),
+      segments: await Promise.all(
+        transcription.segments.map(async segment => ({ ...segment, text: await apply(segment.text) }))
+      )
+    };
+  }
+
   /**
    * Export transcription and optional summary to specified format
    */
@@ -52,6 +91,10 @@ export class ExportService {
         progress: 0
       });
 
+      if (options.postProcessing) {
+        transcription = await this.postProcess(transcription, options.postProcessing);
+      }
+
       const metadata: ExportMetadata = {
         title: `Transcription - ${transcription.modelUsed}`,
         sourceFile: 'Audio File',
@@ -60,7 +103,9 @@ export class ExportService {
         duration: transcription.duration,
         date: new Date().toISOString(),
         compressionRatio: summary?.compressionRatio,
-        processingTime: summary?.processingTime
+        processingTime: summary?.processingTime,
+        recordingStartedAt: transcription.recordingStartedAt,
+        recordingTimezone: transcription.recordingTimezone
       };
 
       onProgress?.({
@@ -151,11 +196,14 @@ export class ExportService {
     content += 'TRANSCRIPTION\n';
     content += '='.repeat(50) + '\n\n';
 
+    const notes = this.footnotes(transcription, options?.comments);
+
     if (transcription.segments.length > 0) {
       // Format with timestamps
-      transcription.segments.forEach((segment: any) => {
-        const timestamp = this.formatTimestamp(segment.startTime);
-        content += `[${timestamp}] ${segment.text}\n`;
+      transcription.segments.forEach((segment: any, index: number) => {
+        const timestamp = this.segmentTime(segment.startTime, metadata, options);
+        const refs = this.footnoteRefs(notes, index, n => ` [${n}]`);
+        content += `[${timestamp}] ${segment.text}${refs}\n`;
         if (segment.confidence) {
           content += `  Confidence: ${Math.round(segment.confidence * 100)}%\n`;
         }
@@ -165,6 +213,15 @@ export class ExportService {
       content += transcription.text + '\n\n';
     }
 
+    if (notes.length > 0) {
+      content += 'NOTES\n';
+      content += '='.repeat(50) + '\n\n';
+      notes.forEach(note => {
+        content += `[${note.number}] ${this.segmentTime(note.comment.anchorMs / 1000, metadata, options)} ${note.comment.author}: ${note.comment.text}\n`;
+      });
+      content += '\n';
+    }
+
     // Add summary if available
     if (summary) {
       content += 'SUMMARY\n';
@@ -204,11 +261,14 @@ export class ExportService {
     // Add transcription content
     content += '# Transcription\n\n';
 
+    const notes = this.footnotes(transcription, options?.comments);
+
     if (transcription.segments.length > 0) {
       // Format with timestamps
-      transcription.segments.forEach((segment: any) => {
-        const timestamp = this.formatTimestamp(segment.startTime);
-        content += `**${timestamp}** ${segment.text}\n`;
+      transcription.segments.forEach((segment: any, index: number) => {
+        const timestamp = this.segmentTime(segment.startTime, metadata, options);
+        const refs = this.footnoteRefs(notes, index, n => `[^${n}]`);
+        content += `**${timestamp}** ${segment.text}${refs}\n`;
         if (segment.confidence) {
           const confidenceLevel = segment.confidence > 0.8 ? '🟢' : segment.confidence > 0.6 ? '🟡' : '🔴';
           content += `*Confidence: ${Math.round(segment.confidence * 100)}% ${confidenceLevel}*\n`;
@@ -219,6 +279,13 @@ export class ExportService {
       content += transcription.text + '\n\n';
     }
 
+    notes.forEach(note => {
+      content += `[^${note.number}]: **${this.segmentTime(note.comment.anchorMs / 1000, metadata, options)}** ${note.comment.author}: ${note.comment.text}\n`;
+    });
+    if (notes.length > 0) {
+      content += '\n';
+    }
+
     // Add summary if available
     if (summary) {
       content += '# Summary\n\n';
@@ -283,9 +350,12 @@ export class ExportService {
       })
     );
 
+    const notes = this.footnotes(transcription, options?.comments);
+
     if (transcription.segments.length > 0) {
-      transcription.segments.forEach((segment: any) => {
-        const timestamp = this.formatTimestamp(segment.startTime);
+      transcription.segments.forEach((segment: any, index: number) => {
+        const timestamp = this.segmentTime(segment.startTime, metadata, options);
+        const refs = this.footnoteRefs(notes, index, n => `[${n}]`);
         
         children.push(
           new Paragraph({
@@ -295,7 +365,8 @@ export class ExportService {
                 bold: true,
                 color: '666666'
               }),
-              new TextRun({ text: segment.text })
+              new TextRun({ text: segment.text }),
+              new TextRun({ text: refs, superScript: true })
             ]
           })
         );
@@ -323,6 +394,30 @@ export class ExportService {
       );
     }
 
+    if (notes.length > 0) {
+      children.push(
+        new Paragraph({
+          text: 'Notes',
+          heading: HeadingLevel.HEADING_2
+        })
+      );
+
+      notes.forEach(note => {
+        children.push(
+          new Paragraph({
+            children: [
+              new TextRun({ text: `[${note.number}] `, bold: true }),
+              new TextRun({
+                text: `${this.segmentTime(note.comment.anchorMs / 1000, metadata, options)} ${note.comment.author}: `,
+                color: '666666'
+              }),
+              new TextRun({ text: note.comment.text })
+            ]
+          })
+        );
+      });
+    }
+
     // Add summary if available
     if (summary) {
       children.push(
@@ -449,9 +544,12 @@ export class ExportService {
     doc.setFont('helvetica', 'normal');
     doc.setFontSize(10);
 
+    const notes = this.footnotes(transcription, options?.comments);
+
     if (transcription.segments.length > 0) {
-      transcription.segments.forEach((segment: any) => {
-        const timestamp = this.formatTimestamp(segment.startTime);
+      transcription.segments.forEach((segment: any, index: number) => {
+        const timestamp = this.segmentTime(segment.startTime, metadata, options);
+        const refs = this.footnoteRefs(notes, index, n => ` [${n}]`);
         
         // Check if we need a new page
         if (yPosition > 250) {
@@ -467,7 +565,7 @@ export class ExportService {
         doc.setFont('helvetica', 'normal');
         const textWidth = doc.getTextWidth(`[${timestamp}] `);
         const remainingWidth = 170 - textWidth;
-        const textLines = doc.splitTextToSize(segment.text, remainingWidth);
+        const textLines = doc.splitTextToSize(segment.text + refs, remainingWidth);
         doc.text(textLines, 20 + textWidth, yPosition);
         yPosition += textLines.length * 5;
 
@@ -488,6 +586,32 @@ export class ExportService {
       yPosition += textLines.length * 5 + 10;
     }
 
+    if (notes.length > 0) {
+      if (yPosition > 230) {
+        doc.addPage();
+        yPosition = 20;
+      }
+
+      doc.setFontSize(14);
+      doc.setFont('helvetica', 'bold');
+      doc.text('Notes', 20, yPosition);
+      yPosition += 10;
+
+      doc.setFont('helvetica', 'normal');
+      doc.setFontSize(9);
+      notes.forEach(note => {
+        if (yPosition > 270) {
+          doc.addPage();
+          yPosition = 20;
+        }
+        const noteText = `[${note.number}] ${this.segmentTime(note.comment.anchorMs / 1000, metadata, options)} ${note.comment.author}: ${note.comment.text}`;
+        const noteLines = doc.splitTextToSize(noteText, 170);
+        doc.text(noteLines, 20, yPosition);
+        yPosition += noteLines.length * 4 + 2;
+      });
+      yPosition += 8;
+    }
+
     // Add summary if available
     if (summary) {
       // Check if we need a new page
@@ -576,6 +700,7 @@ export class ExportService {
       `Model: ${metadata.modelUsed}`,
       `Duration: ${this.formatTimestamp(metadata.duration)}`,
       `Date: ${new Date(metadata.date).toLocaleString()}`,
+      ...(metadata.recordingStartedAt ? [`Recorded: ${this.recordingStart(metadata)}`] : []),
       ...(metadata.compressionRatio ? [`Compression: ${metadata.compressionRatio.toFixed(1)}% shorter`] : []),
       ...(metadata.processingTime ? [`Processing Time: ${(metadata.processingTime / 1000).toFixed(1)}s`] : []),
       '='.repeat(50)
@@ -594,6 +719,8 @@ export class ExportService {
       `model: "${metadata.modelUsed}"`,
       `duration: "${this.formatTimestamp(metadata.duration)}"`,
       `date: "${new Date(metadata.date).toISOString()}"`,
+      ...(metadata.recordingStartedAt ? [`recorded_at: "${metadata.recordingStartedAt}"`] : []),
+      ...(metadata.recordingTimezone ? [`timezone: "${metadata.recordingTimezone}"`] : []),
       ...(metadata.compressionRatio ? [`compression_ratio: ${metadata.compressionRatio.toFixed(1)}`] : []),
       ...(metadata.processingTime ? [`processing_time: ${(metadata.processingTime / 1000).toFixed(1)}s`] : []),
       '---'
@@ -612,6 +739,10 @@ export class ExportService {
       `Date: ${new Date(metadata.date).toLocaleString()}`
     ];
 
+    if (metadata.recordingStartedAt) {
+      lines.push(`Recorded: ${this.recordingStart(metadata)}`);
+    }
+
     if (metadata.compressionRatio) {
       lines.push(`Compression: ${metadata.compressionRatio.toFixed(1)}% shorter`);
     }
@@ -623,6 +754,58 @@ export class ExportService {
     return lines.join('\n');
   }
 
+  /**
+   * Recording start as written in the file, with the timezone name when known
+   */
+  private static recordingStart(metadata: ExportMetadata): string {
+    const zone = metadata.recordingTimezone ? ` (${metadata.recordingTimezone})` : '';
+    return `${metadata.recordingStartedAt}${zone}`;
+  }
+
+  /**
+   * Number comments in timeline order and attach each to the segment it falls in
+   * (or the last segment that starts before it)
+   */
+  private static footnotes(
+    transcription: TranscriptionJobResult,
+    comments: TranscriptComment[] = []
+  ): { number: number; segmentIndex: number; comment: TranscriptComment }[] {
+    return [...comments]
+      .sort((a, b) => a.anchorMs - b.anchorMs)
+      .map((comment, index) => {
+        const seconds = comment.anchorMs / 1000;
+        let segmentIndex = transcription.segments.findIndex(
+          segment => seconds >= segment.startTime && seconds < segment.endTime
+        );
+        if (segmentIndex === -1) {
+          segmentIndex = transcription.segments.filter(segment => segment.startTime <= seconds).length - 1;
+        }
+        return { number: index + 1, segmentIndex: Math.max(segmentIndex, 0), comment };
+      });
+  }
+
+  private static footnoteRefs(
+    notes: { number: number; segmentIndex: number }[],
+    segmentIndex: number,
+    format: (n: number) => string
+  ): string {
+    return notes
+      .filter(note => note.segmentIndex === segmentIndex)
+      .map(note => format(note.number))
+      .join('');
+  }
+
+  /**
+   * Time of a point in the recording: a clock time when absolute timestamps
+   * are requested and the recording start is known, else an offset
+   */
+  private static segmentTime(seconds: number, metadata?: ExportMetadata, options?: ExportOptions): string {
+    if (options?.absoluteTimestamps && metadata?.recordingStartedAt) {
+      return formatClockTime(metadata.recordingStartedAt, seconds);
+    }
+    return this.formatTimestamp(seconds);
+  }
+
   /**
    * Format timestamp in MM:SS format
    */
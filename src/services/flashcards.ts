@@ -0,0 +1,37 @@
+/**
+ * Anki flashcard export
+ *
+ * Writes flashcards.txt and a media folder of clips into a chosen folder.
+ * Import the text file in Anki and copy the clips into the profile's
+ * collection.media folder.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+import { open } from '@tauri-apps/api/dialog';
+
+export interface FlashcardExport {
+  notesPath: string;
+  mediaDir: string;
+  cards: number;
+}
+
+/**
+ * Export the segments at `positions`, with the stored translation into
+ * `translationLanguage` on the back. Returns null when the user cancels.
+ */
+export async function exportFlashcards(
+  transcriptionId: string,
+  positions: number[],
+  translationLanguage?: string,
+  deck?: string
+): Promise<FlashcardExport | null> {
+  const dir = await open({ directory: true, title: 'Choose a folder for the flashcards' });
+  if (typeof dir !== 'string') return null;
+  return invoke<FlashcardExport>('export_flashcards', {
+    transcriptionId,
+    positions,
+    translationLanguage,
+    deck,
+    dir
+  });
+}
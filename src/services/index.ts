@@ -26,10 +26,133 @@ export {
   type TranscriptionRecord, 
   type SummaryRecord, 
   type UserPreference,
+  type TranscriptComment,
+  type UsageReport,
+  type UsageLine,
   type TranscriptionHistoryFilters,
-  type TranscriptionHistoryResult 
+  type TranscriptionHistoryResult,
+  type AudioTrim,
+  type DiarizeOptions,
+  type RetranscribeRangeOptions,
+  type RetranscribeRangeOutcome,
+  type SubtitleReimport,
+  type EditOutcome
 } from './database.js';
 
+// Provider settings
+export {
+  getSummarizationProvider,
+  setSummarizationProvider,
+  setApiKey,
+  testApiKey,
+  deleteApiKey,
+  setOfflineMode,
+  getOfflineMode,
+  getProxySettings,
+  setProxySettings,
+  type ProxySettings,
+  type ApiKeyProvider,
+  type ApiKeyStatus,
+  type SummarizationProviderConfig
+} from './providers.js';
+
+// Batch operations
+export {
+  deleteTranscriptions,
+  tagTranscriptions,
+  exportTranscriptions,
+  exportSpeakerStems,
+  exportReadAlongPage,
+  summarizeTranscriptions,
+  getAutoExportRules,
+  setAutoExportRules,
+  watchBulkJob,
+  type AutoExportRule,
+  type BatchProgress,
+  type BulkExportFormat,
+  type BulkJobUpdate
+} from './bulk.js';
+export {
+  saveTranslations,
+  listTranslations,
+  exportBilingual,
+  type SegmentTranslation,
+  type BilingualFormat
+} from './translations.js';
+export { exportFlashcards, type FlashcardExport } from './flashcards.js';
+export {
+  comparePronunciation,
+  type PronunciationReport,
+  type WordScore,
+  type WordVerdict
+} from './practice.js';
+export {
+  saveSession,
+  restoreSession,
+  type SessionState,
+  type RestoredSession,
+  type RejectedJob,
+  type PlaybackPosition,
+  type RecordingDraft
+} from './session.js';
+export {
+  getSourceCleanup,
+  setSourceCleanup,
+  setWatchFolderCleanup,
+  sourceProcessed,
+  listSourceCleanups,
+  cancelSourceCleanup,
+  type SourceCleanup,
+  type ScheduledCleanup,
+  type CleanupAction
+} from './cleanup.js';
+export { getAuditLog, type AuditEntry, type AuditFilter } from './audit.js';
+export {
+  onDeepLink,
+  getDeepLinksEnabled,
+  setDeepLinksEnabled,
+  type HandledDeepLink,
+} from './deepLinks.js';
+export {
+  runSelfTest,
+  type SelfTestReport,
+  type SelfTestStageResult,
+  type SelfTestStage
+} from './selfTest.js';
+export {
+  getIndexStatus,
+  rebuildIndexes,
+  searchTranscripts,
+  relatedTranscriptions,
+  onIndexUpdated,
+  type IndexStatus,
+  type SearchHit,
+  type RelatedTranscription
+} from './search.js';
+export {
+  getProjectQuotas,
+  setProjectQuota,
+  getStorageBreakdown,
+  quotaViolation,
+  type ProjectQuota,
+  type QuotaViolation,
+  type ProjectUsage,
+  type StorageBreakdown
+} from './quotas.js';
+export {
+  getCompanionStatus,
+  setCompanionSettings,
+  startCompanionPairing,
+  listCompanionDevices,
+  removeCompanionDevice,
+  onMemoReceived,
+  type CompanionSettings,
+  type CompanionStatus,
+  type PairingInfo,
+  type CompanionDevice,
+  type ReceivedMemo
+} from './companion.js';
+
 // Re-export everything for convenience
 export * from './audio.js';
 export * from './storage.js';
@@ -0,0 +1,32 @@
+/**
+ * Practice mode: score a recorded attempt against a reference segment
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+
+export type WordVerdict = 'correct' | 'close' | 'wrong' | 'missed';
+
+export interface WordScore {
+  word: string;
+  heard: string | null;
+  /** 0 to 1 */
+  score: number;
+  verdict: WordVerdict;
+}
+
+export interface PronunciationReport {
+  reference: string;
+  heard: string;
+  /** Mean of the word scores, 0 to 1 */
+  score: number;
+  words: WordScore[];
+  /** Words said that are not in the reference */
+  extra: string[];
+}
+
+export async function comparePronunciation(
+  referenceSegmentId: string,
+  recordedPath: string
+): Promise<PronunciationReport> {
+  return invoke<PronunciationReport>('compare_pronunciation', { referenceSegmentId, recordedPath });
+}
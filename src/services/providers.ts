@@ -0,0 +1,77 @@
+/**
+ * Settings for the cloud and local providers used by the backend
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+
+export type ApiKeyProvider = 'openai' | 'groq' | 'mistral' | 'anthropic';
+
+export interface SummarizationProviderConfig {
+  kind: 'ollama' | 'open_ai_compatible';
+  baseUrl: string;
+  model: string;
+  /** Keychain entry whose API key authenticates requests */
+  apiKey?: ApiKeyProvider | null;
+}
+
+export interface ApiKeyStatus {
+  provider: ApiKeyProvider;
+  stored: boolean;
+  /** Null when no key is stored */
+  valid: boolean | null;
+}
+
+export async function getSummarizationProvider(): Promise<SummarizationProviderConfig> {
+  return invoke<SummarizationProviderConfig>('get_summarization_provider');
+}
+
+export async function setSummarizationProvider(config: SummarizationProviderConfig): Promise<void> {
+  await invoke('set_summarization_provider', { config });
+}
+
+/**
+ * Validate an API key with the provider and store it in the OS keychain
+ */
+export async function setApiKey(provider: ApiKeyProvider, key: string): Promise<void> {
+  await invoke('set_api_key', { provider, key });
+}
+
+export async function testApiKey(provider: ApiKeyProvider): Promise<ApiKeyStatus> {
+  return invoke<ApiKeyStatus>('test_api_key', { provider });
+}
+
+export async function deleteApiKey(provider: ApiKeyProvider): Promise<void> {
+  await invoke('delete_api_key', { provider });
+}
+
+/**
+ * When on, the backend refuses every request that would leave this machine
+ */
+export async function setOfflineMode(enabled: boolean): Promise<void> {
+  await invoke('set_offline_mode', { enabled });
+}
+
+export async function getOfflineMode(): Promise<boolean> {
+  return invoke<boolean>('offline_mode');
+}
+
+/**
+ * `system` uses the proxy environment variables, else the Windows or macOS
+ * proxy settings; PAC scripts and bypass lists are not evaluated
+ */
+export type ProxySettings =
+  | { type: 'system' }
+  | { type: 'direct' }
+  | { type: 'manual'; url: string; username?: string | null };
+
+export async function getProxySettings(): Promise<ProxySettings> {
+  return invoke<ProxySettings>('get_proxy_settings');
+}
+
+/**
+ * Save proxy settings; the password goes to the OS keychain and is kept
+ * unchanged when omitted
+ */
+export async function setProxySettings(settings: ProxySettings, password?: string): Promise<void> {
+  await invoke('set_proxy_settings', { settings, password: password ?? null });
+}
@@ -0,0 +1,76 @@
+/**
+ * Storage quotas per project, and where disk space goes
+ *
+ * An import into a project that would go over its quota fails with code
+ * `QUOTA_EXCEEDED`; the error's `quota` field says which limit it broke.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+
+export interface ProjectQuota {
+  project: string;
+  maxHours: number | null;
+  maxGb: number | null;
+}
+
+export interface QuotaViolation {
+  project: string;
+  resource: 'hours' | 'bytes';
+  /** Already used by the project, in hours or bytes */
+  used: number;
+  /** Added by the import */
+  requested: number;
+  limit: number;
+}
+
+export interface ProjectUsage {
+  /** Null for files imported outside any project */
+  project: string | null;
+  files: number;
+  bytes: number;
+  hours: number;
+  quota: ProjectQuota | null;
+}
+
+export interface StorageBreakdown {
+  projects: ProjectUsage[];
+  audioBytes: number;
+  modelBytes: number;
+  /** The history database with its write-ahead log */
+  databaseBytes: number;
+  totalBytes: number;
+}
+
+export async function getProjectQuotas(): Promise<ProjectQuota[]> {
+  return invoke<ProjectQuota[]>('get_project_quotas');
+}
+
+/**
+ * Set a project's limits; leaving both out removes its quota
+ */
+export async function setProjectQuota(
+  project: string,
+  maxHours?: number,
+  maxGb?: number
+): Promise<void> {
+  await invoke('set_project_quota', {
+    project,
+    maxHours: maxHours ?? null,
+    maxGb: maxGb ?? null,
+  });
+}
+
+export async function getStorageBreakdown(): Promise<StorageBreakdown> {
+  return invoke<StorageBreakdown>('get_storage_breakdown');
+}
+
+/**
+ * The broken limit, if `error` is a rejected import over a quota
+ */
+export function quotaViolation(error: unknown): QuotaViolation | null {
+  if (typeof error === 'object' && error !== null && 'code' in error && 'quota' in error) {
+    const { code, quota } = error as { code: unknown; quota: QuotaViolation };
+    return code === 'QUOTA_EXCEEDED' ? quota : null;
+  }
+  return null;
+}
@@ -0,0 +1,66 @@
+/**
+ * Search over the background indexes
+ *
+ * The backend keeps a full-text index and a vocabulary vector per
+ * transcription, updated while the machine is idle. Transcriptions saved
+ * or edited since the last pass are found once the indexer catches up.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export interface IndexStatus {
+  total: number;
+  indexed: number;
+  /** New or changed since they were last indexed */
+  pending: number;
+  /** A pass is running now */
+  indexing: boolean;
+  lastIndexedAt: string | null;
+}
+
+export interface SearchHit {
+  transcriptionId: string;
+  title: string | null;
+  /** Passage around the match, with matched words in `[` and `]` */
+  snippet: string;
+  /** Lower is a better match */
+  rank: number;
+}
+
+export interface RelatedTranscription {
+  transcriptionId: string;
+  title: string | null;
+  /** 1 for the same vocabulary */
+  similarity: number;
+}
+
+export async function getIndexStatus(): Promise<IndexStatus> {
+  return invoke<IndexStatus>('get_index_status');
+}
+
+/**
+ * Drop the indexes so every transcription is indexed again at the next
+ * idle moment
+ */
+export async function rebuildIndexes(): Promise<IndexStatus> {
+  return invoke<IndexStatus>('rebuild_indexes');
+}
+
+export async function searchTranscripts(query: string, limit?: number): Promise<SearchHit[]> {
+  return invoke<SearchHit[]>('search_transcripts', { query, limit: limit ?? null });
+}
+
+export async function relatedTranscriptions(
+  id: string,
+  limit?: number
+): Promise<RelatedTranscription[]> {
+  return invoke<RelatedTranscription[]>('related_transcriptions', { id, limit: limit ?? null });
+}
+
+/**
+ * Call `onStatus` after each indexing pass
+ */
+export function onIndexUpdated(onStatus: (status: IndexStatus) => void): Promise<UnlistenFn> {
+  return listen<IndexStatus>('index://updated', event => onStatus(event.payload));
+}
@@ -0,0 +1,34 @@
+/**
+ * Install self-test for support
+ *
+ * Runs a bundled sample through decoding, voice activity detection,
+ * transcription, storage and export, and reports each stage. Nothing is
+ * kept afterwards.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+
+export type SelfTestStage = 'decode' | 'vad' | 'transcribe' | 'store' | 'export';
+
+export interface SelfTestStageResult {
+  stage: SelfTestStage;
+  passed: boolean;
+  /** Not run because an earlier stage failed */
+  skipped: boolean;
+  durationMs: number;
+  detail: string | null;
+  error: string | null;
+}
+
+export interface SelfTestReport {
+  passed: boolean;
+  appVersion: string;
+  /** Model the transcription stage used */
+  model: string | null;
+  totalMs: number;
+  stages: SelfTestStageResult[];
+}
+
+export async function runSelfTest(): Promise<SelfTestReport> {
+  return invoke<SelfTestReport>('run_self_test');
+}
@@ -0,0 +1,55 @@
+/**
+ * Session resume: save where the user is and restore it on the next launch
+ *
+ * The backend adds the pending job queue and any recordings in progress
+ * when saving, refreshes them when the window closes, and queues the jobs
+ * again when restoring if they still pass the pre-flight checks.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+
+export interface PlaybackPosition {
+  path: string;
+  /** Seconds */
+  position: number;
+}
+
+export interface RecordingDraft {
+  sessionId: string;
+  tracks: string[];
+}
+
+export interface SessionState {
+  openTranscriptionId?: string | null;
+  playback?: PlaybackPosition | null;
+  /** Filled in by the backend */
+  recordingDrafts?: RecordingDraft[];
+  /** View state only the frontend interprets */
+  ui?: Record<string, unknown>;
+  savedAt?: string | null;
+}
+
+export interface RejectedJob {
+  /** The job as it was queued, e.g. `{ transcribe: { path, ... } }` */
+  kind: Record<string, unknown>;
+  error: string;
+}
+
+export interface RestoredSession {
+  state: SessionState;
+  /** Ids of the jobs queued again */
+  requeuedJobs: string[];
+  /** Saved jobs that failed their pre-flight checks and were dropped */
+  rejectedJobs: RejectedJob[];
+}
+
+export async function saveSession(state: SessionState): Promise<void> {
+  return invoke('save_session', { state });
+}
+
+/**
+ * The last saved session, or null on first launch
+ */
+export async function restoreSession(): Promise<RestoredSession | null> {
+  return invoke<RestoredSession | null>('restore_session');
+}
@@ -2,7 +2,11 @@
  * Local text summarization service using Transformers.js
  */
 
-import { pipeline } from '@xenova/transformers';
+import { env, pipeline } from '@xenova/transformers';
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
+import { chunkText } from '@/utils/chunking';
+import { getOfflineMode } from './providers';
 import type { 
   SummarizationModelSize, 
   SummarizationOptions,
@@ -19,6 +23,43 @@ export interface SummarizationServiceResult {
   modelUsed: string;
 }
 
+/**
+ * A summarization prompt from the library; `{{language}}` and
+ * `{{meeting_type}}` are filled in when it is used
+ */
+export interface SummaryPrompt {
+  id: string;
+  name: string;
+  template: string;
+  builtin: boolean;
+}
+
+export interface ProviderSummary {
+  id: string;
+  transcriptionId: string;
+  summary: string;
+  language: string;
+  modelUsed: string;
+  promptId: string | null;
+  originalLength: number;
+  summaryLength: number;
+  compressionRatio: number;
+  processingTime: number;
+}
+
+export interface ProviderSummaryOptions {
+  promptId?: string | undefined;
+  meetingType?: string | undefined;
+  language?: string | undefined;
+  /** Receives summary tokens as the provider generates them */
+  onToken?: ((token: string) => void) | undefined;
+}
+
+interface SummaryTokenEvent {
+  transcriptionId: string;
+  token: string;
+}
+
 class SummarizationService {
   private model: any = null;
   private currentModelSize: SummarizationModelSize | null = null;
@@ -61,6 +102,9 @@ class SummarizationService {
     try {
       const modelName = this.getModelName(modelSize);
       console.log(`Loading ${modelSize} summarization model: ${modelName}...`);
+
+      // In offline mode only models already in the browser cache may load
+      env.allowRemoteModels = !(await getOfflineMode().catch(() => true));
       
       // Load the summarization model
       this.model = await pipeline('summarization', modelName);
@@ -92,41 +136,6 @@ class SummarizationService {
     return this.currentModelSize;
   }
 
-  /**
-   * Split text into manageable chunks for summarization
-   */
-  private chunkText(text: string, maxChunkLength: number = 1000): string[] {
-    // Split by sentences first to maintain context
-    const sentences = text.split(/[.!?]+\s+/).filter(s => s.trim().length > 0);
-    const chunks: string[] = [];
-    let currentChunk = '';
-
-    for (const sentence of sentences) {
-      const trimmedSentence = sentence.trim();
-      if (!trimmedSentence) continue;
-
-      // If adding this sentence would exceed the limit, save current chunk
-      if (currentChunk.length + trimmedSentence.length + 2 > maxChunkLength && currentChunk.length > 0) {
-        const chunkToAdd = currentChunk.trim();
-        if (chunkToAdd.length > 0) {
-          chunks.push(chunkToAdd);
-        }
-        currentChunk = trimmedSentence;
-      } else {
-        currentChunk += (currentChunk.length > 0 ? '. ' : '') + trimmedSentence;
-      }
-    }
-
-    // Add the last chunk if it has content
-    const finalChunk = currentChunk.trim();
-    if (finalChunk.length > 0) {
-      chunks.push(finalChunk);
-    }
-
-    // Ensure we always return at least one non-empty chunk
-    return chunks.length > 0 ? chunks.filter(chunk => chunk.length > 0) : [text.trim()];
-  }
-
   /**
    * Get summarization parameters based on length preference
    */
@@ -163,6 +172,7 @@ class SummarizationService {
     options: SummarizationOptions = {}
   ): Promise<SummarizationServiceResult> {
     const {
+      language,
       modelSize = 'distilbart',
       length = 'medium',
       maxLength,
@@ -200,7 +210,8 @@ class SummarizationService {
       message: 'Preparing text for summarization...',
     });
 
-    const chunks = this.chunkText(text, 800); // Slightly smaller chunks for summarization
+    // Slightly smaller chunks for summarization, cut at sentence and speaker turn boundaries
+    const chunks = chunkText(text, { maxLength: 800, language });
     const chunkResults: SummarizationChunk[] = [];
 
     onProgress?.({
@@ -213,7 +224,7 @@ class SummarizationService {
 
     // Process each chunk
     for (let i = 0; i < chunks.length; i++) {
-      const chunk = chunks[i]!; // Safe assertion since chunkText ensures no undefined elements
+      const { text: chunk, start, end } = chunks[i]!;
 
       const chunkProgress = 40 + (i / chunks.length) * 50;
 
@@ -254,8 +265,8 @@ class SummarizationService {
           id: `chunk-${i}`,
           text: chunk,
           summary: chunkSummary.trim(),
-          startIndex: text.indexOf(chunk),
-          endIndex: text.indexOf(chunk) + chunk.length,
+          startIndex: start,
+          endIndex: end,
         });
       } catch (error) {
         console.error(`Error summarizing chunk ${i + 1}:`, error);
@@ -264,8 +275,8 @@ class SummarizationService {
           id: `chunk-${i}`,
           text: chunk,
           summary: `[Summary unavailable for chunk ${i + 1}]`,
-          startIndex: text.indexOf(chunk),
-          endIndex: text.indexOf(chunk) + chunk.length,
+          startIndex: start,
+          endIndex: end,
         });
       }
     }
@@ -322,6 +333,60 @@ class SummarizationService {
     };
   }
 
+  /**
+   * Summarize a saved transcription with a library prompt through the
+   * configured provider; the summary is stored by the backend
+   */
+  async summarizeTranscription(
+    transcriptionId: string,
+    options: ProviderSummaryOptions = {}
+  ): Promise<ProviderSummary> {
+    const unlisten = options.onToken
+      ? await listen<SummaryTokenEvent>('summary://token', event => {
+          if (event.payload.transcriptionId === transcriptionId) {
+            options.onToken!(event.payload.token);
+          }
+        })
+      : null;
+
+    try {
+      return await invoke<ProviderSummary>('summarize_transcription', {
+        id: transcriptionId,
+        promptId: options.promptId ?? null,
+        meetingType: options.meetingType ?? null,
+        language: options.language ?? null,
+      });
+    } finally {
+      unlisten?.();
+    }
+  }
+
+  /**
+   * Stop a provider summary in progress; its pending call rejects with CANCELLED
+   */
+  async cancelTranscriptionSummary(transcriptionId: string): Promise<void> {
+    await invoke('cancel_summary', { transcriptionId });
+  }
+
+  async listPrompts(): Promise<SummaryPrompt[]> {
+    return invoke<SummaryPrompt[]>('list_prompts');
+  }
+
+  /**
+   * Create a prompt, or update a custom one when `id` is given
+   */
+  async savePrompt(prompt: { name: string; template: string }, id?: string): Promise<SummaryPrompt> {
+    return invoke<SummaryPrompt>('save_prompt', { id: id ?? null, prompt });
+  }
+
+  async clonePrompt(id: string, name?: string): Promise<SummaryPrompt> {
+    return invoke<SummaryPrompt>('clone_prompt', { id, name: name ?? null });
+  }
+
+  async deletePrompt(id: string): Promise<void> {
+    await invoke('delete_prompt', { id });
+  }
+
   /**
    * Clean up resources
    */
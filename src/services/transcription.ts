@@ -2,7 +2,8 @@
  * Local transcription service using Transformers.js
  */
 
-import { pipeline } from '@xenova/transformers';
+import { env, pipeline } from '@xenova/transformers';
+import { getOfflineMode } from './providers';
 import type { 
   WhisperModelSize, 
   TranscriptionOptions 
@@ -52,6 +53,9 @@ class TranscriptionService {
       // Use the full Hugging Face Hub URL to avoid local path issues
       const modelName = `Xenova/whisper-${modelSize}`;
       console.log(`Loading model: ${modelName}`);
+
+      // In offline mode only models already in the browser cache may load
+      env.allowRemoteModels = !(await getOfflineMode().catch(() => true));
       
       this.model = await pipeline(
         'automatic-speech-recognition',
@@ -0,0 +1,48 @@
+/**
+ * Segment translations and bilingual export
+ *
+ * Translations are matched to segments by position, so they survive edits
+ * that keep the segmentation.
+ */
+
+import { invoke } from '@tauri-apps/api/tauri';
+import { save } from '@tauri-apps/api/dialog';
+
+export interface SegmentTranslation {
+  position: number;
+  text: string;
+}
+
+/** Interleaved and table are Markdown; SRT puts both lines in each cue */
+export type BilingualFormat = 'interleaved' | 'table' | 'srt';
+
+export async function saveTranslations(
+  transcriptionId: string,
+  language: string,
+  translations: SegmentTranslation[]
+): Promise<void> {
+  return invoke('save_translations', { transcriptionId, language, translations });
+}
+
+export async function listTranslations(transcriptionId: string, language: string): Promise<SegmentTranslation[]> {
+  return invoke<SegmentTranslation[]>('list_translations', { transcriptionId, language });
+}
+
+/**
+ * Ask for a destination and write the bilingual transcript there.
+ * Returns false when the user cancels the dialog.
+ */
+export async function exportBilingual(
+  transcriptionId: string,
+  language: string,
+  format: BilingualFormat
+): Promise<boolean> {
+  const extension = format === 'srt' ? 'srt' : 'md';
+  const path = await save({
+    filters: [{ name: format === 'srt' ? 'Subtitles' : 'Markdown', extensions: [extension] }],
+    defaultPath: `transcript.${language}.${extension}`
+  });
+  if (!path) return false;
+  await invoke('export_bilingual', { transcriptionId, language, format, path });
+  return true;
+}
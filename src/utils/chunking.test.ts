@@ -0,0 +1,39 @@
+/**
+ * Tests for text chunking
+ */
+
+import { describe, it, expect } from 'vitest';
+import { chunkText } from './chunking';
+
+describe('chunkText', () => {
+  const transcript =
+    'Speaker 1: Hello there. How are you doing today? I am fine.\n' +
+    "Speaker 2: Good. Let's start the meeting now.\n\n" +
+    'New paragraph here. Another one.';
+
+  it('keeps short text in a single chunk', () => {
+    const chunks = chunkText(transcript, { maxLength: 1000 });
+    expect(chunks).toHaveLength(1);
+    expect(chunks[0]!.text).toBe(transcript);
+  });
+
+  it('breaks at speaker turns and paragraphs rather than mid-sentence', () => {
+    const chunks = chunkText(transcript, { maxLength: 60, language: 'en' });
+    expect(chunks.map(chunk => chunk.text)).toEqual([
+      'Speaker 1: Hello there. How are you doing today? I am fine.',
+      "Speaker 2: Good. Let's start the meeting now.",
+      'New paragraph here. Another one.',
+    ]);
+  });
+
+  it('reports offsets into the source text', () => {
+    for (const chunk of chunkText(transcript, { maxLength: 60 })) {
+      expect(transcript.slice(chunk.start, chunk.end)).toBe(chunk.text);
+    }
+  });
+
+  it('splits oversized sentences at word boundaries', () => {
+    const chunks = chunkText('a'.repeat(25) + ' ' + 'b'.repeat(30), { maxLength: 20 });
+    expect(chunks.every(chunk => chunk.text.length <= 20)).toBe(true);
+  });
+});
@@ -0,0 +1,135 @@
+/**
+ * Sentence- and turn-aware text chunking for summarization
+ */
+
+export interface TextChunk {
+  text: string;
+  /** Offset of the chunk in the source text */
+  start: number;
+  end: number;
+}
+
+export interface ChunkOptions {
+  /** Upper bound on chunk length in characters */
+  maxLength?: number;
+  /** BCP 47 language tag used for sentence segmentation */
+  language?: string | undefined;
+}
+
+interface Span {
+  start: number;
+  end: number;
+  /** Whether a paragraph or speaker turn begins at this span */
+  boundary: boolean;
+}
+
+/** A new speaker turn: "Speaker 1:", "[Alice]" or "- Bob:" at the start of a line */
+const SPEAKER_TURN = /^\s*(?:[-–]\s*)?(?:\[[^\]\n]{1,40}\]|[\p{Lu}][\p{L}\d .'-]{0,30}:)\s/u;
+
+type SentenceSegmenter = {
+  segment(text: string): Iterable<{ segment: string; index: number }>;
+};
+
+function sentenceSegmenter(language?: string): SentenceSegmenter | null {
+  const Segmenter = (Intl as unknown as {
+    Segmenter?: new (locale?: string, options?: { granularity: 'sentence' }) => SentenceSegmenter;
+  }).Segmenter;
+  if (!Segmenter) return null;
+  try {
+    return new Segmenter(language && language !== 'auto' ? language : undefined, { granularity: 'sentence' });
+  } catch {
+    return new Segmenter(undefined, { granularity: 'sentence' });
+  }
+}
+
+/**
+ * Split a block of text into sentence spans, offset by `base`
+ */
+function sentences(block: string, base: number, segmenter: SentenceSegmenter | null): Span[] {
+  const spans: Span[] = [];
+  if (segmenter) {
+    for (const { segment, index } of segmenter.segment(block)) {
+      spans.push({ start: base + index, end: base + index + segment.length, boundary: false });
+    }
+  } else {
+    const pattern = /[^.!?。！？]+(?:[.!?。！？]+["')\]]*\s*|$)/g;
+    for (const match of block.matchAll(pattern)) {
+      spans.push({ start: base + match.index!, end: base + match.index! + match[0].length, boundary: false });
+    }
+  }
+  return spans.filter(span => span.end > span.start);
+}
+
+/**
+ * Break a sentence that is longer than a whole chunk at word boundaries
+ */
+function splitLong(text: string, span: Span, maxLength: number): Span[] {
+  const pieces: Span[] = [];
+  let start = span.start;
+  while (span.end - start > maxLength) {
+    const window = text.slice(start, start + maxLength);
+    const lastSpace = window.lastIndexOf(' ');
+    const cut = lastSpace > maxLength / 2 ? start + lastSpace + 1 : start + maxLength;
+    pieces.push({ start, end: cut, boundary: start === span.start && span.boundary });
+    start = cut;
+  }
+  pieces.push({ start, end: span.end, boundary: start === span.start && span.boundary });
+  return pieces;
+}
+
+/**
+ * Split text into chunks of whole sentences, never longer than `maxLength`.
+ *
+ * Chunks prefer to end where a paragraph or speaker turn ends, so a turn is
+ * only split across chunks when it does not fit in one. Each chunk is a
+ * contiguous slice of the source, so offsets map back to the transcript.
+ */
+export function chunkText(text: string, options: ChunkOptions = {}): TextChunk[] {
+  const maxLength = Math.max(options.maxLength ?? 1000, 1);
+  const segmenter = sentenceSegmenter(options.language);
+
+  // Paragraphs and speaker turns, then the sentences within them
+  const spans: Span[] = [];
+  let offset = 0;
+  let afterBlankLine = true;
+  for (const line of text.split('\n')) {
+    if (line.trim().length === 0) {
+      afterBlankLine = true;
+    } else {
+      const boundary = afterBlankLine || SPEAKER_TURN.test(line);
+      sentences(line, offset, segmenter).forEach((span, index) => {
+        spans.push(...splitLong(text, { ...span, boundary: index === 0 && boundary }, maxLength));
+      });
+      afterBlankLine = false;
+    }
+    offset += line.length + 1;
+  }
+
+  const chunks: TextChunk[] = [];
+  let current: Span | null = null;
+  const flush = () => {
+    if (!current) return;
+    const raw = text.slice(current.start, current.end);
+    const leading = raw.length - raw.trimStart().length;
+    const body = raw.trim();
+    if (body) {
+      chunks.push({ text: body, start: current.start + leading, end: current.start + leading + body.length });
+    }
+    current = null;
+  };
+
+  for (const span of spans) {
+    if (current) {
+      const length = span.end - current.start;
+      // Close at a turn boundary once the chunk is reasonably full
+      const preferBreak = span.boundary && current.end - current.start >= maxLength / 2;
+      if (length > maxLength || preferBreak) {
+        flush();
+      }
+    }
+    current = current ? { ...current, end: span.end } : { ...span };
+  }
+  flush();
+
+  return chunks;
+}
@@ -0,0 +1,23 @@
+/**
+ * Tests for date utility functions
+ */
+
+import { describe, it, expect } from 'vitest';
+import { formatClockTime } from './date';
+
+describe('date utilities', () => {
+  describe('formatClockTime', () => {
+    it('adds the offset to the recording start in its own timezone', () => {
+      expect(formatClockTime('2024-03-05T14:30:00+01:00', 125)).toBe('14:32:05');
+      expect(formatClockTime('2024-03-05T09:00:00-05:00', 3600.9)).toBe('10:00:00');
+    });
+
+    it('handles UTC and crossing midnight', () => {
+      expect(formatClockTime('2024-03-05T23:59:30Z', 45)).toBe('00:00:15');
+    });
+
+    it('rejects an unparseable start time', () => {
+      expect(() => formatClockTime('yesterday', 0)).toThrow();
+    });
+  });
+});
@@ -66,3 +66,27 @@ export function formatProcessingTime(milliseconds: number): string {
 export function getTimestamp(): string {
   return new Date().toISOString().replace(/[:.]/g, '-').slice(0, -1);
 }
+
+/**
+ * Wall-clock time of a point in a recording, as HH:MM:SS in the UTC offset
+ * the recording was made in (e.g. "14:32:05"). `startedAt` is an ISO 8601
+ * timestamp; without an offset it is read as local time.
+ */
+export function formatClockTime(startedAt: string, offsetSeconds: number): string {
+  const start = Date.parse(startedAt);
+  if (Number.isNaN(start)) {
+    throw new Error(`Invalid recording start time: ${startedAt}`);
+  }
+
+  const zone = /(?:([+-])(\d{2}):?(\d{2})|Z)$/i.exec(startedAt);
+  const utcOffsetMinutes = zone
+    ? zone[1]
+      ? (zone[1] === '-' ? -1 : 1) * (Number(zone[2]) * 60 + Number(zone[3]))
+      : 0
+    : -new Date(start).getTimezoneOffset();
+
+  const clock = new Date(start + Math.floor(offsetSeconds) * 1000 + utcOffsetMinutes * 60_000);
+  return [clock.getUTCHours(), clock.getUTCMinutes(), clock.getUTCSeconds()]
+    .map(part => part.toString().padStart(2, '0'))
+    .join(':');
+}
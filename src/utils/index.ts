@@ -16,7 +16,7 @@ export {
 } from './file.js';
 
 // Date utilities
-export { formatDate, formatRelativeTime, formatProcessingTime, getTimestamp } from './date.js';
+export { formatDate, formatRelativeTime, formatProcessingTime, getTimestamp, formatClockTime } from './date.js';
 
 // Platform utilities
 export {
@@ -40,6 +40,9 @@ export {
   type ExtractedFileInfo,
 } from './audioMetadata.js';
 
+// Text chunking utilities
+export { chunkText, type TextChunk, type ChunkOptions } from './chunking.js';
+
 // Re-export everything for convenience
 export * from './file.js';
 export * from './date.js';